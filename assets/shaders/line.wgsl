@@ -0,0 +1,68 @@
+// Lines of the retained debug drawing with a width in pixels, mirrors `LineMaterial` in `draw.rs`.
+// Every segment is a quad, whose vertices hold both end points and are offset perpendicular to the projected segment.
+
+#import bevy_pbr::{
+    mesh_functions::{get_world_from_local, mesh_position_local_to_clip},
+    mesh_view_bindings::view,
+}
+
+@group(2) @binding(0) var<uniform> line_width: f32;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) other: vec3<f32>,
+    @location(2) side: f32,
+    @location(3) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+// Moves the end point onto the near plane, if it lies behind it, so the perspective divide stays valid.
+fn clip_near_plane(a: vec4<f32>, b: vec4<f32>) -> vec4<f32> {
+    if a.z > a.w && b.z <= b.w {
+        let distance_a = a.z - a.w;
+        let distance_b = b.z - b.w;
+
+        // the epsilon keeps the point from ending up just behind the plane due to rounding
+        return mix(a, b, distance_a / (distance_a - distance_b) + 0.0001);
+    }
+
+    return a;
+}
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    let world_from_local = get_world_from_local(vertex.instance_index);
+    let clip = mesh_position_local_to_clip(world_from_local, vec4<f32>(vertex.position, 1.0));
+    let other = mesh_position_local_to_clip(world_from_local, vec4<f32>(vertex.other, 1.0));
+
+    let position = clip_near_plane(clip, other);
+    let other_position = clip_near_plane(other, clip);
+
+    // the direction is measured in pixels, so the width does not depend on the aspect ratio
+    let resolution = view.viewport.zw;
+    let screen = position.xy / position.w * resolution;
+    let other_screen = other_position.xy / other_position.w * resolution;
+    let direction = other_screen - screen;
+
+    // segments shorter than a pixel are collapsed, instead of offset in an arbitrary direction
+    var offset = vec2<f32>(0.0);
+    if dot(direction, direction) > 1.0 {
+        let normal = normalize(vec2<f32>(-direction.y, direction.x));
+        offset = normal * vertex.side * line_width / resolution;
+    }
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(position.xy + offset * position.w, position.zw);
+    out.color = vertex.color;
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
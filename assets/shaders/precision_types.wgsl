@@ -0,0 +1,26 @@
+// GPU layouts of the precision data, mirrored by the structs in `gpu.rs` and `tile_prepass.rs`.
+// The shaders import the structs from here, so there is a single definition of each layout.
+// Offsets are given in bytes.
+
+#define_import_path precision_demo::precision_types
+
+struct SideParameter {
+    c: vec3<f32>,      // 0
+    c_du: vec3<f32>,   // 16
+    c_dv: vec3<f32>,   // 32
+    c_duu: vec3<f32>,  // 48
+    c_duv: vec3<f32>,  // 64
+    c_dvv: vec3<f32>,  // 80
+}                      // size 96
+
+struct CompactTile {
+    face: u32,         // 0
+    lod: u32,          // 4
+    xy: vec2<u32>,     // 8
+}                      // size 16
+
+struct TileData {
+    offset: vec2<i32>,    // 0
+    lod_difference: i32,  // 8
+    face_remap: u32,      // 12
+}                         // size 16
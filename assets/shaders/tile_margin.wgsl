@@ -0,0 +1,23 @@
+// Conversions between uvs on a tile texture with a margin and uvs on the tile, mirrors `TileMargin` in `math.rs`.
+// The tile uv lies outside of the unit square inside the margin, and is what the relative uv is computed from.
+
+struct TileMargin {
+    texture_size: u32,
+    margin: u32,
+}
+
+fn margin_scale(margin: TileMargin) -> f32 {
+    return f32(margin.texture_size) / f32(margin.texture_size - 2u * margin.margin);
+}
+
+fn margin_offset(margin: TileMargin) -> f32 {
+    return f32(margin.margin) / f32(margin.texture_size);
+}
+
+fn tile_uv_from_texture_uv(margin: TileMargin, texture_uv: vec2<f32>) -> vec2<f32> {
+    return (texture_uv - margin_offset(margin)) * margin_scale(margin);
+}
+
+fn texture_uv_from_tile_uv(margin: TileMargin, tile_uv: vec2<f32>) -> vec2<f32> {
+    return tile_uv / margin_scale(margin) + margin_offset(margin);
+}
@@ -0,0 +1,35 @@
+// Derives the per-tile data from the compact tile list, mirrors `derive_tile_data` in `tile_prepass.rs`.
+
+#import precision_demo::precision_types::{CompactTile, TileData}
+
+struct PrepassOrigin {
+    origin_lod: u32,
+    origin_xy: array<vec2<u32>, 6>,
+}
+
+@group(0) @binding(0) var<storage, read> origin: PrepassOrigin;
+@group(0) @binding(1) var<storage, read> tiles: array<CompactTile>;
+@group(0) @binding(2) var<storage, read_write> tile_data: array<TileData>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let index = invocation_id.x;
+
+    if (index >= arrayLength(&tiles)) {
+        return;
+    }
+
+    let tile = tiles[index];
+    let lod_difference = i32(tile.lod) - i32(origin.origin_lod);
+    let origin_xy = origin.origin_xy[tile.face];
+
+    var offset: vec2<i32>;
+
+    if (lod_difference >= 0) {
+        offset = vec2<i32>(tile.xy) - vec2<i32>(origin_xy << vec2<u32>(u32(lod_difference)));
+    } else {
+        offset = vec2<i32>(tile.xy) - vec2<i32>(origin_xy >> vec2<u32>(u32(-lod_difference)));
+    }
+
+    tile_data[index] = TileData(offset, lod_difference, tile.face);
+}
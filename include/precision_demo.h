@@ -0,0 +1,101 @@
+/* C declarations of the precision core, mirrors `src/ffi.rs`.
+ * Build the library with `cargo rustc --lib --release --features ffi --crate-type cdylib` (or `staticlib`).
+ * Every function validates its input and returns a status, the result is only written to `out` on success. */
+
+#ifndef PRECISION_DEMO_H
+#define PRECISION_DEMO_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum PrecisionStatus {
+    PRECISION_STATUS_OK = 0,
+    PRECISION_STATUS_NULL_POINTER = 1,
+    PRECISION_STATUS_INVALID_FACE = 2,
+    PRECISION_STATUS_INVALID_LOD = 3,
+    /* the tile lies outside of its face */
+    PRECISION_STATUS_INVALID_TILE = 4,
+    /* the axes are not positive, or the position is not finite */
+    PRECISION_STATUS_INVALID_MODEL = 5,
+    /* a position or uv is not finite, or the position is the center of the model */
+    PRECISION_STATUS_INVALID_VALUE = 6,
+} PrecisionStatus;
+
+typedef struct PrecisionVec3 {
+    double x;
+    double y;
+    double z;
+} PrecisionVec3;
+
+typedef struct PrecisionVec3f {
+    float x;
+    float y;
+    float z;
+} PrecisionVec3f;
+
+typedef struct PrecisionModel {
+    PrecisionVec3 position;
+    double major_axis;
+    double minor_axis;
+} PrecisionModel;
+
+typedef struct PrecisionCoordinate {
+    uint32_t face;
+    double u;
+    double v;
+} PrecisionCoordinate;
+
+typedef struct PrecisionTile {
+    uint32_t face;
+    uint32_t lod;
+    uint32_t x;
+    uint32_t y;
+    /* position inside the tile, in the range [0, 1] */
+    float u;
+    float v;
+} PrecisionTile;
+
+typedef struct PrecisionApproximation {
+    PrecisionVec3f c;
+    PrecisionVec3f c_du;
+    PrecisionVec3f c_dv;
+    PrecisionVec3f c_duu;
+    PrecisionVec3f c_duv;
+    PrecisionVec3f c_dvv;
+} PrecisionApproximation;
+
+PrecisionStatus precision_coordinate_from_world_position(
+    PrecisionModel model,
+    PrecisionVec3 world_position,
+    PrecisionCoordinate *out);
+
+PrecisionStatus precision_project_to_face(
+    PrecisionModel model,
+    PrecisionCoordinate coordinate,
+    uint32_t face,
+    PrecisionCoordinate *out);
+
+PrecisionStatus precision_surface_approximation_compute(
+    PrecisionModel model,
+    PrecisionCoordinate view_coordinate,
+    PrecisionVec3 view_position,
+    PrecisionApproximation *out);
+
+/* The view coordinate has to be projected onto the face of the tile, see `precision_project_to_face`. */
+PrecisionStatus precision_approximate_relative_position(
+    PrecisionCoordinate view_coordinate,
+    PrecisionApproximation approximation,
+    uint32_t origin_lod,
+    PrecisionTile tile,
+    bool second_order,
+    PrecisionVec3f *out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
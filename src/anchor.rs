@@ -0,0 +1,163 @@
+use bevy::{
+    math::DVec3,
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+};
+use bevy_terrain::{
+    big_space::{GridCell, ReferenceFrame},
+    prelude::*,
+};
+
+use crate::geodesy::{enu_frame, Geodetic};
+
+// Static geometry placed on the planet, with its vertices stored as f32 offsets to an anchor close to them.
+// This is the same trick the tiles use with their origins: the large absolute part of each position is removed in f64,
+// so only the small offsets are rounded, and the anchor itself is placed exactly through the grid.
+pub struct AnchoredMesh {
+    pub anchor: DVec3,
+    pub positions: Vec<Vec3>,
+    pub indices: Vec<u32>,
+}
+
+impl AnchoredMesh {
+    // Anchors the points at the center of their bounding box, which minimizes the largest offset and with it the rounding error.
+    pub fn new(points: &[DVec3], indices: Vec<u32>) -> Self {
+        let (min, max) = points.iter().fold(
+            (DVec3::splat(f64::INFINITY), DVec3::splat(f64::NEG_INFINITY)),
+            |(min, max), &point| (min.min(point), max.max(point)),
+        );
+        let anchor = if points.is_empty() {
+            DVec3::ZERO
+        } else {
+            0.5 * (min + max)
+        };
+
+        Self {
+            anchor,
+            positions: points
+                .iter()
+                .map(|&point| (point - anchor).as_vec3())
+                .collect(),
+            indices,
+        }
+    }
+
+    // upper bound of the rounding error of the vertices in meters
+    pub fn max_error(&self) -> f64 {
+        let max_offset = self
+            .positions
+            .iter()
+            .map(|position| position.abs().max_element())
+            .fold(0.0, f32::max);
+
+        0.5 * max_offset as f64 * f32::EPSILON as f64
+    }
+
+    pub fn grid_position(&self, frame: &ReferenceFrame) -> (GridCell, Vec3) {
+        frame.translation_to_grid(self.anchor)
+    }
+
+    // Triangle mesh with area weighted vertex normals.
+    pub fn mesh(&self) -> Mesh {
+        let mut normals = vec![Vec3::ZERO; self.positions.len()];
+
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| self.positions[triangle[i] as usize]);
+            let normal = (b - a).cross(c - a);
+
+            for &index in triangle {
+                normals[index as usize] += normal;
+            }
+        }
+
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            self.positions
+                .iter()
+                .map(|position| position.to_array())
+                .collect::<Vec<_>>(),
+        )
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_NORMAL,
+            normals
+                .iter()
+                .map(|normal| normal.normalize_or_zero().to_array())
+                .collect::<Vec<_>>(),
+        )
+        .with_inserted_indices(Indices::U32(self.indices.clone()))
+    }
+
+    // The mesh placed at its anchor, to be spawned into the reference frame.
+    pub fn bundle(
+        &self,
+        frame: &ReferenceFrame,
+        meshes: &mut Assets<Mesh>,
+        material: Handle<StandardMaterial>,
+    ) -> (PbrBundle, GridCell) {
+        let (cell, translation) = self.grid_position(frame);
+
+        (
+            PbrBundle {
+                mesh: meshes.add(self.mesh()),
+                material,
+                transform: Transform::from_translation(translation),
+                ..default()
+            },
+            cell,
+        )
+    }
+}
+
+// Extrudes a convex footprint along the surface normal into a prism with walls and a flat roof.
+// The footprint may be given in either winding order, the faces are oriented outwards.
+// Every face has its own vertices, so that the normals stay flat.
+pub fn extruded_footprint(
+    footprint: &[Geodetic],
+    height: f64,
+    model: &TerrainModel,
+) -> (Vec<DVec3>, Vec<u32>) {
+    let mut points = Vec::new();
+    let mut indices = Vec::new();
+
+    if footprint.len() < 3 {
+        return (points, indices);
+    }
+
+    let up = enu_frame(footprint[0], model).z_axis;
+    let mut bases = footprint
+        .iter()
+        .map(|corner| corner.world_position(model))
+        .collect::<Vec<_>>();
+    let mut tops = footprint
+        .iter()
+        .map(|corner| Geodetic::new(corner.latitude, corner.longitude, corner.height + height))
+        .map(|corner| corner.world_position(model))
+        .collect::<Vec<_>>();
+
+    // wind the corners counter-clockwise, seen from above
+    if (bases[1] - bases[0]).cross(bases[2] - bases[0]).dot(up) < 0.0 {
+        bases.reverse();
+        tops.reverse();
+    }
+
+    for i in 0..bases.len() {
+        let j = (i + 1) % bases.len();
+        let start = points.len() as u32;
+
+        points.extend([bases[i], bases[j], tops[j], tops[i]]);
+        indices.extend([start, start + 1, start + 2, start, start + 2, start + 3]);
+    }
+
+    let start = points.len() as u32;
+    points.extend(&tops);
+    indices.extend((1..tops.len() as u32 - 1).flat_map(|k| [start, start + k, start + k + 1]));
+
+    (points, indices)
+}
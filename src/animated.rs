@@ -0,0 +1,202 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    math::{DAffine3, DQuat, DVec3},
+    prelude::*,
+};
+use bevy_terrain::big_space::{FloatingOrigin, GridTransformReadOnly, ReferenceFrames};
+
+// distance between the rendered tip of the arm and its position composed in f64
+pub const ARM_TIP_ERROR: DiagnosticPath = DiagnosticPath::const_new("arm/tip_error");
+// change of that error from one frame to the next, which is what shows up as jitter on screen
+pub const ARM_TIP_JITTER: DiagnosticPath = DiagnosticPath::const_new("arm/tip_jitter");
+
+// A chain of links swinging around their joints, hung below an entity placed in the grid.
+// Only the root has a grid cell, the joints are ordinary children with f32 transforms,
+// which is how animated and skinned meshes end up in the scene. The tip is compared against
+// the same chain composed in f64, to show whether deep hierarchies stay stable far away from the origin.
+#[derive(Component)]
+pub struct AnimatedArm {
+    pub links: u32,
+    pub link_length: f32,
+    // largest angle a joint swings to either side
+    pub amplitude: f32,
+    // swings per second
+    pub frequency: f32,
+    tip: Option<Entity>,
+    previous_error: Option<Vec3>,
+}
+
+impl AnimatedArm {
+    pub fn new(links: u32, link_length: f32) -> Self {
+        Self {
+            links,
+            link_length,
+            amplitude: 0.5,
+            frequency: 0.25,
+            tip: None,
+            previous_error: None,
+        }
+    }
+
+    // Rotation of the joint at the time, the joints swing out of phase so the arm curls and straightens.
+    pub fn joint_rotation(&self, joint: u32, time: f64) -> Quat {
+        let phase = std::f64::consts::TAU
+            * (self.frequency as f64 * time + joint as f64 / self.links as f64);
+
+        Quat::from_rotation_z(self.amplitude * phase.sin() as f32)
+    }
+
+    // Position of the tip relative to the root, composed in f64 from the same f32 rotations the joints receive.
+    pub fn tip_offset(&self, time: f64) -> DVec3 {
+        let link = DVec3::Y * self.link_length as f64;
+
+        (0..self.links)
+            .fold(DAffine3::IDENTITY, |transform, joint| {
+                let offset = if joint == 0 { DVec3::ZERO } else { link };
+
+                transform
+                    * DAffine3::from_rotation_translation(
+                        DQuat::from_quat(self.joint_rotation(joint, time)),
+                        offset,
+                    )
+            })
+            .transform_point3(link)
+    }
+}
+
+#[derive(Component)]
+pub struct ArmJoint {
+    pub arm: Entity,
+    pub index: u32,
+}
+
+pub struct AnimatedArmPlugin;
+
+impl Plugin for AnimatedArmPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(ARM_TIP_ERROR).with_suffix(" m"))
+            .register_diagnostic(Diagnostic::new(ARM_TIP_JITTER).with_suffix(" m"))
+            .add_systems(Update, (build_arms, animate_arms).chain())
+            .add_systems(Last, measure_arm_jitter);
+    }
+}
+
+fn build_arms(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut arm_query: Query<(Entity, &mut AnimatedArm), Added<AnimatedArm>>,
+) {
+    for (arm_entity, mut arm) in &mut arm_query {
+        let mesh = meshes.add(Cuboid::new(0.1, arm.link_length, 0.1));
+        let material = materials.add(StandardMaterial::from_color(Color::srgb(0.2, 0.4, 0.9)));
+
+        let mut parent = arm_entity;
+
+        for index in 0..arm.links {
+            let offset = if index == 0 { 0.0 } else { arm.link_length };
+
+            let joint = commands
+                .spawn((
+                    SpatialBundle::from_transform(Transform::from_xyz(0.0, offset, 0.0)),
+                    ArmJoint {
+                        arm: arm_entity,
+                        index,
+                    },
+                ))
+                .with_children(|joint| {
+                    // the link hangs from its joint up to the next one
+                    joint.spawn(PbrBundle {
+                        mesh: mesh.clone(),
+                        material: material.clone(),
+                        transform: Transform::from_xyz(0.0, 0.5 * arm.link_length, 0.0),
+                        ..default()
+                    });
+                })
+                .id();
+
+            commands.entity(parent).add_child(joint);
+            parent = joint;
+        }
+
+        let tip = commands
+            .spawn(SpatialBundle::from_transform(Transform::from_xyz(
+                0.0,
+                arm.link_length,
+                0.0,
+            )))
+            .id();
+        commands.entity(parent).add_child(tip);
+
+        arm.tip = Some(tip);
+    }
+}
+
+fn animate_arms(
+    time: Res<Time>,
+    arm_query: Query<&AnimatedArm>,
+    mut joint_query: Query<(&ArmJoint, &mut Transform)>,
+) {
+    let time = time.elapsed_seconds_f64();
+
+    for (joint, mut transform) in &mut joint_query {
+        let Ok(arm) = arm_query.get(joint.arm) else {
+            continue;
+        };
+
+        transform.rotation = arm.joint_rotation(joint.index, time);
+    }
+}
+
+// Compares the propagated tip with the f64 composition, both relative to the view.
+// The camera is the floating origin, so its global translation is its position in render space.
+fn measure_arm_jitter(
+    time: Res<Time>,
+    frames: ReferenceFrames,
+    view_query: Query<(Entity, GridTransformReadOnly, &GlobalTransform), With<FloatingOrigin>>,
+    mut arm_query: Query<
+        (Entity, &mut AnimatedArm, GridTransformReadOnly),
+        Without<FloatingOrigin>,
+    >,
+    tip_query: Query<&GlobalTransform, Without<FloatingOrigin>>,
+    mut diagnostics: Diagnostics,
+) {
+    let Ok((view, view_transform, view_global)) = view_query.get_single() else {
+        warn_once!("Skipping measure_arm_jitter: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping measure_arm_jitter: the camera is not part of a reference frame.");
+        return;
+    };
+
+    let view_position = view_transform.position_double(&frame);
+    let time = time.elapsed_seconds_f64();
+
+    for (entity, mut arm, arm_transform) in &mut arm_query {
+        let Some(tip_global) = arm.tip.and_then(|tip| tip_query.get(tip).ok()) else {
+            continue;
+        };
+        let Some(arm_frame) = frames.parent_frame(entity) else {
+            warn_once!(
+                "Skipping measure_arm_jitter for {entity:?}: it is not part of a reference frame."
+            );
+            continue;
+        };
+
+        let root_position = arm_transform.position_double(&arm_frame);
+        let root_rotation = DQuat::from_quat(arm_transform.transform.rotation);
+        let tip_position = root_position + root_rotation * arm.tip_offset(time);
+
+        let expected = view_global.translation() + (tip_position - view_position).as_vec3();
+        let error = tip_global.translation() - expected;
+
+        let jitter = arm.previous_error.map(|previous| error.distance(previous));
+        arm.previous_error = Some(error);
+
+        diagnostics.add_measurement(&ARM_TIP_ERROR, || error.length() as f64);
+        if let Some(jitter) = jitter {
+            diagnostics.add_measurement(&ARM_TIP_JITTER, || jitter as f64);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use bevy::{math::DVec3, prelude::*};
+use bevy_terrain::{
+    big_space::{FloatingOrigin, GridTransform, GridTransformReadOnly, ReferenceFrames},
+    math::Coordinate,
+    prelude::*,
+};
+
+use crate::{
+    draw::PrecisionGizmos,
+    epoch::{shift_model, Rebase, RebaseAppExt},
+    grid::grid_offset,
+    labels::WorldLabel,
+    math::height_derivative,
+    model_copy::{ModelCopy, ModelCopyAppExt},
+};
+
+// A marker floating above a coordinate, connected to the surface by a leader line and named by a label.
+// The entity is placed in the grid from the coordinate in f64, and the marker is drawn relative to the view
+// using the grid offset, so annotations stay put at any distance, like the labels.
+#[derive(Component, Clone, Debug)]
+pub struct Annotation {
+    pub model: TerrainModel,
+    pub coordinate: Coordinate,
+    // height of the marker above the reference surface
+    pub height: f64,
+    pub label: String,
+    pub color: Color,
+}
+
+impl Annotation {
+    pub fn new(
+        model: TerrainModel,
+        coordinate: Coordinate,
+        height: f64,
+        label: impl Into<String>,
+        color: Color,
+    ) -> Self {
+        Self {
+            model,
+            coordinate,
+            height,
+            label: label.into(),
+            color,
+        }
+    }
+}
+
+impl ModelCopy for Annotation {
+    fn set_model(&mut self, model: &TerrainModel) {
+        self.model = model.clone();
+    }
+}
+
+impl Rebase for Annotation {
+    fn rebase(&mut self, offset: DVec3) {
+        self.model = shift_model(&self.model, offset);
+    }
+}
+
+// Places, labels and draws the annotations. The labels need the `LabelPlugin`.
+pub struct AnnotationPlugin;
+
+impl Plugin for AnnotationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_model_copy::<Annotation>()
+            .add_rebase::<Annotation>()
+            .add_systems(Update, (place_annotations, draw_annotations).chain());
+    }
+}
+
+fn place_annotations(
+    mut commands: Commands,
+    frames: ReferenceFrames,
+    mut annotation_query: Query<(Entity, &Annotation, GridTransform), Changed<Annotation>>,
+) {
+    for (entity, annotation, mut transform) in &mut annotation_query {
+        let Some(frame) = frames.parent_frame(entity) else {
+            warn_once!(
+                "Skipping place_annotations for {entity:?}: it is not part of a reference frame."
+            );
+            continue;
+        };
+
+        let (cell, translation) = frame.translation_to_grid(
+            annotation
+                .coordinate
+                .world_position(&annotation.model, annotation.height as f32),
+        );
+
+        *transform.cell = cell;
+        transform.transform.translation = translation;
+
+        commands
+            .entity(entity)
+            .insert(WorldLabel::new(annotation.label.clone(), annotation.color));
+    }
+}
+
+fn draw_annotations(
+    mut gizmos: Gizmos<PrecisionGizmos>,
+    frames: ReferenceFrames,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+    annotation_query: Query<(&Annotation, GridTransformReadOnly), Without<FloatingOrigin>>,
+) {
+    if !gizmos.config_ext.annotations {
+        return;
+    }
+
+    let Ok((view, view_transform)) = view_query.get_single() else {
+        warn_once!("Skipping draw_annotations: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping draw_annotations: the camera is not part of a reference frame.");
+        return;
+    };
+
+    for (annotation, transform) in &annotation_query {
+        let offset = grid_offset(
+            &frame,
+            (view_transform.cell, view_transform.transform),
+            (transform.cell, transform.transform),
+        );
+
+        // the view is the floating origin, so its translation is its position in render space
+        let marker = view_transform.transform.translation + offset.as_vec3();
+        let normal = height_derivative(annotation.coordinate, &annotation.model).as_vec3();
+        let foot = marker - normal * annotation.height as f32;
+
+        // the marker keeps its size on screen
+        let radius = 0.01 * offset.length() as f32;
+
+        gizmos.line(foot, marker, annotation.color);
+        gizmos.sphere(marker, Quat::IDENTITY, radius, annotation.color);
+        if let Ok(normal) = Dir3::new(normal) {
+            gizmos.circle(foot, normal, radius, annotation.color);
+        }
+    }
+}
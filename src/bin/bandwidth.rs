@@ -0,0 +1,97 @@
+use bevy::{
+    math::{DVec3, IVec3, Vec2, Vec3},
+    render::render_resource::ShaderType,
+};
+use bevy_terrain::math::SurfaceApproximation;
+use precision_demo::{
+    tile_mesh::DoubleSplit,
+    tile_prepass::{CompactTile, PrepassOrigin, TileData},
+};
+use std::mem::size_of;
+
+// Compares the memory and bandwidth of the precision strategies for a frame with the given number of tiles.
+// The sizes are taken from the structs that are uploaded, using their GPU layout where they are shader types.
+//
+// Taylor: the view uploads six surface approximations and the compact tile list, the vertices only store their uv.
+// RTC: each tile uploads its origin split into two f32 vectors, the vertices store their offset to it.
+// df64: the view uploads its split position, each vertex stores its position split into two f32 vectors.
+// Fixed point: the view uploads its position in 32.32 fixed point, each vertex stores its position the same way.
+
+struct Strategy {
+    name: &'static str,
+    // bytes uploaded once per frame and per tile
+    frame_bytes: u64,
+    tile_bytes: u64,
+    // bytes stored per vertex, which are read by the vertex shader every frame
+    vertex_bytes: u64,
+    // rough number of f32 operations per vertex to reconstruct its view-relative position
+    vertex_flops: u64,
+}
+
+fn argument(name: &str, default: u64) -> u64 {
+    std::env::args()
+        .skip_while(|arg| arg != name)
+        .nth(1)
+        .map_or(default, |value| {
+            value.parse().expect("the value is not an integer")
+        })
+}
+
+fn main() {
+    let tiles = argument("--tiles", 1000);
+    let resolution = argument("--resolution", 16);
+    let vertices = tiles * (resolution + 1) * (resolution + 1);
+
+    let strategies = [
+        Strategy {
+            name: "Taylor uniforms",
+            frame_bytes: 6 * SurfaceApproximation::min_size().get()
+                + PrepassOrigin::min_size().get(),
+            tile_bytes: CompactTile::min_size().get() + TileData::min_size().get(),
+            vertex_bytes: size_of::<Vec2>() as u64,
+            // relative uv and six terms of the second order series
+            vertex_flops: 8 + 6 * 6,
+        },
+        Strategy {
+            name: "RTC anchors",
+            frame_bytes: size_of::<DoubleSplit>() as u64,
+            tile_bytes: size_of::<DoubleSplit>() as u64,
+            vertex_bytes: size_of::<Vec3>() as u64,
+            // difference of the split origins and offset by the vertex
+            vertex_flops: 3 * 3 + 3,
+        },
+        Strategy {
+            name: "df64",
+            frame_bytes: size_of::<DoubleSplit>() as u64,
+            tile_bytes: 0,
+            vertex_bytes: size_of::<DoubleSplit>() as u64,
+            // error-free two-sum of the high and low parts per component
+            vertex_flops: 3 * 20,
+        },
+        Strategy {
+            name: "Fixed point",
+            frame_bytes: 2 * size_of::<IVec3>() as u64,
+            tile_bytes: 0,
+            vertex_bytes: 2 * size_of::<IVec3>() as u64,
+            // 64 bit subtraction with carry and conversion per component
+            vertex_flops: 3 * 6,
+        },
+    ];
+
+    println!(
+        "{tiles} tiles with {resolution}x{resolution} quads, {vertices} vertices ({} bytes of f64 positions for reference).",
+        vertices * size_of::<DVec3>() as u64
+    );
+    println!("strategy, frame upload [B], vertex buffer [B], bytes per vertex, f32 operations per vertex");
+
+    for strategy in strategies {
+        println!(
+            "{}, {}, {}, {}, {}",
+            strategy.name,
+            strategy.frame_bytes + tiles * strategy.tile_bytes,
+            vertices * strategy.vertex_bytes,
+            strategy.vertex_bytes,
+            strategy.vertex_flops
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use bevy::math::{DVec2, DVec3};
+use bevy_terrain::{math::Coordinate, prelude::*};
+use precision_demo::{
+    format::Length,
+    math::{
+        approximate_world_position, approximations, compute_approximations,
+        compute_culled_approximations, tile_coordinate_from_world_position, view_coordinates,
+    },
+    view_approximation::{ApproximationSnapshot, DualApproximation},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{hint::black_box, time::Instant};
+
+// Compares the time per view position of `SurfaceApproximation::compute` with the hoisted `compute_approximations`,
+// and checks that both produce the same coefficients.
+// Also times the approximations of two nearby views, computed separately and sharing one expansion.
+
+// distance to the view beyond which the sides only get their constant term
+const VALIDITY_RADIUS: f64 = 500000.0;
+// distance between the two views of a pair, e.g. split screen players close to each other
+const PAIR_DISTANCE: f64 = 100.0;
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let model = TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0);
+
+    let samples = 100000;
+
+    let view_positions = (0..samples)
+        .map(|_| {
+            Coordinate::new(
+                rng.gen_range(0..6),
+                DVec2::new(rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0)),
+            )
+            .world_position(&model, rng.gen_range(0.0..10000.0))
+        })
+        .collect::<Vec<_>>();
+    let view_coordinates = view_positions
+        .iter()
+        .map(|&view_position| view_coordinates(view_position, &model))
+        .collect::<Vec<_>>();
+
+    let start = Instant::now();
+    for (view_coordinates, &view_position) in view_coordinates.iter().zip(&view_positions) {
+        black_box(approximations(view_coordinates, view_position, &model));
+    }
+    let reference_time = start.elapsed();
+
+    let start = Instant::now();
+    for (view_coordinates, &view_position) in view_coordinates.iter().zip(&view_positions) {
+        black_box(compute_approximations(
+            view_coordinates,
+            view_position,
+            &model,
+            false,
+        ));
+    }
+    let hoisted_time = start.elapsed();
+
+    let start = Instant::now();
+    for (view_coordinates, &view_position) in view_coordinates.iter().zip(&view_positions) {
+        black_box(compute_approximations(
+            view_coordinates,
+            view_position,
+            &model,
+            true,
+        ));
+    }
+    let neighborhood_time = start.elapsed();
+
+    let mut full_sides = 0;
+    let start = Instant::now();
+    for (view_coordinates, &view_position) in view_coordinates.iter().zip(&view_positions) {
+        let (_, full) = black_box(compute_culled_approximations(
+            view_coordinates,
+            view_position,
+            &model,
+            VALIDITY_RADIUS,
+        ));
+        full_sides += full.iter().filter(|&&full| full).count();
+    }
+    let culled_time = start.elapsed();
+
+    let mut max_deviation: f32 = 0.0;
+    for (view_coordinates, &view_position) in view_coordinates.iter().zip(&view_positions) {
+        let reference = approximations(view_coordinates, view_position, &model);
+        let hoisted = compute_approximations(view_coordinates, view_position, &model, false);
+
+        for (reference, hoisted) in reference.iter().zip(&hoisted) {
+            for (a, b) in [
+                (reference.c, hoisted.c),
+                (reference.c_du, hoisted.c_du),
+                (reference.c_dv, hoisted.c_dv),
+                (reference.c_duu, hoisted.c_duu),
+                (reference.c_duv, hoisted.c_duv),
+                (reference.c_dvv, hoisted.c_dvv),
+            ] {
+                max_deviation = max_deviation.max(a.distance(b));
+            }
+        }
+    }
+
+    // pairs of views, with the second one offset horizontally from the first
+    let pairs = view_positions
+        .iter()
+        .map(|&view_position| {
+            let offset = view_position.normalize().any_orthonormal_vector() * PAIR_DISTANCE;
+
+            [view_position, view_position + offset]
+        })
+        .collect::<Vec<_>>();
+
+    let start = Instant::now();
+    for &pair in &pairs {
+        black_box(pair.map(|view_position| ApproximationSnapshot::compute(view_position, &model)));
+    }
+    let separate_time = start.elapsed();
+
+    let start = Instant::now();
+    for &pair in &pairs {
+        black_box(DualApproximation::compute(
+            pair,
+            &model,
+            2.0 * PAIR_DISTANCE,
+        ));
+    }
+    let dual_time = start.elapsed();
+
+    // the error of the shared expansion at the second view, for the surface below it
+    let max_dual_error = pairs
+        .iter()
+        .take(1000)
+        .map(|&pair| {
+            let dual = DualApproximation::compute(pair, &model, 2.0 * PAIR_DISTANCE);
+            let snapshot = &dual.snapshots[1];
+            let surface_position =
+                model.position_local_to_world(model.position_world_to_local(pair[1]), 0.0);
+
+            surface_position.distance(approximate_world_position(
+                &snapshot.view_coordinates,
+                &snapshot.approximations,
+                16,
+                true,
+                snapshot.view_position,
+                tile_coordinate_from_world_position(surface_position, 16, &model),
+            ))
+        })
+        .fold(0.0, f64::max);
+
+    let per_view = |time: std::time::Duration| time.as_secs_f64() / samples as f64 * 1e9;
+
+    println!(
+        "SurfaceApproximation::compute takes {:.1} ns per view position.",
+        per_view(reference_time)
+    );
+    println!(
+        "The hoisted computation takes {:.1} ns per view position ({:.2}x faster).",
+        per_view(hoisted_time),
+        reference_time.as_secs_f64() / hoisted_time.as_secs_f64()
+    );
+    println!(
+        "Skipping the opposite face takes {:.1} ns per view position ({:.2}x faster).",
+        per_view(neighborhood_time),
+        reference_time.as_secs_f64() / neighborhood_time.as_secs_f64()
+    );
+    println!(
+        "Culling the sides beyond {} takes {:.1} ns per view position ({:.2}x faster), with {:.2} full sides on average.",
+        Length(VALIDITY_RADIUS),
+        per_view(culled_time),
+        reference_time.as_secs_f64() / culled_time.as_secs_f64(),
+        full_sides as f64 / samples as f64
+    );
+    println!(
+        "The coefficients deviate by at most {}.",
+        Length(max_deviation as f64)
+    );
+    println!(
+        "Two views {} apart take {:.1} ns computed separately and {:.1} ns sharing one expansion ({:.2}x faster), which is off by at most {} below the second view.",
+        Length(PAIR_DISTANCE),
+        per_view(separate_time),
+        per_view(dual_time),
+        separate_time.as_secs_f64() / dual_time.as_secs_f64(),
+        Length(max_dual_error)
+    );
+}
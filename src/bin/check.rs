@@ -0,0 +1,383 @@
+use bevy::math::{DVec2, DVec3};
+use bevy_terrain::{math::Coordinate, prelude::*};
+use itertools::Itertools;
+use precision_demo::{
+    format::Length,
+    geodesy::Geodetic,
+    math::{
+        approximate_world_position, approximations,
+        fixture::{model_hash, ApproximationFixture, REPLAY_TOLERANCE},
+        tile_coordinate_from_world_position, validate, view_coordinates, TileMargin,
+    },
+    notation::TileName,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+// Runs a fixed set of numerical scenarios through the math and exits with an error, if any of them exceeds its threshold.
+// Everything is seeded and runs without a window, so the report is the same locally and in automation.
+
+const SURFACE_SAMPLES: usize = 1000;
+const VALIDATION_RESOLUTION: u32 = 64;
+// failing cases saved by `error --save-fixture`, whose errors must not grow beyond the recorded ones
+const FIXTURE_DIRECTORY: &str = "fixtures";
+
+// A view with the surface samples around it, whose second order approximation error has to stay below the threshold.
+struct Scenario {
+    name: &'static str,
+    model: TerrainModel,
+    view_position: DVec3,
+    origin_lod: u32,
+    // radius around the view the surface samples are taken from
+    sample_radius: f64,
+    threshold: f64,
+}
+
+struct Outcome {
+    name: String,
+    error: f64,
+    threshold: f64,
+    details: Vec<String>,
+}
+
+impl Outcome {
+    fn passed(&self) -> bool {
+        self.error <= self.threshold
+    }
+}
+
+fn earth() -> TerrainModel {
+    TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0)
+}
+
+fn jupiter() -> TerrainModel {
+    TerrainModel::ellipsoid(DVec3::ZERO, 71492000.0, 66854000.0, 0.0, 0.0)
+}
+
+fn scenarios() -> Vec<Scenario> {
+    let earth = earth();
+    let jupiter = jupiter();
+    let above = |model: &TerrainModel, face, uv: DVec2, height| {
+        Coordinate::new(face, uv).world_position(model, height)
+    };
+    let geodetic = |model: &TerrainModel, latitude, longitude, height| {
+        Geodetic::from_degrees(latitude, longitude, height).world_position(model)
+    };
+
+    vec![
+        Scenario {
+            name: "face center",
+            view_position: above(&earth, 0, DVec2::splat(0.5), 1000.0),
+            origin_lod: 10,
+            sample_radius: 0.001 * earth.scale(),
+            threshold: 0.01,
+            model: earth.clone(),
+        },
+        // the samples around the view lie on two and three faces
+        Scenario {
+            name: "face edge",
+            view_position: above(&earth, 0, DVec2::new(1.0, 0.5), 100.0),
+            origin_lod: 10,
+            sample_radius: 0.001 * earth.scale(),
+            threshold: 0.01,
+            model: earth.clone(),
+        },
+        Scenario {
+            name: "face corner",
+            view_position: above(&earth, 0, DVec2::ONE, 100.0),
+            origin_lod: 10,
+            sample_radius: 0.001 * earth.scale(),
+            threshold: 0.01,
+            model: earth.clone(),
+        },
+        Scenario {
+            name: "north pole",
+            view_position: geodetic(&earth, 90.0, 0.0, 100.0),
+            origin_lod: 10,
+            sample_radius: 0.001 * earth.scale(),
+            threshold: 0.01,
+            model: earth.clone(),
+        },
+        Scenario {
+            name: "south pole",
+            view_position: geodetic(&earth, -90.0, 0.0, 100.0),
+            origin_lod: 10,
+            sample_radius: 0.001 * earth.scale(),
+            threshold: 0.01,
+            model: earth.clone(),
+        },
+        // a few meters above the ground, where the tiles are a few centimeters large
+        Scenario {
+            name: "deep lod",
+            view_position: geodetic(&earth, 47.2692, 11.4041, 2.0),
+            origin_lod: 20,
+            sample_radius: 100.0,
+            threshold: 0.001,
+            model: earth.clone(),
+        },
+        Scenario {
+            name: "deep lod at edge",
+            view_position: above(&earth, 2, DVec2::new(0.0, 0.25), 2.0),
+            origin_lod: 20,
+            sample_radius: 100.0,
+            threshold: 0.001,
+            model: earth.clone(),
+        },
+        Scenario {
+            name: "large radius",
+            view_position: above(&jupiter, 4, DVec2::new(0.3, 0.7), 1000.0),
+            origin_lod: 14,
+            sample_radius: 0.001 * earth.scale(),
+            threshold: 0.01,
+            model: jupiter.clone(),
+        },
+        Scenario {
+            name: "large radius at edge",
+            view_position: above(&jupiter, 4, DVec2::new(0.0, 0.7), 1000.0),
+            origin_lod: 14,
+            sample_radius: 0.001 * earth.scale(),
+            threshold: 0.01,
+            model: jupiter,
+        },
+    ]
+}
+
+// Position on the surface within the radius around the view, projected down along the normal.
+fn random_surface_position(
+    rng: &mut impl Rng,
+    model: &TerrainModel,
+    view_position: DVec3,
+    radius: f64,
+) -> DVec3 {
+    let direction = DVec3::new(
+        rng.gen_range(-1.0..1.0),
+        rng.gen_range(-1.0..1.0),
+        rng.gen_range(-1.0..1.0),
+    )
+    .normalize();
+
+    model.position_local_to_world(
+        model.position_world_to_local(view_position + rng.gen_range(0.0..radius) * direction),
+        0.0,
+    )
+}
+
+fn run_scenario(scenario: &Scenario, rng: &mut impl Rng) -> Outcome {
+    let Scenario {
+        model,
+        view_position,
+        origin_lod,
+        ..
+    } = scenario;
+
+    let view_coordinates = view_coordinates(*view_position, model);
+    let approximations = approximations(&view_coordinates, *view_position, model);
+
+    let (error, worst_position) = (0..SURFACE_SAMPLES)
+        .map(|_| {
+            let position =
+                random_surface_position(rng, model, *view_position, scenario.sample_radius);
+            let approximated = approximate_world_position(
+                &view_coordinates,
+                &approximations,
+                *origin_lod,
+                true,
+                *view_position,
+                tile_coordinate_from_world_position(position, *origin_lod, model),
+            );
+
+            (position.distance(approximated), position)
+        })
+        .fold((0.0, *view_position), |worst, sample| {
+            if sample.0 > worst.0 {
+                sample
+            } else {
+                worst
+            }
+        });
+
+    let (worst_tile, worst_uv) =
+        tile_coordinate_from_world_position(worst_position, *origin_lod, model);
+
+    Outcome {
+        name: scenario.name.to_string(),
+        error,
+        threshold: scenario.threshold,
+        details: vec![
+            format!(
+                "view at {}, origin lod {origin_lod}",
+                Geodetic::from_world_position(*view_position, model)
+            ),
+            format!(
+                "samples within {} of the view",
+                Length(scenario.sample_radius)
+            ),
+            format!(
+                "largest error at {} in {} at {:.4},{:.4}, {} from the view",
+                Geodetic::from_world_position(worst_position, model),
+                TileName(worst_tile),
+                worst_uv.x,
+                worst_uv.y,
+                Length(worst_position.distance(*view_position))
+            ),
+        ],
+    }
+}
+
+// The round trips of the coordinate mappings, which the scenarios rely on.
+fn validation_outcomes() -> Vec<Outcome> {
+    let model = earth();
+    let outcome = |name: &str, error, threshold| Outcome {
+        name: name.to_string(),
+        error,
+        threshold,
+        details: Vec::new(),
+    };
+
+    let projection = validate::project_to_face_consistency(&model, VALIDATION_RESOLUTION)
+        .into_iter()
+        .flatten()
+        .fold(0.0, f64::max);
+
+    vec![
+        outcome(
+            "cube sphere round trip",
+            validate::cube_sphere_round_trip(VALIDATION_RESOLUTION),
+            1e-12,
+        ),
+        outcome(
+            "world position round trip",
+            validate::world_position_round_trip(&model, VALIDATION_RESOLUTION, 1000.0),
+            1e-6,
+        ),
+        outcome("face projection round trip", projection, 1e-9),
+        outcome(
+            "library mapping",
+            validate::library_mapping_consistency(&model, VALIDATION_RESOLUTION),
+            1e-9,
+        ),
+        // both paths perform the same operations, so they have to agree exactly
+        outcome(
+            "simd kernel",
+            validate::simd_consistency(&model, VALIDATION_RESOLUTION),
+            0.0,
+        ),
+        // in texels of a 256 texel tile with a margin of 2 texels
+        outcome(
+            "tile margin",
+            validate::margin_consistency(TileMargin::new(260, 2)),
+            0.01,
+        ),
+        // the axes are compared against finite differences
+        outcome(
+            "face rotation",
+            validate::face_rotation_consistency(&model),
+            1e-6,
+        ),
+    ]
+}
+
+// Replays every fixture in the directory against the model it was recorded with.
+// A fixture has to reproduce its coefficients, and the errors of its samples must not grow beyond the recorded ones.
+fn fixture_outcomes() -> Vec<Outcome> {
+    let Ok(entries) = std::fs::read_dir(FIXTURE_DIRECTORY) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .sorted()
+        .map(|path| {
+            let name = format!(
+                "fixture {}",
+                path.file_stem().unwrap_or_default().to_string_lossy()
+            );
+            let replay = ApproximationFixture::load(&path).and_then(|fixture| {
+                let model = [earth(), jupiter()]
+                    .into_iter()
+                    .find(|model| model_hash(model) == fixture.model_hash)
+                    .unwrap_or_else(earth);
+                let replay = fixture.replay(&model)?;
+
+                Ok((fixture, replay))
+            });
+
+            match replay {
+                Ok((fixture, replay)) => Outcome {
+                    name,
+                    error: replay.regression(&fixture),
+                    threshold: REPLAY_TOLERANCE,
+                    details: vec![
+                        format!(
+                            "the view coordinates deviate by {:e} and the coefficients by {:e} from the recorded ones",
+                            replay.view_deviation, replay.coefficient_deviation
+                        ),
+                        format!(
+                            "{} samples were recorded with a largest error of {}, and replay with one of {}",
+                            fixture.samples.len(),
+                            Length(fixture.samples.iter().map(|sample| sample.error).fold(0.0, f64::max)),
+                            Length(replay.max_error())
+                        ),
+                    ],
+                },
+                Err(error) => Outcome {
+                    name,
+                    error: f64::INFINITY,
+                    threshold: 0.0,
+                    details: vec![error.to_string()],
+                },
+            }
+        })
+        .collect()
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let outcomes = validation_outcomes()
+        .into_iter()
+        .chain(fixture_outcomes())
+        .chain(
+            scenarios()
+                .iter()
+                .map(|scenario| run_scenario(scenario, &mut rng)),
+        )
+        .collect_vec();
+
+    println!("{:<28} {:>14} {:>14}", "scenario", "error", "threshold");
+    for outcome in &outcomes {
+        println!(
+            "{:<28} {:>14.6e} {:>14.6e} {}",
+            outcome.name,
+            outcome.error,
+            outcome.threshold,
+            if outcome.passed() { "ok" } else { "FAILED" }
+        );
+    }
+
+    let failures = outcomes
+        .iter()
+        .filter(|outcome| !outcome.passed())
+        .collect_vec();
+
+    if failures.is_empty() {
+        println!("All {} checks passed.", outcomes.len());
+        return;
+    }
+
+    for failure in &failures {
+        println!();
+        println!(
+            "{} exceeds its threshold of {:e} with an error of {:e}.",
+            failure.name, failure.threshold, failure.error
+        );
+        for detail in &failure.details {
+            println!("  {detail}");
+        }
+    }
+
+    println!();
+    println!("{} of {} checks failed.", failures.len(), outcomes.len());
+    std::process::exit(1);
+}
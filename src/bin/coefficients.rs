@@ -0,0 +1,90 @@
+use bevy::math::{DVec2, DVec3, Vec3};
+use bevy_terrain::{
+    math::{Coordinate, SurfaceApproximation},
+    prelude::*,
+};
+use itertools::iproduct;
+use precision_demo::math::approximations;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+// Samples the surface approximation coefficients over a grid of view positions and writes them to a CSV file,
+// which can be loaded with `numpy.genfromtxt(path, delimiter=",", names=True)`.
+// Usage: coefficients [output.csv] [grid resolution per face] [altitudes in meters, comma separated]
+
+fn main() -> std::io::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let path = args
+        .next()
+        .unwrap_or_else(|| "coefficients.csv".to_string());
+    let resolution: u32 = args.next().map_or(16, |arg| arg.parse().unwrap());
+    let altitudes: Vec<f64> = args.next().map_or(vec![0.0, 1000.0, 100000.0], |arg| {
+        arg.split(',')
+            .map(|altitude| altitude.parse().unwrap())
+            .collect()
+    });
+
+    let model = TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0);
+
+    let mut writer = BufWriter::new(File::create(&path)?);
+
+    write!(writer, "view_face,view_u,view_v,altitude,face")?;
+    for name in ["c", "c_du", "c_dv", "c_duu", "c_duv", "c_dvv"] {
+        write!(writer, ",{name}_x,{name}_y,{name}_z,{name}_norm")?;
+    }
+    writeln!(writer)?;
+
+    let mut count = 0;
+
+    for (view_face, x, y, &altitude) in iproduct!(0..6, 0..resolution, 0..resolution, &altitudes) {
+        let view_uv = (DVec2::new(x as f64, y as f64) + 0.5) / resolution as f64;
+        let view_coordinate = Coordinate::new(view_face, view_uv);
+        let view_position = view_coordinate.world_position(&model, altitude as f32);
+
+        let view_coordinates =
+            std::array::from_fn(|face| view_coordinate.project_to_face(face as u32, &model));
+        let approximations = approximations(&view_coordinates, view_position, &model);
+
+        for (face, approximation) in approximations.iter().enumerate() {
+            let &SurfaceApproximation {
+                c,
+                c_du,
+                c_dv,
+                c_duu,
+                c_duv,
+                c_dvv,
+            } = approximation;
+
+            write!(
+                writer,
+                "{view_face},{},{},{altitude},{face}",
+                view_uv.x, view_uv.y
+            )?;
+            for coefficient in [c, c_du, c_dv, c_duu, c_duv, c_dvv] {
+                write_coefficient(&mut writer, coefficient)?;
+            }
+            writeln!(writer)?;
+
+            count += 1;
+        }
+    }
+
+    writer.flush()?;
+
+    println!("Wrote {count} approximations to {path}.");
+
+    Ok(())
+}
+
+fn write_coefficient(writer: &mut impl Write, coefficient: Vec3) -> std::io::Result<()> {
+    write!(
+        writer,
+        ",{},{},{},{}",
+        coefficient.x,
+        coefficient.y,
+        coefficient.z,
+        coefficient.length()
+    )
+}
@@ -1,115 +1,2102 @@
 #![allow(dead_code, unused_variables)]
 
-use bevy::{math::DVec3, prelude::*};
+use bevy::{
+    color::palettes::basic,
+    core::FrameCount,
+    diagnostic::{Diagnostics, DiagnosticsStore, FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    math::{DMat3, DVec2, DVec3},
+    prelude::*,
+    render::view::NoFrustumCulling,
+    window::PrimaryWindow,
+};
 use bevy_terrain::{
-    big_space::{GridTransformReadOnly, ReferenceFrames},
-    math::{Coordinate, SurfaceApproximation},
+    big_space::{FloatingOrigin, GridCell, GridTransform, GridTransformReadOnly, ReferenceFrames},
+    math::Coordinate,
     prelude::*,
 };
 use itertools::Itertools;
-use precision_demo::draw::{draw_approximation, draw_earth};
+use precision_demo::{
+    anchor::{extruded_footprint, AnchoredMesh},
+    animated::{AnimatedArm, AnimatedArmPlugin},
+    annotation::{Annotation, AnnotationPlugin},
+    budget::{PrecisionBudget, PrecisionBudgetPlugin},
+    clock::{SimulationTime, SimulationTimePlugin},
+    collider::{integrate_rigid_bodies, RigidBody, TerrainCollider},
+    culling::{cull_tiles, Occluded, TileCulling, TileCullingPlugin},
+    debug_log::{precision_state, CameraStamp, ViewStamp},
+    depth::{update_near_plane, AdaptiveNearPlane},
+    deterministic::{DeterministicPlugin, InputRecording, InputScript, SimulationRng},
+    diagnostics::{
+        probe_lod_errors, probe_max_error, ApproximationFallbacks, OriginTileSwitches,
+        PrecisionDiagnosticsPlugin, ORIGIN_TILE_SWITCHES, PROBE_MAX_ERROR,
+    },
+    draw::{
+        draw_approximation, draw_approximation_orders, draw_earth, draw_polylines, draw_tile,
+        draw_tile_errors, empty_line_mesh, LineMaterial, LineMesh, LinePlugin, PrecisionGizmos,
+    },
+    epoch::{shift_model, EpochPlugin, Rebase, RebaseAppExt, SceneEpoch},
+    error_field::{
+        empty_error_field_mesh, error_color, error_field_material, update_error_field, ErrorField,
+        ErrorFieldShape,
+    },
+    features::{Feature, FeatureStamps},
+    format::Length,
+    geodesy::{enu_frame, horizon_pose, with_axes, Geodetic},
+    geojson::{load_polylines, Polyline},
+    golden::{GoldenPlugin, GoldenPose},
+    grid::entity_distance,
+    height::{ConstantHeight, HeightProvider, TerrainHeights},
+    impostor::{impostor_bundle, update_impostors, Impostor},
+    input_focus::InputFocusPlugin,
+    labels::{LabelPlugin, WorldLabel},
+    landing_site::{LandingSite, LandingSitePlugin, SiteLocal},
+    math::{tile_coordinate_from_world_position, tile_count},
+    measure::{measure, Measurement},
+    model_copy::{ModelCopyAppExt, ModelEdited},
+    ocean::{empty_ocean_mesh, ocean_material, update_ocean, Ocean},
+    orbit::{
+        draw_ground_tracks, draw_orbits, propagate_orbits, GroundTrack, Orbit, OrbitPath,
+        EARTH_GRAVITATIONAL_PARAMETER,
+    },
+    origin_lod::{find_min_origin_lod, OriginLodTable, OriginLodTransition, MAX_ORIGIN_LOD},
+    particles::{ParticleCloud, ParticlePlugin},
+    probe::{tile_at_lod, tile_uv_to_uv, LodHierarchy, Probe, VertexSnap},
+    raycast::pick_terrain,
+    replay::{Replay, ReplayState},
+    scene_check::{SceneCheckPlugin, SceneExpectations},
+    second_view::{SecondView, SecondViewBundle, SecondViewPlugin},
+    shadows::{update_planet_shadows, PlanetShadows, ShadowApproximation},
+    stamped_info, stamped_warn,
+    stereo::{StereoPlugin, StereoRig},
+    tile_mesh::{tile_bundles, tile_material, update_tile_origins, TileOrigin},
+    tile_prepass::{CompactTile, PrepassOrigin, TileList, TilePrepassPlugin},
+    track::{record_camera_track, CameraTrack, Placemark},
+    vehicle::{drive_vehicles, Vehicle},
+    view_approximation::{ApproximationScheduler, ApproximationSnapshot, ViewApproximation},
+};
 
 const RADIUS: f64 = 6371000.0;
-const ORIGIN_LOD: i32 = 8;
+// the demo has no calendar time, so exported tracks start at a fixed epoch
+const TRACK_EPOCH: &str = "2024-01-01T00:00:00Z";
+// distance the view has to move, before the debug drawing is rebuilt
+const REDRAW_DISTANCE: f64 = 0.01;
+// maximum error of the approximation, which the automatic origin lod aims for
+const ORIGIN_LOD_BUDGET: f64 = 0.01;
+// altitude below which the landing site is frozen under the camera
+const LANDING_SITE_ALTITUDE: f64 = 1000.0;
+// lods probed below and above the origin lod by the multi-resolution probe
+const PROBE_LOD_SPREAD: u32 = 2;
+// the goldens are rendered at this window size, and have to be compared at the same one
+const GOLDEN_RESOLUTION: (f32, f32) = (1280.0, 720.0);
 
 #[derive(Component)]
 struct Model(TerrainModel);
 
+impl Rebase for Model {
+    fn rebase(&mut self, offset: DVec3) {
+        self.0 = shift_model(&self.0, offset);
+    }
+}
+
+// Semi-axes of the model, which can be edited at runtime to rebuild the model.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct ModelAxes {
+    major_axis: f64,
+    minor_axis: f64,
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct DrawSettings {
+    freeze: bool,
+    show_error: bool,
+    // factor the errors of the error field are exaggerated by
+    error_exaggeration: f32,
+    error_shape: ErrorFieldShape,
+    // picks the origin lod from the altitude of the view, using the `OriginLodTable`
+    auto_origin_lod: bool,
+    origin_lod: u32,
+    // moves the near plane with the altitude, instead of keeping it at a fixed distance
+    adaptive_near_plane: bool,
+}
+
+impl Default for DrawSettings {
+    fn default() -> Self {
+        Self {
+            freeze: false,
+            show_error: false,
+            error_exaggeration: 0.0,
+            error_shape: ErrorFieldShape::Grid,
+            auto_origin_lod: false,
+            origin_lod: 8,
+            adaptive_near_plane: true,
+        }
+    }
+}
+
+#[derive(Component)]
+struct Satellite;
+
+// A label of the retained drawing, which is replaced whenever the drawing is rebuilt.
+#[derive(Component)]
+struct DrawingLabel;
+
+// Text readout of the view in the corner of the screen.
+#[derive(Component)]
+struct Hud;
+
+// Text readout of the second view, in the corner of its window.
+#[derive(Component)]
+struct SecondViewHud;
+
+// The debug drawing, which is only tessellated again when it changes.
+// Its lines are relative to the entity and uploaded into the line mesh of the entity, whenever they are rebuilt.
+#[derive(Component, Default)]
+struct RetainedDrawing(LineMesh);
+
+#[derive(Resource, Default)]
+struct Overlay(Vec<Polyline>);
+
+// The view at the time the drawing was frozen.
+struct FrozenView {
+    position: DVec3,
+    rotation: Quat,
+    projection: PerspectiveProjection,
+    origin_lod: u32,
+}
+
+// number of dust particles around the camera, raise it with --particles to stress the rebasing
+#[derive(Resource)]
+struct ParticleCount(usize);
+
+// where the camera starts, given with --start <latitude> <longitude> <altitude> in degrees and meters
+#[derive(Resource)]
+struct CameraStart(Option<Geodetic>);
+
+// The seed and initial state of a recorded run, which are saved with its input.
+#[derive(Resource)]
+struct ReplayStart {
+    seed: u64,
+    state: Option<ReplayState>,
+}
+
+#[derive(Resource, Reflect, Default, Clone, Copy, PartialEq, Eq)]
+#[reflect(Resource)]
+enum RenderPath {
+    // vertices are approximated relative to the view with the Taylor series of the surface
+    #[default]
+    Taylor,
+    // vertices are stored relative to their tile origin, which is supplied per tile in double-split form
+    RelativeToTile,
+}
+
 fn main() {
-    App::new()
-        .add_plugins((
-            DefaultPlugins.build().disable::<TransformPlugin>(),
-            TerrainPlugin,
-            TerrainDebugPlugin,
-        ))
-        .add_systems(Startup, setup)
-        .add_systems(Update, update)
-        .run();
+    let overlay = std::env::args()
+        .skip_while(|arg| arg != "--geojson")
+        .nth(1)
+        .map(|path| Overlay(load_polylines(path).unwrap_or_else(|error| panic!("{error}"))))
+        .unwrap_or_default();
+
+    let approximation_rate = std::env::args()
+        .skip_while(|arg| arg != "--approximation-rate")
+        .nth(1)
+        .map_or(64.0, |rate| rate.parse().expect("the rate is not a number"));
+
+    let approximation_budget = std::env::args()
+        .skip_while(|arg| arg != "--approximation-budget")
+        .nth(1)
+        .map_or(6, |budget| {
+            budget.parse().expect("the budget is not an integer")
+        });
+
+    let update_goldens = std::env::args().any(|arg| arg == "--update-goldens");
+    let golden = update_goldens || std::env::args().any(|arg| arg == "--golden");
+
+    let window = if golden {
+        Window {
+            resolution: GOLDEN_RESOLUTION.into(),
+            resizable: false,
+            ..default()
+        }
+    } else {
+        default()
+    };
+
+    let particle_count = std::env::args()
+        .skip_while(|arg| arg != "--particles")
+        .nth(1)
+        .map_or(500, |count| {
+            count.parse().expect("the count is not an integer")
+        });
+
+    let camera_start = std::env::args()
+        .skip_while(|arg| arg != "--start")
+        .skip(1)
+        .take(3)
+        .map(|value| value.parse::<f64>().expect("the start is not a number"))
+        .collect_tuple()
+        .map(|(latitude, longitude, altitude)| {
+            Geodetic::from_degrees(latitude, longitude, altitude)
+        });
+
+    let mut app = App::new();
+
+    app.add_plugins((
+        DefaultPlugins
+            .build()
+            .disable::<TransformPlugin>()
+            .set(WindowPlugin {
+                primary_window: Some(window),
+                ..default()
+            }),
+        TerrainPlugin,
+        TerrainDebugPlugin,
+        TileCullingPlugin,
+        TilePrepassPlugin,
+        InputFocusPlugin,
+        LabelPlugin,
+        AnnotationPlugin,
+        SceneCheckPlugin,
+        PrecisionDiagnosticsPlugin,
+        PrecisionBudgetPlugin,
+        SimulationTimePlugin,
+        ParticlePlugin,
+        AnimatedArmPlugin,
+        (EpochPlugin, LandingSitePlugin, LinePlugin),
+    ))
+    // the copies of the terrain model kept by the systems of the demo, the plugins register their own
+    .add_model_copy::<TerrainCollider>()
+    .add_model_copy::<Ocean>()
+    .add_model_copy::<ErrorField>()
+    .add_model_copy::<GroundTrack>()
+    .add_model_copy::<Vehicle>()
+    .add_model_copy::<PlanetShadows>()
+    .add_model_copy::<Impostor>()
+    .add_model_copy::<AdaptiveNearPlane>()
+    .add_model_copy_resource::<CameraTrack>()
+    .add_model_copy_resource::<TerrainHeights>()
+    // the positions and models moved along with the scene epoch, the plugins register their own
+    .add_rebase::<Model>()
+    .add_rebase::<TerrainCollider>()
+    .add_rebase::<Ocean>()
+    .add_rebase::<ErrorField>()
+    .add_rebase::<Orbit>()
+    .add_rebase::<GroundTrack>()
+    .add_rebase::<Vehicle>()
+    .add_rebase::<PlanetShadows>()
+    .add_rebase::<ShadowApproximation>()
+    .add_rebase::<Impostor>()
+    .add_rebase::<AdaptiveNearPlane>()
+    .add_rebase_resource::<CameraTrack>()
+    .add_rebase_resource::<TerrainHeights>()
+    .add_rebase_resource::<ViewApproximation>()
+    .add_rebase_resource::<ApproximationScheduler>()
+    .insert_resource(overlay)
+    .insert_resource(TileCulling::new(
+        earth_model(),
+        earth_heights().height_range().1,
+    ))
+    .insert_resource(TerrainHeights(Box::new(earth_heights())))
+    .insert_resource(CameraTrack::new(earth_model()))
+    .insert_resource(LandingSite::new(earth_model(), LANDING_SITE_ALTITUDE))
+    .insert_resource(OriginLodTransition::new(DrawSettings::default().origin_lod))
+    .insert_resource(SceneExpectations {
+        model: earth_model(),
+        origin_lod: DrawSettings::default().origin_lod,
+        // the vehicles drive on the surface, with the camera a few meters above it
+        min_altitude: 1.0,
+        budget: ORIGIN_LOD_BUDGET,
+    })
+    .insert_resource(ParticleCount(particle_count))
+    .insert_resource(CameraStart(camera_start))
+    .init_resource::<SimulationRng>()
+    .init_resource::<RenderPath>()
+    .init_resource::<DrawSettings>()
+    .init_resource::<Probe>()
+    .init_resource::<Measurement>()
+    .init_resource::<CameraStamp>()
+    .init_gizmo_group::<PrecisionGizmos>()
+    .init_resource::<ViewApproximation>()
+    .insert_resource(ApproximationScheduler::new(approximation_budget))
+    .insert_resource(Time::<Fixed>::from_hz(approximation_rate))
+    .add_systems(Startup, (compute_origin_lod_table, setup))
+    .add_systems(FixedUpdate, update_view_approximation)
+    .add_systems(
+        Update,
+        (
+            (
+                apply_model_axes,
+                clamp_origin_lod,
+                rebase_epoch_on_view,
+                control_simulation_time,
+                propagate_orbits,
+                follow_satellite,
+                auto_origin_lod,
+                blend_origin_lod,
+                update,
+            )
+                .chain(),
+            update_ocean,
+            drive_vehicles,
+            integrate_rigid_bodies,
+            (update_impostors, toggle_render_path, update_tile_origins)
+                .chain()
+                .after(cull_tiles),
+            (configure_near_plane, update_near_plane).chain(),
+            update_planet_shadows,
+            update_tile_list.after(cull_tiles),
+            (record_camera_track, export_camera_track).chain(),
+            (
+                snap_probe,
+                pick_under_cursor,
+                move_probe,
+                draw_probe_hierarchy,
+            )
+                .chain(),
+            draw_measurement.after(pick_under_cursor),
+            update_hud.after(follow_satellite),
+            (update_camera_stamp, dump_precision_state)
+                .chain()
+                .after(follow_satellite),
+            measure_precision.after(move_probe),
+            (configure_gizmos, draw_frozen_view).chain().after(update),
+            upload_retained_drawing.after(update),
+            (draw_orbits, draw_ground_tracks).after(propagate_orbits),
+            (show_error_field, update_error_field).chain().after(update),
+        ),
+    );
+
+    if let Some(seed) = std::env::args()
+        .skip_while(|arg| arg != "--deterministic")
+        .nth(1)
+    {
+        let seed = seed.parse().expect("the seed is not an integer");
+
+        app.add_plugins(DeterministicPlugin::new(seed).with_script(demo_script()));
+    }
+
+    if let Some(seed) = std::env::args().skip_while(|arg| arg != "--record").nth(1) {
+        let seed = seed.parse().expect("the seed is not an integer");
+
+        app.add_plugins(DeterministicPlugin::new(seed).recording())
+            .insert_resource(ReplayStart { seed, state: None })
+            .add_systems(PostStartup, capture_replay_start)
+            .add_systems(Update, save_replay);
+    }
+
+    if let Some(path) = std::env::args().skip_while(|arg| arg != "--replay").nth(1) {
+        let replay = Replay::load(&path).unwrap_or_else(|error| panic!("{error}"));
+
+        app.add_plugins(DeterministicPlugin::new(replay.seed).with_script(replay.script.clone()))
+            .insert_resource(replay)
+            .add_systems(PostStartup, apply_replay_start)
+            .add_systems(Update, report_budget_violation.after(measure_precision));
+    }
+
+    if golden {
+        let plugin = GoldenPlugin::new(golden_poses(), "assets/golden");
+
+        app.add_plugins((
+            DeterministicPlugin::new(0),
+            if update_goldens {
+                plugin.updating()
+            } else {
+                plugin
+            },
+        ));
+    }
+
+    if std::env::args().any(|arg| arg == "--stereo") {
+        app.add_plugins(StereoPlugin)
+            .add_systems(Startup, enable_stereo.after(setup));
+    }
+
+    if std::env::args().any(|arg| arg == "--second-window") {
+        app.add_plugins(SecondViewPlugin)
+            .add_systems(Startup, spawn_second_view.after(setup))
+            .add_systems(Update, update_second_view_hud);
+    }
+
+    if std::env::args().any(|arg| arg == "--log-diagnostics") {
+        app.add_plugins((FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin::default()));
+    }
+
+    #[cfg(feature = "inspector")]
+    app.add_plugins(precision_demo::inspector::InspectorPlugin)
+        .register_type::<ModelAxes>()
+        .register_type::<DrawSettings>()
+        .register_type::<RenderPath>();
+
+    app.run();
+}
+
+// Drives the vehicle for ten seconds and follows the satellite for a while, at 60 frames per second.
+fn demo_script() -> InputScript {
+    InputScript::default()
+        .hold(60, 360, KeyCode::ArrowUp)
+        .hold(180, 240, KeyCode::ArrowLeft)
+        .hold(360, 660, KeyCode::ArrowDown)
+        .tap(700, KeyCode::KeyT)
+        .tap(1300, KeyCode::KeyT)
 }
 
-fn setup(mut commands: Commands) {
-    let model = TerrainModel::ellipsoid(
+// The table is searched at startup, so that its samples are drawn from the `SimulationRng` of the run.
+fn compute_origin_lod_table(mut commands: Commands, mut rng: ResMut<SimulationRng>) {
+    commands.insert_resource(find_min_origin_lod(
+        &earth_model(),
+        1.0..1e7,
+        0.001 * RADIUS,
+        ORIGIN_LOD_BUDGET,
+        &mut rng.0,
+    ));
+}
+
+fn earth_model() -> TerrainModel {
+    TerrainModel::ellipsoid(
         DVec3::new(0.0, 1.0, 1.0),
         6378137.0,
         6356752.314245,
         0.0,
         0.0,
-    );
+    )
+}
+
+fn earth_heights() -> FeatureStamps {
+    let model = earth_model();
+    let coordinate = |latitude, longitude| {
+        Coordinate::from_world_position(
+            Geodetic::from_degrees(latitude, longitude, 0.0).world_position(&model),
+            &model,
+        )
+    };
+
+    FeatureStamps::new(earth_model(), Box::new(ConstantHeight(0.0)))
+        .with_feature(Feature::crater(coordinate(47.25, 11.35), 1200.0, 170.0))
+        .with_feature(Feature::volcano(coordinate(47.30, 11.45), 3000.0, 1500.0))
+}
+
+fn placemarks() -> Vec<Placemark> {
+    vec![
+        Placemark::new("Crater", Geodetic::from_degrees(47.25, 11.35, 0.0)),
+        Placemark::new("Volcano", Geodetic::from_degrees(47.30, 11.45, 3000.0)),
+        Placemark::new(
+            "Vehicle Start",
+            Geodetic::from_degrees(47.2692, 11.4041, 0.0),
+        ),
+    ]
+}
+
+// Poses covering the whole range of distances, from the planet down to the building next to the vehicle.
+fn golden_poses() -> Vec<GoldenPose> {
+    let model = earth_model();
+    let at = |latitude, longitude, height| {
+        Geodetic::from_degrees(latitude, longitude, height).world_position(&model)
+    };
+    let up = |latitude, longitude| {
+        enu_frame(Geodetic::from_degrees(latitude, longitude, 0.0), &model).z_axis
+    };
+    let north = |latitude, longitude| {
+        enu_frame(Geodetic::from_degrees(latitude, longitude, 0.0), &model).y_axis
+    };
+    let pole = model
+        .world_from_local
+        .transform_vector3(DVec3::Y)
+        .normalize();
+
+    vec![
+        GoldenPose::looking_at("planet", -DVec3::X * RADIUS * 3.0, model.position(), pole),
+        GoldenPose::looking_at(
+            "orbit",
+            at(47.27, 11.40, 1000000.0),
+            at(47.27, 11.40, 0.0),
+            north(47.27, 11.40),
+        ),
+        GoldenPose::looking_at(
+            "horizon",
+            at(47.27, 11.40, 400000.0),
+            at(47.27, 30.0, 0.0),
+            up(47.27, 11.40),
+        ),
+        GoldenPose::looking_at(
+            "valley",
+            at(47.2692, 11.4041, 2000.0),
+            at(47.30, 11.45, 3000.0),
+            up(47.2692, 11.4041),
+        ),
+        GoldenPose::looking_at(
+            "building",
+            at(47.2690, 11.4040, 20.0),
+            at(47.26955, 11.4045, 7.5),
+            up(47.2690, 11.4040),
+        ),
+    ]
+}
+
+fn setup(
+    particles: Res<ParticleCount>,
+    camera_start: Res<CameraStart>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut line_materials: ResMut<Assets<LineMaterial>>,
+    heights: Res<TerrainHeights>,
+) {
+    let model = earth_model();
+    let tile_material = materials.add(tile_material());
+
+    // the second section holds the measurement readout, the third the errors of the probe hierarchy
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new("", TextStyle::default()),
+            TextSection::new("", TextStyle::default()),
+            TextSection::new("", TextStyle::default()),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        }),
+        Hud,
+    ));
 
     commands.spawn_big_space(ReferenceFrame::default(), |root| {
         let frame = root.frame().clone();
 
         let (earth_cell, earth_translation) = frame.translation_to_grid(model.position());
 
+        // the drawing moves with the view, so it is never culled against the bounds it was built with
+        root.spawn_spatial((
+            MaterialMeshBundle {
+                mesh: meshes.add(empty_line_mesh()),
+                material: line_materials.add(LineMaterial::default()),
+                ..default()
+            },
+            NoFrustumCulling,
+            RetainedDrawing::default(),
+        ));
+
+        // dust around the camera, which follows it into every cell it crosses
+        root.spawn_spatial(ParticleCloud::new(particles.0, 20.0, basic::OLIVE));
+
+        for placemark in placemarks() {
+            let Geodetic {
+                latitude,
+                longitude,
+                height,
+            } = placemark.position;
+            let ground = Geodetic::new(latitude, longitude, 0.0).world_position(&model);
+
+            // the marker floats above the placemark, with the leader line reaching down to the reference surface
+            root.spawn_spatial(Annotation::new(
+                earth_model(),
+                Coordinate::from_world_position(ground, &model),
+                height + 1000.0,
+                placemark.name,
+                Color::WHITE,
+            ));
+        }
+
+        // a building next to the start of the vehicle, placed with an anchor instead of absolute positions
+        let (points, indices) = extruded_footprint(
+            &[
+                Geodetic::from_degrees(47.2694, 11.4043, 0.0),
+                Geodetic::from_degrees(47.2694, 11.4047, 0.0),
+                Geodetic::from_degrees(47.2697, 11.4047, 0.0),
+                Geodetic::from_degrees(47.2697, 11.4043, 0.0),
+            ],
+            15.0,
+            &model,
+        );
+        let building = AnchoredMesh::new(&points, indices);
+        // an animated arm of eight links on the roof of the building, to check that deep hierarchies do not jitter
+        let arm_base = Geodetic::from_degrees(47.26955, 11.4045, 15.0);
+        let enu = enu_frame(arm_base, &model);
+        let (arm_cell, arm_translation) =
+            frame.translation_to_grid(arm_base.world_position(&model));
+
+        root.spawn_spatial((
+            SpatialBundle::from_transform(Transform {
+                translation: arm_translation,
+                // the arm points up along the surface normal
+                rotation: Quat::from_mat3(
+                    &DMat3::from_cols(enu.x_axis, enu.z_axis, -enu.y_axis).as_mat3(),
+                ),
+                ..default()
+            }),
+            arm_cell,
+            AnimatedArm::new(8, 1.0),
+            Name::new("Arm"),
+            PrecisionBudget::new(ORIGIN_LOD_BUDGET),
+        ));
+
+        root.spawn_spatial(building.bundle(
+            &frame,
+            &mut meshes,
+            materials.add(StandardMaterial::from_color(basic::MAROON)),
+        ));
+
+        for face in 0..6 {
+            let (cell, translation) = frame.translation_to_grid(
+                Coordinate::new(face, DVec2::splat(0.5)).world_position(&model, 0.0),
+            );
+
+            root.spawn_spatial((
+                cell,
+                Transform::from_translation(translation),
+                WorldLabel::new(format!("Face {face}"), basic::SILVER.into()),
+            ));
+        }
+
         root.spawn_spatial((
-            Model(model),
+            PbrBundle {
+                mesh: meshes.add(empty_error_field_mesh()),
+                material: materials.add(error_field_material()),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            ErrorField::new(earth_model(), DrawSettings::default().origin_lod),
+        ));
+
+        root.spawn_spatial((
+            Model(earth_model()),
+            ModelAxes {
+                major_axis: 6378137.0,
+                minor_axis: 6356752.314245,
+            },
+            TerrainCollider {
+                model: earth_model(),
+            },
             earth_cell,
             Transform::from_translation(earth_translation),
         ));
 
-        root.spawn_spatial(DebugCameraBundle::new(
-            -DVec3::X * RADIUS * 3.0,
-            RADIUS,
-            &frame,
+        root.spawn_spatial((
+            PbrBundle {
+                mesh: meshes.add(empty_ocean_mesh()),
+                material: materials.add(ocean_material()),
+                ..default()
+            },
+            Ocean::new(earth_model()),
+        ));
+
+        let satellite_orbit = Orbit::circular(model.position(), 6778000.0, 51.6_f64.to_radians());
+
+        root.spawn_spatial((
+            PbrBundle {
+                mesh: meshes.add(Cuboid::from_length(20.0)),
+                material: materials.add(StandardMaterial::from_color(basic::RED)),
+                ..default()
+            },
+            satellite_orbit,
+            OrbitPath::new(basic::RED),
+            // one orbit behind and one ahead of the satellite
+            GroundTrack::new(earth_model(), 2.0 * satellite_orbit.period(), basic::MAROON),
+            Satellite,
         ));
+
+        // the moon on its mean orbit, far enough away to show whether its path stays on it
+        root.spawn_spatial((
+            PbrBundle {
+                mesh: meshes.add(Sphere::new(1737400.0).mesh().ico(16).unwrap()),
+                material: materials.add(StandardMaterial::from_color(basic::GRAY)),
+                ..default()
+            },
+            Orbit {
+                center: model.position(),
+                gravitational_parameter: EARTH_GRAVITATIONAL_PARAMETER + 4.9048695e12,
+                semi_major_axis: 384399000.0,
+                eccentricity: 0.0549,
+                inclination: 5.145_f64.to_radians(),
+                longitude_of_ascending_node: 0.0,
+                argument_of_periapsis: 0.0,
+                mean_anomaly_at_epoch: 0.0,
+            },
+            OrbitPath::new(basic::SILVER),
+        ));
+
+        root.spawn_spatial((
+            PbrBundle {
+                mesh: meshes.add(Cuboid::new(2.0, 1.5, 4.0)),
+                material: materials.add(StandardMaterial::from_color(basic::YELLOW)),
+                ..default()
+            },
+            Vehicle::new(
+                earth_model(),
+                Geodetic::from_degrees(47.2692, 11.4041, 0.0),
+                0.0,
+            ),
+            Name::new("Vehicle"),
+            PrecisionBudget::new(ORIGIN_LOD_BUDGET),
+            SiteLocal::default(),
+        ));
+
+        let (ball_cell, ball_translation) = frame.translation_to_grid(
+            Geodetic::from_degrees(47.2692, 11.4041, 50.0).world_position(&model),
+        );
+
+        root.spawn_spatial((
+            PbrBundle {
+                mesh: meshes.add(Sphere::new(1.0)),
+                material: materials.add(StandardMaterial::from_color(basic::FUCHSIA)),
+                transform: Transform::from_translation(ball_translation),
+                ..default()
+            },
+            ball_cell,
+            RigidBody {
+                radius: 1.0,
+                ..default()
+            },
+            Name::new("Ball"),
+            PrecisionBudget::new(ORIGIN_LOD_BUDGET),
+            SiteLocal::default(),
+        ));
+
+        root.spawn_spatial((
+            DirectionalLightBundle {
+                directional_light: DirectionalLight {
+                    shadows_enabled: true,
+                    ..default()
+                },
+                ..default()
+            },
+            PlanetShadows::new(earth_model(), DVec3::new(-1.0, 0.5, 0.3)),
+            ShadowApproximation::default(),
+        ));
+
+        let mut impostor = impostor_bundle(&model, &mut meshes, tile_material.clone());
+        impostor.transform.translation = earth_translation;
+
+        root.spawn_spatial((
+            impostor,
+            earth_cell,
+            Impostor::new(earth_model(), 10.0 * RADIUS),
+        ));
+
+        for (mut tile, tile_origin, occluded) in tile_bundles(
+            &mut meshes,
+            &tile_material,
+            3,
+            16,
+            &model,
+            heights.0.as_ref(),
+        ) {
+            tile.visibility = Visibility::Hidden;
+            root.spawn_spatial((tile, tile_origin, occluded));
+        }
+
+        let camera = match camera_start.0 {
+            Some(geodetic) => {
+                let (position, rotation) = horizon_pose(geodetic, &model);
+                let mut camera = DebugCameraBundle::new(position, RADIUS, &frame);
+                camera.camera.transform.rotation = rotation;
+                camera
+            }
+            None => DebugCameraBundle::new(-DVec3::X * RADIUS * 3.0, RADIUS, &frame),
+        };
+
+        root.spawn_spatial((camera, AdaptiveNearPlane::new(earth_model())));
     });
 }
 
+fn enable_stereo(mut commands: Commands, view_query: Query<Entity, With<FloatingOrigin>>) {
+    for view in &view_query {
+        commands.entity(view).insert(StereoRig::default());
+    }
+}
+
+// Opens a second window with an orbital overview of the planet, above the start of the primary camera,
+// to compare the precision far away from the floating origin with the one at the surface.
+fn spawn_second_view(
+    camera_start: Res<CameraStart>,
+    mut commands: Commands,
+    frame_query: Query<(Entity, &ReferenceFrame), Without<Parent>>,
+) {
+    let Ok((root, frame)) = frame_query.get_single() else {
+        warn!("Skipping spawn_second_view: expected exactly one reference frame root.");
+        return;
+    };
+
+    let model = earth_model();
+    let direction = camera_start.0.map_or(-DVec3::X, |geodetic| {
+        (geodetic.world_position(&model) - model.position()).normalize()
+    });
+    let pole = model
+        .world_from_local
+        .transform_vector3(DVec3::Y)
+        .normalize();
+    // looking straight down at a pole, the pole can not be up
+    let up = if direction.dot(pole).abs() > 0.99 {
+        model
+            .world_from_local
+            .transform_vector3(DVec3::X)
+            .normalize()
+    } else {
+        pole
+    };
+
+    let window = commands
+        .spawn(Window {
+            title: "Overview".into(),
+            ..default()
+        })
+        .id();
+
+    let view = commands
+        .spawn(SecondViewBundle::new(
+            model.clone(),
+            window,
+            model.position() + direction * RADIUS * 3.0,
+            model.position(),
+            up,
+            frame,
+        ))
+        .set_parent(root)
+        .id();
+
+    commands.spawn((
+        TextBundle::from_section("", TextStyle::default()).with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        }),
+        TargetCamera(view),
+        SecondViewHud,
+    ));
+}
+
+// Shows the altitude of the second view, how far off its rendered position is, and the error of its own approximation
+// around the probe, next to the one of the primary view.
+fn update_second_view_hud(
+    settings: Res<DrawSettings>,
+    probe: Res<Probe>,
+    approximation: Res<ViewApproximation>,
+    terrain_query: Query<&Model>,
+    view_query: Query<&SecondView>,
+    mut hud_query: Query<(&TargetCamera, &mut Text), With<SecondViewHud>>,
+) {
+    let Ok(Model(model)) = terrain_query.get_single() else {
+        warn_once!("Skipping update_second_view_hud: expected exactly one terrain model.");
+        return;
+    };
+
+    let primary_error = approximation
+        .current
+        .and_then(|snapshot| probe_max_error(&probe, &snapshot, settings.origin_lod, model));
+
+    for (target, mut hud) in &mut hud_query {
+        let Ok(view) = view_query.get(target.entity()) else {
+            continue;
+        };
+        let Some(snapshot) = view.snapshot else {
+            continue;
+        };
+
+        let altitude = Geodetic::from_world_position(snapshot.view_position, model).height;
+        let error = probe_max_error(&probe, &snapshot, settings.origin_lod, model);
+
+        hud.sections[0].value = format!(
+            "Altitude: {}\nRendered position off by {}\nProbe error: {} (primary view {})",
+            Length(altitude),
+            Length(view.position_error),
+            error.map_or("-".to_string(), |error| Length(error).to_string()),
+            primary_error.map_or("-".to_string(), |error| Length(error).to_string()),
+        );
+    }
+}
+
+fn toggle_render_path(
+    mut render_path: ResMut<RenderPath>,
+    mut tile_query: Query<(&mut Visibility, &Occluded), With<TileOrigin>>,
+    impostor_query: Query<&Impostor>,
+    input: Res<ButtonInput<KeyCode>>,
+) {
+    if input.just_pressed(KeyCode::KeyR) {
+        *render_path = match *render_path {
+            RenderPath::Taylor => RenderPath::RelativeToTile,
+            RenderPath::RelativeToTile => RenderPath::Taylor,
+        };
+    }
+
+    let impostor_active = impostor_query.iter().any(|impostor| impostor.active);
+
+    for (mut visibility, occluded) in &mut tile_query {
+        *visibility =
+            if *render_path == RenderPath::RelativeToTile && !impostor_active && !occluded.0 {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+    }
+}
+
+fn update_tile_list(
+    mut tile_list: ResMut<TileList>,
+    render_path: Res<RenderPath>,
+    settings: Res<DrawSettings>,
+    approximation: Res<ViewApproximation>,
+    tile_query: Query<(&TileOrigin, &Occluded)>,
+) {
+    tile_list.tiles.clear();
+
+    if *render_path != RenderPath::RelativeToTile {
+        return;
+    }
+
+    // the origin tiles follow the view at the rate of the fixed update
+    let Some(ApproximationSnapshot {
+        view_coordinates, ..
+    }) = &approximation.current
+    else {
+        return;
+    };
+
+    tile_list.origin = PrepassOrigin::new(view_coordinates, settings.origin_lod);
+
+    tile_list.tiles.extend(
+        tile_query
+            .iter()
+            .filter(|(_, occluded)| !occluded.0)
+            .map(|(tile_origin, _)| CompactTile::from(tile_origin.tile)),
+    );
+}
+
+fn export_camera_track(track: Res<CameraTrack>, input: Res<ButtonInput<KeyCode>>) {
+    if !input.just_pressed(KeyCode::KeyK) {
+        return;
+    }
+
+    let placemarks = placemarks();
+
+    for (path, contents) in [
+        ("camera_track.kml", track.to_kml(&placemarks)),
+        ("camera_track.czml", track.to_czml(&placemarks, TRACK_EPOCH)),
+    ] {
+        match std::fs::write(path, contents) {
+            Ok(()) => info!("Exported {} track samples to {path}.", track.samples.len()),
+            Err(error) => error!("Failed to export the camera track to {path}: {error}"),
+        }
+    }
+}
+
+fn update_camera_stamp(
+    settings: Res<DrawSettings>,
+    mut stamp: ResMut<CameraStamp>,
+    frames: ReferenceFrames,
+    terrain_query: Query<&Model>,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+) {
+    let (Ok((view, view_transform)), Ok(Model(model))) =
+        (view_query.get_single(), terrain_query.get_single())
+    else {
+        stamp.0 = None;
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        stamp.0 = None;
+        return;
+    };
+
+    stamp.0 = Some(ViewStamp::new(
+        view_transform.position_double(&frame),
+        *view_transform.cell,
+        settings.origin_lod,
+        model,
+    ));
+}
+
+// Writes the model, approximation and camera to a file, which can be attached to bug reports.
+fn dump_precision_state(
+    input: Res<ButtonInput<KeyCode>>,
+    settings: Res<DrawSettings>,
+    stamp: Res<CameraStamp>,
+    approximation: Res<ViewApproximation>,
+    frames: ReferenceFrames,
+    terrain_query: Query<&Model>,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+) {
+    if !input.just_pressed(KeyCode::F9) {
+        return;
+    }
+
+    let (Ok((view, view_transform)), Ok(Model(model)), CameraStamp(Some(view_stamp))) =
+        (view_query.get_single(), terrain_query.get_single(), *stamp)
+    else {
+        warn!("Skipping dump_precision_state: expected exactly one camera and terrain model.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        return;
+    };
+
+    let state = precision_state(
+        model,
+        approximation.current.as_ref(),
+        view_transform.position_double(&frame),
+        view_transform.transform.rotation,
+        &view_stamp,
+        settings.origin_lod,
+    );
+
+    let path = "precision_state.json";
+    match serde_json::to_string_pretty(&state).map(|contents| std::fs::write(path, contents)) {
+        Ok(Ok(())) => stamped_info!(*stamp, "Dumped the precision state to {path}."),
+        Ok(Err(error)) => error!("Failed to dump the precision state to {path}: {error}"),
+        Err(error) => error!("Failed to serialize the precision state: {error}"),
+    }
+}
+
+fn capture_replay_start(
+    settings: Res<DrawSettings>,
+    mut start: ResMut<ReplayStart>,
+    frames: ReferenceFrames,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+) {
+    let Ok((view, view_transform)) = view_query.get_single() else {
+        warn!("Skipping capture_replay_start: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn!("Skipping capture_replay_start: the camera is not part of a reference frame.");
+        return;
+    };
+
+    start.state = Some(ReplayState {
+        camera_position: view_transform.position_double(&frame),
+        camera_rotation: view_transform.transform.rotation,
+        origin_lod: settings.origin_lod,
+    });
+}
+
+fn save_replay(
+    input: Res<ButtonInput<KeyCode>>,
+    start: Res<ReplayStart>,
+    recording: Res<InputRecording>,
+    stamp: Res<CameraStamp>,
+) {
+    if !input.just_pressed(KeyCode::F10) {
+        return;
+    }
+
+    let Some(initial) = start.state else {
+        warn!("Skipping save_replay: the initial state was not captured.");
+        return;
+    };
+
+    let replay = Replay {
+        seed: start.seed,
+        initial,
+        script: recording.0.clone(),
+    };
+
+    let path = "replay.json";
+    match replay.save(path) {
+        Ok(()) => stamped_info!(
+            *stamp,
+            "Saved {} recorded keys to {path}.",
+            replay.script.keys.len()
+        ),
+        Err(error) => error!("Failed to save the replay to {path}: {error}"),
+    }
+}
+
+fn apply_replay_start(
+    replay: Res<Replay>,
+    mut settings: ResMut<DrawSettings>,
+    frames: ReferenceFrames,
+    mut view_query: Query<(Entity, GridTransform), With<FloatingOrigin>>,
+) {
+    let Ok((view, mut view_transform)) = view_query.get_single_mut() else {
+        warn!("Skipping apply_replay_start: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn!("Skipping apply_replay_start: the camera is not part of a reference frame.");
+        return;
+    };
+
+    let (cell, translation) = frame.translation_to_grid(replay.initial.camera_position);
+    *view_transform.cell = cell;
+    view_transform.transform.translation = translation;
+    view_transform.transform.rotation = replay.initial.camera_rotation;
+    settings.origin_lod = replay.initial.origin_lod;
+}
+
+// Reports the first frame of the replay, in which the error around the probe exceeds the budget.
+fn report_budget_violation(
+    mut reported: Local<bool>,
+    frame_count: Res<FrameCount>,
+    stamp: Res<CameraStamp>,
+    diagnostics: Res<DiagnosticsStore>,
+) {
+    if *reported {
+        return;
+    }
+
+    let Some(error) = diagnostics
+        .get(&PROBE_MAX_ERROR)
+        .and_then(|diagnostic| diagnostic.value())
+    else {
+        return;
+    };
+
+    if error > ORIGIN_LOD_BUDGET {
+        *reported = true;
+        stamped_warn!(
+            *stamp,
+            "The probe error of {} exceeds the budget of {} in frame {}.",
+            Length(error),
+            Length(ORIGIN_LOD_BUDGET),
+            frame_count.0
+        );
+    }
+}
+
+fn show_error_field(
+    input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<DrawSettings>,
+    mut field_query: Query<&mut ErrorField>,
+) {
+    // the exaggeration steps through powers of ten, starting from the true errors
+    if input.just_pressed(KeyCode::Equal) {
+        settings.error_exaggeration = (settings.error_exaggeration * 10.0).max(1.0);
+    }
+    if input.just_pressed(KeyCode::Minus) {
+        settings.error_exaggeration = if settings.error_exaggeration > 1.0 {
+            settings.error_exaggeration / 10.0
+        } else {
+            0.0
+        };
+    }
+    if input.just_pressed(KeyCode::KeyV) {
+        settings.error_shape = settings.error_shape.next();
+        info!("Sampling the error field as {:?}.", settings.error_shape);
+    }
+
+    for mut field in &mut field_query {
+        field.enabled = settings.show_error;
+        field.origin_lod = settings.origin_lod;
+        field.exaggeration = settings.error_exaggeration;
+        field.shape = settings.error_shape;
+    }
+}
+
+// Rebuilds the model from the edited axes, with its orientation and position, and hands it to all model copies.
+fn apply_model_axes(
+    mut terrain_query: Query<(&mut Model, &ModelAxes), Changed<ModelAxes>>,
+    mut edited: EventWriter<ModelEdited>,
+) {
+    for (mut model, axes) in &mut terrain_query {
+        model.0 = with_axes(&model.0, axes.major_axis, axes.minor_axis);
+
+        edited.send(ModelEdited(model.0.clone()));
+    }
+}
+
+fn configure_near_plane(
+    settings: Res<DrawSettings>,
+    mut near_plane_query: Query<&mut AdaptiveNearPlane>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for mut near_plane in &mut near_plane_query {
+        near_plane.enabled = settings.adaptive_near_plane;
+    }
+}
+
+// Keeps the origin lod edited in the inspector within the lods, whose tile offsets are exact in f32.
+fn clamp_origin_lod(mut settings: ResMut<DrawSettings>) {
+    if settings.origin_lod > MAX_ORIGIN_LOD {
+        settings.origin_lod = MAX_ORIGIN_LOD;
+    }
+}
+
+// Cycles the mesh resolution the probe snaps to, at the origin lod.
+// With shift held, the multi-resolution probe of the lods around the origin lod is toggled instead.
+fn snap_probe(
+    input: Res<ButtonInput<KeyCode>>,
+    settings: Res<DrawSettings>,
+    mut probe: ResMut<Probe>,
+) {
+    if !input.just_pressed(KeyCode::KeyN) {
+        return;
+    }
+
+    if input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        probe.hierarchy = match probe.hierarchy {
+            Some(_) => None,
+            None => Some(LodHierarchy {
+                spread: PROBE_LOD_SPREAD,
+                threshold: ORIGIN_LOD_BUDGET,
+            }),
+        };
+        return;
+    }
+
+    probe.snap = match probe.snap.map(|snap| snap.resolution) {
+        None => Some(8),
+        Some(resolution) if resolution < 64 => Some(2 * resolution),
+        Some(_) => None,
+    }
+    .map(|resolution| VertexSnap {
+        lod: settings.origin_lod,
+        resolution,
+    });
+
+    if let Some(coordinate) = probe.coordinate {
+        probe.set(coordinate);
+    }
+
+    match probe.snap {
+        Some(snap) => info!(
+            "The probe snaps to the vertices of lod {} tiles with a resolution of {}.",
+            snap.lod, snap.resolution
+        ),
+        None => info!("The probe no longer snaps to vertices."),
+    }
+}
+
+// Draws the geodesic between the measured points, exact and as the approximation places it, and shows the distances.
+fn draw_measurement(
+    settings: Res<DrawSettings>,
+    measurement: Res<Measurement>,
+    approximation: Res<ViewApproximation>,
+    mut fallbacks: ResMut<ApproximationFallbacks>,
+    frames: ReferenceFrames,
+    mut gizmos: Gizmos<PrecisionGizmos>,
+    terrain_query: Query<&Model>,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+    mut hud_query: Query<&mut Text, With<Hud>>,
+) {
+    let Ok(mut hud) = hud_query.get_single_mut() else {
+        return;
+    };
+    let (Some((start, end)), Some(snapshot)) = (measurement.points(), &approximation.current)
+    else {
+        hud.sections[1].value.clear();
+        return;
+    };
+    let (Ok((view, view_transform)), Ok(Model(model))) =
+        (view_query.get_single(), terrain_query.get_single())
+    else {
+        warn_once!("Skipping draw_measurement: expected exactly one camera and terrain model.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping draw_measurement: the camera is not part of a reference frame.");
+        return;
+    };
+
+    let report = measure(
+        start,
+        end,
+        snapshot,
+        settings.origin_lod,
+        fallbacks.validity_radius,
+        model,
+    );
+    fallbacks.count += report.fallbacks;
+
+    // the gizmos are drawn relative to the current view, like everything else in the grid
+    let view_position = view_transform.position_double(&frame);
+    let to_gizmo = |position: DVec3| {
+        view_transform.transform.translation + (position - view_position).as_vec3()
+    };
+
+    gizmos.linestrip(report.path.iter().copied().map(to_gizmo), basic::YELLOW);
+    gizmos.linestrip(
+        report.approximated_path.iter().copied().map(to_gizmo),
+        basic::RED,
+    );
+
+    hud.sections[1].value = format!(
+        "\nSurface distance: {} (approximated {}, difference {})\nChord distance: {} (approximated {}, difference {})\nLargest deviation along the path: {} ({} of {} points beyond {} placed exactly)",
+        Length(report.surface_distance),
+        Length(report.approximated_surface_distance),
+        Length((report.approximated_surface_distance - report.surface_distance).abs()),
+        Length(report.chord_distance),
+        Length(report.approximated_chord_distance),
+        Length((report.approximated_chord_distance - report.chord_distance).abs()),
+        Length(report.max_deviation),
+        report.fallbacks,
+        report.path.len(),
+        Length(fallbacks.validity_radius)
+    );
+}
+
+// Draws the grids of the probe hierarchy colored by their error, and lists the error of each lod.
+// The first lod from the finest on that exceeds the threshold marks where the approximation stops being safe.
+fn draw_probe_hierarchy(
+    settings: Res<DrawSettings>,
+    probe: Res<Probe>,
+    approximation: Res<ViewApproximation>,
+    frames: ReferenceFrames,
+    mut gizmos: Gizmos<PrecisionGizmos>,
+    terrain_query: Query<&Model>,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+    mut hud_query: Query<&mut Text, With<Hud>>,
+) {
+    let Ok(mut hud) = hud_query.get_single_mut() else {
+        return;
+    };
+    let (Some(hierarchy), Some(snapshot)) = (probe.hierarchy, &approximation.current) else {
+        hud.sections[2].value.clear();
+        return;
+    };
+    let (Ok((view, view_transform)), Ok(Model(model))) =
+        (view_query.get_single(), terrain_query.get_single())
+    else {
+        warn_once!("Skipping draw_probe_hierarchy: expected exactly one camera and terrain model.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping draw_probe_hierarchy: the camera is not part of a reference frame.");
+        return;
+    };
+
+    let lod_errors = probe_lod_errors(&probe, hierarchy, snapshot, settings.origin_lod, model);
+
+    let view_position = view_transform.position_double(&frame);
+    let marker_size = 0.000002 * model.scale() as f32;
+
+    for &(lod, error) in &lod_errors {
+        for (tile, tile_uv) in probe.region_at_lod(lod, 4) {
+            let position =
+                Coordinate::new(tile.face, tile_uv_to_uv(tile, tile_uv)).world_position(model, 0.0);
+            let translation =
+                view_transform.transform.translation + (position - view_position).as_vec3();
+
+            gizmos.sphere(translation, Quat::IDENTITY, marker_size, error_color(error));
+        }
+    }
+
+    let first_exceeding = lod_errors
+        .iter()
+        .find(|&&(_, error)| error > hierarchy.threshold);
+
+    let mut text = format!(
+        "\nProbe errors around origin lod {} (threshold {}):",
+        settings.origin_lod,
+        Length(hierarchy.threshold)
+    );
+    for &(lod, error) in &lod_errors {
+        let marker = if first_exceeding.is_some_and(|&(first, _)| first == lod) {
+            " <- first lod exceeding the threshold"
+        } else {
+            ""
+        };
+        text += &format!("\n  lod {lod}: {}{marker}", Length(error));
+    }
+    if first_exceeding.is_none() {
+        text += "\n  all probed lods stay within the threshold";
+    }
+
+    hud.sections[2].value = text;
+}
+
+// Nudges the probe with the numpad arrows 4, 6, 8 and 2 by a vertex, or by an origin tile while shift is held.
+// This steps through the relative uv at tile and face edges, which is logged after each step.
+fn move_probe(
+    input: Res<ButtonInput<KeyCode>>,
+    settings: Res<DrawSettings>,
+    approximation: Res<ViewApproximation>,
+    mut probe: ResMut<Probe>,
+) {
+    let direction = [
+        (KeyCode::Numpad4, IVec2::NEG_X),
+        (KeyCode::Numpad6, IVec2::X),
+        (KeyCode::Numpad8, IVec2::NEG_Y),
+        (KeyCode::Numpad2, IVec2::Y),
+    ]
+    .into_iter()
+    .filter(|&(key, _)| input.just_pressed(key))
+    .map(|(_, direction)| direction)
+    .sum::<IVec2>();
+
+    if direction == IVec2::ZERO {
+        return;
+    }
+
+    let step = if input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        1.0 / tile_count(settings.origin_lod) as f64
+    } else {
+        probe.vertex_step(settings.origin_lod)
+    };
+
+    probe.step(direction, step);
+
+    let (Some(coordinate), Some(snapshot)) = (probe.coordinate, &approximation.current) else {
+        return;
+    };
+    let (tile, tile_uv) = tile_at_lod(coordinate, settings.origin_lod);
+
+    info!(
+        "The probe moved to face {} uv {:.9}, its relative uv is {:?}.",
+        coordinate.face,
+        coordinate.uv,
+        snapshot
+            .view_coordinates_at(settings.origin_lod)
+            .relative_uv((tile, tile_uv))
+    );
+}
+
+// Picks the terrain under the cursor and moves the probe there.
+fn pick_under_cursor(
+    input: Res<ButtonInput<KeyCode>>,
+    heights: Res<TerrainHeights>,
+    mut probe: ResMut<Probe>,
+    mut measurement: ResMut<Measurement>,
+    stamp: Res<CameraStamp>,
+    frames: ReferenceFrames,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    terrain_query: Query<&Model>,
+    view_query: Query<(Entity, &Camera, GridTransformReadOnly), With<FloatingOrigin>>,
+) {
+    if !input.any_just_pressed([KeyCode::KeyP, KeyCode::KeyB]) {
+        return;
+    }
+
+    let Some(cursor) = window_query
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+    else {
+        return;
+    };
+    let (Ok((view, camera, view_transform)), Ok(Model(model))) =
+        (view_query.get_single(), terrain_query.get_single())
+    else {
+        warn_once!("Skipping pick_under_cursor: expected exactly one camera and terrain model.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        return;
+    };
+
+    let view_position = view_transform.position_double(&frame);
+
+    match pick_terrain(
+        camera,
+        view_transform.transform,
+        view_position,
+        cursor,
+        model,
+        heights.0.as_ref(),
+    ) {
+        Some(hit) => {
+            let geodetic = Geodetic::from_world_position(hit.position, model);
+            stamped_info!(
+                *stamp,
+                "Picked {geodetic} at a distance of {}.",
+                Length(hit.distance)
+            );
+
+            if input.just_pressed(KeyCode::KeyP) {
+                probe.set(hit.coordinate);
+            }
+            if input.just_pressed(KeyCode::KeyB) {
+                measurement.pick(geodetic);
+            }
+        }
+        None => info!("There is no terrain under the cursor."),
+    }
+}
+
+fn auto_origin_lod(
+    input: Res<ButtonInput<KeyCode>>,
+    table: Res<OriginLodTable>,
+    mut settings: ResMut<DrawSettings>,
+    frames: ReferenceFrames,
+    terrain_query: Query<&Model>,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+) {
+    if input.just_pressed(KeyCode::KeyL) {
+        settings.auto_origin_lod = !settings.auto_origin_lod;
+    }
+
+    if !settings.auto_origin_lod {
+        return;
+    }
+
+    let (Ok((view, view_transform)), Ok(Model(model))) =
+        (view_query.get_single(), terrain_query.get_single())
+    else {
+        warn_once!("Skipping auto_origin_lod: expected exactly one camera and terrain model.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping auto_origin_lod: the camera is not part of a reference frame.");
+        return;
+    };
+
+    let altitude =
+        Geodetic::from_world_position(view_transform.position_double(&frame), model).height;
+
+    // only assign a new lod, so that the drawing is not rebuilt every frame
+    if let Some(origin_lod) = table
+        .origin_lod(altitude)
+        .filter(|&lod| lod != settings.origin_lod)
+    {
+        settings.origin_lod = origin_lod;
+    }
+}
+
+// Blends the drawn geometry into a new origin lod, whether it was picked automatically or set by hand.
+// The transition is only borrowed mutably while it changes, since a change rebuilds the drawing.
+fn blend_origin_lod(settings: Res<DrawSettings>, mut transition: ResMut<OriginLodTransition>) {
+    if settings.origin_lod != transition.origin_lod {
+        transition.follow(settings.origin_lod);
+    } else if transition.is_blending() {
+        transition.advance();
+    }
+}
+
+fn update_hud(
+    frames: ReferenceFrames,
+    transform_query: Query<(&GridCell, &Transform)>,
+    view_query: Query<Entity, (With<FloatingOrigin>, With<GridCell>)>,
+    satellite_query: Query<Entity, With<Satellite>>,
+    simulation_time: Res<SimulationTime>,
+    landing_site: Res<LandingSite>,
+    local_query: Query<(&Name, &SiteLocal)>,
+    mut hud_query: Query<&mut Text, With<Hud>>,
+) {
+    let (Ok(view), Ok(satellite), Ok(mut hud)) = (
+        view_query.get_single(),
+        satellite_query.get_single(),
+        hud_query.get_single_mut(),
+    ) else {
+        warn_once!("Skipping update_hud: expected exactly one camera, satellite and hud.");
+        return;
+    };
+
+    // the distance is taken from the grid cells, so it stays exact even far away from the origin,
+    // it is shown to the millimeter at any range, to show that precision
+    let Some(distance) = entity_distance(&frames, &transform_query, view, satellite) else {
+        return;
+    };
+
+    hud.sections[0].value = format!(
+        "Satellite distance: {:.3} m\nSimulation time: {} ({}x{})",
+        distance,
+        *simulation_time,
+        simulation_time.scale,
+        if simulation_time.paused {
+            ", paused"
+        } else {
+            ""
+        }
+    );
+
+    // inside the landing site, the surface entities are listed in its east-north-up frame
+    if let Some(site) = &landing_site.frame {
+        hud.sections[0].value += &format!(
+            "\nLanding site at {:.6}° {:.6}°",
+            site.geodetic.latitude_degrees(),
+            site.geodetic.longitude_degrees()
+        );
+
+        for (name, local) in &local_query {
+            if let Some(position) = local.position {
+                hud.sections[0].value += &format!(
+                    "\n  {name}: E {:.4} N {:.4} U {:.4} (off by {})",
+                    position.x,
+                    position.y,
+                    position.z,
+                    Length(local.error)
+                );
+            }
+        }
+    }
+}
+
+// F8 rebases the scene epoch onto the camera, which moves every position in the scene, but nothing on the screen.
+fn rebase_epoch_on_view(
+    input: Res<ButtonInput<KeyCode>>,
+    mut epoch: ResMut<SceneEpoch>,
+    frames: ReferenceFrames,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+) {
+    if !input.just_pressed(KeyCode::F8) {
+        return;
+    }
+
+    let Ok((view, view_transform)) = view_query.get_single() else {
+        warn!("Skipping rebase_epoch_on_view: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        return;
+    };
+
+    let origin = epoch.to_absolute(view_transform.position_double(&frame));
+    epoch.rebase(origin);
+
+    info!(
+        "Rebasing the scene epoch by {}.",
+        Length(view_transform.position_double(&frame).length())
+    );
+}
+
+// H pauses the simulated time, comma and period slow it down and speed it up by a factor of ten.
+fn control_simulation_time(
+    input: Res<ButtonInput<KeyCode>>,
+    mut simulation_time: ResMut<SimulationTime>,
+) {
+    if input.just_pressed(KeyCode::KeyH) {
+        simulation_time.paused = !simulation_time.paused;
+    }
+    if input.just_pressed(KeyCode::Period) {
+        simulation_time.scale = (simulation_time.scale * 10.0).min(1e6);
+    }
+    if input.just_pressed(KeyCode::Comma) {
+        simulation_time.scale = (simulation_time.scale / 10.0).max(1.0);
+    }
+}
+
+fn follow_satellite(
+    mut follow_offset: Local<Option<DVec3>>,
+    input: Res<ButtonInput<KeyCode>>,
+    frames: ReferenceFrames,
+    satellite_query: Query<GridTransformReadOnly, With<Satellite>>,
+    mut view_query: Query<(Entity, GridTransform), (With<FloatingOrigin>, Without<Satellite>)>,
+) {
+    let Ok((view, mut view_transform)) = view_query.get_single_mut() else {
+        warn_once!("Skipping follow_satellite: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping follow_satellite: the camera is not part of a reference frame.");
+        return;
+    };
+    let Ok(satellite_transform) = satellite_query.get_single() else {
+        warn_once!("Skipping follow_satellite: expected exactly one satellite.");
+        *follow_offset = None;
+        return;
+    };
+    let satellite_position = satellite_transform.position_double(&frame);
+
+    if input.just_pressed(KeyCode::KeyT) {
+        *follow_offset = match *follow_offset {
+            Some(_) => None,
+            None => Some(view_transform.position_double(&frame) - satellite_position),
+        };
+    }
+
+    if let Some(offset) = *follow_offset {
+        let (cell, translation) = frame.translation_to_grid(satellite_position + offset);
+
+        *view_transform.cell = cell;
+        view_transform.transform.translation = translation;
+    }
+}
+
+// Recomputes the f64 approximation at the rate of the fixed clock.
+// With a landing site, the approximation stays anchored at the site instead of following the view.
+fn update_view_approximation(
+    settings: Res<DrawSettings>,
+    landing_site: Res<LandingSite>,
+    mut approximation: ResMut<ViewApproximation>,
+    mut scheduler: ResMut<ApproximationScheduler>,
+    terrain_query: Query<(Entity, &Model)>,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+    frames: ReferenceFrames,
+) {
+    if settings.freeze {
+        return;
+    }
+
+    if let Some(site) = &landing_site.frame {
+        let anchored = approximation
+            .current
+            .is_some_and(|current| current.view_position == site.snapshot.view_position);
+
+        if !anchored {
+            approximation.push(site.snapshot);
+        }
+        return;
+    }
+
+    let Ok((view, view_transform)) = view_query.get_single() else {
+        warn_once!("Skipping update_view_approximation: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!(
+            "Skipping update_view_approximation: the camera is not part of a reference frame."
+        );
+        return;
+    };
+    let Ok((terrain, Model(model))) = terrain_query.get_single() else {
+        warn_once!("Skipping update_view_approximation: expected exactly one terrain model.");
+        return;
+    };
+    let view_position = view_transform.position_double(&frame);
+
+    scheduler.update([((view, terrain), view_position, model)]);
+
+    if let Some(snapshot) = scheduler.snapshot((view, terrain), view_position) {
+        approximation.push(snapshot);
+    }
+}
+
+fn measure_precision(
+    time: Res<Time>,
+    settings: Res<DrawSettings>,
+    probe: Res<Probe>,
+    approximation: Res<ViewApproximation>,
+    terrain_query: Query<&Model>,
+    mut switches: ResMut<OriginTileSwitches>,
+    mut diagnostics: Diagnostics,
+) {
+    let (Some(snapshot), Ok(Model(model))) = (&approximation.current, terrain_query.get_single())
+    else {
+        return;
+    };
+
+    let (origin_tile, _) =
+        tile_coordinate_from_world_position(snapshot.view_position, settings.origin_lod, model);
+    switches.record(origin_tile, time.elapsed_seconds_f64());
+
+    diagnostics.add_measurement(&ORIGIN_TILE_SWITCHES, || switches.per_minute());
+
+    if let Some(error) = probe_max_error(&probe, snapshot, settings.origin_lod, model) {
+        diagnostics.add_measurement(&PROBE_MAX_ERROR, || error);
+    }
+}
+
+// Adjusts the precision gizmos, so that thin lines stay visible and arrows below the surface can be shown.
+fn configure_gizmos(input: Res<ButtonInput<KeyCode>>, mut config_store: ResMut<GizmoConfigStore>) {
+    // the store is only borrowed mutably on input, since a change rebuilds the drawing
+    if !input.any_just_pressed([
+        KeyCode::BracketLeft,
+        KeyCode::BracketRight,
+        KeyCode::KeyZ,
+        KeyCode::KeyO,
+        KeyCode::KeyG,
+        KeyCode::KeyC,
+        KeyCode::KeyX,
+        KeyCode::KeyM,
+        KeyCode::KeyU,
+        KeyCode::KeyY,
+    ]) {
+        return;
+    }
+
+    let (config, layers) = config_store.config_mut::<PrecisionGizmos>();
+
+    if input.just_pressed(KeyCode::BracketRight) {
+        config.line_width += 1.0;
+    }
+    if input.just_pressed(KeyCode::BracketLeft) {
+        config.line_width = (config.line_width - 1.0).max(1.0);
+    }
+    // a depth bias of -1 draws the lines on top of everything
+    if input.just_pressed(KeyCode::KeyZ) {
+        config.depth_bias = if config.depth_bias < 0.0 { 0.0 } else { -1.0 };
+    }
+
+    if input.just_pressed(KeyCode::KeyO) {
+        layers.approximation = !layers.approximation;
+    }
+    if input.just_pressed(KeyCode::KeyG) {
+        layers.overlay = !layers.overlay;
+    }
+    if input.just_pressed(KeyCode::KeyC) {
+        layers.tile_errors = !layers.tile_errors;
+    }
+    if input.just_pressed(KeyCode::KeyX) {
+        layers.probe_orders = !layers.probe_orders;
+    }
+    if input.just_pressed(KeyCode::KeyM) {
+        layers.annotations = !layers.annotations;
+    }
+    if input.just_pressed(KeyCode::KeyU) {
+        layers.orbits = !layers.orbits;
+    }
+    if input.just_pressed(KeyCode::KeyY) {
+        layers.particles = !layers.particles;
+    }
+}
+
+// The buffers of the mesh are replaced, since the number of segments changes with every rebuild.
+fn upload_retained_drawing(
+    mut meshes: ResMut<Assets<Mesh>>,
+    drawing_query: Query<(&RetainedDrawing, &Handle<Mesh>), Changed<RetainedDrawing>>,
+) {
+    for (RetainedDrawing(lines), mesh) in &drawing_query {
+        if let Some(mesh) = meshes.get_mut(mesh) {
+            lines.write_mesh(mesh);
+        }
+    }
+}
+
+// Draws the frustum and origin tile of the frozen view, so the stale approximation can be inspected from outside.
+fn draw_frozen_view(
+    mut frozen_view: Local<Option<FrozenView>>,
+    settings: Res<DrawSettings>,
+    frames: ReferenceFrames,
+    mut gizmos: Gizmos<PrecisionGizmos>,
+    terrain_query: Query<&Model>,
+    view_query: Query<(Entity, &Projection, GridTransformReadOnly), With<FloatingOrigin>>,
+) {
+    if !settings.freeze {
+        *frozen_view = None;
+        return;
+    }
+
+    let (Ok((view, projection, view_transform)), Ok(Model(model))) =
+        (view_query.get_single(), terrain_query.get_single())
+    else {
+        warn_once!("Skipping draw_frozen_view: expected exactly one camera and terrain model.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping draw_frozen_view: the camera is not part of a reference frame.");
+        return;
+    };
+    let view_position = view_transform.position_double(&frame);
+
+    if frozen_view.is_none() {
+        let Projection::Perspective(projection) = projection else {
+            warn_once!("Skipping draw_frozen_view: only perspective projections are supported.");
+            return;
+        };
+
+        *frozen_view = Some(FrozenView {
+            position: view_position,
+            rotation: view_transform.transform.rotation,
+            projection: projection.clone(),
+            origin_lod: settings.origin_lod,
+        });
+    }
+    let Some(frozen_view) = frozen_view.as_ref() else {
+        return;
+    };
+
+    // the gizmos are drawn relative to the current view, like everything else in the grid
+    let offset = view_transform.transform.translation.as_dvec3() - view_position;
+    let to_gizmo = |local: Vec3| {
+        (frozen_view.position + (frozen_view.rotation * local).as_dvec3() + offset).as_vec3()
+    };
+
+    let PerspectiveProjection {
+        fov,
+        aspect_ratio,
+        near,
+        far,
+    } = frozen_view.projection;
+
+    let corners = |distance: f32| {
+        let half_height = distance * (0.5 * fov).tan();
+        let half_width = half_height * aspect_ratio;
+
+        [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+            .map(|(x, y)| to_gizmo(Vec3::new(x * half_width, y * half_height, -distance)))
+    };
+    let (near_corners, far_corners) = (corners(near), corners(far));
+
+    let color = Color::from(basic::MAROON);
+    for i in 0..4 {
+        gizmos.line(near_corners[i], near_corners[(i + 1) % 4], color);
+        gizmos.line(far_corners[i], far_corners[(i + 1) % 4], color);
+        gizmos.line(near_corners[i], far_corners[i], color);
+    }
+
+    let (origin_tile, _) =
+        tile_coordinate_from_world_position(frozen_view.position, frozen_view.origin_lod, model);
+    draw_tile(&mut gizmos, model, origin_tile, color, offset);
+}
+
 fn update(
-    mut view_position: Local<DVec3>,
-    mut freeze: Local<bool>,
-    mut show_error: Local<bool>,
-    mut hide_approximation: Local<bool>,
-    mut gizmos: Gizmos,
-    terrain_query: Query<(&Model, GridTransformReadOnly)>,
-    view_query: Query<(Entity, GridTransformReadOnly), With<Camera>>,
+    mut commands: Commands,
+    mut drawn_positions: Local<Option<(DVec3, DVec3)>>,
+    mut settings: ResMut<DrawSettings>,
+    config_store: Res<GizmoConfigStore>,
+    // the system parameters are at their limit, so the ones interpolating the approximation are grouped
+    (fixed_time, approximation, transition): (
+        Res<Time<Fixed>>,
+        Res<ViewApproximation>,
+        Res<OriginLodTransition>,
+    ),
+    overlay: Res<Overlay>,
+    render_path: Res<RenderPath>,
+    terrain_query: Query<(Ref<Model>, GridTransformReadOnly)>,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+    mut drawing_query: Query<
+        (&mut RetainedDrawing, &Parent, GridTransform),
+        (Without<FloatingOrigin>, Without<Model>),
+    >,
+    mut label_query: Query<
+        (Entity, &mut WorldLabel, GridTransform),
+        (
+            With<DrawingLabel>,
+            Without<RetainedDrawing>,
+            Without<FloatingOrigin>,
+            Without<Model>,
+        ),
+    >,
+    probe: Res<Probe>,
+    tile_query: Query<(&TileOrigin, &Occluded)>,
     input: Res<ButtonInput<KeyCode>>,
     frames: ReferenceFrames,
 ) {
     if input.just_pressed(KeyCode::KeyF) {
-        *freeze = !*freeze;
+        settings.freeze = !settings.freeze;
     }
     if input.just_pressed(KeyCode::KeyE) {
-        *show_error = !*show_error;
-    }
-    if input.just_pressed(KeyCode::KeyO) {
-        *hide_approximation = !*hide_approximation;
+        settings.show_error = !settings.show_error;
     }
 
-    if *freeze {
+    if settings.freeze {
         return;
     }
 
-    let (view, transform) = view_query.single();
-    let frame = frames.parent_frame(view).unwrap();
-    *view_position = transform.position_double(&frame);
+    let Ok((view, view_transform)) = view_query.get_single() else {
+        warn_once!("Skipping update: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping update: the camera is not part of a reference frame.");
+        return;
+    };
+    let view_position = view_transform.position_double(&frame);
 
-    let (Model(model), terrain_grid_transform) = terrain_query.single();
+    let Ok((model, terrain_grid_transform)) = terrain_query.get_single() else {
+        warn_once!("Skipping update: expected exactly one terrain model.");
+        return;
+    };
     let terrain_position = terrain_grid_transform.position_double(&frame);
-    let offset = terrain_position - *view_position;
+    let offset = terrain_position - view_position;
 
-    let view_coordinate = Coordinate::from_world_position(*view_position, model);
+    let snapshot = approximation.interpolated(fixed_time.overstep_fraction());
+    let approximation_position = snapshot.map_or(view_position, |snapshot| snapshot.view_position);
 
-    let view_coordinates = (0..6)
-        .map(|face| view_coordinate.project_to_face(face, model))
-        .collect_vec();
+    let moved = drawn_positions.map_or(true, |(drawn_view, drawn_approximation)| {
+        drawn_view.distance(view_position) > REDRAW_DISTANCE
+            || drawn_approximation.distance(approximation_position) > REDRAW_DISTANCE
+    });
 
-    let approximations = view_coordinates
-        .iter()
-        .map(|&view_coordinate| {
-            SurfaceApproximation::compute(view_coordinate, *view_position, model)
-        })
-        .collect_vec();
+    if !moved
+        && !settings.is_changed()
+        && !transition.is_changed()
+        && !config_store.is_changed()
+        && !render_path.is_changed()
+        && !overlay.is_changed()
+        && !probe.is_changed()
+        && !model.is_changed()
+    {
+        return;
+    }
+
+    *drawn_positions = Some((view_position, approximation_position));
+
+    // the lines are relative to the view they were built for, so the drawing is anchored there
+    let Ok((mut drawing, frame_entity, mut drawing_transform)) = drawing_query.get_single_mut()
+    else {
+        warn_once!("Skipping update: expected exactly one retained drawing.");
+        return;
+    };
+
+    let Model(model) = model.into_inner();
+    let (_, layers) = config_store.config::<PrecisionGizmos>();
 
-    draw_earth(&mut gizmos, model, 2, offset);
+    // the previous drawing is collected into again, so its buffers are reused
+    let mut lines = std::mem::take(&mut drawing.0);
+    lines.clear();
 
-    if !*hide_approximation {
+    if layers.earth {
+        draw_earth(&mut lines, model, 2, offset);
+    }
+
+    if layers.overlay {
+        draw_polylines(&mut lines, model, &overlay.0, basic::AQUA.into(), offset);
+    }
+
+    if let Some(snapshot) =
+        snapshot.filter(|_| layers.approximation && *render_path == RenderPath::Taylor)
+    {
         draw_approximation(
-            &mut gizmos,
+            &mut lines,
             model,
-            &view_coordinates,
-            &approximations,
+            &snapshot.view_coordinates,
+            &snapshot.approximations,
             offset,
         );
     }
+
+    if let Some(snapshot) = snapshot.filter(|_| layers.tile_errors) {
+        draw_tile_errors(
+            &mut lines,
+            model,
+            tile_query
+                .iter()
+                .filter(|(_, occluded)| !occluded.0)
+                .map(|(tile_origin, _)| tile_origin.tile),
+            &snapshot,
+            &transition,
+            offset,
+        );
+    }
+
+    if let Some(snapshot) = snapshot.filter(|_| layers.probe_orders) {
+        draw_approximation_orders(
+            &mut lines,
+            model,
+            probe.region(settings.origin_lod, 4),
+            &snapshot,
+            &transition,
+            settings.error_exaggeration.max(1.0),
+            offset,
+        );
+    }
+
+    // the labels of the previous drawing are moved to the new ones, only the surplus is spawned or despawned
+    let mut label_entities = label_query.iter_mut();
+
+    for label in lines.labels.drain(..) {
+        // the labels are placed in the grid, relative to the cell of the view
+        let (cell, translation) = frame.translation_to_grid(
+            view_transform.transform.translation.as_dvec3() + label.position.as_dvec3(),
+        );
+        let cell = *view_transform.cell + cell;
+
+        match label_entities.next() {
+            Some((_, mut world_label, mut label_transform)) => {
+                // the text nodes are only updated for changed labels
+                if world_label.text != label.text || world_label.color != label.color {
+                    world_label.text = label.text;
+                    world_label.color = label.color;
+                }
+
+                *label_transform.cell = cell;
+                label_transform.transform.translation = translation;
+            }
+            None => {
+                commands.entity(frame_entity.get()).with_children(|frame| {
+                    frame.spawn((
+                        SpatialBundle::from_transform(Transform::from_translation(translation)),
+                        cell,
+                        WorldLabel {
+                            font_size: 12.0,
+                            ..WorldLabel::new(label.text, label.color)
+                        },
+                        DrawingLabel,
+                    ));
+                });
+            }
+        }
+    }
+
+    for (label, ..) in label_entities {
+        commands.entity(label).despawn_recursive();
+    }
+
+    drawing.0 = lines;
+    *drawing_transform.cell = *view_transform.cell;
+    drawing_transform.transform.translation = view_transform.transform.translation;
 }
@@ -1,24 +1,78 @@
 use bevy::{
     color::palettes::basic,
     math::{DVec2, DVec3},
+    pbr::wireframe::{Wireframe, WireframePlugin},
     prelude::*,
+    render::{
+        settings::{WgpuFeatures, WgpuSettings},
+        RenderPlugin,
+    },
 };
 use bevy_terrain::{
-    math::{Coordinate, SurfaceApproximation, TileCoordinate, ViewCoordinate},
+    math::{Coordinate, SurfaceApproximation, TileCoordinate},
     prelude::*,
 };
-use itertools::Itertools;
-use precision_demo::draw::draw_earth;
-use rand::{prelude::ThreadRng, thread_rng, Rng};
+use itertools::{iproduct, Itertools};
+use precision_demo::{
+    depth::{update_near_plane, AdaptiveNearPlane},
+    draw::draw_earth,
+    format::Length,
+    geodesy::{horizon_pose, Geodetic},
+    math::{
+        approximate_world_position, approximations,
+        atlas::FaceAtlas,
+        decomposition::{ErrorContributions, ErrorDecomposition},
+        fixture::{ApproximationFixture, FixtureError},
+        simd::{approximate_world_positions_x8, LANES},
+        tile_coordinate_from_world_position, tile_count, validate, view_coordinates, TileMargin,
+        C_SQR, MAX_LOD,
+    },
+    notation::TileName,
+    origin_lod::find_min_origin_lod,
+    path::DPath,
+    track::{load_czml_track, TrackSample},
+    view_approximation::{ApproximationSnapshot, ViewApproximation},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde_json::json;
+use std::{f64::consts::FRAC_PI_2, ops::RangeInclusive, path::Path};
+
+// frame rate of the replayed track and the rate of the approximation updates, the same as in the demo
+const TRACK_FRAME_RATE: f64 = 60.0;
+const TRACK_APPROXIMATION_RATE: f64 = 64.0;
+
+// error, below which an approximation counts as valid in the altitude sweep
+const SWEEP_TOLERANCE: f64 = 0.01;
+const SWEEP_STEPS_PER_DECADE: u32 = 4;
 
-const C_SQR: f32 = 0.87 * 0.87;
+// vertices per tile edge and tiles around the view sampled by the relative uv audit,
+// and the error in vertex spacings from which on the relative uv is the bottleneck
+const AUDIT_VERTEX_RESOLUTION: u32 = 16;
+const AUDIT_NEIGHBORHOOD: i64 = 1;
+const AUDIT_BOTTLENECK: f64 = 0.5;
+// second order error of the study, above which a sample is kept in a saved fixture
+const FIXTURE_THRESHOLD: f64 = 0.01;
 
+const HEATMAP_RESOLUTION: u32 = 128;
+const HEATMAP_SAMPLES: u32 = 32;
+// texels around each face in the atlas, continuing the neighboring faces for seamless filtering
+const HEATMAP_ATLAS_BORDER: u32 = 2;
+// inferno-like color ramp, from the smallest to the largest error
+const HEATMAP_COLORS: [[u8; 3]; 5] = [
+    [0, 0, 4],
+    [87, 16, 110],
+    [188, 55, 84],
+    [249, 142, 9],
+    [252, 255, 164],
+];
+
+// Evaluates the world position in f32 like the shader, with the library's stretch constant rounded to f32.
 fn f32_world_position((tile, tile_uv): (TileCoordinate, Vec2), model: &TerrainModel) -> DVec3 {
-    let uv =
-        (UVec2::new(tile.x, tile.y).as_vec2() + tile_uv) / TileCoordinate::count(tile.lod) as f32;
+    let uv = (UVec2::new(tile.x, tile.y).as_vec2() + tile_uv) / tile_count(tile.lod) as f32;
+    let c_sqr = C_SQR as f32;
 
     let w = (uv - 0.5) / 0.5;
-    let uv = w / (1.0 + C_SQR - C_SQR * w * w).powf(0.5);
+    let uv = w / (1.0 + c_sqr - c_sqr * w * w).powf(0.5);
 
     let local_position = match tile.face {
         0 => Vec3::new(-1.0, -uv.y, uv.x),
@@ -38,42 +92,77 @@ fn f32_world_position((tile, tile_uv): (TileCoordinate, Vec2), model: &TerrainMo
         .as_dvec3()
 }
 
-fn approximate_world_position(
-    view_coordinates: &[Coordinate],
-    approximations: &[SurfaceApproximation],
-    origin_lod: u32,
-    second_order: bool,
-    view_position: DVec3,
-    (tile, tile_uv): (TileCoordinate, Vec2),
-) -> DVec3 {
-    let ViewCoordinate {
-        xy: view_xy,
-        uv: view_uv,
-    } = ViewCoordinate::new(view_coordinates[tile.face as usize], origin_lod);
-
-    let &SurfaceApproximation {
-        c,
-        c_du,
-        c_dv,
-        c_duu,
-        c_duv,
-        c_dvv,
-    } = &approximations[tile.face as usize];
-
-    let Vec2 { x: u, y: v } = ((tile.xy() - view_xy).as_vec2() + tile_uv - view_uv)
-        / TileCoordinate::count(tile.lod) as f32;
-
-    let approximate_relative_position = if second_order {
-        c + c_du * u + c_dv * v + c_duu * u * u + c_duv * u * v + c_dvv * v * v
-    } else {
-        c + c_du * u + c_dv * v
-    };
+// The same mapping as `f32_world_position`, but evaluated in f64 with an arbitrary stretch constant.
+// Comparing it with the library separates errors of the mapping itself from the rounding of the f32 path.
+fn reference_world_position(face: u32, uv: DVec2, c_sqr: f64, model: &TerrainModel) -> DVec3 {
+    let w = (uv - 0.5) / 0.5;
+    let uv = w / (1.0 + c_sqr - c_sqr * w * w).powf(0.5);
+
+    let local_position = match face {
+        0 => DVec3::new(-1.0, -uv.y, uv.x),
+        1 => DVec3::new(uv.x, -uv.y, 1.0),
+        2 => DVec3::new(uv.x, 1.0, uv.y),
+        3 => DVec3::new(1.0, -uv.x, uv.y),
+        4 => DVec3::new(uv.y, -uv.x, -1.0),
+        5 => DVec3::new(uv.y, -1.0, uv.x),
+        _ => unreachable!(),
+    }
+    .normalize();
+
+    model.world_from_local.transform_point3(local_position)
+}
+
+// Largest distance between the reference mapping with the stretch constant and the library's mapping.
+fn mapping_mismatch(model: &TerrainModel, c_sqr: f64, resolution: u32) -> f64 {
+    iproduct!(0..6, 0..=resolution, 0..=resolution)
+        .map(|(face, x, y)| {
+            let uv = DVec2::new(x as f64, y as f64) / resolution as f64;
 
-    view_position + approximate_relative_position.as_dvec3()
+            reference_world_position(face, uv, c_sqr, model)
+                .distance(Coordinate::new(face, uv).world_position(model, 0.0))
+        })
+        .fold(0.0, f64::max)
+}
+
+// Sweeps the stretch constant c of C_SQR = c * c, to show how sensitive the positions are to a diverging constant.
+// The mismatch has to vanish at the library's constant, otherwise the mapping of the reference path is off.
+fn c_sqr_sweep() {
+    let model = TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0);
+    let resolution = 32;
+
+    println!("c, mismatch to the library [m]");
+
+    let (best_c, best_mismatch) = (0..=30)
+        .map(|step| {
+            let c = 0.80 + 0.005 * step as f64;
+            let mismatch = mapping_mismatch(&model, c * c, resolution);
+
+            println!("{c:.3}, {mismatch:.6}");
+
+            (c, mismatch)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap();
+
+    println!(
+        "The smallest mismatch of {} occurs at c = {best_c:.3}, the library uses c = {:.3}.",
+        Length(best_mismatch),
+        C_SQR.sqrt()
+    );
+}
+
+// Runs with the same `--seed <seed>` reproduce the same samples.
+fn seeded_rng() -> StdRng {
+    std::env::args()
+        .skip_while(|arg| arg != "--seed")
+        .nth(1)
+        .map_or_else(StdRng::from_entropy, |seed| {
+            StdRng::seed_from_u64(seed.parse().expect("the seed is not an integer"))
+        })
 }
 
 fn random_test_position(
-    rng: &mut ThreadRng,
+    rng: &mut impl Rng,
     model: &TerrainModel,
     threshold: f64,
     view_position: DVec3,
@@ -94,7 +183,7 @@ fn random_test_position(
     )
 }
 
-fn random_view_position(rng: &mut ThreadRng, model: &TerrainModel, max_height: f64) -> DVec3 {
+fn random_view_position(rng: &mut impl Rng, model: &TerrainModel, max_height: f64) -> DVec3 {
     Coordinate::new(
         rng.gen_range(0..6),
         DVec2::new(rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0)),
@@ -102,22 +191,6 @@ fn random_view_position(rng: &mut ThreadRng, model: &TerrainModel, max_height: f
     .world_position(&model, rng.gen_range(0.0..max_height as f32))
 }
 
-fn tile_coordinate_from_world_position(
-    world_position: DVec3,
-    lod: u32,
-    model: &TerrainModel,
-) -> (TileCoordinate, Vec2) {
-    let coordinate = Coordinate::from_world_position(world_position, &model);
-    let uv = coordinate.uv * (1 << lod) as f64;
-    let tile_xy = uv.as_uvec2();
-    let tile_uv = uv.fract().as_vec2();
-
-    (
-        TileCoordinate::new(coordinate.face, lod, tile_xy.x, tile_xy.y),
-        tile_uv,
-    )
-}
-
 #[derive(Default)]
 struct ViewError {
     position: Vec3,
@@ -128,10 +201,12 @@ struct ViewError {
 struct Errors {
     view_errors: Vec<ViewError>,
     max_error: f64,
+    // the view with the largest error and its samples above the threshold, which `--save-fixture` saves
+    worst_view: Option<ApproximationFixture>,
 }
 
 fn compute_errors() -> Errors {
-    let mut rng = thread_rng();
+    let mut rng = seeded_rng();
 
     let model = TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0);
 
@@ -152,51 +227,57 @@ fn compute_errors() -> Errors {
     let mut f32_avg: f64 = 0.0;
     let mut cast_max: f64 = 0.0;
     let mut cast_avg: f64 = 0.0;
+    let mut mismatch_max: f64 = 0.0;
+    let mut contributions_max = ErrorContributions::default();
+    let mut contributions_avg = ErrorContributions::default();
 
     let mut view_errors = vec![];
+    let mut worst_view = None;
 
     for _ in 0..view_samples {
         let view_position = random_view_position(&mut rng, &model, threshold);
-        let view_coordinate = Coordinate::from_world_position(view_position, &model);
-
-        let view_coordinates = (0..6)
-            .map(|face| view_coordinate.project_to_face(face))
-            .collect_vec();
-
-        let approximations = view_coordinates
-            .iter()
-            .map(|&view_coordinate| {
-                SurfaceApproximation::compute(view_coordinate, view_position, &model)
-            })
-            .collect_vec();
+        let view_coordinates = view_coordinates(view_position, &model);
+        let approximations = approximations(&view_coordinates, view_position, &model);
+        let decomposition = ErrorDecomposition::new(&view_coordinates, view_position, &model);
 
         let mut max_error: f64 = 0.0;
 
-        for _ in 0..surface_samples {
-            let surface_position = random_test_position(&mut rng, &model, threshold, view_position);
+        let samples = (0..surface_samples)
+            .map(|_| {
+                let surface_position =
+                    random_test_position(&mut rng, &model, threshold, view_position);
 
-            let coordinate =
-                tile_coordinate_from_world_position(surface_position, view_lod, &model);
+                (
+                    surface_position,
+                    tile_coordinate_from_world_position(surface_position, view_lod, &model),
+                )
+            })
+            .collect_vec();
 
-            let taylor1_error = surface_position.distance(approximate_world_position(
+        let [taylor1_errors, taylor2_errors] = [false, true].map(|second_order| {
+            taylor_errors(
                 &view_coordinates,
                 &approximations,
                 view_lod,
-                false,
+                second_order,
                 view_position,
-                coordinate,
-            ));
-            let taylor2_error = surface_position.distance(approximate_world_position(
-                &view_coordinates,
-                &approximations,
-                view_lod,
-                true,
-                view_position,
-                coordinate,
-            ));
+                &samples,
+            )
+        });
+
+        for ((&(surface_position, coordinate), taylor1_error), taylor2_error) in
+            samples.iter().zip(taylor1_errors).zip(taylor2_errors)
+        {
             let f32_error = surface_position.distance(f32_world_position(coordinate, &model));
             let cast_error = surface_position.distance(surface_position.as_vec3().as_dvec3());
 
+            // the part of the f32 error caused by the mapping of the reference path, rather than by rounding
+            let (tile, tile_uv) = coordinate;
+            let uv = (UVec2::new(tile.x, tile.y).as_dvec2() + tile_uv.as_dvec2())
+                / tile_count(tile.lod) as f64;
+            let mismatch_error =
+                surface_position.distance(reference_world_position(tile.face, uv, C_SQR, &model));
+
             count += 1;
             taylor1_max = taylor1_max.max(taylor1_error);
             taylor1_avg = taylor1_avg + taylor1_error;
@@ -206,10 +287,25 @@ fn compute_errors() -> Errors {
             f32_avg = f32_avg + f32_error;
             cast_max = cast_max.max(cast_error);
             cast_avg = cast_avg + cast_error;
+            mismatch_max = mismatch_max.max(mismatch_error);
+
+            let contributions = decomposition.decompose(view_lod, true, coordinate, &model);
+            contributions_max = contributions_max.max(contributions);
+            contributions_avg = contributions_avg.sum(contributions);
 
             max_error = max_error.max(taylor2_error);
         }
 
+        if max_error >= taylor2_max {
+            worst_view = Some((
+                view_position,
+                samples
+                    .iter()
+                    .map(|&(_, coordinate)| coordinate)
+                    .collect_vec(),
+            ));
+        }
+
         view_errors.push(ViewError {
             position: (view_position / model.scale() * RADIUS).as_vec3(), // (view_position.normalize() * RADIUS).as_vec3(),
             max_error,
@@ -220,45 +316,773 @@ fn compute_errors() -> Errors {
     taylor2_avg = taylor2_avg / count as f64;
     f32_avg = f32_avg / count as f64;
     cast_avg = cast_avg / count as f64;
+    contributions_avg = contributions_avg.average(count);
 
-    println!("With a threshold factor of {} and an view LOD of {view_lod}, the error in a sample distance of {:.4} m around the camera looks like this.", threshold / model.scale(), threshold);
-    println!("The world space error introduced by the first order taylor approximation is {:.4} m on average and {:.4} m at the maximum.", taylor1_avg, taylor1_max);
-    println!("The world space error introduced by the second order taylor approximation is {:.4} m on average and {:.4} m at the maximum.", taylor2_avg, taylor2_max);
-    println!("The world space error introduced by computing the position using f32 is {:.4} m on average and {:.4} m at the maximum.", f32_avg, f32_max);
-    println!("The world space error introduced by downcasting from f64 to f32 is {:.4} m on average and {:.4} m at the maximum.", cast_avg, cast_max);
+    println!("With a threshold factor of {} and an view LOD of {view_lod}, the error in a sample distance of {} around the camera looks like this.", threshold / model.scale(), Length(threshold));
+    println!("The world space error introduced by the first order taylor approximation is {} on average and {} at the maximum.", Length(taylor1_avg), Length(taylor1_max));
+    println!("The world space error introduced by the second order taylor approximation is {} on average and {} at the maximum.", Length(taylor2_avg), Length(taylor2_max));
+    println!("The world space error introduced by computing the position using f32 is {} on average and {} at the maximum.", Length(f32_avg), Length(f32_max));
+    println!("Of the f32 error, up to {} are caused by the mapping of the reference path instead of rounding.", Length(mismatch_max));
+    println!("The world space error introduced by downcasting from f64 to f32 is {} on average and {} at the maximum.", Length(cast_avg), Length(cast_max));
+
+    println!("The second order error decomposes into these contributions, average and maximum:");
+    for (name, average, max) in [
+        (
+            "truncation of the taylor series",
+            contributions_avg.truncation,
+            contributions_max.truncation,
+        ),
+        (
+            "f32 rounding of the coefficients",
+            contributions_avg.coefficient_rounding,
+            contributions_max.coefficient_rounding,
+        ),
+        (
+            "f32 rounding of the relative uv",
+            contributions_avg.relative_uv_rounding,
+            contributions_max.relative_uv_rounding,
+        ),
+        (
+            "integer offset to the origin tile",
+            contributions_avg.origin_offset,
+            contributions_max.origin_offset,
+        ),
+        (
+            "complete f32 path",
+            contributions_avg.total,
+            contributions_max.total,
+        ),
+    ] {
+        println!("    {name}: {} / {}", Length(average), Length(max));
+    }
 
     Errors {
         view_errors,
         max_error: taylor2_max,
+        worst_view: worst_view.map(|(view_position, samples)| {
+            ApproximationFixture::record(
+                view_position,
+                view_lod,
+                &samples,
+                FIXTURE_THRESHOLD,
+                &model,
+            )
+        }),
+    }
+}
+
+// Errors of the approximated positions of the samples, evaluated eight at a time.
+fn taylor_errors(
+    view_coordinates: &[Coordinate; 6],
+    approximations: &[SurfaceApproximation; 6],
+    origin_lod: u32,
+    second_order: bool,
+    view_position: DVec3,
+    samples: &[(DVec3, (TileCoordinate, Vec2))],
+) -> Vec<f64> {
+    samples
+        .chunks(LANES)
+        .flat_map(|chunk| {
+            // the lanes of the last chunk beyond the samples repeat its first sample
+            let coordinates = std::array::from_fn(|lane| chunk.get(lane).unwrap_or(&chunk[0]).1);
+
+            let positions = approximate_world_positions_x8(
+                view_coordinates,
+                approximations,
+                origin_lod,
+                second_order,
+                view_position,
+                coordinates,
+            );
+
+            chunk
+                .iter()
+                .zip(positions)
+                .map(|(&(surface_position, _), position)| surface_position.distance(position))
+                .collect_vec()
+        })
+        .collect()
+}
+
+// Bakes the maximum second order error around view positions on the surface into one heatmap per cube face,
+// and into an atlas of all faces with borders for sampling them without seams.
+// The colors are scaled logarithmically, the scale is described in the `heatmaps.json` sidecar.
+fn export_heatmaps(directory: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut rng = seeded_rng();
+
+    let model = TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0);
+
+    let view_lod = 10;
+    let threshold = 0.001 * model.scale();
+
+    let face_errors = (0..6)
+        .map(|face| {
+            iproduct!(0..HEATMAP_RESOLUTION, 0..HEATMAP_RESOLUTION)
+                .map(|(y, x)| {
+                    let uv = (UVec2::new(x, y).as_dvec2() + 0.5) / HEATMAP_RESOLUTION as f64;
+                    let view_position = Coordinate::new(face, uv).world_position(&model, 0.0);
+                    let view_coordinates = view_coordinates(view_position, &model);
+                    let approximations = approximations(&view_coordinates, view_position, &model);
+
+                    (0..HEATMAP_SAMPLES)
+                        .map(|_| {
+                            let surface_position =
+                                random_test_position(&mut rng, &model, threshold, view_position);
+                            let coordinate = tile_coordinate_from_world_position(
+                                surface_position,
+                                view_lod,
+                                &model,
+                            );
+
+                            surface_position.distance(approximate_world_position(
+                                &view_coordinates,
+                                &approximations,
+                                view_lod,
+                                true,
+                                view_position,
+                                coordinate,
+                            ))
+                        })
+                        .fold(0.0, f64::max)
+                })
+                .collect_vec()
+        })
+        .collect_vec();
+
+    // clamp to a tiny positive error, so that exact samples do not break the logarithmic scale
+    let errors = face_errors.iter().flatten().map(|&error| error.max(1e-12));
+    let min_error = errors.clone().fold(f64::INFINITY, f64::min);
+    let max_error = errors.fold(0.0, f64::max);
+    let (log_min, log_max) = (min_error.log10(), max_error.log10());
+
+    std::fs::create_dir_all(directory)?;
+
+    for (face, errors) in face_errors.iter().enumerate() {
+        let image = image::RgbImage::from_fn(HEATMAP_RESOLUTION, HEATMAP_RESOLUTION, |x, y| {
+            let error = errors[(y * HEATMAP_RESOLUTION + x) as usize].max(1e-12);
+            let t = ((error.log10() - log_min) / (log_max - log_min).max(f64::EPSILON)) as f32;
+
+            image::Rgb(heatmap_color(t))
+        });
+
+        image.save(directory.join(format!("face{face}.png")))?;
+    }
+
+    // the same heatmaps in one atlas, each border texel takes the nearest pixel of the face it continues onto
+    let atlas = FaceAtlas::from_face_resolution(HEATMAP_RESOLUTION, HEATMAP_ATLAS_BORDER);
+    let image = image::RgbImage::from_fn(atlas.size.x, atlas.size.y, |x, y| {
+        let Some(coordinate) = atlas.texel_coordinate(UVec2::new(x, y)) else {
+            return image::Rgb([0, 0, 0]);
+        };
+
+        let pixel = (coordinate.uv * HEATMAP_RESOLUTION as f64)
+            .as_uvec2()
+            .min(UVec2::splat(HEATMAP_RESOLUTION - 1));
+        let error = face_errors[coordinate.face as usize]
+            [(pixel.y * HEATMAP_RESOLUTION + pixel.x) as usize]
+            .max(1e-12);
+        let t = ((error.log10() - log_min) / (log_max - log_min).max(f64::EPSILON)) as f32;
+
+        image::Rgb(heatmap_color(t))
+    });
+
+    image.save(directory.join("atlas.png"))?;
+
+    let sidecar = json!({
+        "faces": (0..6).map(|face| format!("face{face}.png")).collect_vec(),
+        "atlas": {
+            "image": "atlas.png",
+            "size": [atlas.size.x, atlas.size.y],
+            "border": atlas.border,
+            "layout": "faces 0 to 2 in the top row and 3 to 5 in the bottom row, each inside a border continuing its neighbors",
+        },
+        "resolution": HEATMAP_RESOLUTION,
+        "layout": "pixel (x, y) covers the face uv cell (x, y), with uv (0, 0) in the top left corner",
+        "quantity": "maximum second order approximation error around the view position",
+        "unit": "m",
+        "scale": "log10",
+        "min": min_error,
+        "max": max_error,
+        "colors": HEATMAP_COLORS,
+        "view_lod": view_lod,
+        "sample_distance": threshold,
+        "samples_per_pixel": HEATMAP_SAMPLES,
+    });
+
+    std::fs::write(
+        directory.join("heatmaps.json"),
+        serde_json::to_string_pretty(&sidecar)?,
+    )?;
+
+    println!(
+        "Wrote error heatmaps ranging from {} to {} to {}.",
+        Length(min_error),
+        Length(max_error),
+        directory.display()
+    );
+
+    Ok(())
+}
+
+// Sweeps the view altitude logarithmically from 1 m to 10,000 km above fixed ground positions.
+// The surface samples around each ground position are drawn once, so only the altitude changes between the rows.
+// Each row reports the maximum error at the default origin lod, the validity radius within which
+// all samples stay below the tolerance, and the smallest origin lod for which all samples do.
+fn altitude_sweep() {
+    let mut rng = seeded_rng();
+
+    let model = TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0);
+
+    let view_samples = 64;
+    let surface_samples = 64;
+    let view_lod = 10;
+    let max_lod = 20;
+    let threshold = 0.01 * model.scale();
+
+    let views = (0..view_samples)
+        .map(|_| {
+            let ground = Coordinate::new(
+                rng.gen_range(0..6),
+                DVec2::new(rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0)),
+            );
+            let ground_position = ground.world_position(&model, 0.0);
+
+            let surface_positions = (0..surface_samples)
+                .map(|_| random_test_position(&mut rng, &model, threshold, ground_position))
+                .collect_vec();
+
+            (ground, ground_position, surface_positions)
+        })
+        .collect_vec();
+
+    println!("altitude [m], max error [m], validity radius [m], min origin lod");
+
+    for step in 0..=7 * SWEEP_STEPS_PER_DECADE {
+        let altitude = 10.0_f64.powf(step as f64 / SWEEP_STEPS_PER_DECADE as f64);
+
+        // (distance to the ground position, error) for each sample and origin lod
+        let lod_errors = (0..=max_lod)
+            .map(|lod| {
+                views
+                    .iter()
+                    .flat_map(|(ground, ground_position, surface_positions)| {
+                        let view_position = ground.world_position(&model, altitude as f32);
+                        let view_coordinates = view_coordinates(view_position, &model);
+                        let approximations =
+                            approximations(&view_coordinates, view_position, &model);
+
+                        surface_positions
+                            .iter()
+                            .map(|&surface_position| {
+                                let coordinate = tile_coordinate_from_world_position(
+                                    surface_position,
+                                    lod,
+                                    &model,
+                                );
+
+                                let error = surface_position.distance(approximate_world_position(
+                                    &view_coordinates,
+                                    &approximations,
+                                    lod,
+                                    true,
+                                    view_position,
+                                    coordinate,
+                                ));
+
+                                (surface_position.distance(*ground_position), error)
+                            })
+                            .collect_vec()
+                    })
+                    .collect_vec()
+            })
+            .collect_vec();
+
+        let mut errors = lod_errors[view_lod].clone();
+        errors.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let max_error = errors.iter().map(|&(_, error)| error).fold(0.0, f64::max);
+        let validity_radius = errors
+            .iter()
+            .find(|&&(_, error)| error > SWEEP_TOLERANCE)
+            .map_or(threshold, |&(distance, _)| distance);
+        let min_lod = lod_errors
+            .iter()
+            .position(|errors| errors.iter().all(|&(_, error)| error <= SWEEP_TOLERANCE))
+            .map_or("none".to_string(), |lod| lod.to_string());
+
+        println!("{altitude:.1}, {max_error:.6}, {validity_radius:.1}, {min_lod}");
+    }
+}
+
+// Reports how precisely the relative uv is computed at each lod, and the first lod at which its rounding
+// moves vertices by a noticeable part of their spacing, beyond which finer tiles need a fixed-point sub-tile offset.
+fn relative_uv_audit(origin_lod: u32, lods: RangeInclusive<u32>) {
+    let model = TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0);
+    let face_length = model.scale() * FRAC_PI_2;
+
+    let audits = validate::relative_uv_audit(
+        origin_lod,
+        lods,
+        AUDIT_VERTEX_RESOLUTION,
+        AUDIT_NEIGHBORHOOD,
+    );
+
+    println!(
+        "Relative uv of {AUDIT_VERTEX_RESOLUTION} vertices per tile edge, around an origin lod of {origin_lod}:"
+    );
+    println!("lod, exact [%], correctly rounded, max error [vertex spacings], max error [m]");
+    for audit in &audits {
+        let spacing = face_length / (tile_count(audit.lod) as f64 * AUDIT_VERTEX_RESOLUTION as f64);
+
+        println!(
+            "{}, {:.1}, {}, {:.3e}, {:.3e}",
+            audit.lod,
+            100.0 * audit.exact,
+            audit.correctly_rounded,
+            audit.max_error,
+            audit.max_error * spacing
+        );
+    }
+
+    match audits
+        .iter()
+        .find(|audit| audit.max_error > AUDIT_BOTTLENECK)
+    {
+        Some(audit) => println!(
+            "From lod {} on, the relative uv is off by more than {AUDIT_BOTTLENECK} vertex spacings and becomes the bottleneck.",
+            audit.lod
+        ),
+        None => println!(
+            "The relative uv stays within {AUDIT_BOTTLENECK} vertex spacings at all audited lods."
+        ),
+    }
+}
+
+fn print_origin_lod_table(budget: f64) {
+    let model = TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0);
+    let sample_radius = 0.001 * model.scale();
+
+    let table = find_min_origin_lod(&model, 1.0..1e7, sample_radius, budget, &mut seeded_rng());
+
+    println!(
+        "Smallest origin lod with a maximum error of {} within {}:",
+        Length(budget),
+        Length(sample_radius)
+    );
+    println!("altitude [m], origin lod");
+    for (altitude, lod) in table.entries {
+        println!("{altitude:.1}, {lod}");
+    }
+}
+
+// Position along the track, on a Catmull-Rom spline through the samples, so the replay has no kinks at them.
+fn track_position(samples: &[TrackSample], path: &DPath, time: f64) -> DVec3 {
+    let index = samples
+        .partition_point(|sample| sample.time <= time)
+        .clamp(1, samples.len() - 1);
+    let (start, end) = (samples[index - 1], samples[index]);
+    let t = ((time - start.time) / (end.time - start.time).max(f64::EPSILON)).clamp(0.0, 1.0);
+
+    path.position((index - 1) as f64 + t)
+}
+
+// Replays a recorded camera track frame by frame, the way the demo updates its approximation.
+// The approximation is recomputed at its own fixed rate and blended between frames,
+// so the errors include the lag of the approximation and the transients when the origin tile changes.
+fn track_errors(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let samples = load_czml_track(path)?;
+    if samples.len() < 2 {
+        return Err("the track needs at least two samples".into());
+    }
+
+    let mut rng = seeded_rng();
+
+    let model = TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0);
+    let track = DPath::catmull_rom(
+        samples
+            .iter()
+            .map(|sample| sample.position.world_position(&model))
+            .collect(),
+    );
+
+    let surface_samples = 100;
+    let view_lod = 10;
+    let threshold = 0.001 * model.scale();
+
+    let (start, end) = (samples[0].time, samples[samples.len() - 1].time);
+    let step = TRACK_APPROXIMATION_RATE.recip();
+
+    let mut approximation = ViewApproximation::default();
+    let mut fixed_time = start;
+    let mut origin_tile = None;
+
+    let mut count = 0;
+    let mut error_max: f64 = 0.0;
+    let mut error_avg: f64 = 0.0;
+    let mut worst_time = start;
+    let mut switch_count = 0;
+    let mut switch_max: f64 = 0.0;
+    let mut steady_max: f64 = 0.0;
+    let mut lag_max: f64 = 0.0;
+
+    for frame in 0..=((end - start) * TRACK_FRAME_RATE).ceil() as usize {
+        let time = (start + frame as f64 / TRACK_FRAME_RATE).min(end);
+        let view_position = track_position(&samples, &track, time);
+
+        while fixed_time <= time {
+            approximation.push(ApproximationSnapshot::compute(
+                track_position(&samples, &track, fixed_time),
+                &model,
+            ));
+            fixed_time += step;
+        }
+
+        let overstep = ((time - (fixed_time - step)) / step) as f32;
+        let Some(snapshot) = approximation.interpolated(overstep) else {
+            continue;
+        };
+
+        let view_tile = tile_coordinate_from_world_position(view_position, view_lod, &model).0;
+        let switched = origin_tile.is_some_and(|tile| tile != view_tile);
+        origin_tile = Some(view_tile);
+
+        let mut frame_max: f64 = 0.0;
+
+        for _ in 0..surface_samples {
+            let surface_position = random_test_position(&mut rng, &model, threshold, view_position);
+            let coordinate =
+                tile_coordinate_from_world_position(surface_position, view_lod, &model);
+
+            let error = surface_position.distance(approximate_world_position(
+                &snapshot.view_coordinates,
+                &snapshot.approximations,
+                view_lod,
+                true,
+                snapshot.view_position,
+                coordinate,
+            ));
+
+            count += 1;
+            error_avg += error;
+            frame_max = frame_max.max(error);
+        }
+
+        if frame_max > error_max {
+            error_max = frame_max;
+            worst_time = time;
+        }
+
+        if switched {
+            switch_count += 1;
+            switch_max = switch_max.max(frame_max);
+        } else {
+            steady_max = steady_max.max(frame_max);
+        }
+
+        lag_max = lag_max.max(view_position.distance(snapshot.view_position));
+    }
+
+    error_avg /= count as f64;
+
+    println!(
+        "Replayed {:.1} s of the track at {TRACK_FRAME_RATE} fps, with approximation updates at {TRACK_APPROXIMATION_RATE} Hz.",
+        end - start
+    );
+    println!(
+        "The second order error is {} on average and {} at the maximum, which occurs at {:.2} s.",
+        Length(error_avg),
+        Length(error_max),
+        worst_time - start
+    );
+    println!("The origin tile at lod {view_lod} changed {switch_count} times, with a maximum error of {} in those frames and {} in all others.", Length(switch_max), Length(steady_max));
+    println!(
+        "The blended approximation lags the camera by up to {}.",
+        Length(lag_max)
+    );
+
+    Ok(())
+}
+
+// Saves the view with the largest error of the study, with the samples above the threshold, as a fixture.
+fn save_fixture(errors: &Errors, path: &Path) -> Result<(), FixtureError> {
+    let Some(fixture) = &errors.worst_view else {
+        println!("The study did not sample any view.");
+        return Ok(());
+    };
+
+    if fixture.samples.is_empty() {
+        println!(
+            "No sample exceeded {}, so there is no failing case to save.",
+            Length(FIXTURE_THRESHOLD)
+        );
+        return Ok(());
+    }
+
+    fixture.save(path)?;
+    println!(
+        "Saved {} samples above {} at the view {:?} to {}.",
+        fixture.samples.len(),
+        Length(FIXTURE_THRESHOLD),
+        fixture.view_position,
+        path.display()
+    );
+
+    Ok(())
+}
+
+// Replays a saved fixture against the current math, and compares the errors with the recorded ones.
+fn replay_fixture(path: &Path) -> Result<(), FixtureError> {
+    let model = TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0);
+
+    let fixture = ApproximationFixture::load(path)?;
+    let replay = fixture.replay(&model)?;
+
+    println!(
+        "The view coordinates deviate by {:e} in uv and the coefficients by {:e} from the recorded ones.",
+        replay.view_deviation, replay.coefficient_deviation
+    );
+    for (sample, error) in fixture.samples.iter().zip(&replay.errors) {
+        println!(
+            "    {} at {:?}: recorded {}, replayed {}",
+            TileName(sample.tile),
+            sample.tile_uv,
+            Length(sample.error),
+            Length(*error)
+        );
+    }
+    println!(
+        "The largest replayed error is {}, against a threshold of {}.",
+        Length(replay.max_error()),
+        Length(fixture.threshold)
+    );
+
+    Ok(())
+}
+
+fn heatmap_color(t: f32) -> [u8; 3] {
+    let t = t.clamp(0.0, 1.0) * (HEATMAP_COLORS.len() - 1) as f32;
+    let index = (t as usize).min(HEATMAP_COLORS.len() - 2);
+    let fraction = t - index as f32;
+
+    let (start, end) = (HEATMAP_COLORS[index], HEATMAP_COLORS[index + 1]);
+
+    std::array::from_fn(|i| {
+        (start[i] as f32 + (end[i] as f32 - start[i] as f32) * fraction).round() as u8
+    })
+}
+
+fn validate() {
+    let model = TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0);
+    let resolution = 64;
+
+    println!(
+        "cube_to_sphere and sphere_to_cube round trip within {:e}.",
+        validate::cube_sphere_round_trip(resolution)
+    );
+    println!(
+        "The f32x8 kernel deviates from the scalar approximation by {}.",
+        Length(validate::simd_consistency(&model, resolution))
+    );
+    println!(
+        "The crate's cube mapping deviates from the library's by at most {:e}.",
+        validate::library_mapping_consistency(&model, resolution)
+    );
+    println!(
+        "Texels in the margin of a tile texture land on their neighboring tile within {:.2e} texels.",
+        validate::margin_consistency(TileMargin::new(260, 2))
+    );
+    println!(
+        "The face rotations match the cube mapping within {:e}.",
+        validate::face_rotation_consistency(&model)
+    );
+    for height in [0.0, 1000.0, 100000.0] {
+        println!(
+            "World positions at a height of {} round trip within {}.",
+            Length(height as f64),
+            Length(validate::world_position_round_trip(
+                &model, resolution, height
+            ))
+        );
+    }
+
+    let mismatch = mapping_mismatch(&model, C_SQR, resolution);
+    let constant_mismatch = mapping_mismatch(&model, C_SQR as f32 as f64, resolution);
+    println!(
+        "The f32 reference path evaluated in f64 deviates from the library's mapping by {}.",
+        Length(mismatch)
+    );
+    println!(
+        "Rounding its stretch constant to f32 deviates by {}.",
+        Length(constant_mismatch)
+    );
+
+    let deviations = validate::project_to_face_consistency(&model, resolution);
+    println!("Projecting between faces (rows) and target faces (columns) round trips within:");
+    for row in deviations {
+        println!(
+            "{}",
+            row.iter()
+                .map(|deviation| format!("{deviation:10.3e}"))
+                .join(" ")
+        );
     }
 }
 
 fn main() {
+    if std::env::args().any(|arg| arg == "--validate") {
+        validate();
+        return;
+    }
+
+    if let Some(directory) = std::env::args()
+        .skip_while(|arg| arg != "--heatmaps")
+        .nth(1)
+    {
+        export_heatmaps(Path::new(&directory)).unwrap_or_else(|error| panic!("{error}"));
+        return;
+    }
+
+    if let Some(path) = std::env::args().skip_while(|arg| arg != "--track").nth(1) {
+        track_errors(Path::new(&path)).unwrap_or_else(|error| panic!("{error}"));
+        return;
+    }
+
+    if let Some(budget) = std::env::args()
+        .skip_while(|arg| arg != "--origin-lod-budget")
+        .nth(1)
+    {
+        print_origin_lod_table(budget.parse().expect("the budget is not a number"));
+        return;
+    }
+
+    if std::env::args().any(|arg| arg == "--c-sqr-sweep") {
+        c_sqr_sweep();
+        return;
+    }
+
+    if std::env::args().any(|arg| arg == "--altitude-sweep") {
+        altitude_sweep();
+        return;
+    }
+
+    if std::env::args().any(|arg| arg == "--relative-uv-audit") {
+        let mut lods = std::env::args()
+            .skip_while(|arg| arg != "--relative-uv-audit")
+            .skip(1)
+            .map_while(|arg| arg.parse::<u32>().ok());
+
+        let origin_lod = lods.next().unwrap_or(10);
+        let first_lod = lods.next().unwrap_or(origin_lod);
+        let last_lod = lods.next().unwrap_or(MAX_LOD).min(MAX_LOD);
+
+        relative_uv_audit(origin_lod, first_lod..=last_lod);
+        return;
+    }
+
+    if let Some(path) = std::env::args()
+        .skip_while(|arg| arg != "--replay-fixture")
+        .nth(1)
+    {
+        replay_fixture(Path::new(&path)).unwrap_or_else(|error| panic!("{error}"));
+        return;
+    }
+
+    let camera_start = std::env::args()
+        .skip_while(|arg| arg != "--start")
+        .skip(1)
+        .take(3)
+        .map(|value| value.parse::<f64>().expect("the start is not a number"))
+        .collect_tuple()
+        .map(|(latitude, longitude, altitude)| {
+            Geodetic::from_degrees(latitude, longitude, altitude)
+        });
+
     let errors = compute_errors();
 
+    if let Some(path) = std::env::args()
+        .skip_while(|arg| arg != "--save-fixture")
+        .nth(1)
+    {
+        save_fixture(&errors, Path::new(&path)).unwrap_or_else(|error| panic!("{error}"));
+        return;
+    }
+
     if true {
         App::new()
             .add_plugins((
-                DefaultPlugins.build().disable::<TransformPlugin>(),
+                DefaultPlugins
+                    .build()
+                    .disable::<TransformPlugin>()
+                    .set(RenderPlugin {
+                        // wireframes need line polygons
+                        render_creation: WgpuSettings {
+                            features: WgpuFeatures::POLYGON_MODE_LINE,
+                            ..default()
+                        }
+                        .into(),
+                        ..default()
+                    }),
+                WireframePlugin,
                 TerrainPlugin,
                 TerrainDebugPlugin,
             ))
             .insert_resource(errors)
+            .insert_resource(CameraStart(camera_start))
             .insert_resource(ClearColor(basic::WHITE.into()))
             .add_systems(Startup, setup)
-            .add_systems(Update, update)
+            .add_systems(Update, (update, update_near_plane, toggle_earth_display))
             .run();
     }
 }
 
 const RADIUS: f64 = 10.0;
 
+// where the camera starts, given with --start <latitude> <longitude> <altitude> in degrees and meters
+// the altitude is scaled down to the scene like the view positions, so that the camera looks at the same horizon
+#[derive(Resource)]
+struct CameraStart(Option<Geodetic>);
+
+// How the earth sphere below the gizmos is shown, cycled with V.
+// The wireframe lets the gizmos behind the surface shine through, while still showing where the surface is.
+#[derive(Component, Default, Clone, Copy, PartialEq, Eq, Debug)]
+enum EarthDisplay {
+    #[default]
+    Solid,
+    Wireframe,
+    Hidden,
+}
+
+impl EarthDisplay {
+    fn next(self) -> Self {
+        match self {
+            EarthDisplay::Solid => EarthDisplay::Wireframe,
+            EarthDisplay::Wireframe => EarthDisplay::Hidden,
+            EarthDisplay::Hidden => EarthDisplay::Solid,
+        }
+    }
+}
+
 fn setup(
+    camera_start: Res<CameraStart>,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    let camera_position = -DVec3::X * RADIUS * 3.0;
+    let sphere = TerrainModel::sphere(DVec3::ZERO, RADIUS, 0.0, 0.0);
+
+    let (camera_position, camera_rotation) = match camera_start.0 {
+        Some(geodetic) => horizon_pose(
+            Geodetic {
+                height: geodetic.height / 6378137.0 * RADIUS,
+                ..geodetic
+            },
+            &sphere,
+        ),
+        None => (
+            -DVec3::X * RADIUS * 3.0,
+            Transform::IDENTITY.looking_to(Vec3::X, Vec3::Y).rotation,
+        ),
+    };
 
     commands.spawn_big_space(ReferenceFrame::default(), |root| {
         let frame = root.frame().clone();
@@ -266,34 +1090,67 @@ fn setup(
         let mut material = StandardMaterial::from_color(basic::GRAY);
         material.unlit = true;
 
-        root.spawn_spatial(PbrBundle {
-            mesh: meshes.add(Sphere::new(0.9999 * RADIUS as f32).mesh().ico(20).unwrap()),
-            material: materials.add(material),
-            ..default()
-        });
+        root.spawn_spatial((
+            PbrBundle {
+                mesh: meshes.add(Sphere::new(0.9999 * RADIUS as f32).mesh().ico(20).unwrap()),
+                material: materials.add(material),
+                ..default()
+            },
+            EarthDisplay::default(),
+        ));
 
         let (camera_cell, camera_translation) = frame.translation_to_grid(camera_position);
-        root.spawn_spatial(DebugCameraBundle {
-            camera: Camera3dBundle {
-                transform: Transform::from_translation(camera_translation)
-                    .looking_to(Vec3::X, Vec3::Y),
-                projection: PerspectiveProjection {
-                    near: 0.001,
+        root.spawn_spatial((
+            DebugCameraBundle {
+                camera: Camera3dBundle {
+                    transform: Transform::from_translation(camera_translation)
+                        .with_rotation(camera_rotation),
+                    projection: PerspectiveProjection {
+                        near: 0.001,
+                        ..default()
+                    }
+                    .into(),
                     ..default()
-                }
-                .into(),
+                },
+                cell: camera_cell,
+                controller: DebugCameraController {
+                    translation_speed: RADIUS,
+                    ..default()
+                },
                 ..default()
             },
-            cell: camera_cell,
-            controller: DebugCameraController {
-                translation_speed: RADIUS,
-                ..default()
+            AdaptiveNearPlane {
+                min_near: 0.001,
+                ..AdaptiveNearPlane::new(sphere)
             },
-            ..default()
-        });
+        ));
     });
 }
 
+fn toggle_earth_display(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    mut earth_query: Query<(Entity, &mut EarthDisplay, &mut Visibility)>,
+) {
+    if !input.just_pressed(KeyCode::KeyV) {
+        return;
+    }
+
+    for (entity, mut display, mut visibility) in &mut earth_query {
+        *display = display.next();
+
+        *visibility = match *display {
+            EarthDisplay::Hidden => Visibility::Hidden,
+            _ => Visibility::Inherited,
+        };
+
+        match *display {
+            EarthDisplay::Wireframe => commands.entity(entity).insert(Wireframe),
+            _ => commands.entity(entity).remove::<Wireframe>(),
+        };
+    }
+}
+
 fn update(errors: Res<Errors>, mut gizmos: Gizmos) {
     let model = TerrainModel::sphere(DVec3::ZERO, RADIUS, 0.0, 0.0);
 
@@ -0,0 +1,139 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    math::{DAffine3, DQuat},
+    prelude::*,
+};
+use bevy_terrain::big_space::{FloatingOrigin, GridCell, GridTransformReadOnly, ReferenceFrames};
+
+use crate::format::Length;
+
+// number of annotated entities whose rendered position currently exceeds their budget
+pub const BUDGET_VIOLATIONS: DiagnosticPath =
+    DiagnosticPath::const_new("precision/budget_violations");
+
+// The largest error in meters the rendered position of the entity may have, compared to its position composed in f64.
+// Annotated entities are checked every frame, so "is this entity precise enough?" is answered by the diagnostics
+// and the log instead of by looking for jitter.
+// The entity may be placed in the grid itself, or be a descendant of an entity that is.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct PrecisionBudget {
+    pub max_error: f64,
+    // error measured in the last frame
+    pub error: Option<f64>,
+    violating: bool,
+}
+
+impl PrecisionBudget {
+    pub fn new(max_error: f64) -> Self {
+        Self {
+            max_error,
+            error: None,
+            violating: false,
+        }
+    }
+
+    pub fn is_violated(&self) -> bool {
+        self.violating
+    }
+}
+
+pub struct PrecisionBudgetPlugin;
+
+impl Plugin for PrecisionBudgetPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(BUDGET_VIOLATIONS))
+            .add_systems(Last, verify_precision_budgets);
+    }
+}
+
+fn local_affine(transform: &Transform) -> DAffine3 {
+    DAffine3::from_scale_rotation_translation(
+        transform.scale.as_dvec3(),
+        DQuat::from_quat(transform.rotation),
+        transform.translation.as_dvec3(),
+    )
+}
+
+// Composes the transforms from the entity up to the first ancestor placed in the grid in f64,
+// the same f32 components the transform propagation uses, without its rounding.
+fn ground_truth(
+    entity: Entity,
+    frames: &ReferenceFrames,
+    hierarchy_query: &Query<(&Transform, Option<&GridCell>, Option<&Parent>)>,
+) -> Option<DAffine3> {
+    let mut local = DAffine3::IDENTITY;
+    let mut current = entity;
+
+    loop {
+        let (transform, cell, parent) = hierarchy_query.get(current).ok()?;
+
+        if let Some(cell) = cell {
+            let frame = frames.parent_frame(current)?;
+            let mut root = local_affine(transform);
+            root.translation = frame.grid_position_double(cell, transform);
+
+            return Some(root * local);
+        }
+
+        local = local_affine(transform) * local;
+        current = parent?.get();
+    }
+}
+
+// Compares the propagated translation of each annotated entity with its ground truth, both relative to the view.
+// The camera is the floating origin, so its global translation is its position in render space.
+fn verify_precision_budgets(
+    frames: ReferenceFrames,
+    view_query: Query<(Entity, GridTransformReadOnly, &GlobalTransform), With<FloatingOrigin>>,
+    mut budget_query: Query<(
+        Entity,
+        &mut PrecisionBudget,
+        &GlobalTransform,
+        Option<&Name>,
+    )>,
+    hierarchy_query: Query<(&Transform, Option<&GridCell>, Option<&Parent>)>,
+    mut diagnostics: Diagnostics,
+) {
+    let Ok((view, view_transform, view_global)) = view_query.get_single() else {
+        warn_once!("Skipping verify_precision_budgets: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!(
+            "Skipping verify_precision_budgets: the camera is not part of a reference frame."
+        );
+        return;
+    };
+
+    let view_position = view_transform.position_double(&frame);
+    let mut violations = 0;
+
+    for (entity, mut budget, global, name) in &mut budget_query {
+        let Some(truth) = ground_truth(entity, &frames, &hierarchy_query) else {
+            warn_once!(
+                "Skipping verify_precision_budgets for {entity:?}: it is not placed in a reference frame."
+            );
+            continue;
+        };
+
+        let expected = view_global.translation() + (truth.translation - view_position).as_vec3();
+        let error = global.translation().distance(expected) as f64;
+        let violating = error > budget.max_error;
+
+        if violating && !budget.violating {
+            let name = name.map_or_else(|| format!("{entity:?}"), |name| name.to_string());
+
+            warn!(
+                "The rendered position of {name} is off by {}, exceeding its budget of {}.",
+                Length(error),
+                Length(budget.max_error)
+            );
+        }
+
+        budget.error = Some(error);
+        budget.violating = violating;
+        violations += violating as usize;
+    }
+
+    diagnostics.add_measurement(&BUDGET_VIOLATIONS, || violations as f64);
+}
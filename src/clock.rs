@@ -0,0 +1,87 @@
+use bevy::{prelude::*, time::TimeSystem};
+use std::fmt;
+
+// The simulated time, which drives everything that follows the clock of the scene rather than the frames,
+// like the orbits. It can be paused and scaled, so that days pass in seconds.
+// The whole seconds are kept apart from the fraction, so that the fraction keeps its precision over years of simulated time.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct SimulationTime {
+    seconds: i64,
+    fraction: f64,
+    delta: f64,
+    // simulated seconds per real second
+    pub scale: f64,
+    pub paused: bool,
+}
+
+impl Default for SimulationTime {
+    fn default() -> Self {
+        Self {
+            seconds: 0,
+            fraction: 0.0,
+            delta: 0.0,
+            scale: 1.0,
+            paused: false,
+        }
+    }
+}
+
+impl SimulationTime {
+    pub fn advance(&mut self, real_delta: f64) {
+        self.delta = if self.paused {
+            0.0
+        } else {
+            real_delta * self.scale
+        };
+
+        self.fraction += self.delta;
+        let whole = self.fraction.floor();
+        self.seconds += whole as i64;
+        self.fraction -= whole;
+    }
+
+    pub fn elapsed_seconds(&self) -> f64 {
+        self.seconds as f64 + self.fraction
+    }
+
+    // The elapsed time modulo the period, computed before the fraction is added,
+    // so that periodic motion stays as precise after years as in the first period.
+    pub fn elapsed_seconds_wrapped(&self, period: f64) -> f64 {
+        (self.seconds as f64).rem_euclid(period) + self.fraction
+    }
+
+    // simulated seconds passed during the last frame
+    pub fn delta_seconds(&self) -> f64 {
+        self.delta
+    }
+}
+
+// e.g. `T+12d 03:25:07.250`
+impl fmt::Display for SimulationTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.seconds.rem_euclid(60) as f64 + self.fraction;
+        let minutes = self.seconds.div_euclid(60);
+
+        write!(
+            f,
+            "T+{}d {:02}:{:02}:{:06.3}",
+            minutes.div_euclid(1440),
+            minutes.div_euclid(60).rem_euclid(24),
+            minutes.rem_euclid(60),
+            seconds
+        )
+    }
+}
+
+pub struct SimulationTimePlugin;
+
+impl Plugin for SimulationTimePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationTime>()
+            .add_systems(First, advance_simulation_time.after(TimeSystem));
+    }
+}
+
+fn advance_simulation_time(time: Res<Time>, mut simulation_time: ResMut<SimulationTime>) {
+    simulation_time.advance(time.delta_seconds_f64());
+}
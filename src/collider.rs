@@ -0,0 +1,143 @@
+use bevy::{math::DVec3, prelude::*};
+use bevy_terrain::{
+    big_space::{GridTransform, ReferenceFrames},
+    math::Coordinate,
+    prelude::*,
+};
+
+use crate::{
+    epoch::{shift_model, Rebase},
+    geodesy::{enu_frame, Geodetic},
+    height::{HeightProvider, TerrainHeights},
+    model_copy::ModelCopy,
+};
+
+const GRAVITY: f64 = 9.81;
+const FRICTION: f64 = 0.5;
+
+pub struct Contact {
+    // world space position of the contact point on the terrain
+    pub point: DVec3,
+    pub normal: DVec3,
+    pub penetration: f64,
+}
+
+// Exposes height and normal queries of the terrain to physics, evaluated in f64.
+#[derive(Component)]
+pub struct TerrainCollider {
+    pub model: TerrainModel,
+}
+
+impl TerrainCollider {
+    pub fn height(&self, position: DVec3, heights: &dyn HeightProvider) -> f64 {
+        heights.height(Coordinate::from_world_position(position, &self.model))
+    }
+
+    pub fn altitude(&self, position: DVec3, heights: &dyn HeightProvider) -> f64 {
+        Geodetic::from_world_position(position, &self.model).height - self.height(position, heights)
+    }
+
+    // Terrain normal from central differences of the height along the local east and north directions.
+    pub fn normal(&self, position: DVec3, heights: &dyn HeightProvider) -> DVec3 {
+        let geodetic = Geodetic::from_world_position(position, &self.model);
+        let enu = enu_frame(geodetic, &self.model);
+        let delta = 1.0;
+
+        let slope = |direction: DVec3| {
+            (self.height(position + delta * direction, heights)
+                - self.height(position - delta * direction, heights))
+                / (2.0 * delta)
+        };
+
+        (enu.z_axis - slope(enu.x_axis) * enu.x_axis - slope(enu.y_axis) * enu.y_axis).normalize()
+    }
+
+    pub fn contact(
+        &self,
+        position: DVec3,
+        radius: f64,
+        heights: &dyn HeightProvider,
+    ) -> Option<Contact> {
+        let altitude = self.altitude(position, heights);
+
+        if altitude > radius {
+            return None;
+        }
+
+        let geodetic = Geodetic::from_world_position(position, &self.model);
+        let up = enu_frame(geodetic, &self.model).z_axis;
+
+        Some(Contact {
+            point: position - altitude * up,
+            normal: self.normal(position, heights),
+            penetration: radius - altitude,
+        })
+    }
+}
+
+impl ModelCopy for TerrainCollider {
+    fn set_model(&mut self, model: &TerrainModel) {
+        self.model = model.clone();
+    }
+}
+
+impl Rebase for TerrainCollider {
+    fn rebase(&mut self, offset: DVec3) {
+        self.model = shift_model(&self.model, offset);
+    }
+}
+
+// A minimal rigid body, that falls towards the planet and rests on the terrain.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct RigidBody {
+    pub velocity: DVec3,
+    pub radius: f64,
+}
+
+// The bodies are integrated from their f64 position, which is reconstructed from the grid cell every step,
+// so the contact resolution never sees the rounding of a large f32 translation.
+pub fn integrate_rigid_bodies(
+    time: Res<Time>,
+    heights: Res<TerrainHeights>,
+    frames: ReferenceFrames,
+    collider_query: Query<&TerrainCollider>,
+    mut body_query: Query<(Entity, &mut RigidBody, GridTransform)>,
+) {
+    let delta_time = time.delta_seconds_f64();
+
+    for (entity, mut body, mut grid_transform) in &mut body_query {
+        let Some(frame) = frames.parent_frame(entity) else {
+            warn_once!("Skipping integrate_rigid_bodies for {entity:?}: it is not part of a reference frame.");
+            continue;
+        };
+        let mut position = grid_transform.position_double(&frame);
+
+        for collider in &collider_query {
+            let geodetic = Geodetic::from_world_position(position, &collider.model);
+            let up = enu_frame(geodetic, &collider.model).z_axis;
+
+            body.velocity -= GRAVITY * delta_time * up;
+        }
+
+        position += body.velocity * delta_time;
+
+        for collider in &collider_query {
+            if let Some(contact) = collider.contact(position, body.radius, heights.0.as_ref()) {
+                position += contact.penetration * contact.normal;
+
+                let normal_velocity = body.velocity.dot(contact.normal);
+
+                if normal_velocity < 0.0 {
+                    let tangential_velocity = body.velocity - normal_velocity * contact.normal;
+                    body.velocity = tangential_velocity * (1.0 - FRICTION * delta_time).max(0.0);
+                }
+            }
+        }
+
+        let (cell, translation) = frame.translation_to_grid(position);
+
+        *grid_transform.cell = cell;
+        grid_transform.transform.translation = translation;
+    }
+}
@@ -0,0 +1,251 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    math::{DVec2, DVec3},
+    prelude::*,
+};
+use bevy_terrain::{
+    big_space::{FloatingOrigin, GridTransformReadOnly, ReferenceFrames},
+    math::TileCoordinate,
+    prelude::*,
+};
+use itertools::iproduct;
+use std::f64::consts::FRAC_PI_2;
+
+use crate::{
+    epoch::{shift_model, Rebase, RebaseAppExt},
+    geodesy::axes,
+    math::{cube_to_sphere, tile_size},
+    model_copy::{ModelCopy, ModelCopyAppExt},
+    tile_mesh::TileOrigin,
+};
+
+pub const TILES_VISIBLE: DiagnosticPath = DiagnosticPath::const_new("tiles/visible");
+pub const TILES_OCCLUDED: DiagnosticPath = DiagnosticPath::const_new("tiles/occluded");
+
+#[derive(Resource)]
+pub struct TileCulling {
+    pub model: TerrainModel,
+    pub max_height: f64,
+    pub enabled: bool,
+}
+
+impl TileCulling {
+    pub fn new(model: TerrainModel, max_height: f64) -> Self {
+        Self {
+            model,
+            max_height,
+            enabled: true,
+        }
+    }
+}
+
+impl ModelCopy for TileCulling {
+    fn set_model(&mut self, model: &TerrainModel) {
+        self.model = model.clone();
+    }
+}
+
+impl Rebase for TileCulling {
+    fn rebase(&mut self, offset: DVec3) {
+        self.model = shift_model(&self.model, offset);
+    }
+}
+
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Occluded(pub bool);
+
+pub struct TileCullingPlugin;
+
+impl Plugin for TileCullingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_model_copy_resource::<TileCulling>()
+            .add_rebase_resource::<TileCulling>()
+            .register_diagnostic(Diagnostic::new(TILES_VISIBLE))
+            .register_diagnostic(Diagnostic::new(TILES_OCCLUDED))
+            .add_systems(Update, cull_tiles);
+    }
+}
+
+// Horizon test in the scaled space of the model, where the ellipsoid becomes the unit sphere.
+// A point is occluded, if it lies behind the horizon plane of the view and inside the cone the sphere casts from the view.
+pub fn behind_horizon(view_local: DVec3, point_local: DVec3) -> bool {
+    let horizon = view_local.length_squared() - 1.0;
+
+    // the view is inside the ellipsoid
+    if horizon <= 0.0 {
+        return false;
+    }
+
+    let view_to_point = point_local - view_local;
+    let projection = -view_to_point.dot(view_local);
+
+    projection > horizon && projection * projection / view_to_point.length_squared() > horizon
+}
+
+// Bounding cap of the tile on the unit sphere, its center direction and angular radius.
+// The edges of a tile map onto great circles, so the corners are the points farthest from its center.
+pub fn tile_bounding_cap(tile: TileCoordinate) -> (DVec3, f64) {
+    let size = tile_size(tile.lod);
+    let tile_min = UVec2::new(tile.x, tile.y).as_dvec2() * size;
+    let center = cube_to_sphere(tile.face, tile_min + 0.5 * size);
+
+    let angular_radius = iproduct!(0..2, 0..2)
+        .map(|(x, y)| {
+            cube_to_sphere(tile.face, tile_min + DVec2::new(x as f64, y as f64) * size)
+                .angle_between(center)
+        })
+        .fold(0.0, f64::max);
+
+    (center, angular_radius)
+}
+
+// Conservative occlusion test in the scaled space of the model.
+// The terrain of the tile lies within its bounding cap on the surface, raised by up to the maximum height,
+// which is enclosed by a sphere. The tile is only culled, if that sphere lies behind the horizon plane of the view
+// and inside the cone the planet casts from the view, so no part of the tile can be visible.
+// The test runs on the tile meshes of this crate. The tile tree of bevy_terrain selects and culls its tiles
+// in its own shaders, which offer no hook for a horizon test, so it cannot move there.
+pub fn tile_occluded(
+    tile: TileCoordinate,
+    view_position: DVec3,
+    model: &TerrainModel,
+    max_height: f64,
+) -> bool {
+    let view_local = model
+        .world_from_local
+        .inverse()
+        .transform_point3(view_position);
+    let view_distance = view_local.length();
+
+    // the view is inside the ellipsoid
+    if view_distance <= 1.0 {
+        return false;
+    }
+
+    let (center, angular_radius) = tile_bounding_cap(tile);
+
+    if angular_radius >= FRAC_PI_2 {
+        return false;
+    }
+
+    // a height is the longest in scaled space along the minor axis, so dividing by it bounds the offset of the terrain
+    let (_, minor_axis) = axes(model);
+    let offset = max_height.max(0.0) / minor_axis;
+
+    // the cap on the surface, bounded by a cylinder around its axis, and grown by the offset
+    let low = angular_radius.cos();
+    let sphere_center = center * 0.5 * (low + 1.0);
+    let sphere_radius = (0.5 * (1.0 - low)).hypot(angular_radius.sin()) + offset;
+
+    let view_direction = view_local / view_distance;
+
+    // the sphere has to lie beyond the horizon plane, whose distance from the center is the inverse of the view distance
+    if sphere_center.dot(view_direction) + sphere_radius >= view_distance.recip() {
+        return false;
+    }
+
+    // and inside the cone of the planet
+    let to_sphere = sphere_center - view_local;
+    let distance = to_sphere.length();
+    if distance <= sphere_radius {
+        return false;
+    }
+
+    let cone_angle = view_distance.recip().asin();
+    let axis_angle = to_sphere.angle_between(-view_local);
+
+    axis_angle + (sphere_radius / distance).asin() < cone_angle
+}
+
+pub fn cull_tiles(
+    culling: Res<TileCulling>,
+    frames: ReferenceFrames,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+    mut tile_query: Query<(&TileOrigin, &mut Occluded)>,
+    mut diagnostics: Diagnostics,
+) {
+    let Ok((view, view_transform)) = view_query.get_single() else {
+        warn_once!("Skipping cull_tiles: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping cull_tiles: the camera is not part of a reference frame.");
+        return;
+    };
+    let view_position = view_transform.position_double(&frame);
+
+    let mut visible = 0;
+    let mut occluded = 0;
+
+    for (tile_origin, mut tile_occluded_state) in &mut tile_query {
+        tile_occluded_state.0 = culling.enabled
+            && tile_occluded(
+                tile_origin.tile,
+                view_position,
+                &culling.model,
+                culling.max_height,
+            );
+
+        if tile_occluded_state.0 {
+            occluded += 1;
+        } else {
+            visible += 1;
+        }
+    }
+
+    diagnostics.add_measurement(&TILES_VISIBLE, || visible as f64);
+    diagnostics.add_measurement(&TILES_OCCLUDED, || occluded as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_terrain::math::Coordinate;
+
+    #[test]
+    fn occluded_tiles_are_hidden_everywhere() {
+        let model = TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0);
+        let max_height = 9000.0;
+        let lod = 3;
+        let samples = 8;
+        let local_from_world = model.world_from_local.inverse();
+
+        for view_position in [
+            Coordinate::new(0, DVec2::new(0.5, 0.5)).world_position(&model, 1000.0),
+            Coordinate::new(2, DVec2::new(0.1, 0.9)).world_position(&model, 400000.0),
+            Coordinate::new(4, DVec2::new(0.7, 0.2)).world_position(&model, 36000000.0),
+        ] {
+            let view_local = local_from_world.transform_point3(view_position);
+            let mut culled = 0;
+
+            for (face, x, y) in iproduct!(0..6, 0..1 << lod, 0..1 << lod) {
+                let tile = TileCoordinate::new(face, lod, x, y);
+
+                if !tile_occluded(tile, view_position, &model, max_height) {
+                    continue;
+                }
+                culled += 1;
+
+                for (i, j, height) in iproduct!(
+                    0..=samples,
+                    0..=samples,
+                    [0.0, 0.5 * max_height, max_height]
+                ) {
+                    let uv = (DVec2::new(x as f64, y as f64)
+                        + DVec2::new(i as f64, j as f64) / samples as f64)
+                        * tile_size(lod);
+                    let position = Coordinate::new(face, uv).world_position(&model, height as f32);
+
+                    assert!(
+                        behind_horizon(view_local, local_from_world.transform_point3(position)),
+                        "tile {x}, {y} of face {face} is culled, but visible at uv {uv}"
+                    );
+                }
+            }
+
+            // the planet hides at least the far side
+            assert!(culled > 0);
+        }
+    }
+}
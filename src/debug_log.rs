@@ -0,0 +1,126 @@
+use bevy::{math::DVec3, prelude::*};
+use bevy_terrain::{big_space::GridCell, math::TileCoordinate, prelude::*};
+use serde_json::{json, Value};
+use std::fmt;
+
+use crate::{
+    format::Length,
+    geodesy::Geodetic,
+    math::tile_coordinate_from_world_position,
+    notation::{CoordinateName, TileName},
+    view_approximation::ApproximationSnapshot,
+};
+
+// Where the view was when a line was logged, so that a report can be traced back to a place on the planet.
+#[derive(Clone, Copy, Debug)]
+pub struct ViewStamp {
+    // origin tile of the view
+    pub tile: TileCoordinate,
+    pub geodetic: Geodetic,
+    pub cell: GridCell,
+}
+
+impl ViewStamp {
+    pub fn new(
+        view_position: DVec3,
+        cell: GridCell,
+        origin_lod: u32,
+        model: &TerrainModel,
+    ) -> Self {
+        Self {
+            tile: tile_coordinate_from_world_position(view_position, origin_lod, model).0,
+            geodetic: Geodetic::from_world_position(view_position, model),
+            cell,
+        }
+    }
+}
+
+impl fmt::Display for ViewStamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:.5}°,{:.5}° {} cell {}/{}/{}",
+            TileName(self.tile),
+            self.geodetic.latitude_degrees(),
+            self.geodetic.longitude_degrees(),
+            Length(self.geodetic.height),
+            self.cell.x,
+            self.cell.y,
+            self.cell.z
+        )
+    }
+}
+
+// The stamp of the camera in the current frame, kept up to date by the scene.
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct CameraStamp(pub Option<ViewStamp>);
+
+impl fmt::Display for CameraStamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(stamp) => stamp.fmt(f),
+            None => write!(f, "no camera"),
+        }
+    }
+}
+
+// Logs a line prefixed with a stamp, e.g. `stamped_info!(stamp, "Picked {coordinate}.")`.
+#[macro_export]
+macro_rules! stamped_info {
+    ($stamp:expr, $($arg:tt)+) => {
+        bevy::log::info!("[{}] {}", $stamp, format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! stamped_warn {
+    ($stamp:expr, $($arg:tt)+) => {
+        bevy::log::warn!("[{}] {}", $stamp, format_args!($($arg)+))
+    };
+}
+
+// Everything needed to reproduce the approximation of a frame, for attaching to bug reports.
+// Positions are written in full f64 precision, the coefficients in the f32 precision the shaders receive.
+pub fn precision_state(
+    model: &TerrainModel,
+    snapshot: Option<&ApproximationSnapshot>,
+    view_position: DVec3,
+    view_rotation: Quat,
+    stamp: &ViewStamp,
+    origin_lod: u32,
+) -> Value {
+    let approximation = snapshot.map(|snapshot| {
+        json!({
+            "view_position": snapshot.view_position.to_array(),
+            "sides": (0..6).map(|face| {
+                let approximation = &snapshot.approximations[face];
+
+                json!({
+                    "view_coordinate": CoordinateName(snapshot.view_coordinates[face]).to_string(),
+                    "c": approximation.c.to_array(),
+                    "c_du": approximation.c_du.to_array(),
+                    "c_dv": approximation.c_dv.to_array(),
+                    "c_duu": approximation.c_duu.to_array(),
+                    "c_duv": approximation.c_duv.to_array(),
+                    "c_dvv": approximation.c_dvv.to_array(),
+                    "c_dh": snapshot.height_coefficients[face].to_array(),
+                })
+            }).collect::<Vec<_>>(),
+        })
+    });
+
+    json!({
+        "model": {
+            "world_from_local": model.world_from_local.to_cols_array(),
+        },
+        "camera": {
+            "position": view_position.to_array(),
+            "rotation": view_rotation.to_array(),
+            "cell": [stamp.cell.x, stamp.cell.y, stamp.cell.z],
+            "geodetic": stamp.geodetic.to_string(),
+            "origin_tile": TileName(stamp.tile).to_string(),
+            "origin_lod": origin_lod,
+        },
+        "approximation": approximation,
+    })
+}
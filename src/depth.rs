@@ -0,0 +1,87 @@
+use bevy::{math::DVec3, prelude::*};
+use bevy_terrain::{
+    big_space::{GridTransformReadOnly, ReferenceFrames},
+    prelude::*,
+};
+
+use crate::{
+    epoch::{shift_model, Rebase},
+    geodesy::Geodetic,
+    model_copy::ModelCopy,
+};
+
+// Bevy renders with an infinite reversed-z projection, where the depth precision is determined by the near plane alone.
+// Moving the near plane with the altitude keeps the depth resolution proportional to the distance to the surface,
+// which prevents z-fighting between the gizmos, the sphere mesh and the tiles at every scale.
+// When disabled, the near plane stays at a fixed distance, to compare the depth precision against it.
+#[derive(Component)]
+pub struct AdaptiveNearPlane {
+    pub model: TerrainModel,
+    pub enabled: bool,
+    // fraction of the altitude used as the near plane distance
+    pub factor: f64,
+    pub min_near: f32,
+    pub max_near: f32,
+    // near plane distance while disabled
+    pub fixed_near: f32,
+}
+
+impl AdaptiveNearPlane {
+    pub fn new(model: TerrainModel) -> Self {
+        Self {
+            model,
+            enabled: true,
+            factor: 0.1,
+            min_near: 0.01,
+            max_near: 1000.0,
+            fixed_near: 0.1,
+        }
+    }
+
+    pub fn near(&self, altitude: f64) -> f32 {
+        if !self.enabled {
+            return self.fixed_near;
+        }
+
+        ((self.factor * altitude.abs()) as f32).clamp(self.min_near, self.max_near)
+    }
+}
+
+impl ModelCopy for AdaptiveNearPlane {
+    fn set_model(&mut self, model: &TerrainModel) {
+        self.model = model.clone();
+    }
+}
+
+impl Rebase for AdaptiveNearPlane {
+    fn rebase(&mut self, offset: DVec3) {
+        self.model = shift_model(&self.model, offset);
+    }
+}
+
+pub fn update_near_plane(
+    frames: ReferenceFrames,
+    mut view_query: Query<(
+        Entity,
+        &AdaptiveNearPlane,
+        &mut Projection,
+        GridTransformReadOnly,
+    )>,
+) {
+    for (view, near_plane, mut projection, view_transform) in &mut view_query {
+        let Projection::Perspective(perspective) = projection.as_mut() else {
+            continue;
+        };
+
+        let Some(frame) = frames.parent_frame(view) else {
+            warn_once!(
+                "Skipping update_near_plane for {view:?}: it is not part of a reference frame."
+            );
+            continue;
+        };
+        let view_position = view_transform.position_double(&frame);
+        let altitude = Geodetic::from_world_position(view_position, &near_plane.model).height;
+
+        perspective.near = near_plane.near(altitude);
+    }
+}
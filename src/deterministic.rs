@@ -0,0 +1,177 @@
+use bevy::{
+    core::FrameCount,
+    input::{mouse::MouseMotion, InputSystem},
+    prelude::*,
+    time::TimeUpdateStrategy,
+    utils::HashSet,
+};
+use rand::{rngs::StdRng, SeedableRng};
+use std::time::Duration;
+
+// Runs the simulation with a fixed time step, a seeded random number generator and scripted keyboard input.
+// The real input is discarded, so two runs with the same seed and script produce bit-identical positions,
+// independent of the frame rate and of what the user does with the window.
+pub struct DeterministicPlugin {
+    pub seed: u64,
+    pub delta_time: Duration,
+    pub script: InputScript,
+    // keeps the real input and records it into the `InputRecording`, instead of replaying the script
+    pub record: bool,
+}
+
+impl DeterministicPlugin {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            delta_time: Duration::from_secs_f64(1.0 / 60.0),
+            script: InputScript::default(),
+            record: false,
+        }
+    }
+
+    pub fn recording(mut self) -> Self {
+        self.record = true;
+        self
+    }
+
+    pub fn with_script(mut self, script: InputScript) -> Self {
+        self.script = script;
+        self
+    }
+}
+
+impl Plugin for DeterministicPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(self.delta_time))
+            .insert_resource(Time::<Fixed>::from_duration(self.delta_time))
+            .insert_resource(SimulationRng(StdRng::seed_from_u64(self.seed)))
+            .insert_resource(Deterministic);
+
+        if self.record {
+            app.init_resource::<InputRecording>()
+                .add_systems(PreUpdate, record_input.after(InputSystem));
+        } else {
+            app.insert_resource(self.script.clone())
+                .add_systems(PreUpdate, replay_input_script.after(InputSystem));
+        }
+    }
+}
+
+// Present while the deterministic mode runs, for systems that wait for background work,
+// which would otherwise finish in a frame depending on the load of the machine.
+#[derive(Resource)]
+pub struct Deterministic;
+
+// The only source of randomness systems should use, so that deterministic runs can seed it.
+#[derive(Resource)]
+pub struct SimulationRng(pub StdRng);
+
+impl Default for SimulationRng {
+    fn default() -> Self {
+        Self(StdRng::from_entropy())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ScriptedKey {
+    pub frame: u32,
+    pub key: KeyCode,
+    pub pressed: bool,
+}
+
+// Key presses and releases, ordered by the frame they occur in.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct InputScript {
+    pub keys: Vec<ScriptedKey>,
+}
+
+impl InputScript {
+    pub fn press(mut self, frame: u32, key: KeyCode) -> Self {
+        self.keys.push(ScriptedKey {
+            frame,
+            key,
+            pressed: true,
+        });
+        self
+    }
+
+    pub fn release(mut self, frame: u32, key: KeyCode) -> Self {
+        self.keys.push(ScriptedKey {
+            frame,
+            key,
+            pressed: false,
+        });
+        self
+    }
+
+    // presses the key for a single frame
+    pub fn tap(self, frame: u32, key: KeyCode) -> Self {
+        self.press(frame, key).release(frame + 1, key)
+    }
+
+    // holds the key from the start frame until the end frame
+    pub fn hold(self, start: u32, end: u32, key: KeyCode) -> Self {
+        self.press(start, key).release(end, key)
+    }
+}
+
+fn replay_input_script(
+    mut held: Local<HashSet<KeyCode>>,
+    frame_count: Res<FrameCount>,
+    script: Res<InputScript>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut mouse_buttons: ResMut<ButtonInput<MouseButton>>,
+    mut mouse_motion: ResMut<Events<MouseMotion>>,
+) {
+    // discard the real input, but keep the keys held down by the script from earlier frames
+    keys.reset_all();
+
+    for &key in held.iter() {
+        keys.press(key);
+        keys.clear_just_pressed(key);
+    }
+
+    for scripted in script
+        .keys
+        .iter()
+        .filter(|scripted| scripted.frame == frame_count.0)
+    {
+        if scripted.pressed {
+            keys.press(scripted.key);
+            held.insert(scripted.key);
+        } else {
+            keys.release(scripted.key);
+            held.remove(&scripted.key);
+        }
+    }
+
+    mouse_buttons.reset_all();
+    mouse_motion.clear();
+}
+
+// The keyboard input of a recorded run, in the form the script replays it.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct InputRecording(pub InputScript);
+
+fn record_input(
+    frame_count: Res<FrameCount>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut recording: ResMut<InputRecording>,
+) {
+    let script = &mut recording.0;
+
+    for &key in keys.get_just_pressed() {
+        script.keys.push(ScriptedKey {
+            frame: frame_count.0,
+            key,
+            pressed: true,
+        });
+    }
+    for &key in keys.get_just_released() {
+        script.keys.push(ScriptedKey {
+            frame: frame_count.0,
+            key,
+            pressed: false,
+        });
+    }
+}
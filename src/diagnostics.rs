@@ -0,0 +1,202 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+    render::view::VisibilitySystems,
+};
+use bevy_terrain::{
+    math::{Coordinate, TileCoordinate},
+    prelude::*,
+};
+use std::collections::VecDeque;
+
+use crate::{
+    math::MAX_LOD,
+    probe::{tile_uv_to_uv, LodHierarchy, Probe},
+    tile_mesh::TileOrigin,
+    view_approximation::{ApproximationSnapshot, ViewApproximation},
+};
+
+pub const PROBE_MAX_ERROR: DiagnosticPath = DiagnosticPath::const_new("precision/probe_max_error");
+pub const APPROXIMATION_RECOMPUTES: DiagnosticPath =
+    DiagnosticPath::const_new("precision/approximation_recomputes");
+pub const ORIGIN_TILE_SWITCHES: DiagnosticPath =
+    DiagnosticPath::const_new("precision/origin_tile_switches");
+pub const TILES_DRAWN: DiagnosticPath = DiagnosticPath::const_new("precision/tiles_drawn");
+// evaluations per frame beyond the validity radius, which fell back to the exact position
+pub const APPROXIMATION_FALLBACKS: DiagnosticPath =
+    DiagnosticPath::const_new("precision/approximation_fallbacks");
+
+// Window over which the origin tile switches are counted.
+const SWITCH_WINDOW: f64 = 60.0;
+// distance from the view, beyond which the second order error on Earth grows past a few millimeters
+const DEFAULT_VALIDITY_RADIUS: f64 = 10000.0;
+
+// Registers the precision metrics with the diagnostics, so that they are logged with the frame time
+// and can be read from the `DiagnosticsStore` in automated runs.
+// The recomputes and drawn tiles are measured here, the probe error and origin tile switches depend on the
+// origin lod of the scene and are measured by it with `probe_max_error` and `OriginTileSwitches`.
+pub struct PrecisionDiagnosticsPlugin;
+
+impl Plugin for PrecisionDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(PROBE_MAX_ERROR).with_suffix(" m"))
+            .register_diagnostic(Diagnostic::new(APPROXIMATION_RECOMPUTES))
+            .register_diagnostic(Diagnostic::new(ORIGIN_TILE_SWITCHES).with_suffix(" /min"))
+            .register_diagnostic(Diagnostic::new(TILES_DRAWN))
+            .register_diagnostic(Diagnostic::new(APPROXIMATION_FALLBACKS))
+            .init_resource::<OriginTileSwitches>()
+            .init_resource::<ApproximationFallbacks>()
+            .add_systems(
+                PostUpdate,
+                (
+                    measure_approximation_recomputes,
+                    measure_approximation_fallbacks,
+                    measure_tiles_drawn.after(VisibilitySystems::CheckVisibility),
+                ),
+            );
+    }
+}
+
+// Counts how often the origin tile changed during the last minute.
+#[derive(Resource, Default)]
+pub struct OriginTileSwitches {
+    pub origin_tile: Option<TileCoordinate>,
+    // times of the switches, oldest first
+    pub switches: VecDeque<f64>,
+}
+
+impl OriginTileSwitches {
+    pub fn record(&mut self, origin_tile: TileCoordinate, time: f64) {
+        if self
+            .origin_tile
+            .replace(origin_tile)
+            .is_some_and(|previous| previous != origin_tile)
+        {
+            self.switches.push_back(time);
+        }
+
+        while self
+            .switches
+            .front()
+            .is_some_and(|&switch| switch < time - SWITCH_WINDOW)
+        {
+            self.switches.pop_front();
+        }
+    }
+
+    pub fn per_minute(&self) -> f64 {
+        self.switches.len() as f64 * 60.0 / SWITCH_WINDOW
+    }
+}
+
+// The validity radius of the evaluations in the scene, and how often they fell back to the exact position this frame.
+// Systems evaluating the approximation add their fallbacks, the count is measured and reset in `PostUpdate`.
+#[derive(Resource)]
+pub struct ApproximationFallbacks {
+    pub validity_radius: f64,
+    pub count: usize,
+}
+
+impl Default for ApproximationFallbacks {
+    fn default() -> Self {
+        Self {
+            validity_radius: DEFAULT_VALIDITY_RADIUS,
+            count: 0,
+        }
+    }
+}
+
+// Largest distance between the approximated and the exact surface positions in the region around the probe.
+pub fn probe_max_error(
+    probe: &Probe,
+    snapshot: &ApproximationSnapshot,
+    origin_lod: u32,
+    model: &TerrainModel,
+) -> Option<f64> {
+    let view_coordinates = snapshot.view_coordinates_at(origin_lod);
+
+    probe
+        .region(origin_lod, 4)
+        .into_iter()
+        .map(|(tile, tile_uv)| {
+            let approximate = view_coordinates.approximate_world_position(
+                &snapshot.approximations,
+                true,
+                snapshot.view_position,
+                (tile, tile_uv),
+            );
+            let exact =
+                Coordinate::new(tile.face, tile_uv_to_uv(tile, tile_uv)).world_position(model, 0.0);
+
+            approximate.distance(exact)
+        })
+        .reduce(f64::max)
+}
+
+// Largest error of the probe region at each lod of its hierarchy, from the finest to the coarsest lod.
+// All grids are evaluated relative to the same origin lod, like the tiles of those lods would be.
+pub fn probe_lod_errors(
+    probe: &Probe,
+    hierarchy: LodHierarchy,
+    snapshot: &ApproximationSnapshot,
+    origin_lod: u32,
+    model: &TerrainModel,
+) -> Vec<(u32, f64)> {
+    let view_coordinates = snapshot.view_coordinates_at(origin_lod);
+    let lods =
+        origin_lod.saturating_sub(hierarchy.spread)..=(origin_lod + hierarchy.spread).min(MAX_LOD);
+
+    lods.rev()
+        .filter_map(|lod| {
+            probe
+                .region_at_lod(lod, 4)
+                .into_iter()
+                .map(|(tile, tile_uv)| {
+                    let approximate = view_coordinates.approximate_world_position(
+                        &snapshot.approximations,
+                        true,
+                        snapshot.view_position,
+                        (tile, tile_uv),
+                    );
+                    let exact = Coordinate::new(tile.face, tile_uv_to_uv(tile, tile_uv))
+                        .world_position(model, 0.0);
+
+                    approximate.distance(exact)
+                })
+                .reduce(f64::max)
+                .map(|error| (lod, error))
+        })
+        .collect()
+}
+
+fn measure_approximation_recomputes(
+    approximation: Res<ViewApproximation>,
+    mut last_recomputes: Local<u64>,
+    mut diagnostics: Diagnostics,
+) {
+    let recomputes = approximation.recomputes - *last_recomputes;
+    *last_recomputes = approximation.recomputes;
+
+    diagnostics.add_measurement(&APPROXIMATION_RECOMPUTES, || recomputes as f64);
+}
+
+fn measure_approximation_fallbacks(
+    mut fallbacks: ResMut<ApproximationFallbacks>,
+    mut diagnostics: Diagnostics,
+) {
+    let count = std::mem::take(&mut fallbacks.count);
+
+    diagnostics.add_measurement(&APPROXIMATION_FALLBACKS, || count as f64);
+}
+
+fn measure_tiles_drawn(
+    tile_query: Query<&ViewVisibility, With<TileOrigin>>,
+    mut diagnostics: Diagnostics,
+) {
+    let drawn = tile_query
+        .iter()
+        .filter(|visibility| visibility.get())
+        .count();
+
+    diagnostics.add_measurement(&TILES_DRAWN, || drawn as f64);
+}
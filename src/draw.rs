@@ -1,7 +1,17 @@
 use bevy::{
     color::palettes::basic,
+    gizmos::config::GizmoConfigGroup,
     math::{DVec2, DVec3, Quat},
+    pbr::{MaterialPipeline, MaterialPipelineKey},
     prelude::*,
+    render::{
+        mesh::{Indices, MeshVertexAttribute, MeshVertexBufferLayoutRef, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+        render_resource::{
+            AsBindGroup, CompareFunction, RenderPipelineDescriptor, ShaderRef,
+            SpecializedMeshPipelineError, VertexFormat,
+        },
+    },
 };
 
 use bevy_terrain::{
@@ -10,51 +20,473 @@ use bevy_terrain::{
 };
 use itertools::{iproduct, Itertools};
 
+use crate::{
+    error_field::error_color,
+    geodesy::{geodesic, Geodetic},
+    math::{tile_corner_positions, tile_count, tile_size, ViewCoordinates},
+    origin_lod::OriginLodTransition,
+    view_approximation::ApproximationSnapshot,
+};
+
 const DEBUG_SCALE: f32 = 1.0 / (1 << 5) as f32;
+// length of an arrow per decade of the magnitude of its coefficient, relative to the scale of the model
+const ARROW_DECADE: f32 = 0.001;
 const ERROR_SCALE: f32 = 4.0;
 
+// The primitives the draw functions are built from.
+// They are either drawn immediately with gizmos, or tessellated once into a retained line mesh.
+pub trait LineSink {
+    fn line(&mut self, start: Vec3, end: Vec3, color: Color);
+
+    fn linestrip(&mut self, positions: impl IntoIterator<Item = Vec3>, color: Color) {
+        for (start, end) in positions.into_iter().tuple_windows() {
+            self.line(start, end, color);
+        }
+    }
+
+    // the shorter arc around the center, from the start to the end position
+    fn arc(&mut self, center: Vec3, start: Vec3, end: Vec3, color: Color, resolution: u32) {
+        let (start, end) = (start - center, end - center);
+        let angle = start.angle_between(end);
+
+        self.linestrip(
+            (0..=resolution).map(|i| {
+                let t = i as f32 / resolution as f32;
+
+                if angle < f32::EPSILON {
+                    return center + start.lerp(end, t);
+                }
+
+                let direction = (start.normalize() * ((1.0 - t) * angle).sin()
+                    + end.normalize() * (t * angle).sin())
+                    / angle.sin();
+
+                center + direction * start.length().lerp(end.length(), t)
+            }),
+            color,
+        );
+    }
+
+    fn arrow(&mut self, start: Vec3, end: Vec3, color: Color) {
+        let direction = end - start;
+        let side = direction.any_orthonormal_vector() * 0.1 * direction.length();
+
+        self.line(start, end, color);
+        self.line(end, end - 0.1 * direction + side, color);
+        self.line(end, end - 0.1 * direction - side, color);
+    }
+
+    // text next to the position, sinks that cannot render text skip it
+    fn label(&mut self, _position: Vec3, _text: String, _color: Color) {}
+
+    fn sphere(&mut self, center: Vec3, radius: f32, color: Color) {
+        for (axis_a, axis_b) in [(Vec3::X, Vec3::Y), (Vec3::Y, Vec3::Z), (Vec3::Z, Vec3::X)] {
+            self.linestrip(
+                (0..=24).map(|i| {
+                    let angle = i as f32 / 24.0 * std::f32::consts::TAU;
+                    center + radius * (axis_a * angle.cos() + axis_b * angle.sin())
+                }),
+                color,
+            );
+        }
+    }
+}
+
+impl<Config: GizmoConfigGroup> LineSink for Gizmos<'_, '_, Config> {
+    fn line(&mut self, start: Vec3, end: Vec3, color: Color) {
+        Gizmos::line(self, start, end, color);
+    }
+
+    fn linestrip(&mut self, positions: impl IntoIterator<Item = Vec3>, color: Color) {
+        Gizmos::linestrip(self, positions, color);
+    }
+
+    fn arc(&mut self, center: Vec3, start: Vec3, end: Vec3, color: Color, resolution: u32) {
+        self.short_arc_3d_between(center, start, end, color)
+            .resolution(resolution);
+    }
+
+    fn arrow(&mut self, start: Vec3, end: Vec3, color: Color) {
+        Gizmos::arrow(self, start, end, color);
+    }
+
+    fn sphere(&mut self, center: Vec3, radius: f32, color: Color) {
+        Gizmos::sphere(self, center, Quat::IDENTITY, radius, color);
+    }
+}
+
+// Gizmo group of the precision drawings, with a flag to enable each layer.
+// The line width and depth bias of its `GizmoConfig` are meant to be adjusted at runtime,
+// since thin arcs vanish on high resolution displays and depth testing hides the arrows below the surface.
+#[derive(Reflect, GizmoConfigGroup)]
+pub struct PrecisionGizmos {
+    pub earth: bool,
+    pub overlay: bool,
+    pub approximation: bool,
+    pub tile_errors: bool,
+    pub probe_orders: bool,
+    pub annotations: bool,
+    pub orbits: bool,
+    pub particles: bool,
+}
+
+impl Default for PrecisionGizmos {
+    fn default() -> Self {
+        Self {
+            earth: true,
+            overlay: true,
+            approximation: true,
+            tile_errors: false,
+            probe_orders: false,
+            annotations: true,
+            orbits: true,
+            particles: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Label {
+    pub position: Vec3,
+    pub text: String,
+    pub color: Color,
+}
+
+// Line segments collected into a mesh, which is only rebuilt when the drawing changes.
+// The labels are collected alongside, since the mesh cannot hold text.
+#[derive(Default)]
+pub struct LineMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub colors: Vec<[f32; 4]>,
+    pub labels: Vec<Label>,
+}
+
+impl LineSink for LineMesh {
+    fn line(&mut self, start: Vec3, end: Vec3, color: Color) {
+        let color = color.to_linear().to_f32_array();
+
+        self.positions.extend([start.to_array(), end.to_array()]);
+        self.colors.extend([color, color]);
+    }
+
+    fn label(&mut self, position: Vec3, text: String, color: Color) {
+        self.labels.push(Label {
+            position,
+            text,
+            color,
+        });
+    }
+}
+
+impl LineMesh {
+    // Empties the drawing, but keeps the buffers to collect the next one into.
+    pub fn clear(&mut self) {
+        self.positions.clear();
+        self.colors.clear();
+        self.labels.clear();
+    }
+
+    // Writes the segments into a mesh for the `LineMaterial`, as quads that are widened in the vertex shader.
+    // Both end points are stored at every vertex of a quad, together with the side it is offset to.
+    pub fn write_mesh(&self, mesh: &mut Mesh) {
+        let segments = self.positions.len() / 2;
+
+        let mut positions = Vec::with_capacity(4 * segments);
+        let mut others = Vec::with_capacity(4 * segments);
+        let mut sides = Vec::with_capacity(4 * segments);
+        let mut colors = Vec::with_capacity(4 * segments);
+        let mut indices = Vec::with_capacity(6 * segments);
+
+        for (segment, (ends, end_colors)) in self
+            .positions
+            .chunks_exact(2)
+            .zip(self.colors.chunks_exact(2))
+            .enumerate()
+        {
+            let first = 4 * segment as u32;
+
+            // the direction is reversed at the end point, so is the side
+            positions.extend([ends[0], ends[0], ends[1], ends[1]]);
+            others.extend([ends[1], ends[1], ends[0], ends[0]]);
+            sides.extend([1.0, -1.0, -1.0, 1.0]);
+            colors.extend([end_colors[0], end_colors[0], end_colors[1], end_colors[1]]);
+            indices.extend([first, first + 1, first + 2, first + 1, first + 3, first + 2]);
+        }
+
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(ATTRIBUTE_LINE_OTHER, others);
+        mesh.insert_attribute(ATTRIBUTE_LINE_SIDE, sides);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        mesh.insert_indices(Indices::U32(indices));
+    }
+}
+
+pub const ATTRIBUTE_LINE_OTHER: MeshVertexAttribute =
+    MeshVertexAttribute::new("LineOther", 988540917, VertexFormat::Float32x3);
+pub const ATTRIBUTE_LINE_SIDE: MeshVertexAttribute =
+    MeshVertexAttribute::new("LineSide", 735119864, VertexFormat::Float32);
+
+pub fn empty_line_mesh() -> Mesh {
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+    LineMesh::default().write_mesh(&mut mesh);
+    mesh
+}
+
+// Draws a line mesh with the width and depth test of the precision gizmos, which are synced by `LinePlugin`.
+// Drawing on top disables the depth test, the same as a depth bias of -1 does for gizmos.
+#[derive(Asset, TypePath, AsBindGroup, Clone, Debug)]
+#[bind_group_data(LineMaterialKey)]
+pub struct LineMaterial {
+    #[uniform(0)]
+    pub line_width: f32,
+    pub on_top: bool,
+}
+
+impl Default for LineMaterial {
+    fn default() -> Self {
+        Self {
+            line_width: 2.0,
+            on_top: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LineMaterialKey {
+    on_top: bool,
+}
+
+impl From<&LineMaterial> for LineMaterialKey {
+    fn from(material: &LineMaterial) -> Self {
+        Self {
+            on_top: material.on_top,
+        }
+    }
+}
+
+impl Material for LineMaterial {
+    fn vertex_shader() -> ShaderRef {
+        "shaders/line.wgsl".into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        "shaders/line.wgsl".into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        descriptor.vertex.buffers = vec![layout.0.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            ATTRIBUTE_LINE_OTHER.at_shader_location(1),
+            ATTRIBUTE_LINE_SIDE.at_shader_location(2),
+            Mesh::ATTRIBUTE_COLOR.at_shader_location(3),
+        ])?];
+        // the winding of a quad depends on the direction it is viewed from
+        descriptor.primitive.cull_mode = None;
+
+        if key.bind_group_data.on_top {
+            if let Some(depth_stencil) = descriptor.depth_stencil.as_mut() {
+                depth_stencil.depth_compare = CompareFunction::Always;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Renders the line materials and keeps them in sync with the config of the precision gizmos.
+// The lines are not part of the prepass or shadows, since their vertices are only placed in the vertex shader.
+pub struct LinePlugin;
+
+impl Plugin for LinePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MaterialPlugin::<LineMaterial> {
+            prepass_enabled: false,
+            shadows_enabled: false,
+            ..default()
+        })
+        .add_systems(PostUpdate, configure_line_materials);
+    }
+}
+
+fn configure_line_materials(
+    config_store: Res<GizmoConfigStore>,
+    mut materials: ResMut<Assets<LineMaterial>>,
+) {
+    if !config_store.is_changed() {
+        return;
+    }
+
+    let (config, _) = config_store.config::<PrecisionGizmos>();
+
+    for (_, material) in materials.iter_mut() {
+        material.line_width = config.line_width;
+        material.on_top = config.depth_bias < 0.0;
+    }
+}
+
 pub fn draw_tile(
-    gizmos: &mut Gizmos,
+    gizmos: &mut impl LineSink,
     model: &TerrainModel,
     tile: TileCoordinate,
     color: Color,
     offset: DVec3,
 ) {
-    let size = 1.0 / TileCoordinate::count(tile.lod) as f64;
+    let corners = tile_corner_positions(tile, model);
 
-    for (start, end) in [(0, 0), (0, 1), (1, 1), (1, 0), (0, 0)]
-        .into_iter()
-        .map(|(x, y)| {
-            let corner_st = UVec2::new(tile.x + x, tile.y + y).as_dvec2() * size;
-            Coordinate::new(tile.face, corner_st).world_position(model, 0.0)
-        })
-        .tuple_windows()
-    {
-        gizmos
-            .short_arc_3d_between(
-                (model.position() + offset).as_vec3(),
-                (start + offset).as_vec3(),
-                (end + offset).as_vec3(),
-                color,
-            )
-            .resolution(20);
+    for (start, end) in corners.into_iter().circular_tuple_windows() {
+        gizmos.arc(
+            (model.position() + offset).as_vec3(),
+            (start + offset).as_vec3(),
+            (end + offset).as_vec3(),
+            color,
+            20,
+        );
+    }
+}
+
+// The view coordinates of the snapshot split at both lods of the transition, computed once for all points drawn.
+fn transition_view_coordinates(
+    snapshot: &ApproximationSnapshot,
+    transition: &OriginLodTransition,
+) -> impl Fn(u32) -> ViewCoordinates {
+    let current = snapshot.view_coordinates_at(transition.origin_lod);
+    let previous = transition
+        .previous
+        .map(|origin_lod| snapshot.view_coordinates_at(origin_lod));
+
+    move |origin_lod| match previous {
+        Some(previous) if origin_lod == previous.origin_lod => previous,
+        _ => current,
+    }
+}
+
+// Outlines the tiles with the largest second order error at their corners and center,
+// colored from green to red like the error field.
+pub fn draw_tile_errors(
+    gizmos: &mut impl LineSink,
+    model: &TerrainModel,
+    tiles: impl IntoIterator<Item = TileCoordinate>,
+    snapshot: &ApproximationSnapshot,
+    transition: &OriginLodTransition,
+    offset: DVec3,
+) {
+    let views = transition_view_coordinates(snapshot, transition);
+
+    for tile in tiles {
+        let error = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.5, 0.5)]
+            .into_iter()
+            .map(|(x, y)| {
+                let tile_uv = Vec2::new(x, y);
+                let uv = (tile.xy().as_dvec2() + tile_uv.as_dvec2()) * tile_size(tile.lod);
+                let position = Coordinate::new(tile.face, uv).world_position(model, 0.0);
+
+                position.distance(transition.blend(|origin_lod| {
+                    views(origin_lod).approximate_world_position(
+                        &snapshot.approximations,
+                        true,
+                        snapshot.view_position,
+                        (tile, tile_uv),
+                    )
+                }))
+            })
+            .fold(0.0, f64::max);
+
+        draw_tile(gizmos, model, tile, error_color(error).into(), offset);
     }
 }
 
-pub fn draw_earth(gizmos: &mut Gizmos, model: &TerrainModel, lod: u32, offset: DVec3) {
-    for (face, x, y) in iproduct!(0..6, 0..1 << lod, 0..1 << lod) {
+// Draws the first (red) and second (green) order approximations of the points against their exact positions (white).
+// The offsets to the exact positions are scaled by the exaggeration, to make sub-centimeter differences visible.
+pub fn draw_approximation_orders(
+    gizmos: &mut impl LineSink,
+    model: &TerrainModel,
+    tile_coordinates: impl IntoIterator<Item = (TileCoordinate, Vec2)>,
+    snapshot: &ApproximationSnapshot,
+    transition: &OriginLodTransition,
+    exaggeration: f32,
+    offset: DVec3,
+) {
+    let marker_size = 0.00001 * model.scale() as f32;
+    let views = transition_view_coordinates(snapshot, transition);
+
+    for tile_coordinate @ (tile, tile_uv) in tile_coordinates {
+        let uv = (tile.xy().as_dvec2() + tile_uv.as_dvec2()) * tile_size(tile.lod);
+        let position = Coordinate::new(tile.face, uv).world_position(model, 0.0);
+        let exact = (position + offset).as_vec3();
+
+        gizmos.sphere(exact, marker_size, Color::WHITE);
+
+        for (second_order, color) in [(false, basic::RED), (true, basic::GREEN)] {
+            let approximate_position = transition.blend(|origin_lod| {
+                views(origin_lod).approximate_world_position(
+                    &snapshot.approximations,
+                    second_order,
+                    snapshot.view_position,
+                    tile_coordinate,
+                )
+            });
+            let end = exact + (approximate_position - position).as_vec3() * exaggeration;
+
+            gizmos.line(exact, end, color.into());
+            gizmos.sphere(end, 0.5 * marker_size, color.into());
+        }
+    }
+}
+
+pub fn draw_earth(gizmos: &mut impl LineSink, model: &TerrainModel, lod: u32, offset: DVec3) {
+    for (face, x, y) in iproduct!(0..6, 0..tile_count(lod), 0..tile_count(lod)) {
         draw_tile(
             gizmos,
             model,
-            TileCoordinate::new(face, lod, x, y),
+            TileCoordinate::new(face, lod, x as u32, y as u32),
             Color::BLACK,
             offset,
         )
     }
 }
 
+pub fn draw_polylines(
+    gizmos: &mut impl LineSink,
+    model: &TerrainModel,
+    polylines: &[Vec<Geodetic>],
+    color: Color,
+    offset: DVec3,
+) {
+    // subdivide long segments, so that they follow the curvature of the surface
+    let max_segment_angle = 0.5_f64.to_radians();
+
+    for polyline in polylines {
+        let positions = polyline.iter().tuple_windows().flat_map(|(&start, &end)| {
+            let angle = start
+                .local_position(model)
+                .angle_between(end.local_position(model));
+            let segments = (angle / max_segment_angle).ceil().max(1.0) as usize;
+
+            geodesic(start, end, segments, model).skip(1)
+        });
+
+        gizmos.linestrip(
+            polyline
+                .first()
+                .map(|start| start.world_position(model))
+                .into_iter()
+                .chain(positions)
+                .map(|position| (position + offset).as_vec3()),
+            color,
+        );
+    }
+}
+
 pub fn draw_approximation(
-    gizmos: &mut Gizmos,
+    gizmos: &mut impl LineSink,
     model: &TerrainModel,
     view_coordinates: &[Coordinate],
     approximations: &[SurfaceApproximation],
@@ -75,36 +507,31 @@ pub fn draw_approximation(
 
         gizmos.sphere(
             view_position.as_vec3(),
-            Quat::IDENTITY,
             0.0001 * model.scale() as f32,
-            basic::OLIVE,
-        );
-        gizmos.arrow(
-            view_position.as_vec3(),
-            view_position.as_vec3() + c_du * DEBUG_SCALE,
-            basic::YELLOW,
+            basic::OLIVE.into(),
         );
-        gizmos.arrow(
+        gizmos.label(
             view_position.as_vec3(),
-            view_position.as_vec3() + c_dv * DEBUG_SCALE,
-            basic::GREEN,
-        );
-        gizmos.arrow(
-            view_position.as_vec3(),
-            view_position.as_vec3() + c_duu * DEBUG_SCALE,
-            basic::RED,
-        );
-        gizmos.arrow(
-            view_position.as_vec3(),
-            view_position.as_vec3() + c_duv * DEBUG_SCALE,
-            basic::BLUE,
-        );
-        gizmos.arrow(
-            view_position.as_vec3(),
-            view_position.as_vec3() + c_dvv * DEBUG_SCALE,
-            basic::FUCHSIA,
+            format!("c {:.3e}", c.length()),
+            basic::OLIVE.into(),
         );
 
+        // the coefficients span many orders of magnitude, so the arrows grow with the logarithm of their length
+        for (name, coefficient, color) in [
+            ("c_du", c_du, basic::YELLOW),
+            ("c_dv", c_dv, basic::GREEN),
+            ("c_duu", c_duu, basic::RED),
+            ("c_duv", c_duv, basic::BLUE),
+            ("c_dvv", c_dvv, basic::FUCHSIA),
+        ] {
+            let magnitude = coefficient.length();
+            let length = ARROW_DECADE * model.scale() as f32 * (1.0 + magnitude).log10();
+            let end = view_position.as_vec3() + coefficient.normalize_or_zero() * length;
+
+            gizmos.arrow(view_position.as_vec3(), end, color.into());
+            gizmos.label(end, format!("{name} {magnitude:.3e}"), color.into());
+        }
+
         for (start, end) in [(0, 0), (0, 1), (1, 1), (1, 0), (0, 0)]
             .into_iter()
             .map(|(x, y)| {
@@ -115,11 +542,12 @@ pub fn draw_approximation(
             })
             .tuple_windows()
         {
-            gizmos.short_arc_3d_between(
+            gizmos.arc(
                 (model.position() + offset).as_vec3(),
                 (start + offset).as_vec3(),
                 (end + offset).as_vec3(),
                 Color::WHITE,
+                8,
             );
         }
     }
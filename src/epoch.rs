@@ -0,0 +1,230 @@
+use bevy::{
+    math::{DMat4, DVec3},
+    prelude::*,
+};
+use bevy_terrain::{
+    big_space::{GridCell, ReferenceFrame},
+    prelude::*,
+};
+
+use crate::geodesy::transformed_model;
+
+// The point all scene positions are relative to, in the absolute coordinates of external data,
+// e.g. the solar system barycentric positions of an ephemeris. Absolute positions lose centimeters to f64
+// at the distance of the outer planets, so the scene is placed around an epoch close to where the action is,
+// like a landing site, and can be rebased onto another one at runtime.
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct SceneEpoch {
+    pub origin: DVec3,
+    requested: Option<DVec3>,
+}
+
+impl SceneEpoch {
+    pub fn new(origin: DVec3) -> Self {
+        Self {
+            origin,
+            requested: None,
+        }
+    }
+
+    pub fn to_scene(&self, absolute_position: DVec3) -> DVec3 {
+        absolute_position - self.origin
+    }
+
+    pub fn to_absolute(&self, scene_position: DVec3) -> DVec3 {
+        scene_position + self.origin
+    }
+
+    // Requests to move the epoch onto the absolute position, which is applied before the next update.
+    pub fn rebase(&mut self, origin: DVec3) {
+        self.requested = Some(origin);
+    }
+}
+
+// Sent after the epoch was rebased, with the offset all scene positions moved by.
+// The types registered with `add_rebase` are moved by it, anything else keeping positions has to read it.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct EpochRebased {
+    pub offset: DVec3,
+}
+
+// The same model, moved by the offset. Its axes and orientation stay as they are.
+pub fn shift_model(model: &TerrainModel, offset: DVec3) -> TerrainModel {
+    transformed_model(
+        model,
+        DMat4::from_translation(offset) * model.world_from_local,
+    )
+}
+
+// Components and resources keeping f64 scene positions or models, which have to move along with the epoch.
+// Every type registered with `add_rebase` is moved, whenever the epoch is rebased.
+pub trait Rebase {
+    fn rebase(&mut self, offset: DVec3);
+}
+
+// The rebased positions are moved before the update, right after the epoch.
+#[derive(SystemSet, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct RebaseSet;
+
+pub trait RebaseAppExt {
+    fn add_rebase<T: Component + Rebase>(&mut self) -> &mut Self;
+
+    fn add_rebase_resource<T: Resource + Rebase>(&mut self) -> &mut Self;
+}
+
+impl RebaseAppExt for App {
+    fn add_rebase<T: Component + Rebase>(&mut self) -> &mut Self {
+        self.add_event::<EpochRebased>()
+            .add_systems(PreUpdate, rebase_components::<T>.in_set(RebaseSet))
+    }
+
+    fn add_rebase_resource<T: Resource + Rebase>(&mut self) -> &mut Self {
+        self.add_event::<EpochRebased>()
+            .add_systems(PreUpdate, rebase_resource::<T>.in_set(RebaseSet))
+    }
+}
+
+pub struct EpochPlugin;
+
+impl Plugin for EpochPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SceneEpoch>()
+            .add_event::<EpochRebased>()
+            .add_systems(PreUpdate, rebase_epoch.before(RebaseSet));
+    }
+}
+
+// Moves everything placed directly in a root reference frame, the nested frames move along with their parents.
+// The offset is split into whole cells and a remainder once, so the cells move exactly,
+// and each translation is only rounded once when the remainder is added.
+fn rebase_epoch(
+    mut epoch: ResMut<SceneEpoch>,
+    mut rebased: EventWriter<EpochRebased>,
+    root_query: Query<(Entity, &ReferenceFrame), Without<Parent>>,
+    mut grid_query: Query<(&mut GridCell, &mut Transform, &Parent)>,
+) {
+    let Some(origin) = epoch.requested.take() else {
+        return;
+    };
+
+    let offset = epoch.origin - origin;
+    epoch.origin = origin;
+
+    for (root, frame) in &root_query {
+        let (offset_cell, offset_translation) = frame.translation_to_grid(offset);
+
+        for (mut cell, mut transform, parent) in &mut grid_query {
+            if parent.get() != root {
+                continue;
+            }
+
+            let (extra_cell, translation) =
+                frame.translation_to_grid((transform.translation + offset_translation).as_dvec3());
+
+            *cell = *cell + offset_cell + extra_cell;
+            transform.translation = translation;
+        }
+    }
+
+    rebased.send(EpochRebased { offset });
+}
+
+fn rebase_components<T: Component + Rebase>(
+    mut rebased: EventReader<EpochRebased>,
+    mut rebase_query: Query<&mut T>,
+) {
+    let offset = rebased.read().map(|rebased| rebased.offset).sum::<DVec3>();
+
+    if offset == DVec3::ZERO {
+        return;
+    }
+
+    for mut rebase in &mut rebase_query {
+        rebase.rebase(offset);
+    }
+}
+
+fn rebase_resource<T: Resource + Rebase>(
+    mut rebased: EventReader<EpochRebased>,
+    rebase: Option<ResMut<T>>,
+) {
+    let offset = rebased.read().map(|rebased| rebased.offset).sum::<DVec3>();
+
+    let Some(mut rebase) = rebase else {
+        return;
+    };
+
+    if offset != DVec3::ZERO {
+        rebase.rebase(offset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::{DQuat, DVec2, EulerRot};
+    use bevy_terrain::math::Coordinate;
+
+    fn rotated_model() -> TerrainModel {
+        let model = TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0);
+
+        transformed_model(
+            &model,
+            DMat4::from_scale_rotation_translation(
+                DVec3::new(6378137.0, 6356752.314245, 6378137.0),
+                DQuat::from_euler(EulerRot::YXZ, 0.4, -0.2, 1.1),
+                DVec3::new(1000.0, -20.0, 3.5),
+            ),
+        )
+    }
+
+    #[test]
+    fn shifted_model_keeps_its_orientation() {
+        let model = rotated_model();
+        let offset = DVec3::new(-2.5e7, 3.0e6, 1234.5);
+        let shifted = shift_model(&model, offset);
+
+        for (face, uv) in [(0, 0.5), (2, 0.1), (5, 0.9)] {
+            let coordinate = Coordinate::new(face, DVec2::splat(uv));
+            let position = coordinate.world_position(&model, 100.0);
+
+            assert!(
+                coordinate
+                    .world_position(&shifted, 100.0)
+                    .distance(position + offset)
+                    < 1e-6
+            );
+        }
+    }
+
+    #[derive(Component)]
+    struct Position(DVec3);
+
+    impl Rebase for Position {
+        fn rebase(&mut self, offset: DVec3) {
+            self.0 += offset;
+        }
+    }
+
+    #[test]
+    fn rebase_moves_registered_positions() {
+        let mut app = App::new();
+        app.add_plugins(EpochPlugin).add_rebase::<Position>();
+
+        let entity = app
+            .world_mut()
+            .spawn(Position(DVec3::new(1.0, 2.0, 3.0)))
+            .id();
+
+        app.world_mut()
+            .resource_mut::<SceneEpoch>()
+            .rebase(DVec3::new(10.0, 0.0, 0.0));
+        app.update();
+
+        assert_eq!(app.world().resource::<SceneEpoch>().origin.x, 10.0);
+        assert_eq!(
+            app.world().get::<Position>(entity).unwrap().0,
+            DVec3::new(-9.0, 2.0, 3.0)
+        );
+    }
+}
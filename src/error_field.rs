@@ -0,0 +1,390 @@
+use bevy::{
+    math::{DVec2, DVec3},
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        primitives::Aabb,
+        render_asset::RenderAssetUsages,
+    },
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+};
+use bevy_terrain::{
+    big_space::{FloatingOrigin, GridTransform, GridTransformReadOnly, ReferenceFrames},
+    math::Coordinate,
+    prelude::*,
+};
+use itertools::iproduct;
+use std::f64::consts::TAU;
+
+use crate::{
+    deterministic::Deterministic,
+    epoch::{shift_model, Rebase},
+    geodesy::{enu_frame, Geodetic},
+    math::{
+        approximate_world_position, approximations, tile_coordinate_from_world_position,
+        tile_count, view_coordinates,
+    },
+    model_copy::ModelCopy,
+};
+
+// Pattern the error field is sampled in. Each one isolates a different way the error grows:
+// rings show the angular variation at a fixed distance, spokes the radial growth,
+// the origin tile the error of the tile the offsets are relative to, and the frustum what is actually on screen.
+// Every shape uses the same number of samples, the square of the resolution.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect)]
+pub enum ErrorFieldShape {
+    // regular grid in uv space around the view coordinate, as large as the extent
+    #[default]
+    Grid,
+    // circle at a fixed distance around the view
+    Ring {
+        radius: f64,
+    },
+    // lines from the view outwards, sampled from the view up to the radius
+    Spokes {
+        count: u32,
+        radius: f64,
+    },
+    // regular st grid on the tile at the origin lod containing the view
+    OriginTile,
+    // footprint of the view direction on the surface, up to the distance and within the angle to either side
+    Frustum {
+        distance: f64,
+        half_angle: f64,
+    },
+}
+
+impl ErrorFieldShape {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Grid => Self::Ring { radius: 10000.0 },
+            Self::Ring { .. } => Self::Spokes {
+                count: 16,
+                radius: 20000.0,
+            },
+            Self::Spokes { .. } => Self::OriginTile,
+            Self::OriginTile => Self::Frustum {
+                distance: 20000.0,
+                half_angle: 45.0_f64.to_radians(),
+            },
+            Self::Frustum { .. } => Self::Grid,
+        }
+    }
+}
+
+pub struct ErrorSample {
+    // position of the exact surface point relative to the view
+    pub position: Vec3,
+    // offset from the exact to the approximate position
+    pub error_vector: Vec3,
+    pub error: f64,
+}
+
+pub struct ErrorFieldResult {
+    pub view_position: DVec3,
+    pub samples: Vec<ErrorSample>,
+}
+
+// Positions on the reference surface covered by the shape.
+fn sample_positions(
+    shape: ErrorFieldShape,
+    view_position: DVec3,
+    view_forward: DVec3,
+    model: &TerrainModel,
+    origin_lod: u32,
+    extent: f64,
+    resolution: u32,
+) -> Vec<DVec3> {
+    let steps = (resolution - 1).max(1) as f64;
+    let view_coordinate = Coordinate::from_world_position(view_position, model);
+
+    // offsets are placed in the tangent plane below the view and then dropped onto the surface
+    let view_geodetic = Geodetic::from_world_position(view_position, model);
+    let ground =
+        Geodetic::new(view_geodetic.latitude, view_geodetic.longitude, 0.0).world_position(model);
+    let enu = enu_frame(view_geodetic, model);
+    let on_surface = |offset: DVec2| {
+        let position = ground + enu.x_axis * offset.x + enu.y_axis * offset.y;
+
+        model.position_local_to_world(model.position_world_to_local(position), 0.0)
+    };
+    let polar = |distance: f64, angle: f64| DVec2::new(angle.cos(), angle.sin()) * distance;
+
+    match shape {
+        ErrorFieldShape::Grid => iproduct!(0..resolution, 0..resolution)
+            .map(|(x, y)| {
+                let offset = (DVec2::new(x as f64, y as f64) / steps - 0.5) * extent;
+                let uv = (view_coordinate.uv + offset).clamp(DVec2::ZERO, DVec2::ONE);
+
+                Coordinate::new(view_coordinate.face, uv).world_position(model, 0.0)
+            })
+            .collect(),
+        ErrorFieldShape::Ring { radius } => (0..resolution * resolution)
+            .map(|i| {
+                on_surface(polar(
+                    radius,
+                    TAU * i as f64 / (resolution * resolution) as f64,
+                ))
+            })
+            .collect(),
+        ErrorFieldShape::Spokes { count, radius } => {
+            let samples = (resolution * resolution / count.max(1)).max(2);
+
+            iproduct!(0..count, 0..samples)
+                .map(|(spoke, i)| {
+                    let distance = radius * i as f64 / (samples - 1) as f64;
+
+                    on_surface(polar(distance, TAU * spoke as f64 / count as f64))
+                })
+                .collect()
+        }
+        ErrorFieldShape::OriginTile => {
+            let (tile, _) = tile_coordinate_from_world_position(view_position, origin_lod, model);
+            let count = tile_count(origin_lod) as f64;
+
+            iproduct!(0..resolution, 0..resolution)
+                .map(|(x, y)| {
+                    let st = DVec2::new(x as f64, y as f64) / steps;
+                    let uv = (DVec2::new(tile.x as f64, tile.y as f64) + st) / count;
+
+                    Coordinate::new(tile.face, uv).world_position(model, 0.0)
+                })
+                .collect()
+        }
+        ErrorFieldShape::Frustum {
+            distance,
+            half_angle,
+        } => {
+            // the heading of the view in the tangent plane, looking straight down it is north
+            let forward = DVec2::new(view_forward.dot(enu.x_axis), view_forward.dot(enu.y_axis));
+            let heading = forward
+                .try_normalize()
+                .map_or(0.5 * std::f64::consts::PI, |forward| {
+                    forward.y.atan2(forward.x)
+                });
+
+            iproduct!(0..resolution, 0..resolution)
+                .map(|(x, y)| {
+                    let angle = heading + half_angle * (2.0 * x as f64 / steps - 1.0);
+
+                    on_surface(polar(distance * y as f64 / steps, angle))
+                })
+                .collect()
+        }
+    }
+}
+
+// Compares the exact surface positions with the second order approximation, at the samples of the shape around the view.
+pub fn error_field(
+    shape: ErrorFieldShape,
+    view_position: DVec3,
+    view_forward: DVec3,
+    model: &TerrainModel,
+    origin_lod: u32,
+    extent: f64,
+    resolution: u32,
+) -> ErrorFieldResult {
+    let view_coordinates = view_coordinates(view_position, model);
+    let approximations = approximations(&view_coordinates, view_position, model);
+
+    let samples = sample_positions(
+        shape,
+        view_position,
+        view_forward,
+        model,
+        origin_lod,
+        extent,
+        resolution,
+    )
+    .into_iter()
+    .map(|position| {
+        let approximate_position = approximate_world_position(
+            &view_coordinates,
+            &approximations,
+            origin_lod,
+            true,
+            view_position,
+            tile_coordinate_from_world_position(position, origin_lod, model),
+        );
+
+        ErrorSample {
+            position: (position - view_position).as_vec3(),
+            error_vector: (approximate_position - position).as_vec3(),
+            error: position.distance(approximate_position),
+        }
+    })
+    .collect();
+
+    ErrorFieldResult {
+        view_position,
+        samples,
+    }
+}
+
+// Ramp from green (a tenth of a millimeter) to red (a meter) on a logarithmic scale.
+pub fn error_color(error: f64) -> LinearRgba {
+    let t = ((error.max(1e-12).log10() + 4.0) / 4.0).clamp(0.0, 1.0) as f32;
+
+    LinearRgba::GREEN.mix(&LinearRgba::RED, t)
+}
+
+// The points are displaced along their error, scaled by the exaggeration,
+// since the actual errors of less than a centimeter are invisible at planet scale.
+pub fn error_field_mesh(result: &ErrorFieldResult, exaggeration: f32) -> Mesh {
+    let positions = result
+        .samples
+        .iter()
+        .map(|sample| (sample.position + sample.error_vector * exaggeration).to_array())
+        .collect::<Vec<_>>();
+    let colors = result
+        .samples
+        .iter()
+        .map(|sample| error_color(sample.error).to_f32_array())
+        .collect::<Vec<_>>();
+    let indices = (0..positions.len() as u32).collect();
+
+    Mesh::new(PrimitiveTopology::PointList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+        .with_inserted_indices(Indices::U32(indices))
+}
+
+// The mesh of the error field until the first result arrives.
+pub fn empty_error_field_mesh() -> Mesh {
+    Mesh::new(PrimitiveTopology::PointList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, Vec::<[f32; 3]>::new())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, Vec::<[f32; 4]>::new())
+}
+
+// Unlit, so the colors of the points show their error regardless of the lighting.
+pub fn error_field_material() -> StandardMaterial {
+    StandardMaterial {
+        unlit: true,
+        ..default()
+    }
+}
+
+// The error field is computed on the async compute pool, so a dense grid does not stall the frame.
+// A new computation starts once the previous one finished, and its point cloud is anchored at the view it was computed for.
+#[derive(Component)]
+pub struct ErrorField {
+    pub model: TerrainModel,
+    pub origin_lod: u32,
+    pub shape: ErrorFieldShape,
+    // size of the sampled area in uv space of the view face
+    pub extent: f64,
+    pub resolution: u32,
+    // factor the errors are scaled by, when displacing the points, zero shows the exact positions
+    pub exaggeration: f32,
+    pub enabled: bool,
+    task: Option<Task<ErrorFieldResult>>,
+}
+
+impl ErrorField {
+    pub fn new(model: TerrainModel, origin_lod: u32) -> Self {
+        Self {
+            model,
+            origin_lod,
+            shape: ErrorFieldShape::Grid,
+            extent: 1.0 / 256.0,
+            resolution: 256,
+            exaggeration: 0.0,
+            enabled: false,
+            task: None,
+        }
+    }
+}
+
+impl ModelCopy for ErrorField {
+    fn set_model(&mut self, model: &TerrainModel) {
+        self.model = model.clone();
+    }
+}
+
+impl Rebase for ErrorField {
+    fn rebase(&mut self, offset: DVec3) {
+        self.model = shift_model(&self.model, offset);
+    }
+}
+
+pub fn update_error_field(
+    mut commands: Commands,
+    frames: ReferenceFrames,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+    mut field_query: Query<
+        (
+            Entity,
+            &mut ErrorField,
+            &Handle<Mesh>,
+            &mut Visibility,
+            GridTransform,
+        ),
+        Without<FloatingOrigin>,
+    >,
+    mut meshes: ResMut<Assets<Mesh>>,
+    deterministic: Option<Res<Deterministic>>,
+) {
+    let Ok((view, view_transform)) = view_query.get_single() else {
+        warn_once!("Skipping update_error_field: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping update_error_field: the camera is not part of a reference frame.");
+        return;
+    };
+    let view_position = view_transform.position_double(&frame);
+    let view_forward = view_transform.transform.forward().as_dvec3();
+
+    for (entity, mut field, mesh, mut visibility, mut grid_transform) in &mut field_query {
+        if !field.enabled {
+            *visibility = Visibility::Hidden;
+            field.task = None;
+            continue;
+        }
+
+        if let Some(task) = field.task.as_mut() {
+            // deterministic runs wait for the field, so it is shown in the same frame on every run
+            let result = if deterministic.is_some() {
+                Some(block_on(task))
+            } else {
+                block_on(future::poll_once(task))
+            };
+            let Some(result) = result else {
+                continue;
+            };
+
+            let Some(frame) = frames.parent_frame(entity) else {
+                warn_once!("Skipping update_error_field for {entity:?}: it is not part of a reference frame.");
+                continue;
+            };
+            let (cell, translation) = frame.translation_to_grid(result.view_position);
+
+            meshes.insert(mesh, error_field_mesh(&result, field.exaggeration));
+            // the bounds are only computed for entities without any, drop the ones of the previous field
+            commands.entity(entity).remove::<Aabb>();
+            *visibility = Visibility::Inherited;
+            *grid_transform.cell = cell;
+            grid_transform.transform.translation = translation;
+        }
+
+        let model = field.model.clone();
+        let (shape, origin_lod, extent, resolution) = (
+            field.shape,
+            field.origin_lod,
+            field.extent,
+            field.resolution,
+        );
+
+        field.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+            error_field(
+                shape,
+                view_position,
+                view_forward,
+                &model,
+                origin_lod,
+                extent,
+                resolution,
+            )
+        }));
+    }
+}
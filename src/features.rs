@@ -0,0 +1,146 @@
+use bevy::math::DVec3;
+use bevy_terrain::{math::Coordinate, prelude::*};
+
+use crate::{epoch::shift_model, geodesy::axes, height::HeightProvider};
+
+#[derive(Clone, Copy, Debug)]
+pub enum FeatureShape {
+    // bowl with a raised rim
+    Crater { depth: f64, rim_height: f64 },
+    // cone with a summit crater
+    Volcano { height: f64, summit_radius: f64 },
+}
+
+// A landmark stamped onto the heightfield, centered at an exact coordinate.
+#[derive(Clone, Copy)]
+pub struct Feature {
+    pub center: Coordinate,
+    // surface radius in meters
+    pub radius: f64,
+    pub shape: FeatureShape,
+}
+
+impl Feature {
+    pub fn crater(center: Coordinate, radius: f64, depth: f64) -> Self {
+        Self {
+            center,
+            radius,
+            shape: FeatureShape::Crater {
+                depth,
+                rim_height: 0.2 * depth,
+            },
+        }
+    }
+
+    pub fn volcano(center: Coordinate, radius: f64, height: f64) -> Self {
+        Self {
+            center,
+            radius,
+            shape: FeatureShape::Volcano {
+                height,
+                summit_radius: 0.1 * radius,
+            },
+        }
+    }
+
+    // height offset at the normalized distance to the center
+    fn profile(&self, r: f64) -> f64 {
+        match self.shape {
+            FeatureShape::Crater { depth, rim_height } => {
+                let rim = rim_height * (-((r - 1.0) / 0.15).powi(2)).exp();
+
+                if r < 1.0 {
+                    depth * (r * r - 1.0) + rim
+                } else {
+                    rim
+                }
+            }
+            FeatureShape::Volcano {
+                height,
+                summit_radius,
+            } => {
+                let summit = summit_radius / self.radius;
+
+                if r >= 1.0 {
+                    0.0
+                } else if r > summit {
+                    height * (1.0 - r) / (1.0 - summit)
+                } else {
+                    height - 0.5 * height * summit * (1.0 - (r / summit).powi(2))
+                }
+            }
+        }
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        match self.shape {
+            FeatureShape::Crater { depth, rim_height } => (-depth, rim_height),
+            FeatureShape::Volcano { height, .. } => (0.0, height),
+        }
+    }
+}
+
+// Adds features to a base heightfield.
+// Distances are evaluated in f64 on the model, so the features stay at their exact position regardless of the view.
+pub struct FeatureStamps {
+    pub model: TerrainModel,
+    pub base: Box<dyn HeightProvider>,
+    pub features: Vec<Feature>,
+}
+
+impl FeatureStamps {
+    pub fn new(model: TerrainModel, base: Box<dyn HeightProvider>) -> Self {
+        Self {
+            model,
+            base,
+            features: Vec::new(),
+        }
+    }
+
+    pub fn with_feature(mut self, feature: Feature) -> Self {
+        self.features.push(feature);
+        self
+    }
+
+    fn direction(&self, coordinate: Coordinate) -> DVec3 {
+        (coordinate.world_position(&self.model, 0.0) - self.model.position()).normalize()
+    }
+}
+
+impl HeightProvider for FeatureStamps {
+    fn height(&self, coordinate: Coordinate) -> f64 {
+        let (major_axis, _) = axes(&self.model);
+        let direction = self.direction(coordinate);
+
+        self.base.height(coordinate)
+            + self
+                .features
+                .iter()
+                .map(|feature| {
+                    let distance =
+                        major_axis * direction.angle_between(self.direction(feature.center));
+
+                    feature.profile(distance / feature.radius)
+                })
+                .sum::<f64>()
+    }
+
+    fn height_range(&self) -> (f64, f64) {
+        self.features
+            .iter()
+            .fold(self.base.height_range(), |(min, max), feature| {
+                let (feature_min, feature_max) = feature.extent();
+                (min + feature_min, max + feature_max)
+            })
+    }
+
+    fn set_model(&mut self, model: &TerrainModel) {
+        self.model = model.clone();
+        self.base.set_model(model);
+    }
+
+    fn rebase(&mut self, offset: DVec3) {
+        self.model = shift_model(&self.model, offset);
+        self.base.rebase(offset);
+    }
+}
@@ -0,0 +1,450 @@
+// C bindings of the precision core, so that other engines and tools can evaluate the same math for cross-validation.
+// All structs are plain data passed by value, positions and coordinates in f64, approximation coefficients in f32.
+// The crate is built as an rlib only, build the shared or static library with
+// `cargo rustc --lib --release --features ffi --crate-type cdylib` (or `staticlib`),
+// the declarations are in `include/precision_demo.h`.
+// Every function validates its input and returns a status, the result is written to the output pointer on success.
+
+use bevy::math::{DVec2, DVec3, Vec2, Vec3};
+use bevy_terrain::{
+    math::{Coordinate, SurfaceApproximation, TileCoordinate},
+    prelude::*,
+};
+
+use crate::math::{
+    coordinate_from_world_position, evaluate_approximation, relative_uv, tile_count, MAX_LOD,
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrecisionStatus {
+    Ok = 0,
+    NullPointer = 1,
+    InvalidFace = 2,
+    InvalidLod = 3,
+    // the tile lies outside of its face
+    InvalidTile = 4,
+    // the axes are not positive, or the position is not finite
+    InvalidModel = 5,
+    // a position or uv is not finite, or the position is the center of the model
+    InvalidValue = 6,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PrecisionVec3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PrecisionVec3f {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PrecisionModel {
+    pub position: PrecisionVec3,
+    pub major_axis: f64,
+    pub minor_axis: f64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PrecisionCoordinate {
+    pub face: u32,
+    pub u: f64,
+    pub v: f64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PrecisionTile {
+    pub face: u32,
+    pub lod: u32,
+    pub x: u32,
+    pub y: u32,
+    // position inside the tile, in the range [0, 1]
+    pub u: f32,
+    pub v: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PrecisionApproximation {
+    pub c: PrecisionVec3f,
+    pub c_du: PrecisionVec3f,
+    pub c_dv: PrecisionVec3f,
+    pub c_duu: PrecisionVec3f,
+    pub c_duv: PrecisionVec3f,
+    pub c_dvv: PrecisionVec3f,
+}
+
+impl From<PrecisionVec3> for DVec3 {
+    fn from(v: PrecisionVec3) -> Self {
+        DVec3::new(v.x, v.y, v.z)
+    }
+}
+
+impl From<Vec3> for PrecisionVec3f {
+    fn from(v: Vec3) -> Self {
+        Self {
+            x: v.x,
+            y: v.y,
+            z: v.z,
+        }
+    }
+}
+
+impl From<PrecisionVec3f> for Vec3 {
+    fn from(v: PrecisionVec3f) -> Self {
+        Vec3::new(v.x, v.y, v.z)
+    }
+}
+
+impl TryFrom<PrecisionModel> for TerrainModel {
+    type Error = PrecisionStatus;
+
+    fn try_from(model: PrecisionModel) -> Result<Self, Self::Error> {
+        let position = DVec3::from(model.position);
+
+        if !position.is_finite()
+            || !(model.major_axis.is_finite() && model.major_axis > 0.0)
+            || !(model.minor_axis.is_finite() && model.minor_axis > 0.0)
+        {
+            return Err(PrecisionStatus::InvalidModel);
+        }
+
+        Ok(TerrainModel::ellipsoid(
+            position,
+            model.major_axis,
+            model.minor_axis,
+            0.0,
+            0.0,
+        ))
+    }
+}
+
+impl TryFrom<PrecisionCoordinate> for Coordinate {
+    type Error = PrecisionStatus;
+
+    fn try_from(coordinate: PrecisionCoordinate) -> Result<Self, Self::Error> {
+        let uv = DVec2::new(coordinate.u, coordinate.v);
+
+        validate_face(coordinate.face)?;
+        if !uv.is_finite() {
+            return Err(PrecisionStatus::InvalidValue);
+        }
+
+        Ok(Coordinate::new(coordinate.face, uv))
+    }
+}
+
+impl TryFrom<PrecisionTile> for (TileCoordinate, Vec2) {
+    type Error = PrecisionStatus;
+
+    fn try_from(tile: PrecisionTile) -> Result<Self, Self::Error> {
+        let tile_uv = Vec2::new(tile.u, tile.v);
+
+        validate_face(tile.face)?;
+        validate_lod(tile.lod)?;
+        if tile.x as u64 >= tile_count(tile.lod) || tile.y as u64 >= tile_count(tile.lod) {
+            return Err(PrecisionStatus::InvalidTile);
+        }
+        if !tile_uv.is_finite() {
+            return Err(PrecisionStatus::InvalidValue);
+        }
+
+        Ok((
+            TileCoordinate::new(tile.face, tile.lod, tile.x, tile.y),
+            tile_uv,
+        ))
+    }
+}
+
+impl From<Coordinate> for PrecisionCoordinate {
+    fn from(coordinate: Coordinate) -> Self {
+        Self {
+            face: coordinate.face,
+            u: coordinate.uv.x,
+            v: coordinate.uv.y,
+        }
+    }
+}
+
+impl From<&SurfaceApproximation> for PrecisionApproximation {
+    fn from(approximation: &SurfaceApproximation) -> Self {
+        Self {
+            c: approximation.c.into(),
+            c_du: approximation.c_du.into(),
+            c_dv: approximation.c_dv.into(),
+            c_duu: approximation.c_duu.into(),
+            c_duv: approximation.c_duv.into(),
+            c_dvv: approximation.c_dvv.into(),
+        }
+    }
+}
+
+impl From<PrecisionApproximation> for SurfaceApproximation {
+    fn from(approximation: PrecisionApproximation) -> Self {
+        SurfaceApproximation {
+            c: approximation.c.into(),
+            c_du: approximation.c_du.into(),
+            c_dv: approximation.c_dv.into(),
+            c_duu: approximation.c_duu.into(),
+            c_duv: approximation.c_duv.into(),
+            c_dvv: approximation.c_dvv.into(),
+        }
+    }
+}
+
+fn validate_face(face: u32) -> Result<(), PrecisionStatus> {
+    (face < 6).then_some(()).ok_or(PrecisionStatus::InvalidFace)
+}
+
+fn validate_lod(lod: u32) -> Result<(), PrecisionStatus> {
+    (lod <= MAX_LOD)
+        .then_some(())
+        .ok_or(PrecisionStatus::InvalidLod)
+}
+
+fn validate_position(position: PrecisionVec3) -> Result<DVec3, PrecisionStatus> {
+    let position = DVec3::from(position);
+
+    position
+        .is_finite()
+        .then_some(position)
+        .ok_or(PrecisionStatus::InvalidValue)
+}
+
+// Writes the result to the output, a null output is rejected before anything is computed.
+fn respond<T>(
+    out: Option<&mut T>,
+    result: impl FnOnce() -> Result<T, PrecisionStatus>,
+) -> PrecisionStatus {
+    let Some(out) = out else {
+        return PrecisionStatus::NullPointer;
+    };
+
+    match result() {
+        Ok(value) => {
+            *out = value;
+            PrecisionStatus::Ok
+        }
+        Err(status) => status,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn precision_coordinate_from_world_position(
+    model: PrecisionModel,
+    world_position: PrecisionVec3,
+    out: Option<&mut PrecisionCoordinate>,
+) -> PrecisionStatus {
+    respond(out, || {
+        let model = TerrainModel::try_from(model)?;
+        let world_position = validate_position(world_position)?;
+
+        // the center of the model has no direction, so it lies on no face
+        if world_position.distance(model.position()) < 1e-12 * model.scale() {
+            return Err(PrecisionStatus::InvalidValue);
+        }
+
+        Ok(coordinate_from_world_position(world_position, &model).into())
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn precision_project_to_face(
+    model: PrecisionModel,
+    coordinate: PrecisionCoordinate,
+    face: u32,
+    out: Option<&mut PrecisionCoordinate>,
+) -> PrecisionStatus {
+    respond(out, || {
+        let model = TerrainModel::try_from(model)?;
+        let coordinate = Coordinate::try_from(coordinate)?;
+        validate_face(face)?;
+
+        Ok(coordinate.project_to_face(face, &model).into())
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn precision_surface_approximation_compute(
+    model: PrecisionModel,
+    view_coordinate: PrecisionCoordinate,
+    view_position: PrecisionVec3,
+    out: Option<&mut PrecisionApproximation>,
+) -> PrecisionStatus {
+    respond(out, || {
+        let model = TerrainModel::try_from(model)?;
+        let view_coordinate = Coordinate::try_from(view_coordinate)?;
+        let view_position = validate_position(view_position)?;
+
+        Ok((&SurfaceApproximation::compute(view_coordinate, view_position, &model)).into())
+    })
+}
+
+// The view coordinate has to be projected onto the face of the tile, see `precision_project_to_face`.
+#[no_mangle]
+pub extern "C" fn precision_approximate_relative_position(
+    view_coordinate: PrecisionCoordinate,
+    approximation: PrecisionApproximation,
+    origin_lod: u32,
+    tile: PrecisionTile,
+    second_order: bool,
+    out: Option<&mut PrecisionVec3f>,
+) -> PrecisionStatus {
+    respond(out, || {
+        let view_coordinate = Coordinate::try_from(view_coordinate)?;
+        let coordinate = <(TileCoordinate, Vec2)>::try_from(tile)?;
+        validate_lod(origin_lod)?;
+
+        let relative_uv = relative_uv(view_coordinate, origin_lod, coordinate);
+
+        Ok(evaluate_approximation(&approximation.into(), second_order, relative_uv).into())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EARTH: PrecisionModel = PrecisionModel {
+        position: PrecisionVec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        },
+        major_axis: 6378137.0,
+        minor_axis: 6356752.314245,
+    };
+
+    #[test]
+    fn invalid_input_returns_a_status() {
+        let mut coordinate = PrecisionCoordinate::default();
+        let mut position = PrecisionVec3f::default();
+        let valid = PrecisionCoordinate {
+            face: 0,
+            u: 0.5,
+            v: 0.5,
+        };
+        let tile = PrecisionTile {
+            face: 0,
+            lod: 4,
+            x: 8,
+            y: 8,
+            u: 0.5,
+            v: 0.5,
+        };
+
+        assert_eq!(
+            precision_project_to_face(EARTH, valid, 6, Some(&mut coordinate)),
+            PrecisionStatus::InvalidFace
+        );
+        assert_eq!(
+            precision_project_to_face(
+                EARTH,
+                PrecisionCoordinate { face: 7, ..valid },
+                0,
+                Some(&mut coordinate)
+            ),
+            PrecisionStatus::InvalidFace
+        );
+        assert_eq!(
+            precision_project_to_face(EARTH, valid, 0, None),
+            PrecisionStatus::NullPointer
+        );
+        assert_eq!(
+            precision_coordinate_from_world_position(
+                PrecisionModel {
+                    minor_axis: 0.0,
+                    ..EARTH
+                },
+                PrecisionVec3::default(),
+                Some(&mut coordinate)
+            ),
+            PrecisionStatus::InvalidModel
+        );
+        assert_eq!(
+            precision_coordinate_from_world_position(
+                EARTH,
+                PrecisionVec3 {
+                    x: f64::NAN,
+                    ..PrecisionVec3::default()
+                },
+                Some(&mut coordinate)
+            ),
+            PrecisionStatus::InvalidValue
+        );
+        assert_eq!(
+            precision_coordinate_from_world_position(EARTH, EARTH.position, Some(&mut coordinate)),
+            PrecisionStatus::InvalidValue
+        );
+
+        let approximation = PrecisionApproximation::default();
+        let relative_position =
+            |origin_lod: u32, tile: PrecisionTile, out: Option<&mut PrecisionVec3f>| {
+                precision_approximate_relative_position(
+                    valid,
+                    approximation,
+                    origin_lod,
+                    tile,
+                    true,
+                    out,
+                )
+            };
+
+        assert_eq!(
+            relative_position(MAX_LOD + 1, tile, Some(&mut position)),
+            PrecisionStatus::InvalidLod
+        );
+        assert_eq!(
+            relative_position(
+                4,
+                PrecisionTile {
+                    lod: MAX_LOD + 1,
+                    ..tile
+                },
+                Some(&mut position)
+            ),
+            PrecisionStatus::InvalidLod
+        );
+        assert_eq!(
+            relative_position(4, PrecisionTile { x: 16, ..tile }, Some(&mut position)),
+            PrecisionStatus::InvalidTile
+        );
+        assert_eq!(
+            relative_position(4, tile, Some(&mut position)),
+            PrecisionStatus::Ok
+        );
+    }
+
+    #[test]
+    fn valid_input_writes_the_result() {
+        let mut coordinate = PrecisionCoordinate::default();
+        let position = PrecisionVec3 {
+            x: 0.0,
+            y: 0.0,
+            z: -EARTH.major_axis,
+        };
+
+        assert_eq!(
+            precision_coordinate_from_world_position(EARTH, position, Some(&mut coordinate)),
+            PrecisionStatus::Ok
+        );
+
+        let expected = coordinate_from_world_position(
+            position.into(),
+            &TerrainModel::try_from(EARTH).unwrap(),
+        );
+        assert_eq!(coordinate.face, expected.face);
+        assert_eq!((coordinate.u, coordinate.v), (expected.uv.x, expected.uv.y));
+    }
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+
+// Readouts of lengths and angles with a unit prefix that fits their magnitude, e.g. `3.29 µm` instead of `0.0000032894 m`.
+// The precision of the format string is the number of significant digits, three by default.
+
+const SIGNIFICANT_DIGITS: usize = 3;
+
+const LENGTH_UNITS: [(f64, &str); 6] = [
+    (1e3, "km"),
+    (1.0, "m"),
+    (1e-2, "cm"),
+    (1e-3, "mm"),
+    (1e-6, "µm"),
+    (1e-9, "nm"),
+];
+
+// A length in meters.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Length(pub f64);
+
+// An angle in radians.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Angle(pub f64);
+
+fn write_significant(
+    f: &mut fmt::Formatter<'_>,
+    value: f64,
+    digits: usize,
+    unit: &str,
+) -> fmt::Result {
+    // digits in front of the decimal point, which are not available as decimals
+    let magnitude = if value == 0.0 {
+        0
+    } else {
+        value.abs().log10().floor() as i32
+    };
+    let decimals = (digits as i32 - 1 - magnitude).max(0) as usize;
+
+    write!(f, "{value:.decimals$} {unit}")
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = f.precision().unwrap_or(SIGNIFICANT_DIGITS);
+
+        if !self.0.is_finite() {
+            return write!(f, "{} m", self.0);
+        }
+
+        // zero has no magnitude to pick a prefix for, it is shown in meters
+        if self.0 == 0.0 {
+            return write_significant(f, 0.0, digits, "m");
+        }
+
+        let &(scale, unit) = LENGTH_UNITS
+            .iter()
+            .find(|&&(scale, _)| self.0.abs() >= scale)
+            .unwrap_or(&LENGTH_UNITS[LENGTH_UNITS.len() - 1]);
+
+        write_significant(f, self.0 / scale, digits, unit)
+    }
+}
+
+impl fmt::Display for Angle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = f.precision().unwrap_or(SIGNIFICANT_DIGITS);
+        let degrees = self.0.to_degrees();
+
+        if !degrees.is_finite() || degrees.abs() >= 1.0 {
+            write_significant(f, degrees, digits, "°")
+        } else {
+            write_significant(f, degrees * 3600.0, digits, "arcsec")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lengths_pick_a_prefix() {
+        assert_eq!(Length(0.0).to_string(), "0.00 m");
+        assert_eq!(Length(0.0000032894).to_string(), "3.29 µm");
+        assert_eq!(Length(6778000.0).to_string(), "6778 km");
+        assert_eq!(format!("{:.5}", Length(1.5)), "1.5000 m");
+    }
+}
@@ -0,0 +1,310 @@
+use bevy::math::{DMat3, DMat4, DVec3, Quat};
+use bevy_terrain::prelude::*;
+
+// The terrain model maps the unit sphere onto the ellipsoid, with the local y axis as the polar axis.
+// The reference meridian lies in the xy plane and longitudes increase eastwards, towards the negative z axis.
+// All geodetic conversions below are derived from `world_from_local`, so they work for spheres and ellipsoids alike.
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Geodetic {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub height: f64,
+}
+
+impl Geodetic {
+    pub fn new(latitude: f64, longitude: f64, height: f64) -> Self {
+        Self {
+            latitude,
+            longitude,
+            height,
+        }
+    }
+
+    pub fn from_degrees(latitude: f64, longitude: f64, height: f64) -> Self {
+        Self::new(latitude.to_radians(), longitude.to_radians(), height)
+    }
+
+    pub fn latitude_degrees(&self) -> f64 {
+        self.latitude.to_degrees()
+    }
+
+    pub fn longitude_degrees(&self) -> f64 {
+        self.longitude.to_degrees()
+    }
+
+    pub fn local_position(&self, model: &TerrainModel) -> DVec3 {
+        let (major_axis, minor_axis) = axes(model);
+
+        // reduced (parametric) latitude
+        let beta = ((minor_axis / major_axis) * self.latitude.tan()).atan();
+
+        DVec3::new(
+            beta.cos() * self.longitude.cos(),
+            beta.sin(),
+            -beta.cos() * self.longitude.sin(),
+        )
+    }
+
+    pub fn world_position(&self, model: &TerrainModel) -> DVec3 {
+        let local_position = self.local_position(model);
+        let surface_position = model.world_from_local.transform_point3(local_position);
+
+        surface_position + self.height * surface_normal(model, local_position)
+    }
+
+    pub fn from_world_position(world_position: DVec3, model: &TerrainModel) -> Self {
+        let (major_axis, minor_axis) = axes(model);
+        let e_sqr = 1.0 - (minor_axis * minor_axis) / (major_axis * major_axis);
+
+        let local_position = model
+            .world_from_local
+            .inverse()
+            .transform_point3(world_position);
+        let frame_position = local_position * DVec3::new(major_axis, minor_axis, major_axis);
+
+        let p = frame_position.x.hypot(frame_position.z);
+        let y = frame_position.y;
+        let longitude = (-frame_position.z).atan2(frame_position.x);
+
+        if p < 1e-9 * major_axis {
+            return Self::new(
+                std::f64::consts::FRAC_PI_2.copysign(y),
+                longitude,
+                y.abs() - minor_axis,
+            );
+        }
+
+        let mut latitude = y.atan2(p * (1.0 - e_sqr));
+        let mut height = 0.0;
+
+        for _ in 0..8 {
+            let n = major_axis / (1.0 - e_sqr * latitude.sin().powi(2)).sqrt();
+            height = p / latitude.cos() - n;
+            latitude = y.atan2(p * (1.0 - e_sqr * n / (n + height)));
+        }
+
+        Self::new(latitude, longitude, height)
+    }
+}
+
+// Local east-north-up frame at the geodetic position, with the columns pointing east, north and up.
+pub fn enu_frame(geodetic: Geodetic, model: &TerrainModel) -> DMat3 {
+    let up = surface_normal(model, geodetic.local_position(model));
+    let pole = model
+        .world_from_local
+        .transform_vector3(DVec3::Y)
+        .normalize();
+
+    let east = pole.cross(up).try_normalize().unwrap_or_else(|| {
+        // at the poles the east direction is arbitrary, use the one of the reference meridian
+        model
+            .world_from_local
+            .transform_vector3(DVec3::NEG_Z)
+            .normalize()
+    });
+    let north = up.cross(east);
+
+    DMat3::from_cols(east, north, up)
+}
+
+// Camera position at the geodetic position, and a rotation looking north at the horizon.
+// Above the surface the horizon lies below the horizontal plane by the dip angle, so the view is tilted down by it.
+pub fn horizon_pose(geodetic: Geodetic, model: &TerrainModel) -> (DVec3, Quat) {
+    let frame = enu_frame(geodetic, model);
+    let position = geodetic.world_position(model);
+
+    let ground = Geodetic {
+        height: 0.0,
+        ..geodetic
+    }
+    .world_position(model);
+    let radius = ground.distance(model.position());
+    let dip = (radius / (radius + geodetic.height.max(0.0))).acos();
+
+    let forward = frame.y_axis * dip.cos() - frame.z_axis * dip.sin();
+    let up = frame.z_axis * dip.cos() + frame.y_axis * dip.sin();
+    let right = forward.cross(up);
+
+    (
+        position,
+        Quat::from_mat3(&DMat3::from_cols(right, up, -forward).as_mat3()),
+    )
+}
+
+pub fn axes(model: &TerrainModel) -> (f64, f64) {
+    (
+        model.world_from_local.x_axis.truncate().length(),
+        model.world_from_local.y_axis.truncate().length(),
+    )
+}
+
+// The model with another transform, which keeps everything the constructors set besides it, e.g. the height range.
+pub fn transformed_model(model: &TerrainModel, world_from_local: DMat4) -> TerrainModel {
+    let mut model = model.clone();
+    model.world_from_local = world_from_local;
+    model.local_from_world = world_from_local.inverse();
+    model
+}
+
+// The model with other axes, but the same orientation and position.
+pub fn with_axes(model: &TerrainModel, major_axis: f64, minor_axis: f64) -> TerrainModel {
+    let (_, rotation, translation) = model.world_from_local.to_scale_rotation_translation();
+
+    transformed_model(
+        model,
+        DMat4::from_scale_rotation_translation(
+            DVec3::new(major_axis, minor_axis, major_axis),
+            rotation,
+            translation,
+        ),
+    )
+}
+
+pub fn surface_normal(model: &TerrainModel, local_position: DVec3) -> DVec3 {
+    normal_from_local(&model.world_from_local, local_position)
+}
+
+fn normal_from_local(world_from_local: &DMat4, local_position: DVec3) -> DVec3 {
+    world_from_local
+        .inverse()
+        .transpose()
+        .transform_vector3(local_position)
+        .normalize()
+}
+
+// Interpolates between two geodetic positions along the great circle of the parametric sphere.
+// On the ellipsoid this is not the exact geodesic, but it stays within a few meters of it for country-sized segments.
+pub fn geodesic(
+    start: Geodetic,
+    end: Geodetic,
+    segments: usize,
+    model: &TerrainModel,
+) -> impl Iterator<Item = DVec3> + '_ {
+    let start_local = start.local_position(model);
+    let end_local = end.local_position(model);
+    let angle = start_local.angle_between(end_local);
+
+    (0..=segments).map(move |i| {
+        let t = i as f64 / segments.max(1) as f64;
+
+        let local_position = if angle < 1e-12 {
+            start_local
+        } else {
+            (start_local * ((1.0 - t) * angle).sin() + end_local * (t * angle).sin()) / angle.sin()
+        };
+        let height = start.height + (end.height - start.height) * t;
+
+        model.world_from_local.transform_point3(local_position)
+            + height * surface_normal(model, local_position)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::iproduct;
+    use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+    const MAJOR_AXIS: f64 = 6378137.0;
+    const MINOR_AXIS: f64 = 6356752.314245;
+
+    fn earth() -> TerrainModel {
+        TerrainModel::ellipsoid(DVec3::ZERO, MAJOR_AXIS, MINOR_AXIS, 0.0, 0.0)
+    }
+
+    #[test]
+    fn longitude_increases_eastwards_towards_negative_z() {
+        let model = earth();
+
+        for (longitude, expected) in [
+            (0.0, DVec3::X),
+            (90.0, DVec3::NEG_Z),
+            (-90.0, DVec3::Z),
+            (180.0, DVec3::NEG_X),
+        ] {
+            let position = Geodetic::from_degrees(0.0, longitude, 0.0).world_position(&model);
+
+            assert!(
+                position.distance(expected * MAJOR_AXIS) < 1e-6,
+                "{position}"
+            );
+        }
+
+        let east = Geodetic::from_world_position(DVec3::NEG_Z * MAJOR_AXIS, &model);
+        assert!((east.longitude_degrees() - 90.0).abs() < 1e-12);
+        assert!(east.latitude.abs() < 1e-12);
+    }
+
+    #[test]
+    fn poles_lie_on_the_minor_axis() {
+        let model = earth();
+
+        for (latitude, pole) in [(90.0, DVec3::Y), (-90.0, DVec3::NEG_Y)] {
+            let geodetic = Geodetic::from_degrees(latitude, 0.0, 1000.0);
+            let position = geodetic.world_position(&model);
+
+            assert!(position.distance(pole * (MINOR_AXIS + 1000.0)) < 1e-6);
+
+            let round_trip = Geodetic::from_world_position(position, &model);
+            assert!((round_trip.latitude - latitude.to_radians()).abs() < 1e-15);
+            assert!((round_trip.height - 1000.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn geodetic_round_trip() {
+        let model = earth();
+
+        for (latitude, longitude, height) in iproduct!(
+            [-90.0, -60.0, -0.5, 0.0, 30.0, 89.99, 90.0],
+            [-180.0, -179.99, -90.0, 0.0, 45.0, 179.99, 180.0],
+            [-400.0, 0.0, 8848.0, 400000.0]
+        ) {
+            let geodetic = Geodetic::from_degrees(latitude, longitude, height);
+            let position = geodetic.world_position(&model);
+            let round_trip = Geodetic::from_world_position(position, &model);
+
+            // close to the poles, the height is sensitive to the last bits of the latitude
+            assert!(
+                round_trip.world_position(&model).distance(position) < 1e-4,
+                "{geodetic:?} came back as {round_trip:?}"
+            );
+            assert!((round_trip.latitude - geodetic.latitude).abs() < 1e-12);
+            assert!((round_trip.height - height).abs() < 1e-4);
+
+            // the longitude is undefined at the poles, and -180° and 180° are the same meridian
+            if latitude.abs() < 90.0 {
+                let difference = (round_trip.longitude - geodetic.longitude).rem_euclid(TAU);
+                assert!(difference.min(TAU - difference) < 1e-12);
+                assert!(round_trip.longitude.abs() <= PI);
+            } else {
+                assert_eq!(round_trip.latitude.abs(), FRAC_PI_2);
+            }
+        }
+    }
+
+    #[test]
+    fn with_axes_keeps_orientation_and_position() {
+        let rotation = bevy::math::DQuat::from_euler(bevy::math::EulerRot::YXZ, 0.4, -0.2, 1.1);
+        let translation = DVec3::new(1000.0, -20.0, 3.5);
+        let model = transformed_model(
+            &earth(),
+            DMat4::from_scale_rotation_translation(
+                DVec3::new(MAJOR_AXIS, MINOR_AXIS, MAJOR_AXIS),
+                rotation,
+                translation,
+            ),
+        );
+
+        let edited = with_axes(&model, 6400000.0, 6300000.0);
+        let (scale, edited_rotation, edited_translation) =
+            edited.world_from_local.to_scale_rotation_translation();
+
+        assert!(scale.distance(DVec3::new(6400000.0, 6300000.0, 6400000.0)) < 1e-6);
+        assert!(edited_rotation.angle_between(rotation) < 1e-12);
+        assert!(edited_translation.distance(translation) < 1e-6);
+        let (major_axis, minor_axis) = axes(&edited);
+        assert!((major_axis - 6400000.0).abs() < 1e-6 && (minor_axis - 6300000.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::geodesy::Geodetic;
+use serde_json::Value;
+use std::{fmt, fs, path::Path};
+
+#[derive(Debug)]
+pub enum GeoJsonError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Invalid(&'static str),
+}
+
+impl fmt::Display for GeoJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeoJsonError::Io(error) => write!(f, "failed to read GeoJSON file: {error}"),
+            GeoJsonError::Json(error) => write!(f, "failed to parse GeoJSON: {error}"),
+            GeoJsonError::Invalid(message) => write!(f, "invalid GeoJSON: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for GeoJsonError {}
+
+// A polyline on the surface, one per line string or polygon ring.
+pub type Polyline = Vec<Geodetic>;
+
+pub fn load_polylines(path: impl AsRef<Path>) -> Result<Vec<Polyline>, GeoJsonError> {
+    let source = fs::read_to_string(path).map_err(GeoJsonError::Io)?;
+    parse_polylines(&source)
+}
+
+pub fn parse_polylines(source: &str) -> Result<Vec<Polyline>, GeoJsonError> {
+    let value: Value = serde_json::from_str(source).map_err(GeoJsonError::Json)?;
+
+    let mut polylines = Vec::new();
+    collect_object(&value, &mut polylines)?;
+    Ok(polylines)
+}
+
+fn collect_object(value: &Value, polylines: &mut Vec<Polyline>) -> Result<(), GeoJsonError> {
+    let object_type = value
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or(GeoJsonError::Invalid("object without type"))?;
+
+    match object_type {
+        "FeatureCollection" => {
+            let features = value
+                .get("features")
+                .and_then(Value::as_array)
+                .ok_or(GeoJsonError::Invalid("collection without features"))?;
+
+            for feature in features {
+                collect_object(feature, polylines)?;
+            }
+        }
+        "Feature" => match value.get("geometry") {
+            Some(Value::Null) | None => {}
+            Some(geometry) => collect_object(geometry, polylines)?,
+        },
+        "GeometryCollection" => {
+            let geometries = value
+                .get("geometries")
+                .and_then(Value::as_array)
+                .ok_or(GeoJsonError::Invalid("collection without geometries"))?;
+
+            for geometry in geometries {
+                collect_object(geometry, polylines)?;
+            }
+        }
+        "LineString" => polylines.push(parse_line(coordinates(value)?)?),
+        "MultiLineString" | "Polygon" => {
+            for line in as_array(coordinates(value)?)? {
+                polylines.push(parse_line(line)?);
+            }
+        }
+        "MultiPolygon" => {
+            for polygon in as_array(coordinates(value)?)? {
+                for ring in as_array(polygon)? {
+                    polylines.push(parse_line(ring)?);
+                }
+            }
+        }
+        // points carry no line geometry
+        "Point" | "MultiPoint" => {}
+        _ => return Err(GeoJsonError::Invalid("unknown object type")),
+    }
+
+    Ok(())
+}
+
+fn coordinates(value: &Value) -> Result<&Value, GeoJsonError> {
+    value
+        .get("coordinates")
+        .ok_or(GeoJsonError::Invalid("geometry without coordinates"))
+}
+
+fn as_array(value: &Value) -> Result<&Vec<Value>, GeoJsonError> {
+    value
+        .as_array()
+        .ok_or(GeoJsonError::Invalid("expected an array"))
+}
+
+fn parse_line(value: &Value) -> Result<Polyline, GeoJsonError> {
+    as_array(value)?.iter().map(parse_position).collect()
+}
+
+// GeoJSON positions are [longitude, latitude, (height)] in degrees.
+fn parse_position(value: &Value) -> Result<Geodetic, GeoJsonError> {
+    let position = as_array(value)?;
+
+    let component = |index: usize| position.get(index).and_then(Value::as_f64);
+
+    let (Some(longitude), Some(latitude)) = (component(0), component(1)) else {
+        return Err(GeoJsonError::Invalid("position without coordinates"));
+    };
+
+    Ok(Geodetic::from_degrees(
+        latitude,
+        longitude,
+        component(2).unwrap_or(0.0),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::DVec3;
+    use bevy_terrain::prelude::*;
+
+    // A square around 10° E, 20° N, counter-clockwise as seen from above, like RFC 7946 requires of outer rings.
+    const SQUARE: &str = r#"{
+        "type": "Feature",
+        "geometry": {
+            "type": "Polygon",
+            "coordinates": [[[9.0, 19.0], [11.0, 19.0], [11.0, 21.0], [9.0, 21.0], [9.0, 19.0]]]
+        }
+    }"#;
+
+    #[test]
+    fn rings_keep_their_winding_on_the_ellipsoid() {
+        let model = TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0);
+        let polylines = parse_polylines(SQUARE).unwrap();
+        assert_eq!(polylines.len(), 1);
+
+        let positions = polylines[0]
+            .iter()
+            .map(|geodetic| geodetic.world_position(&model))
+            .collect::<Vec<_>>();
+        let center = Geodetic::from_degrees(20.0, 10.0, 0.0).world_position(&model);
+
+        // a mirrored longitude would turn the ring clockwise, with its normal pointing into the ellipsoid
+        let normal = positions
+            .windows(2)
+            .map(|pair| (pair[0] - center).cross(pair[1] - center))
+            .sum::<DVec3>();
+
+        assert!(normal.normalize().dot(center.normalize()) > 0.99);
+    }
+
+    #[test]
+    fn positions_are_longitude_first() {
+        let polylines =
+            parse_polylines(r#"{"type": "LineString", "coordinates": [[30.0, -10.0, 5.0]]}"#)
+                .unwrap();
+
+        let geodetic = polylines[0][0];
+        assert!((geodetic.longitude_degrees() - 30.0).abs() < 1e-12);
+        assert!((geodetic.latitude_degrees() + 10.0).abs() < 1e-12);
+        assert_eq!(geodetic.height, 5.0);
+    }
+}
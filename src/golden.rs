@@ -0,0 +1,432 @@
+use bevy::{
+    math::{DMat3, DVec3},
+    prelude::*,
+    render::view::screenshot::ScreenshotManager,
+    window::PrimaryWindow,
+};
+use bevy_terrain::big_space::{FloatingOrigin, GridTransform, ReferenceFrames};
+use image::{Rgba, RgbaImage};
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+// largest difference in the YIQ space between two 8 bit colors, used to normalize the deviation to [0, 1]
+const MAX_YIQ_DELTA: f32 = 35215.0;
+
+// A camera pose rendered and compared against a stored golden image of the same name.
+#[derive(Clone, Debug)]
+pub struct GoldenPose {
+    pub name: String,
+    pub position: DVec3,
+    pub rotation: Quat,
+}
+
+impl GoldenPose {
+    // The rotation is computed in f64, since the poses are often built from positions far away from the origin.
+    pub fn looking_at(name: impl Into<String>, position: DVec3, target: DVec3, up: DVec3) -> Self {
+        let back = (position - target).normalize();
+        let right = up.cross(back).normalize();
+        let up = back.cross(right);
+
+        Self {
+            name: name.into(),
+            position,
+            rotation: Quat::from_mat3(&DMat3::from_cols(right, up, back).as_mat3()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GoldenReport {
+    pub pose: String,
+    // largest perceptual difference of a single pixel, between 0 and 1
+    pub max_deviation: f32,
+    pub mean_deviation: f32,
+    // number of pixels deviating by more than the tolerance
+    pub deviating_pixels: usize,
+    pub passed: bool,
+}
+
+impl GoldenReport {
+    // Report of a pose that could not be compared at all, which counts every pixel as deviating.
+    pub fn failed(pose: &str, pixels: usize, reason: &str) -> Self {
+        error!("Golden {pose} failed: {reason}");
+
+        Self {
+            pose: pose.to_string(),
+            max_deviation: 1.0,
+            mean_deviation: 1.0,
+            deviating_pixels: pixels,
+            passed: false,
+        }
+    }
+}
+
+// Perceptual difference between two colors, measured in the YIQ space like pixelmatch does.
+// Changes in brightness weigh more than changes in hue, which matches what is noticeable on screen.
+pub fn perceptual_deviation(a: Rgba<u8>, b: Rgba<u8>) -> f32 {
+    let yiq = |color: Rgba<u8>| {
+        let [r, g, b] = [0, 1, 2].map(|i| color[i] as f32);
+
+        Vec3::new(
+            0.29889531 * r + 0.58662247 * g + 0.11448223 * b,
+            0.59597799 * r - 0.27417610 * g - 0.32180189 * b,
+            0.21147017 * r - 0.52261711 * g + 0.31114694 * b,
+        )
+    };
+
+    let delta = yiq(a) - yiq(b);
+    let delta = 0.5053 * delta.x * delta.x + 0.299 * delta.y * delta.y + 0.1957 * delta.z * delta.z;
+
+    (delta / MAX_YIQ_DELTA).sqrt()
+}
+
+// Compares a frame against its golden and returns the report, together with an image marking the deviating pixels in red.
+pub fn compare_images(
+    pose: &str,
+    actual: &RgbaImage,
+    golden: &RgbaImage,
+    tolerance: f32,
+) -> (GoldenReport, RgbaImage) {
+    let mut diff = RgbaImage::new(actual.width(), actual.height());
+    let mut max_deviation: f32 = 0.0;
+    let mut total_deviation = 0.0;
+    let mut deviating_pixels = 0;
+
+    for ((actual, golden), diff) in actual.pixels().zip(golden.pixels()).zip(diff.pixels_mut()) {
+        let deviation = perceptual_deviation(*actual, *golden);
+
+        max_deviation = max_deviation.max(deviation);
+        total_deviation += deviation as f64;
+
+        *diff = if deviation > tolerance {
+            deviating_pixels += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            // the golden faded to gray, so that the deviations stand out
+            let luminance =
+                0.299 * golden[0] as f32 + 0.587 * golden[1] as f32 + 0.114 * golden[2] as f32;
+            let gray = 160 + (0.25 * luminance) as u8;
+            Rgba([gray, gray, gray, 255])
+        };
+    }
+
+    let report = GoldenReport {
+        pose: pose.to_string(),
+        max_deviation,
+        mean_deviation: (total_deviation / (actual.width() * actual.height()).max(1) as f64) as f32,
+        deviating_pixels,
+        passed: deviating_pixels == 0,
+    };
+
+    (report, diff)
+}
+
+enum GoldenStage {
+    // waits for the tiles of the pose to load, before the frame is captured
+    Settle(u32),
+    Capture(Arc<Mutex<Option<Image>>>),
+    Finished,
+}
+
+#[derive(Resource)]
+struct GoldenRun {
+    pose: usize,
+    stage: GoldenStage,
+    reports: Vec<GoldenReport>,
+}
+
+// Renders each pose, compares the frames against the goldens and exits with an error if any pose deviates.
+// With `update` set, the frames are written as the new goldens instead.
+// The frames are captured from the primary window, since screenshots are only supported for windows,
+// so the window has to keep the resolution the goldens were rendered with.
+#[derive(Resource, Clone)]
+pub struct GoldenPlugin {
+    pub poses: Vec<GoldenPose>,
+    pub directory: PathBuf,
+    // perceptual deviation a pixel may have, before the pose fails
+    pub tolerance: f32,
+    // frames rendered at a pose before it is captured
+    pub settle_frames: u32,
+    pub update: bool,
+}
+
+impl GoldenPlugin {
+    pub fn new(poses: Vec<GoldenPose>, directory: impl Into<PathBuf>) -> Self {
+        Self {
+            poses,
+            directory: directory.into(),
+            tolerance: 0.1,
+            settle_frames: 120,
+            update: false,
+        }
+    }
+
+    pub fn updating(mut self) -> Self {
+        self.update = true;
+        self
+    }
+}
+
+impl Plugin for GoldenPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.clone())
+            .insert_resource(GoldenRun {
+                pose: 0,
+                stage: GoldenStage::Settle(self.settle_frames),
+                reports: Vec::new(),
+            })
+            .add_systems(Update, run_golden_poses);
+    }
+}
+
+fn run_golden_poses(
+    settings: Res<GoldenPlugin>,
+    mut run: ResMut<GoldenRun>,
+    mut screenshots: ResMut<ScreenshotManager>,
+    mut exit: EventWriter<AppExit>,
+    frames: ReferenceFrames,
+    window_query: Query<Entity, With<PrimaryWindow>>,
+    mut view_query: Query<(Entity, GridTransform), With<FloatingOrigin>>,
+) {
+    let Some(pose) = settings.poses.get(run.pose) else {
+        if !matches!(run.stage, GoldenStage::Finished) {
+            finish(&settings, &run.reports, &mut exit);
+            run.stage = GoldenStage::Finished;
+        }
+        return;
+    };
+
+    let Ok((view, mut view_transform)) = view_query.get_single_mut() else {
+        warn_once!("Skipping run_golden_poses: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping run_golden_poses: the camera is not part of a reference frame.");
+        return;
+    };
+    let Ok(window) = window_query.get_single() else {
+        warn_once!("Skipping run_golden_poses: expected exactly one primary window.");
+        return;
+    };
+
+    match &mut run.stage {
+        GoldenStage::Settle(frames_left) if *frames_left > 0 => {
+            // the pose is applied every frame, so that nothing else moves the camera away from it
+            let (cell, translation) = frame.translation_to_grid(pose.position);
+            *view_transform.cell = cell;
+            view_transform.transform.translation = translation;
+            view_transform.transform.rotation = pose.rotation;
+
+            *frames_left -= 1;
+        }
+        GoldenStage::Settle(_) => {
+            let capture = Arc::new(Mutex::new(None));
+            let slot = capture.clone();
+
+            if screenshots
+                .take_screenshot(window, move |image| {
+                    *slot.lock().unwrap() = Some(image);
+                })
+                .is_ok()
+            {
+                run.stage = GoldenStage::Capture(capture);
+            }
+        }
+        GoldenStage::Capture(capture) => {
+            let Some(image) = capture.lock().unwrap().take() else {
+                return;
+            };
+
+            // a frame that cannot be converted fails the pose, instead of dropping it from the run
+            let pixels = (image.width() * image.height()) as usize;
+            let report = match image.try_into_dynamic() {
+                Ok(image) => check_pose(&settings, pose, &image.to_rgba8()),
+                Err(error) => Some(GoldenReport::failed(
+                    &pose.name,
+                    pixels,
+                    &format!("the frame could not be converted: {error}"),
+                )),
+            };
+
+            run.reports.extend(report);
+            run.pose += 1;
+            run.stage = GoldenStage::Settle(settings.settle_frames);
+        }
+        GoldenStage::Finished => {}
+    }
+}
+
+fn check_pose(
+    settings: &GoldenPlugin,
+    pose: &GoldenPose,
+    actual: &RgbaImage,
+) -> Option<GoldenReport> {
+    let golden_path = settings.directory.join(format!("{}.png", pose.name));
+
+    let failed = |reason: String| {
+        GoldenReport::failed(
+            &pose.name,
+            (actual.width() * actual.height()) as usize,
+            &reason,
+        )
+    };
+
+    if settings.update {
+        return fs::create_dir_all(&settings.directory)
+            .map_err(image::ImageError::IoError)
+            .and_then(|_| actual.save(&golden_path))
+            .err()
+            .map(|error| failed(format!("{}: {error}", golden_path.display())));
+    }
+
+    let golden = match image::open(&golden_path) {
+        Ok(golden) => golden.to_rgba8(),
+        Err(error) => return Some(failed(format!("{}: {error}", golden_path.display()))),
+    };
+
+    if golden.dimensions() != actual.dimensions() {
+        return Some(failed(format!(
+            "the frame is {:?}, but the golden is {:?}",
+            actual.dimensions(),
+            golden.dimensions()
+        )));
+    }
+
+    let (report, diff) = compare_images(&pose.name, actual, &golden, settings.tolerance);
+
+    if !report.passed {
+        let failures = settings.directory.join("failures");
+        let result = fs::create_dir_all(&failures)
+            .map_err(image::ImageError::IoError)
+            .and_then(|_| actual.save(failures.join(format!("{}.png", pose.name))))
+            .and_then(|_| diff.save(failures.join(format!("{}.diff.png", pose.name))));
+
+        if let Err(error) = result {
+            error!("Failed to write the failures of {}: {error}", pose.name);
+        }
+    }
+
+    Some(report)
+}
+
+fn finish(settings: &GoldenPlugin, reports: &[GoldenReport], exit: &mut EventWriter<AppExit>) {
+    let failed = reports.iter().filter(|report| !report.passed).count();
+
+    // while updating, only the poses that failed to be captured or written are reported
+    if settings.update {
+        info!(
+            "Wrote {} goldens to {}.",
+            settings.poses.len() - failed,
+            settings.directory.display()
+        );
+        exit.send(if failed == 0 {
+            AppExit::Success
+        } else {
+            AppExit::from_code(1)
+        });
+        return;
+    }
+
+    for report in reports {
+        info!(
+            "{:<24} {} max deviation {:.4}, mean deviation {:.6}, {} pixels above the tolerance",
+            report.pose,
+            if report.passed { "passed" } else { "FAILED" },
+            report.max_deviation,
+            report.mean_deviation,
+            report.deviating_pixels
+        );
+    }
+
+    if failed == 0 {
+        exit.send(AppExit::Success);
+    } else {
+        error!(
+            "{failed} of {} poses deviate from their goldens.",
+            reports.len()
+        );
+        exit.send(AppExit::from_code(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+    #[test]
+    fn deviation_is_normalized() {
+        assert_eq!(perceptual_deviation(WHITE, WHITE), 0.0);
+        assert_eq!(
+            perceptual_deviation(BLACK, WHITE),
+            perceptual_deviation(WHITE, BLACK)
+        );
+
+        let colors = [0, 255]
+            .into_iter()
+            .flat_map(|r| [0, 255].map(|g| (r, g)))
+            .flat_map(|(r, g)| [0, 255].map(|b| Rgba([r, g, b, 255])))
+            .collect::<Vec<_>>();
+
+        for (a, b) in colors
+            .iter()
+            .flat_map(|a| colors.iter().map(move |b| (*a, *b)))
+        {
+            let deviation = perceptual_deviation(a, b);
+            assert!((0.0..=1.0).contains(&deviation), "{a:?} {b:?} {deviation}");
+        }
+
+        assert!(perceptual_deviation(BLACK, WHITE) > 0.9);
+    }
+
+    #[test]
+    fn brightness_weighs_more_than_hue() {
+        let gray = Rgba([128, 128, 128, 255]);
+        let brighter = Rgba([148, 148, 148, 255]);
+        // the channels change by as much, but mostly in hue, since red rises as green falls
+        let shifted = Rgba([148, 108, 128, 255]);
+
+        assert!(perceptual_deviation(gray, brighter) > perceptual_deviation(gray, shifted));
+    }
+
+    #[test]
+    fn identical_images_pass() {
+        let image = RgbaImage::from_fn(4, 3, |x, y| Rgba([x as u8 * 60, y as u8 * 80, 30, 255]));
+
+        let (report, diff) = compare_images("identical", &image, &image, 0.1);
+
+        assert!(report.passed);
+        assert_eq!(report.pose, "identical");
+        assert_eq!(report.max_deviation, 0.0);
+        assert_eq!(report.mean_deviation, 0.0);
+        assert_eq!(report.deviating_pixels, 0);
+        assert!(diff
+            .pixels()
+            .all(|pixel| pixel[0] == pixel[1] && pixel[0] >= 160));
+    }
+
+    #[test]
+    fn deviating_pixels_are_marked() {
+        let golden = RgbaImage::from_pixel(4, 3, BLACK);
+        let mut actual = golden.clone();
+        actual.put_pixel(2, 1, WHITE);
+        // below the tolerance, so it only counts towards the mean
+        actual.put_pixel(0, 0, Rgba([2, 2, 2, 255]));
+
+        let (report, diff) = compare_images("deviating", &actual, &golden, 0.1);
+        let deviation = perceptual_deviation(WHITE, BLACK);
+        let small_deviation = perceptual_deviation(Rgba([2, 2, 2, 255]), BLACK);
+
+        assert!(!report.passed);
+        assert_eq!(report.deviating_pixels, 1);
+        assert_eq!(report.max_deviation, deviation);
+        assert!((report.mean_deviation - (deviation + small_deviation) / 12.0).abs() < 1e-6);
+        assert_eq!(*diff.get_pixel(2, 1), Rgba([255, 0, 0, 255]));
+        assert_eq!(*diff.get_pixel(0, 0), Rgba([160, 160, 160, 255]));
+    }
+}
@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+use bevy_terrain::math::SurfaceApproximation;
+use bytemuck::{Pod, Zeroable};
+use std::mem::{offset_of, size_of};
+
+use crate::{
+    tile_prepass::{CompactTile, TileData},
+    view_approximation::DualApproximation,
+};
+
+// GPU mirror of the surface approximation of one side.
+// In WGSL every vec3<f32> is aligned to 16 bytes, so each coefficient is followed by four bytes of padding.
+// The matching WGSL struct is `SideParameter` in `assets/shaders/precision_types.wgsl`, which the shaders import.
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy, Default, Debug, PartialEq)]
+pub struct SideParameter {
+    pub c: Vec3,
+    _padding0: u32,
+    pub c_du: Vec3,
+    _padding1: u32,
+    pub c_dv: Vec3,
+    _padding2: u32,
+    pub c_duu: Vec3,
+    _padding3: u32,
+    pub c_duv: Vec3,
+    _padding4: u32,
+    pub c_dvv: Vec3,
+    _padding5: u32,
+}
+
+impl From<&SurfaceApproximation> for SideParameter {
+    fn from(approximation: &SurfaceApproximation) -> Self {
+        Self {
+            c: approximation.c,
+            c_du: approximation.c_du,
+            c_dv: approximation.c_dv,
+            c_duu: approximation.c_duu,
+            c_duv: approximation.c_duv,
+            c_dvv: approximation.c_dvv,
+            ..default()
+        }
+    }
+}
+
+// One side per face of the topology, six for the cube.
+pub fn side_parameters<const FACES: usize>(
+    approximations: &[SurfaceApproximation; FACES],
+) -> [SideParameter; FACES] {
+    std::array::from_fn(|face| SideParameter::from(&approximations[face]))
+}
+
+// The sides of both views one after the other, so that the shader indexes them with `6 * view + face`.
+pub fn dual_side_parameters(dual: &DualApproximation) -> [SideParameter; 12] {
+    std::array::from_fn(|index| {
+        SideParameter::from(&dual.snapshots[index / 6].approximations[index % 6])
+    })
+}
+
+// The layouts have to match the WGSL structs byte for byte, since they are uploaded with bytemuck.
+const _: () = {
+    assert!(size_of::<SideParameter>() == 96);
+    assert!(offset_of!(SideParameter, c) == 0);
+    assert!(offset_of!(SideParameter, c_du) == 16);
+    assert!(offset_of!(SideParameter, c_dv) == 32);
+    assert!(offset_of!(SideParameter, c_duu) == 48);
+    assert!(offset_of!(SideParameter, c_duv) == 64);
+    assert!(offset_of!(SideParameter, c_dvv) == 80);
+
+    assert!(size_of::<CompactTile>() == 16);
+    assert!(offset_of!(CompactTile, face) == 0);
+    assert!(offset_of!(CompactTile, lod) == 4);
+    assert!(offset_of!(CompactTile, xy) == 8);
+
+    assert!(size_of::<TileData>() == 16);
+    assert!(offset_of!(TileData, offset) == 0);
+    assert!(offset_of!(TileData, lod_difference) == 8);
+    assert!(offset_of!(TileData, face_remap) == 12);
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::render_resource::{encase::StorageBuffer, ShaderType};
+
+    // The WGSL layout of `SideParameter`, as encase lays out the struct without the explicit padding.
+    #[derive(ShaderType)]
+    struct SideParameterLayout {
+        c: Vec3,
+        c_du: Vec3,
+        c_dv: Vec3,
+        c_duu: Vec3,
+        c_duv: Vec3,
+        c_dvv: Vec3,
+    }
+
+    fn shader_bytes(value: &impl ShaderType) -> Vec<u8> {
+        let mut buffer = StorageBuffer::new(Vec::new());
+        buffer.write(value).unwrap();
+        buffer.into_inner()
+    }
+
+    #[test]
+    fn side_parameter_matches_the_shader_layout() {
+        let coefficient = |index: f32| Vec3::new(index, index + 0.25, index + 0.5);
+        let approximation = SurfaceApproximation {
+            c: coefficient(1.0),
+            c_du: coefficient(2.0),
+            c_dv: coefficient(3.0),
+            c_duu: coefficient(4.0),
+            c_duv: coefficient(5.0),
+            c_dvv: coefficient(6.0),
+        };
+        let layout = SideParameterLayout {
+            c: approximation.c,
+            c_du: approximation.c_du,
+            c_dv: approximation.c_dv,
+            c_duu: approximation.c_duu,
+            c_duv: approximation.c_duv,
+            c_dvv: approximation.c_dvv,
+        };
+
+        assert_eq!(
+            bytemuck::bytes_of(&SideParameter::from(&approximation)),
+            shader_bytes(&layout)
+        );
+    }
+
+    #[test]
+    fn tiles_match_the_shader_layout() {
+        let tile = CompactTile {
+            face: 3,
+            lod: 17,
+            xy: UVec2::new(70_001, 12_345),
+        };
+        let tile_data = TileData {
+            offset: IVec2::new(-5, 9),
+            lod_difference: -2,
+            face_remap: 4,
+        };
+
+        assert_eq!(bytemuck::bytes_of(&tile), shader_bytes(&tile));
+        assert_eq!(bytemuck::bytes_of(&tile_data), shader_bytes(&tile_data));
+    }
+}
@@ -0,0 +1,50 @@
+use bevy::{math::DVec3, prelude::*};
+use bevy_terrain::big_space::{GridCell, ReferenceFrame, ReferenceFrames};
+
+// Offset between two positions of the same reference frame.
+// The cells are subtracted as integers, before anything is converted to f64, so the result keeps its full precision,
+// even if the absolute positions are too large to be represented accurately.
+pub fn grid_offset(
+    frame: &ReferenceFrame,
+    (from_cell, from_transform): (&GridCell, &Transform),
+    (to_cell, to_transform): (&GridCell, &Transform),
+) -> DVec3 {
+    frame.grid_position_double(&(*to_cell - *from_cell), &Transform::IDENTITY)
+        + (to_transform.translation.as_dvec3() - from_transform.translation.as_dvec3())
+}
+
+// Offset between two entities, which have to share the same reference frame.
+pub fn entity_offset(
+    frames: &ReferenceFrames,
+    transform_query: &Query<(&GridCell, &Transform)>,
+    from: Entity,
+    to: Entity,
+) -> Option<DVec3> {
+    let frame = frames.parent_frame(from)?;
+    let from_transform = transform_query.get(from).ok()?;
+    let to_transform = transform_query.get(to).ok()?;
+
+    Some(grid_offset(&frame, from_transform, to_transform))
+}
+
+pub fn entity_distance(
+    frames: &ReferenceFrames,
+    transform_query: &Query<(&GridCell, &Transform)>,
+    from: Entity,
+    to: Entity,
+) -> Option<f64> {
+    entity_offset(frames, transform_query, from, to).map(DVec3::length)
+}
+
+// Moves a translation into another cell of the frame, keeping the position it describes.
+// The cell difference is exact, so the only error is the single rounding of the new translation to f32.
+pub fn rebase_translation(
+    frame: &ReferenceFrame,
+    from_cell: &GridCell,
+    to_cell: &GridCell,
+    translation: Vec3,
+) -> Vec3 {
+    (frame.grid_position_double(&(*from_cell - *to_cell), &Transform::IDENTITY)
+        + translation.as_dvec3())
+    .as_vec3()
+}
@@ -0,0 +1,52 @@
+use bevy::{math::DVec3, prelude::*};
+use bevy_terrain::{math::Coordinate, prelude::*};
+
+use crate::{epoch::Rebase, model_copy::ModelCopy};
+
+// Provides the terrain height above the reference surface of the model at a coordinate.
+pub trait HeightProvider: Send + Sync {
+    fn height(&self, coordinate: Coordinate) -> f64;
+
+    // lower and upper bound of all heights, used to limit searches against the heightfield
+    fn height_range(&self) -> (f64, f64);
+
+    // replaces the copy of the model the heights are evaluated on, for providers keeping one
+    fn set_model(&mut self, _model: &TerrainModel) {}
+
+    // moves the copy of the model along with the scene epoch, for providers keeping one
+    fn rebase(&mut self, _offset: DVec3) {}
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConstantHeight(pub f64);
+
+impl HeightProvider for ConstantHeight {
+    fn height(&self, _coordinate: Coordinate) -> f64 {
+        self.0
+    }
+
+    fn height_range(&self) -> (f64, f64) {
+        (self.0, self.0)
+    }
+}
+
+#[derive(Resource)]
+pub struct TerrainHeights(pub Box<dyn HeightProvider>);
+
+impl ModelCopy for TerrainHeights {
+    fn set_model(&mut self, model: &TerrainModel) {
+        self.0.set_model(model);
+    }
+}
+
+impl Rebase for TerrainHeights {
+    fn rebase(&mut self, offset: DVec3) {
+        self.0.rebase(offset);
+    }
+}
+
+impl Default for TerrainHeights {
+    fn default() -> Self {
+        Self(Box::new(ConstantHeight(0.0)))
+    }
+}
@@ -0,0 +1,85 @@
+use bevy::{math::DVec3, prelude::*};
+use bevy_terrain::{
+    big_space::{FloatingOrigin, GridTransformReadOnly, ReferenceFrames},
+    prelude::*,
+};
+
+use crate::{
+    epoch::{shift_model, Rebase},
+    model_copy::ModelCopy,
+};
+
+// A cheap stand-in for the terrain, used once the view is further away from the planet than the switch distance.
+// The impostor is a single ellipsoid mesh, while the tiles are hidden.
+#[derive(Component)]
+pub struct Impostor {
+    pub model: TerrainModel,
+    // distance between the view and the model center, beyond which the impostor is shown
+    pub switch_distance: f64,
+    pub active: bool,
+}
+
+impl Impostor {
+    pub fn new(model: TerrainModel, switch_distance: f64) -> Self {
+        Self {
+            model,
+            switch_distance,
+            active: false,
+        }
+    }
+}
+
+impl ModelCopy for Impostor {
+    fn set_model(&mut self, model: &TerrainModel) {
+        self.model = model.clone();
+    }
+}
+
+impl Rebase for Impostor {
+    fn rebase(&mut self, offset: DVec3) {
+        self.model = shift_model(&self.model, offset);
+    }
+}
+
+pub fn impostor_bundle(
+    model: &TerrainModel,
+    meshes: &mut Assets<Mesh>,
+    material: Handle<StandardMaterial>,
+) -> PbrBundle {
+    let (scale, rotation, _) = model.world_from_local.to_scale_rotation_translation();
+
+    PbrBundle {
+        mesh: meshes.add(Sphere::new(1.0).mesh().uv(64, 32)),
+        material,
+        transform: Transform::from_rotation(rotation.as_quat()).with_scale(scale.as_vec3()),
+        visibility: Visibility::Hidden,
+        ..default()
+    }
+}
+
+pub fn update_impostors(
+    frames: ReferenceFrames,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+    mut impostor_query: Query<(&mut Impostor, &mut Visibility)>,
+) {
+    let Ok((view, view_transform)) = view_query.get_single() else {
+        warn_once!("Skipping update_impostors: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping update_impostors: the camera is not part of a reference frame.");
+        return;
+    };
+    let view_position = view_transform.position_double(&frame);
+
+    for (mut impostor, mut visibility) in &mut impostor_query {
+        let distance = view_position.distance(impostor.model.position());
+
+        impostor.active = distance > impostor.switch_distance;
+        *visibility = if impostor.active {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
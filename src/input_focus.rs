@@ -0,0 +1,47 @@
+use bevy::{
+    input::{
+        mouse::{MouseMotion, MouseWheel},
+        InputSystem,
+    },
+    prelude::*,
+    window::PrimaryWindow,
+};
+
+// Suspends the input while the primary window is unfocused, so the camera controller does not react to
+// keys and mouse motion meant for other windows. The motion accumulated while alt-tabbing is dropped on refocus,
+// which would otherwise be applied as one large rotation.
+pub struct InputFocusPlugin;
+
+impl Plugin for InputFocusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreUpdate, suspend_unfocused_input.after(InputSystem));
+    }
+}
+
+fn suspend_unfocused_input(
+    mut was_focused: Local<bool>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut mouse_buttons: ResMut<ButtonInput<MouseButton>>,
+    mut mouse_motion: ResMut<Events<MouseMotion>>,
+    mut mouse_wheel: ResMut<Events<MouseWheel>>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+
+    let focused = window.focused;
+    let refocused = focused && !*was_focused;
+    *was_focused = focused;
+
+    if !focused {
+        keys.reset_all();
+        mouse_buttons.reset_all();
+    }
+
+    // the cursor is released, when it left the window
+    if !focused || refocused || window.cursor_position().is_none() {
+        mouse_motion.clear();
+        mouse_wheel.clear();
+    }
+}
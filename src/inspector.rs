@@ -0,0 +1,17 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::quick::WorldInspectorPlugin;
+
+use crate::{collider::RigidBody, culling::Occluded, orbit::Orbit};
+
+// Registers the reflected types of the crate and opens the world inspector, to live-edit them while debugging.
+// Binaries register their own reflected types in addition.
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Orbit>()
+            .register_type::<RigidBody>()
+            .register_type::<Occluded>()
+            .add_plugins(WorldInspectorPlugin::new());
+    }
+}
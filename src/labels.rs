@@ -0,0 +1,138 @@
+use bevy::{prelude::*, ui::UiSystem};
+use bevy_terrain::big_space::{FloatingOrigin, GridCell, GridTransformReadOnly, ReferenceFrames};
+
+use crate::grid::grid_offset;
+
+// Text attached to a position in the grid, e.g. a marker name or a coefficient value.
+// Each label is shown by a screen-space text node, which is created once and only updated when the label changes.
+// The node is placed using the grid offset of the label to the camera, instead of the f32 global transforms,
+// so labels at planetary distances do not jitter.
+#[derive(Component, Clone, Debug)]
+pub struct WorldLabel {
+    pub text: String,
+    pub color: Color,
+    pub font_size: f32,
+}
+
+impl WorldLabel {
+    pub fn new(text: impl Into<String>, color: Color) -> Self {
+        Self {
+            text: text.into(),
+            color,
+            font_size: 14.0,
+        }
+    }
+}
+
+// The text node showing the label of the target entity.
+#[derive(Component)]
+struct LabelNode(Entity);
+
+pub struct LabelPlugin;
+
+impl Plugin for LabelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            (sync_label_nodes, position_label_nodes)
+                .chain()
+                .before(UiSystem::Layout),
+        );
+    }
+}
+
+fn label_text(label: &WorldLabel) -> Text {
+    Text::from_section(
+        label.text.clone(),
+        TextStyle {
+            font_size: label.font_size,
+            color: label.color,
+            ..default()
+        },
+    )
+}
+
+// Updates the single section of the text in place, which keeps its string buffer.
+fn update_label_text(text: &mut Text, label: &WorldLabel) {
+    let [section] = &mut text.sections[..] else {
+        *text = label_text(label);
+        return;
+    };
+
+    section.value.clone_from(&label.text);
+    section.style.font_size = label.font_size;
+    section.style.color = label.color;
+}
+
+fn sync_label_nodes(
+    mut commands: Commands,
+    label_query: Query<(Entity, Ref<WorldLabel>)>,
+    mut node_query: Query<(&LabelNode, &mut Text)>,
+) {
+    for (entity, label) in &label_query {
+        if label.is_added() {
+            commands.spawn((
+                TextBundle {
+                    text: label_text(&label),
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        ..default()
+                    },
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                LabelNode(entity),
+            ));
+        }
+    }
+
+    for (&LabelNode(target), mut text) in &mut node_query {
+        if let Ok((_, label)) = label_query.get(target) {
+            if label.is_changed() {
+                update_label_text(&mut text, &label);
+            }
+        }
+    }
+}
+
+fn position_label_nodes(
+    mut commands: Commands,
+    frames: ReferenceFrames,
+    view_query: Query<(Entity, &Camera, GridTransformReadOnly), With<FloatingOrigin>>,
+    label_query: Query<(&GridCell, &Transform), With<WorldLabel>>,
+    mut node_query: Query<(Entity, &LabelNode, &mut Style, &mut Visibility)>,
+) {
+    let Ok((view, camera, view_transform)) = view_query.get_single() else {
+        warn_once!("Skipping position_label_nodes: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping position_label_nodes: the camera is not part of a reference frame.");
+        return;
+    };
+
+    // the labels are projected relative to the camera, which sits at the origin of this transform
+    let view_rotation = GlobalTransform::from_rotation(view_transform.transform.rotation);
+
+    for (node, &LabelNode(target), mut style, mut visibility) in &mut node_query {
+        let Ok(label_transform) = label_query.get(target) else {
+            commands.entity(node).despawn();
+            continue;
+        };
+
+        let offset = grid_offset(
+            &frame,
+            (view_transform.cell, view_transform.transform),
+            label_transform,
+        );
+
+        match camera.world_to_viewport(&view_rotation, offset.as_vec3()) {
+            Some(screen_position) => {
+                style.left = Val::Px(screen_position.x);
+                style.top = Val::Px(screen_position.y);
+                *visibility = Visibility::Inherited;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
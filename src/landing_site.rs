@@ -0,0 +1,178 @@
+use bevy::{
+    math::{DMat3, DVec3},
+    prelude::*,
+};
+use bevy_terrain::{
+    big_space::{FloatingOrigin, GridCell, GridTransformReadOnly, ReferenceFrames},
+    prelude::*,
+};
+
+use crate::{
+    epoch::{shift_model, Rebase, RebaseAppExt},
+    format::Length,
+    geodesy::{enu_frame, Geodetic},
+    model_copy::{ModelCopy, ModelCopyAppExt},
+    view_approximation::ApproximationSnapshot,
+};
+
+// The site is released again once the camera climbs above this multiple of the altitude,
+// so that hovering around the altitude does not toggle the mode every frame.
+const RELEASE_FACTOR: f64 = 2.0;
+
+// A local east-north-up frame frozen at a point on the surface, with the approximation anchored there.
+// Surface operations like rovers and bases stay within a few kilometers of the site, where f32 offsets
+// in this frame resolve a tenth of a millimeter, and the anchored approximation does not move with the camera.
+#[derive(Clone, Copy)]
+pub struct SiteFrame {
+    pub geodetic: Geodetic,
+    pub origin: DVec3,
+    // columns pointing east, north and up in world space
+    pub world_from_enu: DMat3,
+    pub snapshot: ApproximationSnapshot,
+}
+
+impl SiteFrame {
+    pub fn new(geodetic: Geodetic, model: &TerrainModel) -> Self {
+        let geodetic = Geodetic {
+            height: 0.0,
+            ..geodetic
+        };
+        let origin = geodetic.world_position(model);
+
+        Self {
+            geodetic,
+            origin,
+            world_from_enu: enu_frame(geodetic, model),
+            snapshot: ApproximationSnapshot::compute(origin, model),
+        }
+    }
+
+    pub fn to_local(&self, world_position: DVec3) -> DVec3 {
+        self.world_from_enu.transpose() * (world_position - self.origin)
+    }
+
+    pub fn to_world(&self, local_position: DVec3) -> DVec3 {
+        self.origin + self.world_from_enu * local_position
+    }
+
+    // Moves the frame along with the whole scene, e.g. when the scene epoch is rebased.
+    // The model moves by the same offset, so the orientation stays as it is.
+    pub fn translate(&mut self, offset: DVec3) {
+        self.origin += offset;
+        self.snapshot.translate(offset);
+    }
+}
+
+// Freezes a site frame at the point under the camera, once it descends below the altitude.
+#[derive(Resource)]
+pub struct LandingSite {
+    pub model: TerrainModel,
+    pub altitude: f64,
+    pub frame: Option<SiteFrame>,
+}
+
+impl LandingSite {
+    pub fn new(model: TerrainModel, altitude: f64) -> Self {
+        Self {
+            model,
+            altitude,
+            frame: None,
+        }
+    }
+}
+
+impl ModelCopy for LandingSite {
+    fn set_model(&mut self, model: &TerrainModel) {
+        self.model = model.clone();
+    }
+}
+
+impl Rebase for LandingSite {
+    fn rebase(&mut self, offset: DVec3) {
+        self.model = shift_model(&self.model, offset);
+        if let Some(frame) = &mut self.frame {
+            frame.translate(offset);
+        }
+    }
+}
+
+// The position of the entity in the east-north-up frame of the site, as f32 offsets like a local scene would store them.
+// The error is the distance between the position reconstructed from the offsets and the exact one.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct SiteLocal {
+    pub position: Option<Vec3>,
+    pub error: f64,
+}
+
+pub struct LandingSitePlugin;
+
+impl Plugin for LandingSitePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_model_copy_resource::<LandingSite>()
+            .add_rebase_resource::<LandingSite>()
+            .add_systems(
+                PostUpdate,
+                (update_landing_site, express_in_site)
+                    .chain()
+                    .run_if(resource_exists::<LandingSite>),
+            );
+    }
+}
+
+fn update_landing_site(
+    mut site: ResMut<LandingSite>,
+    frames: ReferenceFrames,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+) {
+    let Ok((view, view_transform)) = view_query.get_single() else {
+        warn_once!("Skipping update_landing_site: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping update_landing_site: the camera is not part of a reference frame.");
+        return;
+    };
+
+    let view_position = view_transform.position_double(&frame);
+    let geodetic = Geodetic::from_world_position(view_position, &site.model);
+
+    match site.frame {
+        None if geodetic.height < site.altitude => {
+            let frame = SiteFrame::new(geodetic, &site.model);
+
+            info!(
+                "Froze the landing site at {:.6}° {:.6}°, below an altitude of {}.",
+                frame.geodetic.latitude_degrees(),
+                frame.geodetic.longitude_degrees(),
+                Length(site.altitude)
+            );
+            site.frame = Some(frame);
+        }
+        Some(_) if geodetic.height > RELEASE_FACTOR * site.altitude => {
+            info!("Released the landing site.");
+            site.frame = None;
+        }
+        _ => {}
+    }
+}
+
+fn express_in_site(
+    site: Res<LandingSite>,
+    frames: ReferenceFrames,
+    mut local_query: Query<(Entity, &mut SiteLocal, &GridCell, &Transform)>,
+) {
+    for (entity, mut local, cell, transform) in &mut local_query {
+        let (Some(site_frame), Some(frame)) = (&site.frame, frames.parent_frame(entity)) else {
+            *local = SiteLocal::default();
+            continue;
+        };
+
+        let world_position = frame.grid_position_double(cell, transform);
+        let position = site_frame.to_local(world_position).as_vec3();
+
+        local.position = Some(position);
+        local.error = site_frame
+            .to_world(position.as_dvec3())
+            .distance(world_position);
+    }
+}
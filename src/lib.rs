@@ -1,3 +1,69 @@
+//! Experiments on rendering planetary terrain with sub-meter precision.
+//!
+//! The [`math`] module is the precision core: it evaluates the Taylor approximation of the surface
+//! relative to the view, which is what the terrain shaders do in f32.
+//! The remaining modules build the demo scenes on top of it.
+
 #![allow(dead_code, unused_variables)]
 
+pub mod anchor;
+pub mod animated;
+pub mod annotation;
+pub mod budget;
+pub mod clock;
+pub mod collider;
+pub mod culling;
+pub mod debug_log;
+pub mod depth;
+pub mod deterministic;
+pub mod diagnostics;
 pub mod draw;
+pub mod epoch;
+pub mod error_field;
+pub mod features;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod format;
+pub mod geodesy;
+pub mod geojson;
+pub mod golden;
+pub mod gpu;
+pub mod grid;
+pub mod height;
+pub mod impostor;
+pub mod input_focus;
+#[cfg(feature = "inspector")]
+pub mod inspector;
+pub mod labels;
+pub mod landing_site;
+pub mod math;
+pub mod measure;
+pub mod model_copy;
+pub mod notation;
+pub mod ocean;
+pub mod orbit;
+pub mod origin_lod;
+pub mod particles;
+pub mod path;
+pub mod probe;
+pub mod raycast;
+pub mod replay;
+pub mod scene_check;
+pub mod second_view;
+pub mod shadows;
+pub mod stereo;
+pub mod tile_key;
+pub mod tile_mesh;
+pub mod tile_prepass;
+pub mod track;
+pub mod vehicle;
+pub mod view_approximation;
+
+pub use bevy_terrain::math::{Coordinate, SurfaceApproximation, TileCoordinate, ViewCoordinate};
+pub use geodesy::Geodetic;
+pub use height::HeightProvider;
+pub use math::{
+    approximate_relative_position, approximate_world_position, approximate_world_position_within,
+    approximations, evaluate_approximation, tile_coordinate_from_world_position, view_coordinates,
+    ViewCoordinates,
+};
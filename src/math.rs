@@ -0,0 +1,1104 @@
+use bevy::math::{DMat2, DMat3, DQuat, DVec2, DVec3, UVec2, Vec2, Vec3};
+use bevy_terrain::{
+    math::{Coordinate, SurfaceApproximation, TileCoordinate, ViewCoordinate},
+    prelude::*,
+};
+use itertools::iproduct;
+use std::f64::consts::PI;
+
+use crate::math::topology::{Cube, FaceTopology};
+
+pub mod atlas;
+pub mod decomposition;
+pub mod fixture;
+pub mod simd;
+pub mod topology;
+pub mod validate;
+
+/// Deepest lod, whose tile indices still fit into the u32 coordinates of a tile.
+pub const MAX_LOD: u32 = 32;
+
+/// Number of tiles along each axis of a face at the lod.
+/// It is computed in u64, so that it does not wrap for the deepest lods like `1 << lod` in u32 does.
+pub fn tile_count(lod: u32) -> u64 {
+    debug_assert!(
+        lod <= MAX_LOD,
+        "the lod {lod} exceeds the maximum lod {MAX_LOD}"
+    );
+
+    1u64 << lod
+}
+
+/// Size of a tile at the lod in uv space of the face.
+pub fn tile_size(lod: u32) -> f64 {
+    1.0 / tile_count(lod) as f64
+}
+
+/// Same as [`Coordinate::from_world_position`], but with defined results at degenerate positions.
+/// The center of the model has no direction, it maps to the center of the first face.
+/// Positions on a cube edge or corner may land on either adjacent face, their uv is clamped onto that face.
+pub fn coordinate_from_world_position(world_position: DVec3, model: &TerrainModel) -> Coordinate {
+    let local_position = model
+        .world_from_local
+        .inverse()
+        .transform_point3(world_position);
+
+    if !local_position.is_finite() || local_position.length() < 1e-12 {
+        return Coordinate::new(0, DVec2::splat(0.5));
+    }
+
+    let coordinate = Coordinate::from_world_position(world_position, model);
+
+    if !coordinate.uv.is_finite() {
+        return Coordinate::new(coordinate.face, DVec2::splat(0.5));
+    }
+
+    Coordinate::new(
+        coordinate.face,
+        coordinate.uv.clamp(DVec2::ZERO, DVec2::ONE),
+    )
+}
+
+/// The edges of a tile, named after the uv coordinate that is constant along them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileEdge {
+    MinU,
+    MaxU,
+    MinV,
+    MaxV,
+}
+
+/// World positions of the tile corners on the surface, counterclockwise in uv space starting at the uv origin.
+pub fn tile_corner_positions(tile: TileCoordinate, model: &TerrainModel) -> [DVec3; 4] {
+    let size = tile_size(tile.lod);
+
+    [(0, 0), (1, 0), (1, 1), (0, 1)].map(|(x, y)| {
+        let corner_uv = (UVec2::new(tile.x, tile.y) + UVec2::new(x, y)).as_dvec2() * size;
+        Coordinate::new(tile.face, corner_uv).world_position(model, 0.0)
+    })
+}
+
+/// Samples the world positions of `n + 1` evenly spaced points along the edge of the tile, including both corners.
+pub fn sample_tile_edge(
+    tile: TileCoordinate,
+    edge: TileEdge,
+    n: u32,
+    model: &TerrainModel,
+) -> impl Iterator<Item = DVec3> + '_ {
+    let size = tile_size(tile.lod);
+    let tile_uv = UVec2::new(tile.x, tile.y).as_dvec2() * size;
+
+    (0..=n).map(move |i| {
+        let t = i as f64 / n.max(1) as f64;
+
+        let edge_uv = match edge {
+            TileEdge::MinU => DVec2::new(0.0, t),
+            TileEdge::MaxU => DVec2::new(1.0, t),
+            TileEdge::MinV => DVec2::new(t, 0.0),
+            TileEdge::MaxV => DVec2::new(t, 1.0),
+        };
+
+        Coordinate::new(tile.face, tile_uv + edge_uv * size).world_position(model, 0.0)
+    })
+}
+
+// solid angle of the spherical triangle spanned by three unit vectors (Van Oosterom and Strackee)
+fn triangle_solid_angle(a: DVec3, b: DVec3, c: DVec3) -> f64 {
+    2.0 * a
+        .dot(b.cross(c))
+        .atan2(1.0 + a.dot(b) + b.dot(c) + c.dot(a))
+        .abs()
+}
+
+/// Surface area of the tile in square meters.
+/// The tile edges are great circles of the unit sphere, so on a sphere the area follows exactly from the solid angle
+/// of the corners. On an ellipsoid the tile is subdivided and the areas of the flat triangles are summed.
+pub fn tile_area(tile: TileCoordinate, model: &TerrainModel) -> f64 {
+    let (major_axis, minor_axis) = crate::geodesy::axes(model);
+
+    if (major_axis - minor_axis).abs() <= 1e-9 * major_axis {
+        let local_from_world = model.world_from_local.inverse();
+        let [a, b, c, d] = tile_corner_positions(tile, model)
+            .map(|corner| local_from_world.transform_point3(corner).normalize());
+
+        return (triangle_solid_angle(a, b, c) + triangle_solid_angle(a, c, d))
+            * major_axis
+            * major_axis;
+    }
+
+    let subdivisions = 16;
+    let size = tile_size(tile.lod) / subdivisions as f64;
+    let tile_uv = UVec2::new(tile.x, tile.y).as_dvec2() * tile_size(tile.lod);
+    let position = |x: u32, y: u32| {
+        Coordinate::new(tile.face, tile_uv + UVec2::new(x, y).as_dvec2() * size)
+            .world_position(model, 0.0)
+    };
+
+    iproduct!(0..subdivisions, 0..subdivisions)
+        .map(|(x, y)| {
+            let (a, b) = (position(x, y), position(x + 1, y));
+            let (c, d) = (position(x + 1, y + 1), position(x, y + 1));
+
+            0.5 * ((b - a).cross(c - a).length() + (c - a).cross(d - a).length())
+        })
+        .sum()
+}
+
+/// Solid angle in steradians the tile covers, as seen from the view position.
+pub fn tile_solid_angle(tile: TileCoordinate, view_position: DVec3, model: &TerrainModel) -> f64 {
+    let [a, b, c, d] = tile_corner_positions(tile, model)
+        .map(|corner| (corner - view_position).normalize_or_zero());
+
+    triangle_solid_angle(a, b, c) + triangle_solid_angle(a, c, d)
+}
+
+/// Projects the view position onto all six faces of the model.
+pub fn view_coordinates(view_position: DVec3, model: &TerrainModel) -> [Coordinate; 6] {
+    let view_coordinate = coordinate_from_world_position(view_position, model);
+
+    std::array::from_fn(|face| view_coordinate.project_to_face(face as u32, model))
+}
+
+/// Computes the surface approximation around the view coordinate of each face.
+pub fn approximations(
+    view_coordinates: &[Coordinate; 6],
+    view_position: DVec3,
+    model: &TerrainModel,
+) -> [SurfaceApproximation; 6] {
+    std::array::from_fn(|face| {
+        SurfaceApproximation::compute(view_coordinates[face], view_position, model)
+    })
+}
+
+/// Returns the tile at the lod containing the world position, together with the position inside the tile.
+pub fn tile_coordinate_from_world_position(
+    world_position: DVec3,
+    lod: u32,
+    model: &TerrainModel,
+) -> (TileCoordinate, Vec2) {
+    let coordinate = coordinate_from_world_position(world_position, model);
+    let count = tile_count(lod) as f64;
+    let uv = coordinate.uv * count;
+    // the upper edge of the face belongs to the last tile
+    let tile_xy = uv.floor().min(DVec2::splat(count - 1.0));
+    let tile_uv = (uv - tile_xy).as_vec2();
+    let tile_xy = tile_xy.as_uvec2();
+
+    (
+        TileCoordinate::new(coordinate.face, lod, tile_xy.x, tile_xy.y),
+        tile_uv,
+    )
+}
+
+/// Evaluates the Taylor series of the surface at the uv offset to the view coordinate.
+/// The result is the position relative to the view.
+pub fn evaluate_approximation(
+    approximation: &SurfaceApproximation,
+    second_order: bool,
+    Vec2 { x: u, y: v }: Vec2,
+) -> Vec3 {
+    let &SurfaceApproximation {
+        c,
+        c_du,
+        c_dv,
+        c_duu,
+        c_duv,
+        c_dvv,
+    } = approximation;
+
+    if second_order {
+        c + c_du * u + c_dv * v + c_duu * u * u + c_duv * u * v + c_dvv * v * v
+    } else {
+        c + c_du * u + c_dv * v
+    }
+}
+
+/// Derivative of `Coordinate::world_position` with respect to the height.
+/// Heights displace the surface along its geodetic normal, so this is the unit normal at the coordinate.
+pub fn height_derivative(coordinate: Coordinate, model: &TerrainModel) -> DVec3 {
+    crate::geodesy::surface_normal(model, cube_to_sphere(coordinate.face, coordinate.uv))
+}
+
+/// Height coefficients `c_dh` of the approximations, the derivative with respect to the height at each view coordinate.
+pub fn height_coefficients(view_coordinates: &[Coordinate; 6], model: &TerrainModel) -> [Vec3; 6] {
+    std::array::from_fn(|face| height_derivative(view_coordinates[face], model).as_vec3())
+}
+
+/// Same as [`evaluate_approximation`], but displaced by the height along the normal at the view coordinate.
+/// The change of the normal across the uv offset is neglected, which is exact at the view and grows with the offset.
+pub fn evaluate_displaced_approximation(
+    approximation: &SurfaceApproximation,
+    c_dh: Vec3,
+    second_order: bool,
+    uv: Vec2,
+    height: f32,
+) -> Vec3 {
+    evaluate_approximation(approximation, second_order, uv) + c_dh * height
+}
+
+/// Computes the uv offset of a point inside a tile to the view coordinate on the same face.
+/// The offset is computed from integer tile offsets at the origin lod, like in the shader.
+/// Tiles finer than the origin lod shift the origin tile up to their lod.
+/// Tiles coarser than the origin lod shift the origin tile down instead,
+/// and fold the dropped bits of the origin tile into its fractional part.
+pub fn relative_uv(
+    view_coordinate: Coordinate,
+    origin_lod: u32,
+    coordinate: (TileCoordinate, Vec2),
+) -> Vec2 {
+    let ViewCoordinate { xy, uv } = ViewCoordinate::new(view_coordinate, origin_lod);
+
+    relative_uv_to_origin(xy, uv, origin_lod, coordinate)
+}
+
+// The relative uv from the origin tile of the view and the uv of the view inside it.
+fn relative_uv_to_origin(
+    origin_xy: UVec2,
+    view_uv: Vec2,
+    origin_lod: u32,
+    (tile, tile_uv): (TileCoordinate, Vec2),
+) -> Vec2 {
+    // the integer offsets are computed in i64, so that the shifts do not overflow for deep lods
+    let (tile_xy, view_xy) = (tile.xy().as_i64vec2(), origin_xy.as_i64vec2());
+
+    let relative_uv = if tile.lod >= origin_lod {
+        let lod_difference = tile.lod - origin_lod;
+        let scale = tile_count(lod_difference) as f32;
+
+        (tile_xy - (view_xy << lod_difference)).as_vec2() + tile_uv - view_uv * scale
+    } else {
+        let lod_difference = origin_lod - tile.lod;
+        let scale = tile_count(lod_difference) as f32;
+        let remainder = view_xy & ((1 << lod_difference) - 1);
+
+        (tile_xy - (view_xy >> lod_difference)).as_vec2() + tile_uv
+            - (remainder.as_vec2() + view_uv) / scale
+    };
+
+    relative_uv / tile_count(tile.lod) as f32
+}
+
+/// The view coordinates of all six faces, with the origin tile and the uv of the view inside it at the origin lod.
+/// Projecting the view and splitting off its origin tile is the same for every point evaluated around the view,
+/// so it is done once and shared by everything evaluating the approximation within a frame.
+#[derive(Clone, Copy)]
+pub struct ViewCoordinates {
+    pub coordinates: [Coordinate; 6],
+    pub origin_lod: u32,
+    pub origin_xy: [UVec2; 6],
+    pub view_uv: [Vec2; 6],
+}
+
+impl ViewCoordinates {
+    pub fn new(coordinates: [Coordinate; 6], origin_lod: u32) -> Self {
+        let view_coordinates =
+            coordinates.map(|coordinate| ViewCoordinate::new(coordinate, origin_lod));
+
+        Self {
+            coordinates,
+            origin_lod,
+            origin_xy: view_coordinates.each_ref().map(|view| view.xy),
+            view_uv: view_coordinates.each_ref().map(|view| view.uv),
+        }
+    }
+
+    /// Projects the view position onto all six faces, see [`view_coordinates`].
+    pub fn from_view_position(view_position: DVec3, origin_lod: u32, model: &TerrainModel) -> Self {
+        Self::new(view_coordinates(view_position, model), origin_lod)
+    }
+
+    /// Same as [`relative_uv`], without recomputing the origin tile of the view.
+    pub fn relative_uv(&self, coordinate: (TileCoordinate, Vec2)) -> Vec2 {
+        let face = coordinate.0.face as usize;
+
+        relative_uv_to_origin(
+            self.origin_xy[face],
+            self.view_uv[face],
+            self.origin_lod,
+            coordinate,
+        )
+    }
+
+    /// Same as [`approximate_world_position`], without recomputing the origin tile of the view.
+    pub fn approximate_world_position(
+        &self,
+        approximations: &[SurfaceApproximation],
+        second_order: bool,
+        view_position: DVec3,
+        coordinate: (TileCoordinate, Vec2),
+    ) -> DVec3 {
+        let relative_position = evaluate_approximation(
+            &approximations[coordinate.0.face as usize],
+            second_order,
+            self.relative_uv(coordinate),
+        );
+
+        view_position + relative_position.as_dvec3()
+    }
+}
+
+/// Overlap of tile textures, which store a margin of texels copied from the neighboring tiles around the tile itself.
+/// The margin lets the textures be filtered across tile edges, but shifts and shrinks the tile inside the texture.
+/// Mirrored by `tile_margin.wgsl`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileMargin {
+    /// Size of the texture in texels, including the margin on both sides.
+    pub texture_size: u32,
+    /// Width of the margin on each side in texels.
+    pub margin: u32,
+}
+
+impl TileMargin {
+    pub fn new(texture_size: u32, margin: u32) -> Self {
+        debug_assert!(
+            2 * margin < texture_size,
+            "the margin of {margin} texels leaves nothing of a texture of {texture_size} texels"
+        );
+
+        Self {
+            texture_size,
+            margin,
+        }
+    }
+
+    /// Factor by which the tile is larger than the part of the texture it covers.
+    pub fn scale(&self) -> f32 {
+        self.texture_size as f32 / (self.texture_size - 2 * self.margin) as f32
+    }
+
+    /// Converts a uv on the texture into a uv on the tile, which lies outside of the unit square inside the margin.
+    pub fn tile_uv(&self, texture_uv: Vec2) -> Vec2 {
+        let offset = self.margin as f32 / self.texture_size as f32;
+
+        (texture_uv - offset) * self.scale()
+    }
+
+    /// Inverse of [`TileMargin::tile_uv`], the uv at which to sample the texture for a uv on the tile.
+    pub fn texture_uv(&self, tile_uv: Vec2) -> Vec2 {
+        let offset = self.margin as f32 / self.texture_size as f32;
+
+        tile_uv / self.scale() + offset
+    }
+}
+
+/// Same as [`relative_uv`], but for a uv on a tile texture with a margin.
+/// The margin is removed before the integer tile offsets are added, so texels in the margin
+/// land on the neighboring tiles they were copied from, instead of being squeezed into the tile.
+pub fn relative_uv_with_margin(
+    view_coordinate: Coordinate,
+    origin_lod: u32,
+    margin: TileMargin,
+    (tile, texture_uv): (TileCoordinate, Vec2),
+) -> Vec2 {
+    relative_uv(
+        view_coordinate,
+        origin_lod,
+        (tile, margin.tile_uv(texture_uv)),
+    )
+}
+
+/// Approximates the position of a point inside a tile relative to the view.
+pub fn approximate_relative_position(
+    view_coordinates: &[Coordinate],
+    approximations: &[SurfaceApproximation],
+    origin_lod: u32,
+    second_order: bool,
+    (tile, tile_uv): (TileCoordinate, Vec2),
+) -> Vec3 {
+    let relative_uv = relative_uv(
+        view_coordinates[tile.face as usize],
+        origin_lod,
+        (tile, tile_uv),
+    );
+
+    evaluate_approximation(
+        &approximations[tile.face as usize],
+        second_order,
+        relative_uv,
+    )
+}
+
+/// Same as [`approximate_relative_position`], but offset by the view position.
+pub fn approximate_world_position(
+    view_coordinates: &[Coordinate],
+    approximations: &[SurfaceApproximation],
+    origin_lod: u32,
+    second_order: bool,
+    view_position: DVec3,
+    coordinate: (TileCoordinate, Vec2),
+) -> DVec3 {
+    view_position
+        + approximate_relative_position(
+            view_coordinates,
+            approximations,
+            origin_lod,
+            second_order,
+            coordinate,
+        )
+        .as_dvec3()
+}
+
+/// Whether the relative uv lies within the validity radius of the approximation around its view coordinate.
+/// The distance is estimated from the first order terms alone, which stay accurate far beyond the radius
+/// at which the second order terms start to deviate by meters.
+pub fn within_validity_radius(
+    approximation: &SurfaceApproximation,
+    relative_uv: Vec2,
+    validity_radius: f64,
+) -> bool {
+    let distance = approximation.c_du * relative_uv.x + approximation.c_dv * relative_uv.y;
+
+    (distance.length() as f64) <= validity_radius
+}
+
+/// Same as [`approximate_world_position`] with the second order terms, but falls back to the exact position
+/// computed in f64, if the point lies outside of the validity radius, see [`within_validity_radius`].
+/// Returns whether the fallback was taken, so that it can be counted.
+pub fn approximate_world_position_within(
+    view_coordinates: &[Coordinate],
+    approximations: &[SurfaceApproximation],
+    origin_lod: u32,
+    view_position: DVec3,
+    (tile, tile_uv): (TileCoordinate, Vec2),
+    validity_radius: f64,
+    model: &TerrainModel,
+) -> (DVec3, bool) {
+    let face = tile.face as usize;
+    let relative_uv = relative_uv(view_coordinates[face], origin_lod, (tile, tile_uv));
+
+    if within_validity_radius(&approximations[face], relative_uv, validity_radius) {
+        let relative_position = evaluate_approximation(&approximations[face], true, relative_uv);
+
+        (view_position + relative_position.as_dvec3(), false)
+    } else {
+        let uv = (tile.xy().as_dvec2() + tile_uv.as_dvec2()) * tile_size(tile.lod);
+
+        (
+            Coordinate::new(tile.face, uv).world_position(model, 0.0),
+            true,
+        )
+    }
+}
+
+/// Squared stretch factor of the cube to sphere mapping, the same as in the terrain shaders.
+pub const C_SQR: f64 = 0.87 * 0.87;
+
+// origin and u, v directions of each face on the unit cube
+const FACE_AXES: [(DVec3, DVec3, DVec3); 6] = [
+    (DVec3::NEG_X, DVec3::Z, DVec3::NEG_Y),
+    (DVec3::Z, DVec3::X, DVec3::NEG_Y),
+    (DVec3::Y, DVec3::X, DVec3::Z),
+    (DVec3::X, DVec3::NEG_Y, DVec3::Z),
+    (DVec3::NEG_Z, DVec3::NEG_Y, DVec3::X),
+    (DVec3::NEG_Y, DVec3::Z, DVec3::X),
+];
+
+/// Rotation from the frame of the face into the local space of the model.
+/// The frame of a face has x along u, y along the outward normal through its center and z along v,
+/// so meshes can be generated y up on the face, like any other mesh, and rotated into place.
+pub fn face_rotation(face: u32) -> DQuat {
+    let (origin, axis_u, axis_v) = FACE_AXES[face as usize];
+
+    DQuat::from_mat3(&DMat3::from_cols(axis_u, origin, axis_v))
+}
+
+/// Rotates a direction from the frame of the face into the local space of the model.
+pub fn face_to_local(face: u32, direction: DVec3) -> DVec3 {
+    face_rotation(face) * direction
+}
+
+/// Rotates a direction from the local space of the model into the frame of the face.
+pub fn local_to_face(face: u32, direction: DVec3) -> DVec3 {
+    face_rotation(face).inverse() * direction
+}
+
+/// Transforms a direction from the frame of the face into world space, normalized again after the scale of the model.
+pub fn face_to_world(face: u32, direction: DVec3, model: &TerrainModel) -> DVec3 {
+    (DMat3::from_mat4(model.world_from_local) * face_to_local(face, direction)).normalize()
+}
+
+/// Inverse of [`face_to_world`].
+pub fn world_to_face(face: u32, direction: DVec3, model: &TerrainModel) -> DVec3 {
+    let local_from_world = DMat3::from_mat4(model.world_from_local).inverse();
+
+    local_to_face(face, local_from_world * direction).normalize()
+}
+
+/// Maps the uv of a face onto the unit sphere, in the local space of the model.
+pub fn cube_to_sphere(face: u32, uv: DVec2) -> DVec3 {
+    let (origin, axis_u, axis_v) = FACE_AXES[face as usize];
+    let w = 2.0 * uv - 1.0;
+    let s = w / (1.0 + C_SQR - C_SQR * w * w).powf(0.5);
+
+    (origin + s.x * axis_u + s.y * axis_v).normalize()
+}
+
+/// Inverse of [`cube_to_sphere`], the face is the one the local position points at the most.
+pub fn sphere_to_cube(local_position: DVec3) -> (u32, DVec2) {
+    let face = (0..6)
+        .max_by(|&a, &b| {
+            let (a, b) = (FACE_AXES[a].0, FACE_AXES[b].0);
+            a.dot(local_position).total_cmp(&b.dot(local_position))
+        })
+        .unwrap() as u32;
+
+    (face, face_uv(face, local_position).unwrap())
+}
+
+/// Projects the local position onto the plane of the face, the uv lies outside of the unit square beyond its edges.
+/// Directions pointing away from the face have no projection.
+pub fn face_uv(face: u32, local_position: DVec3) -> Option<DVec2> {
+    let (origin, axis_u, axis_v) = FACE_AXES[face as usize];
+    let distance = origin.dot(local_position);
+
+    if distance <= 0.0 {
+        return None;
+    }
+
+    let p = local_position / distance;
+    let s = DVec2::new(p.dot(axis_u), p.dot(axis_v));
+    let w = s * ((1.0 + C_SQR) / (1.0 + C_SQR * s * s)).powf(0.5);
+
+    Some((w + 1.0) / 2.0)
+}
+
+/// Returns all tiles at the lod, that intersect the disc with the radius in meters around the center.
+/// The disc is treated as a spherical cap in the local space of the model, whose angular radius is derived
+/// from the smallest radius of curvature, so the selection errs on the side of too many tiles on an ellipsoid.
+/// The tiles are found by descending the quadtree of each face, keeping the tiles within the angular radius,
+/// so tiles across face edges and cube corners are found like any other.
+pub fn tiles_in_region(
+    center: Coordinate,
+    radius: f64,
+    lod: u32,
+    model: &TerrainModel,
+) -> Vec<TileCoordinate> {
+    let (major_axis, minor_axis) = crate::geodesy::axes(model);
+    let angular_radius = (radius * major_axis / (minor_axis * minor_axis)).min(PI);
+
+    let local_from_world = model.world_from_local.inverse();
+    let center_direction = local_from_world
+        .transform_point3(center.world_position(model, 0.0))
+        .normalize();
+
+    let mut pending = (0..6)
+        .map(|face| TileCoordinate::new(face, 0, 0, 0))
+        .collect::<Vec<_>>();
+    let mut tiles = Vec::new();
+
+    while let Some(tile) = pending.pop() {
+        if angle_to_tile(center_direction, tile) > angular_radius {
+            continue;
+        }
+
+        if tile.lod == lod {
+            tiles.push(tile);
+            continue;
+        }
+
+        pending.extend(iproduct!(0..2, 0..2).map(|(x, y)| {
+            TileCoordinate::new(tile.face, tile.lod + 1, 2 * tile.x + x, 2 * tile.y + y)
+        }));
+    }
+
+    tiles
+}
+
+/// Smallest angle between the direction and the tile on the unit sphere, zero if the direction points into the tile.
+/// The edges of a tile have a constant u or v, which the cube mapping maps onto great circles,
+/// so the tile is a convex spherical quadrilateral, and its distance is the one to the closest edge.
+pub fn angle_to_tile(direction: DVec3, tile: TileCoordinate) -> f64 {
+    let size = tile_size(tile.lod);
+    let tile_min = DVec2::new(tile.x as f64, tile.y as f64) * size;
+    let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]
+        .map(|(x, y)| cube_to_sphere(tile.face, tile_min + DVec2::new(x, y) * size));
+    let tile_center = cube_to_sphere(tile.face, tile_min + 0.5 * size);
+
+    let edges = [0, 1, 2, 3].map(|index| {
+        let (a, b) = (corners[index], corners[(index + 1) % 4]);
+        let normal = a.cross(b).normalize();
+
+        // orient the plane of the edge towards the inside of the tile
+        let normal = if normal.dot(tile_center) < 0.0 {
+            -normal
+        } else {
+            normal
+        };
+
+        (a, b, normal)
+    });
+
+    if edges
+        .iter()
+        .all(|&(_, _, normal)| normal.dot(direction) >= 0.0)
+    {
+        return 0.0;
+    }
+
+    edges
+        .iter()
+        .map(|&(a, b, normal)| {
+            // the closest point on the great circle of the edge, if it lies between the corners
+            let closest = direction - normal * normal.dot(direction);
+
+            if closest.length() > 1e-12
+                && a.cross(closest).dot(b.cross(closest)) <= 0.0
+                && closest.dot(a + b) > 0.0
+            {
+                direction.angle_between(closest)
+            } else {
+                direction.angle_between(a).min(direction.angle_between(b))
+            }
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// Interpolates between two coordinates along the great circle of the unit sphere in the local space of the model.
+/// The path may cross face boundaries, the result lies on the face the interpolated direction points at.
+/// On an ellipsoid this follows the parametric sphere, which stays close to the geodesic for short distances.
+pub fn slerp_coordinate(a: Coordinate, b: Coordinate, t: f64, model: &TerrainModel) -> Coordinate {
+    let local_from_world = model.world_from_local.inverse();
+    let start = local_from_world
+        .transform_point3(a.world_position(model, 0.0))
+        .normalize();
+    let end = local_from_world
+        .transform_point3(b.world_position(model, 0.0))
+        .normalize();
+
+    let angle = start.angle_between(end);
+
+    let direction = if angle < 1e-12 {
+        start
+    } else if std::f64::consts::PI - angle < 1e-12 {
+        // antipodal coordinates have no unique great circle, rotate around any perpendicular axis
+        let axis = start.any_orthonormal_vector();
+        start * (t * angle).cos() + axis * (t * angle).sin()
+    } else {
+        (start * ((1.0 - t) * angle).sin() + end * (t * angle).sin()) / angle.sin()
+    };
+
+    coordinate_from_world_position(model.world_from_local.transform_point3(direction), model)
+}
+
+// stretched cube coordinate and its first and second derivative with respect to uv
+fn stretch(uv: f64) -> (f64, f64, f64) {
+    let w = 2.0 * uv - 1.0;
+    let k = 1.0 + C_SQR - C_SQR * w * w;
+    let k_sqrt = k.sqrt();
+    let k_pow_3 = k * k_sqrt;
+
+    (
+        w / k_sqrt,
+        2.0 * (1.0 + C_SQR) / k_pow_3,
+        12.0 * C_SQR * (1.0 + C_SQR) * w / (k * k_pow_3),
+    )
+}
+
+/// Partial derivatives of the surface position with respect to u and v at the coordinate, in world space.
+pub fn surface_jacobian(coordinate: Coordinate, model: &TerrainModel) -> (DVec3, DVec3) {
+    let (origin, axis_u, axis_v) = FACE_AXES[coordinate.face as usize];
+    let (s_u, ds_u, _) = stretch(coordinate.uv.x);
+    let (s_v, ds_v, _) = stretch(coordinate.uv.y);
+
+    let p = origin + s_u * axis_u + s_v * axis_v;
+    let r_inv = p.length_recip();
+    let n = p * r_inv;
+
+    let dn = |x: DVec3| (x - n * n.dot(x)) * r_inv;
+    let linear = DMat3::from_mat4(model.world_from_local);
+
+    (linear * dn(ds_u * axis_u), linear * dn(ds_v * axis_v))
+}
+
+/// Converts a world space velocity at the coordinate into the rate of change of its uv.
+/// The part of the velocity normal to the surface is dropped, by solving the normal equations of the jacobian.
+pub fn velocity_to_uv(coordinate: Coordinate, velocity: DVec3, model: &TerrainModel) -> DVec2 {
+    let (p_u, p_v) = surface_jacobian(coordinate, model);
+
+    let metric = DMat2::from_cols(
+        DVec2::new(p_u.dot(p_u), p_u.dot(p_v)),
+        DVec2::new(p_u.dot(p_v), p_v.dot(p_v)),
+    );
+
+    metric.inverse() * DVec2::new(p_u.dot(velocity), p_v.dot(velocity))
+}
+
+/// Converts a rate of change of the uv at the coordinate into the world space velocity along the surface.
+pub fn uv_to_velocity(coordinate: Coordinate, uv_velocity: DVec2, model: &TerrainModel) -> DVec3 {
+    let (p_u, p_v) = surface_jacobian(coordinate, model);
+
+    p_u * uv_velocity.x + p_v * uv_velocity.y
+}
+
+/// Number of origin tiles per second the view crosses, when it moves with the velocity along the surface.
+pub fn origin_tile_rate(
+    coordinate: Coordinate,
+    velocity: DVec3,
+    origin_lod: u32,
+    model: &TerrainModel,
+) -> f64 {
+    let uv_velocity = velocity_to_uv(coordinate, velocity, model);
+
+    uv_velocity.abs().max_element() * tile_count(origin_lod) as f64
+}
+
+/// Computes the same approximations as [`approximations`], with the terms shared between the faces hoisted.
+/// The linear part of `world_from_local` is extracted once, and each stretch factor costs a single square root.
+/// With `neighborhood_only`, the face opposite to the view is skipped and left zeroed,
+/// since no tile around the view can lie on it.
+pub fn compute_approximations(
+    view_coordinates: &[Coordinate; 6],
+    view_position: DVec3,
+    model: &TerrainModel,
+    neighborhood_only: bool,
+) -> [SurfaceApproximation; 6] {
+    let world_from_local = model.world_from_local;
+    let linear = DMat3::from_mat4(world_from_local);
+    let relative_translation = world_from_local.w_axis.truncate() - view_position;
+
+    let view_face = Coordinate::from_world_position(view_position, model).face as usize;
+    // faces are numbered so that opposite faces are three apart
+    let opposite_face = (view_face + 3) % 6;
+
+    std::array::from_fn(|face| {
+        if neighborhood_only && face == opposite_face {
+            return SurfaceApproximation {
+                c: Vec3::ZERO,
+                c_du: Vec3::ZERO,
+                c_dv: Vec3::ZERO,
+                c_duu: Vec3::ZERO,
+                c_duv: Vec3::ZERO,
+                c_dvv: Vec3::ZERO,
+            };
+        }
+
+        side_approximation::<Cube, 6>(
+            face,
+            view_coordinates[face].uv,
+            linear,
+            relative_translation,
+            f64::INFINITY,
+        )
+        .0
+    })
+}
+
+/// Same as [`compute_approximations`], but only the sides whose view coordinate lies within the validity radius
+/// of the view get their derivative terms.
+/// The other sides fall back to the constant term alone, which is all that is needed to place their far tiles,
+/// since the error beyond the validity radius exceeds the budget anyway.
+/// Returns which of the sides were computed in full, only those have to be uploaded each time the view moves.
+pub fn compute_culled_approximations(
+    view_coordinates: &[Coordinate; 6],
+    view_position: DVec3,
+    model: &TerrainModel,
+    validity_radius: f64,
+) -> ([SurfaceApproximation; 6], [bool; 6]) {
+    compute_topology_approximations::<Cube, 6>(
+        view_coordinates,
+        view_position,
+        model,
+        validity_radius,
+    )
+}
+
+/// Computes the approximations of all faces of the topology, culled like [`compute_culled_approximations`].
+/// An infinite validity radius computes every side in full.
+pub fn compute_topology_approximations<T: FaceTopology<FACES>, const FACES: usize>(
+    view_coordinates: &[Coordinate; FACES],
+    view_position: DVec3,
+    model: &TerrainModel,
+    validity_radius: f64,
+) -> ([SurfaceApproximation; FACES], [bool; FACES]) {
+    let world_from_local = model.world_from_local;
+    let linear = DMat3::from_mat4(world_from_local);
+    let relative_translation = world_from_local.w_axis.truncate() - view_position;
+
+    let sides: [_; FACES] = std::array::from_fn(|face| {
+        side_approximation::<T, FACES>(
+            face,
+            view_coordinates[face].uv,
+            linear,
+            relative_translation,
+            validity_radius,
+        )
+    });
+
+    (sides.map(|(side, _)| side), sides.map(|(_, full)| full))
+}
+
+// The approximation of a single side, and whether it was computed in full.
+// The constant term doubles as the distance of the view coordinate to the view, which decides whether
+// the derivative terms are needed.
+fn side_approximation<T: FaceTopology<FACES>, const FACES: usize>(
+    face: usize,
+    view_uv: DVec2,
+    linear: DMat3,
+    relative_translation: DVec3,
+    validity_radius: f64,
+) -> (SurfaceApproximation, bool) {
+    if validity_radius.is_finite() {
+        let c = linear * T::position(face, view_uv) + relative_translation;
+
+        if c.length() > validity_radius {
+            let fallback = SurfaceApproximation {
+                c: c.as_vec3(),
+                c_du: Vec3::ZERO,
+                c_dv: Vec3::ZERO,
+                c_duu: Vec3::ZERO,
+                c_duv: Vec3::ZERO,
+                c_dvv: Vec3::ZERO,
+            };
+
+            return (fallback, false);
+        }
+    }
+
+    let point = T::surface_point(face, view_uv);
+
+    let approximation = SurfaceApproximation {
+        c: (linear * point.position + relative_translation).as_vec3(),
+        c_du: (linear * point.du).as_vec3(),
+        c_dv: (linear * point.dv).as_vec3(),
+        c_duu: (linear * (0.5 * point.duu)).as_vec3(),
+        c_duv: (linear * point.duv).as_vec3(),
+        c_dvv: (linear * (0.5 * point.dvv)).as_vec3(),
+    };
+
+    (approximation, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The offset of the point to the view in f64, which the relative uv approximates in f32.
+    fn exact_relative_uv(
+        view_coordinate: Coordinate,
+        (tile, tile_uv): (TileCoordinate, Vec2),
+    ) -> DVec2 {
+        let uv = (tile.xy().as_dvec2() + tile_uv.as_dvec2()) / tile_count(tile.lod) as f64;
+
+        uv - view_coordinate.uv
+    }
+
+    // The tile at the lod containing the uv, and the uv inside it.
+    fn tile_at(face: u32, lod: u32, uv: DVec2) -> (TileCoordinate, Vec2) {
+        let xy = (uv * tile_count(lod) as f64).floor();
+        let tile_uv = (uv * tile_count(lod) as f64 - xy).as_vec2();
+
+        (
+            TileCoordinate::new(face, lod, xy.x as u32, xy.y as u32),
+            tile_uv,
+        )
+    }
+
+    fn assert_relative_uv(
+        view_coordinate: Coordinate,
+        origin_lod: u32,
+        coordinate: (TileCoordinate, Vec2),
+    ) {
+        let relative_uv = relative_uv(view_coordinate, origin_lod, coordinate).as_dvec2();
+        let exact = exact_relative_uv(view_coordinate, coordinate);
+        // the f32 rounding of a few tiles, far below the size of any tile a wrong offset would shift by
+        let tolerance = 1e-5 * tile_size(coordinate.0.lod.min(origin_lod));
+
+        assert!(
+            relative_uv.distance(exact) < tolerance,
+            "relative uv {relative_uv} of {:?} at the origin lod {origin_lod} differs from {exact}",
+            coordinate.0.xy()
+        );
+    }
+
+    #[test]
+    fn relative_uv_of_finer_tiles() {
+        let view_coordinate = Coordinate::new(2, DVec2::new(0.3, 0.7));
+
+        // tile (11, 20) at lod 5 with the origin tile (2, 5) at lod 3, the integer offset is (11 - (2 << 2), 20 - (5 << 2)),
+        // while (11 - 2) << 2 would shift the tile itself and miss by more than the whole face
+        let coordinate = tile_at(2, 5, DVec2::new(0.35, 0.65));
+        assert_eq!(coordinate.0.xy(), UVec2::new(11, 20));
+        assert_relative_uv(view_coordinate, 3, coordinate);
+
+        for (origin_lod, lod_difference) in iproduct!([1, 3, 10, 16], 1..=6) {
+            let lod = origin_lod + lod_difference;
+
+            for offset in [-0.4, -0.1, 0.0, 0.25, 0.45] {
+                let uv = view_coordinate.uv + DVec2::new(offset, -offset) * tile_size(origin_lod);
+                assert_relative_uv(view_coordinate, origin_lod, tile_at(2, lod, uv));
+            }
+        }
+    }
+
+    #[test]
+    fn relative_uv_of_tiles_at_the_origin_lod() {
+        let view_coordinate = Coordinate::new(4, DVec2::new(0.423, 0.576));
+
+        for origin_lod in [0, 1, 5, 12, 20] {
+            for offset in [-0.3, -0.1, 0.0, 0.1, 0.3] {
+                let uv =
+                    view_coordinate.uv + DVec2::new(offset, 0.5 * offset) * tile_size(origin_lod);
+                assert_relative_uv(view_coordinate, origin_lod, tile_at(4, origin_lod, uv));
+            }
+        }
+    }
+
+    #[test]
+    fn relative_uv_of_coarser_tiles() {
+        let view_coordinate = Coordinate::new(1, DVec2::new(0.3, 0.7));
+
+        // the origin tile (9, 22) at lod 5 keeps its lowest three bits (1, 6) as the remainder for tiles at lod 2,
+        // a mask of 1 << 3 - 1, which is 1 << 2, would keep only two of them and miss by a tile of the origin lod
+        let coordinate = tile_at(1, 2, DVec2::new(0.35, 0.65));
+        assert_eq!(coordinate.0.xy(), UVec2::new(1, 2));
+        assert_relative_uv(view_coordinate, 5, coordinate);
+
+        for (origin_lod, lod_difference) in iproduct!([3, 8, 16, 24], 1..=3) {
+            let lod = origin_lod - lod_difference;
+
+            for offset in [-0.4, -0.1, 0.0, 0.25, 0.45] {
+                // half a tile at most, which stays on the face even at lod 0
+                let uv = view_coordinate.uv + DVec2::new(offset, -offset) * tile_size(lod + 1);
+                assert_relative_uv(view_coordinate, origin_lod, tile_at(1, lod, uv));
+            }
+        }
+    }
+
+    fn earth() -> TerrainModel {
+        TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0)
+    }
+
+    // The point on the surface of the model in the direction of the local position.
+    fn surface_position(direction: DVec3, model: &TerrainModel) -> DVec3 {
+        model
+            .world_from_local
+            .transform_point3(direction.normalize())
+    }
+
+    #[test]
+    fn coordinate_of_the_model_center() {
+        let model = earth();
+        let coordinate = coordinate_from_world_position(model.position(), &model);
+
+        assert_eq!(coordinate.face, 0);
+        assert_eq!(coordinate.uv, DVec2::splat(0.5));
+    }
+
+    #[test]
+    fn coordinate_of_non_finite_positions() {
+        let model = earth();
+
+        for position in [
+            DVec3::NAN,
+            DVec3::new(f64::INFINITY, 0.0, 0.0),
+            DVec3::new(0.0, f64::NEG_INFINITY, 1.0),
+            DVec3::new(1.0, 2.0, f64::NAN),
+        ] {
+            let coordinate = coordinate_from_world_position(position, &model);
+
+            assert_eq!(coordinate.face, 0);
+            assert_eq!(coordinate.uv, DVec2::splat(0.5));
+        }
+    }
+
+    #[test]
+    fn coordinates_on_cube_edges() {
+        let model = earth();
+
+        for (x, y, z) in iproduct!([-1.0, 0.0, 1.0], [-1.0, 0.0, 1.0], [-1.0, 0.0, 1.0]) {
+            let direction = DVec3::new(x, y, z);
+            // two non-zero components lie on an edge, three on a corner
+            let dominant = direction
+                .abs()
+                .to_array()
+                .iter()
+                .filter(|&&c| c == 1.0)
+                .count();
+            if dominant < 2 {
+                continue;
+            }
+
+            let position = surface_position(direction, &model);
+            let coordinate = coordinate_from_world_position(position, &model);
+
+            assert!(coordinate.face < 6);
+            assert!(
+                coordinate.uv.cmpge(DVec2::ZERO).all() && coordinate.uv.cmple(DVec2::ONE).all(),
+                "uv {} of {direction} is not on the face",
+                coordinate.uv
+            );
+
+            // on the edge of the face, one uv component is at its bounds, on a corner both are
+            let on_bounds = coordinate
+                .uv
+                .to_array()
+                .iter()
+                .filter(|&&c| c.min(1.0 - c) < 1e-9)
+                .count();
+            assert_eq!(
+                on_bounds,
+                dominant - 1,
+                "uv {} of {direction}",
+                coordinate.uv
+            );
+
+            let round_trip =
+                Coordinate::new(coordinate.face, coordinate.uv).world_position(&model, 0.0);
+            assert!(
+                round_trip.distance(position) < 1e-5,
+                "{direction} maps back to a position {} away",
+                round_trip.distance(position)
+            );
+        }
+    }
+
+    #[test]
+    fn tiles_in_region_across_face_edges_and_corners() {
+        const RADIUS: f64 = 6371000.0;
+        let model = TerrainModel::sphere(DVec3::ZERO, RADIUS, 0.0, 0.0);
+        let samples = 16;
+
+        for (center, radius, lod) in [
+            // next to the edge between two faces
+            (Coordinate::new(0, DVec2::new(0.999, 0.4)), 600000.0, 4),
+            // next to a cube corner, where three faces meet
+            (Coordinate::new(2, DVec2::new(0.002, 0.998)), 600000.0, 4),
+            (Coordinate::new(5, DVec2::new(0.5, 0.0)), 3000000.0, 3),
+        ] {
+            let angular_radius = radius / RADIUS;
+            let center_direction = cube_to_sphere(center.face, center.uv);
+
+            let tiles = tiles_in_region(center, radius, lod, &model)
+                .into_iter()
+                .map(|tile| (tile.face, tile.x, tile.y))
+                .collect::<std::collections::HashSet<_>>();
+
+            // the brute force check samples each tile on a grid, including its edges
+            for (face, x, y) in
+                iproduct!(0..6, 0..tile_count(lod) as u32, 0..tile_count(lod) as u32)
+            {
+                let tile_min = DVec2::new(x as f64, y as f64) * tile_size(lod);
+                let closest = iproduct!(0..=samples, 0..=samples)
+                    .map(|(i, j)| {
+                        let uv = tile_min
+                            + DVec2::new(i as f64, j as f64) / samples as f64 * tile_size(lod);
+                        cube_to_sphere(face, uv).angle_between(center_direction)
+                    })
+                    .fold(f64::INFINITY, f64::min);
+                let spacing = 2.0 * tile_size(lod) / samples as f64;
+
+                if closest <= angular_radius {
+                    assert!(
+                        tiles.contains(&(face, x, y)),
+                        "tile {x}, {y} of face {face} at lod {lod} is missing"
+                    );
+                } else if closest > angular_radius + spacing {
+                    assert!(
+                        !tiles.contains(&(face, x, y)),
+                        "tile {x}, {y} of face {face} at lod {lod} lies outside of the region"
+                    );
+                }
+            }
+        }
+    }
+}
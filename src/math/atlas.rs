@@ -0,0 +1,171 @@
+use bevy::math::{DVec2, UVec2, Vec2};
+use bevy_terrain::math::{Coordinate, TileCoordinate};
+
+use crate::math::{cube_to_sphere, sphere_to_cube, tile_count};
+
+/// Number of faces along each axis of the atlas, faces are stored row by row.
+pub const ATLAS_FACES: UVec2 = UVec2::new(3, 2);
+
+/// Layout of the six faces in one texture, three faces wide and two high.
+/// Each face is surrounded by a border of texels continuing the neighboring faces,
+/// so that filtering across a face edge blends with the right neighbor instead of the unrelated face next to it in the atlas.
+/// The uv inside a face is the same as the uv of a [`Coordinate`], so tiles are located with the same st math as everywhere else.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FaceAtlas {
+    /// Size of the atlas in texels.
+    pub size: UVec2,
+    /// Width of the border around each face in texels.
+    pub border: u32,
+}
+
+impl FaceAtlas {
+    pub fn new(size: UVec2, border: u32) -> Self {
+        Self { size, border }
+    }
+
+    /// Atlas for faces of the given resolution, with the border added around each of them.
+    pub fn from_face_resolution(resolution: u32, border: u32) -> Self {
+        Self::new(ATLAS_FACES * (resolution + 2 * border), border)
+    }
+
+    /// Size of the cell of one face in texels, including its border.
+    pub fn cell_size(&self) -> UVec2 {
+        self.size / ATLAS_FACES
+    }
+
+    /// Size of the face inside its cell in texels, without the border.
+    pub fn face_size(&self) -> UVec2 {
+        self.cell_size()
+            .saturating_sub(UVec2::splat(2 * self.border))
+    }
+
+    /// Texel in the top left corner of the cell of the face.
+    pub fn cell_origin(&self, face: u32) -> UVec2 {
+        UVec2::new(face % ATLAS_FACES.x, face / ATLAS_FACES.x) * self.cell_size()
+    }
+
+    /// Atlas uv of a uv on the face, a uv outside of the unit square lands in the border.
+    pub fn face_uv_to_atlas(&self, face: u32, uv: DVec2) -> DVec2 {
+        let origin = (self.cell_origin(face) + self.border).as_dvec2();
+
+        (origin + uv * self.face_size().as_dvec2()) / self.size.as_dvec2()
+    }
+
+    /// Atlas uv of the coordinate.
+    pub fn atlas_uv(&self, coordinate: Coordinate) -> DVec2 {
+        self.face_uv_to_atlas(coordinate.face, coordinate.uv)
+    }
+
+    /// Atlas uv of a point inside a tile.
+    /// The face uv is computed in f64 from the integer tile offset, before the result is rounded to f32.
+    pub fn tile_uv(&self, (tile, tile_uv): (TileCoordinate, Vec2)) -> Vec2 {
+        let uv = (tile.xy().as_dvec2() + tile_uv.as_dvec2()) / tile_count(tile.lod) as f64;
+
+        self.face_uv_to_atlas(tile.face, uv).as_vec2()
+    }
+
+    /// Atlas uv of the top left and bottom right corner of the tile.
+    pub fn tile_rect(&self, tile: TileCoordinate) -> (Vec2, Vec2) {
+        (
+            self.tile_uv((tile, Vec2::ZERO)),
+            self.tile_uv((tile, Vec2::ONE)),
+        )
+    }
+
+    /// Coordinate at the center of the texel, which is what should be baked into it.
+    /// Texels in the border of a face are continued onto the neighboring face, through the sphere.
+    /// Texels outside of all cells, left over when the atlas size is not divisible into cells, have none.
+    pub fn texel_coordinate(&self, texel: UVec2) -> Option<Coordinate> {
+        let cell = texel / self.cell_size().max(UVec2::ONE);
+
+        if cell.x >= ATLAS_FACES.x || cell.y >= ATLAS_FACES.y || self.face_size().min_element() == 0
+        {
+            return None;
+        }
+
+        let face = cell.y * ATLAS_FACES.x + cell.x;
+        let offset = texel.as_dvec2() + 0.5 - (self.cell_origin(face) + self.border).as_dvec2();
+        let uv = offset / self.face_size().as_dvec2();
+
+        if uv.cmpge(DVec2::ZERO).all() && uv.cmple(DVec2::ONE).all() {
+            return Some(Coordinate::new(face, uv));
+        }
+
+        let (neighbor, neighbor_uv) = sphere_to_cube(cube_to_sphere(face, uv));
+
+        Some(Coordinate::new(neighbor, neighbor_uv))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::iproduct;
+
+    const RESOLUTION: u32 = 64;
+
+    fn atlas() -> FaceAtlas {
+        FaceAtlas::from_face_resolution(RESOLUTION, 2)
+    }
+
+    #[test]
+    fn tiles_round_trip_through_their_texels() {
+        let atlas = atlas();
+        let to_texel = |uv: Vec2| (uv.as_dvec2() * atlas.size.as_dvec2()).round().as_uvec2();
+
+        for (face, lod) in iproduct!(0..6, 0..=3) {
+            let last = (tile_count(lod) - 1) as u32;
+
+            // the tiles along the face edges, and one inside the face
+            for (x, y) in iproduct!([0, last / 2, last], [0, last]) {
+                let tile = TileCoordinate::new(face, lod, x, y);
+                let (start, end) = atlas.tile_rect(tile);
+                let (start, end) = (to_texel(start), to_texel(end));
+
+                assert_eq!(end - start, UVec2::splat(RESOLUTION >> lod));
+
+                for texel in [
+                    start,
+                    end - 1,
+                    UVec2::new(start.x, end.y - 1),
+                    UVec2::new(end.x - 1, start.y),
+                ] {
+                    let coordinate = atlas.texel_coordinate(texel).unwrap();
+                    let xy = (coordinate.uv * tile_count(lod) as f64).floor().as_uvec2();
+
+                    assert_eq!((coordinate.face, xy), (face, UVec2::new(x, y)));
+
+                    let center = atlas.atlas_uv(coordinate) * atlas.size.as_dvec2();
+                    assert!(center.distance(texel.as_dvec2() + 0.5) < 1e-9, "{center}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn border_texels_continue_onto_the_neighbors() {
+        let atlas = atlas();
+
+        for face in 0..6 {
+            let origin = atlas.cell_origin(face) + atlas.border;
+            let (middle, far) = (RESOLUTION / 2, RESOLUTION);
+
+            for texel in [
+                UVec2::new(origin.x - 1, origin.y + middle),
+                UVec2::new(origin.x + middle, origin.y - 1),
+                UVec2::new(origin.x + far, origin.y + middle),
+                UVec2::new(origin.x + middle, origin.y + far),
+            ] {
+                let uv = (texel.as_dvec2() + 0.5 - origin.as_dvec2()) / RESOLUTION as f64;
+                let coordinate = atlas.texel_coordinate(texel).unwrap();
+
+                assert_ne!(coordinate.face, face);
+                assert!(
+                    cube_to_sphere(coordinate.face, coordinate.uv)
+                        .distance(cube_to_sphere(face, uv))
+                        < 1e-12
+                );
+            }
+        }
+    }
+}
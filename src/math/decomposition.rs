@@ -0,0 +1,289 @@
+use bevy::math::{DMat3, DVec2, DVec3, UVec2, Vec2};
+use bevy_terrain::{
+    math::{Coordinate, SurfaceApproximation, TileCoordinate},
+    prelude::*,
+};
+
+use crate::math::{
+    evaluate_approximation, relative_uv, tile_count,
+    topology::{Cube, FaceTopology},
+};
+
+/// The error of an approximated position, split into the contributions of each step of the shader path, in meters.
+/// Every contribution is measured by changing only its step against an otherwise exact evaluation,
+/// so the contributions do not add up to the total exactly, but show which of them dominates.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ErrorContributions {
+    /// Truncation of the Taylor series, with exact coefficients and relative uv.
+    pub truncation: f64,
+    /// Rounding of the coefficients to f32.
+    pub coefficient_rounding: f64,
+    /// Rounding of the exact relative uv to the nearest f32.
+    pub relative_uv_rounding: f64,
+    /// Computing the relative uv from the integer offset to the origin tile, instead of rounding the exact one.
+    pub origin_offset: f64,
+    /// Error of the complete f32 path against the exact position.
+    pub total: f64,
+}
+
+impl ErrorContributions {
+    /// Largest value of each contribution.
+    pub fn max(self, other: Self) -> Self {
+        Self {
+            truncation: self.truncation.max(other.truncation),
+            coefficient_rounding: self.coefficient_rounding.max(other.coefficient_rounding),
+            relative_uv_rounding: self.relative_uv_rounding.max(other.relative_uv_rounding),
+            origin_offset: self.origin_offset.max(other.origin_offset),
+            total: self.total.max(other.total),
+        }
+    }
+
+    /// Sum of each contribution, for averaging them.
+    pub fn sum(self, other: Self) -> Self {
+        Self {
+            truncation: self.truncation + other.truncation,
+            coefficient_rounding: self.coefficient_rounding + other.coefficient_rounding,
+            relative_uv_rounding: self.relative_uv_rounding + other.relative_uv_rounding,
+            origin_offset: self.origin_offset + other.origin_offset,
+            total: self.total + other.total,
+        }
+    }
+
+    /// Each contribution divided by the count.
+    pub fn average(self, count: usize) -> Self {
+        let count = count.max(1) as f64;
+
+        Self {
+            truncation: self.truncation / count,
+            coefficient_rounding: self.coefficient_rounding / count,
+            relative_uv_rounding: self.relative_uv_rounding / count,
+            origin_offset: self.origin_offset / count,
+            total: self.total / count,
+        }
+    }
+}
+
+// Taylor coefficients of one side in f64, in the layout of `SurfaceApproximation`.
+#[derive(Clone, Copy)]
+struct Coefficients {
+    c: DVec3,
+    c_du: DVec3,
+    c_dv: DVec3,
+    c_duu: DVec3,
+    c_duv: DVec3,
+    c_dvv: DVec3,
+}
+
+impl Coefficients {
+    fn rounded(&self) -> Self {
+        let round = |x: DVec3| x.as_vec3().as_dvec3();
+
+        Self {
+            c: round(self.c),
+            c_du: round(self.c_du),
+            c_dv: round(self.c_dv),
+            c_duu: round(self.c_duu),
+            c_duv: round(self.c_duv),
+            c_dvv: round(self.c_dvv),
+        }
+    }
+
+    fn to_f32(self) -> SurfaceApproximation {
+        SurfaceApproximation {
+            c: self.c.as_vec3(),
+            c_du: self.c_du.as_vec3(),
+            c_dv: self.c_dv.as_vec3(),
+            c_duu: self.c_duu.as_vec3(),
+            c_duv: self.c_duv.as_vec3(),
+            c_dvv: self.c_dvv.as_vec3(),
+        }
+    }
+
+    fn evaluate(&self, second_order: bool, DVec2 { x: u, y: v }: DVec2) -> DVec3 {
+        let first_order = self.c + self.c_du * u + self.c_dv * v;
+
+        if second_order {
+            first_order + self.c_duu * u * u + self.c_duv * u * v + self.c_dvv * v * v
+        } else {
+            first_order
+        }
+    }
+}
+
+/// Decomposes the error of approximated positions around one view into its contributions.
+/// The coefficients of all six sides are computed once in f64, and reused for every sample.
+pub struct ErrorDecomposition {
+    view_coordinates: [Coordinate; 6],
+    view_position: DVec3,
+    coefficients: [Coefficients; 6],
+}
+
+impl ErrorDecomposition {
+    pub fn new(
+        view_coordinates: &[Coordinate; 6],
+        view_position: DVec3,
+        model: &TerrainModel,
+    ) -> Self {
+        let linear = DMat3::from_mat4(model.world_from_local);
+        let relative_translation = model.world_from_local.w_axis.truncate() - view_position;
+
+        let coefficients = std::array::from_fn(|face| {
+            let point = Cube::surface_point(face, view_coordinates[face].uv);
+
+            Coefficients {
+                c: linear * point.position + relative_translation,
+                c_du: linear * point.du,
+                c_dv: linear * point.dv,
+                c_duu: linear * (0.5 * point.duu),
+                c_duv: linear * point.duv,
+                c_dvv: linear * (0.5 * point.dvv),
+            }
+        });
+
+        Self {
+            view_coordinates: *view_coordinates,
+            view_position,
+            coefficients,
+        }
+    }
+
+    /// Contributions to the error of the sample inside the tile, with the relative uv computed at the origin lod.
+    pub fn decompose(
+        &self,
+        origin_lod: u32,
+        second_order: bool,
+        (tile, tile_uv): (TileCoordinate, Vec2),
+        model: &TerrainModel,
+    ) -> ErrorContributions {
+        let face = tile.face as usize;
+        let view_coordinate = self.view_coordinates[face];
+        let coefficients = &self.coefficients[face];
+
+        let uv = (UVec2::new(tile.x, tile.y).as_dvec2() + tile_uv.as_dvec2())
+            / tile_count(tile.lod) as f64;
+        let exact = Coordinate::new(tile.face, uv).world_position(model, 0.0) - self.view_position;
+
+        let exact_uv = uv - view_coordinate.uv;
+        let rounded_uv = exact_uv.as_vec2().as_dvec2();
+        let shader_uv = relative_uv(view_coordinate, origin_lod, (tile, tile_uv));
+
+        let reference = coefficients.evaluate(second_order, exact_uv);
+        let approximation = coefficients.to_f32();
+
+        ErrorContributions {
+            truncation: reference.distance(exact),
+            coefficient_rounding: coefficients
+                .rounded()
+                .evaluate(second_order, exact_uv)
+                .distance(reference),
+            relative_uv_rounding: coefficients
+                .evaluate(second_order, rounded_uv)
+                .distance(reference),
+            origin_offset: coefficients
+                .evaluate(second_order, shader_uv.as_dvec2())
+                .distance(coefficients.evaluate(second_order, rounded_uv)),
+            total: evaluate_approximation(&approximation, second_order, shader_uv)
+                .as_dvec3()
+                .distance(exact),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{
+        approximate_world_position, compute_approximations, tile_coordinate_from_world_position,
+        view_coordinates,
+    };
+    use itertools::iproduct;
+
+    const ORIGIN_LOD: u32 = 12;
+
+    fn earth() -> TerrainModel {
+        TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0)
+    }
+
+    // Samples within a few kilometers around a view one kilometer above face 0, in tiles below and above the origin lod.
+    fn samples(model: &TerrainModel) -> (DVec3, Vec<(TileCoordinate, Vec2)>) {
+        let view_uv = DVec2::new(0.4321, 0.5678);
+        let view_position = Coordinate::new(0, view_uv).world_position(model, 1000.0);
+
+        let samples = iproduct!(-3..=3, -3..=3, [ORIGIN_LOD - 2, ORIGIN_LOD, ORIGIN_LOD + 3])
+            .map(|(du, dv, lod)| {
+                let uv = view_uv + DVec2::new(du as f64, dv as f64) * 1.7e-4;
+                let position = Coordinate::new(0, uv).world_position(model, 0.0);
+
+                tile_coordinate_from_world_position(position, lod, model)
+            })
+            .collect();
+
+        (view_position, samples)
+    }
+
+    #[test]
+    fn total_is_the_error_of_the_shader_path() {
+        let model = earth();
+        let (view_position, samples) = samples(&model);
+        let view_coordinates = view_coordinates(view_position, &model);
+        let decomposition = ErrorDecomposition::new(&view_coordinates, view_position, &model);
+        let approximations =
+            compute_approximations(&view_coordinates, view_position, &model, false);
+
+        for coordinate @ (tile, tile_uv) in samples {
+            let uv = (tile.xy().as_dvec2() + tile_uv.as_dvec2()) / tile_count(tile.lod) as f64;
+            let exact = Coordinate::new(tile.face, uv).world_position(&model, 0.0);
+            let error = approximate_world_position(
+                &view_coordinates,
+                &approximations,
+                ORIGIN_LOD,
+                true,
+                view_position,
+                coordinate,
+            )
+            .distance(exact);
+
+            let total = decomposition
+                .decompose(ORIGIN_LOD, true, coordinate, &model)
+                .total;
+
+            // only the view position is added in f64 on top of the shader path, at the scale of the planet
+            assert!((total - error).abs() < 1e-8, "{total} m, {error} m");
+        }
+    }
+
+    #[test]
+    fn contributions_account_for_the_total() {
+        let model = earth();
+        let (view_position, samples) = samples(&model);
+        let view_coordinates = view_coordinates(view_position, &model);
+        let decomposition = ErrorDecomposition::new(&view_coordinates, view_position, &model);
+
+        for (coordinate, second_order) in iproduct!(samples, [false, true]) {
+            let contributions =
+                decomposition.decompose(ORIGIN_LOD, second_order, coordinate, &model);
+            let parts = contributions.truncation
+                + contributions.coefficient_rounding
+                + contributions.relative_uv_rounding
+                + contributions.origin_offset;
+
+            // the contributions leave out the rounding of the f32 evaluation itself,
+            // which is at most a few units in the last place of the largest term
+            let face = coordinate.0.face as usize;
+            let coefficients = &decomposition.coefficients[face];
+            let relative_uv =
+                relative_uv(view_coordinates[face], ORIGIN_LOD, coordinate).as_dvec2();
+            let largest_term = coefficients
+                .c
+                .length()
+                .max((coefficients.c_du * relative_uv.x).length())
+                .max((coefficients.c_dv * relative_uv.y).length());
+            let evaluation = 8.0 * f32::EPSILON as f64 * largest_term;
+
+            assert!(
+                contributions.total <= parts + evaluation,
+                "{contributions:?} exceed the sum of the parts by more than {evaluation} m"
+            );
+        }
+    }
+}
@@ -0,0 +1,512 @@
+use bevy::math::{DVec2, DVec3, Vec2, Vec3};
+use bevy_terrain::{
+    math::{Coordinate, SurfaceApproximation, TileCoordinate},
+    prelude::*,
+};
+use serde_json::{json, Value};
+use std::{fmt, fs, path::Path};
+
+use crate::math::{approximations, tile_count, view_coordinates, ViewCoordinates};
+
+/// Version of the fixture documents, files with a newer version are rejected.
+pub const FIXTURE_VERSION: u64 = 1;
+
+/// Growth of the error of a sample over its recorded error, in meters, up to which a replay counts as reproduced.
+/// It covers the f32 rounding of the evaluation, which may differ between the machines that record and replay.
+pub const REPLAY_TOLERANCE: f64 = 0.001;
+
+#[derive(Debug)]
+pub enum FixtureError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Version(u64),
+    Invalid(&'static str),
+    /// The fixture was recorded with a different model, the hash it was recorded with is attached.
+    Model(u64),
+}
+
+impl fmt::Display for FixtureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FixtureError::Io(error) => write!(f, "failed to access fixture file: {error}"),
+            FixtureError::Json(error) => write!(f, "failed to parse fixture: {error}"),
+            FixtureError::Version(version) => write!(
+                f,
+                "fixture version {version} is newer than the supported version {FIXTURE_VERSION}"
+            ),
+            FixtureError::Invalid(message) => write!(f, "invalid fixture: {message}"),
+            FixtureError::Model(hash) => write!(
+                f,
+                "fixture was recorded with the model {hash:016x}, which does not match the given one"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FixtureError {}
+
+/// Hash of the transform of the model, which identifies the model a fixture was recorded with.
+/// The bits of the f64 values are hashed with FNV-1a, so the hash is the same on every platform and run.
+pub fn model_hash(model: &TerrainModel) -> u64 {
+    model
+        .world_from_local
+        .to_cols_array()
+        .into_iter()
+        // negative zeros hash like positive ones, they describe the same transform
+        .flat_map(|value| (value + 0.0).to_bits().to_le_bytes())
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+/// A sample on the surface and the error of its approximated position, when it was recorded.
+#[derive(Clone, Copy, Debug)]
+pub struct FixtureSample {
+    pub tile: TileCoordinate,
+    pub tile_uv: Vec2,
+    pub error: f64,
+}
+
+/// The approximations computed for a view, together with the samples whose error exceeded the threshold and their errors.
+/// Failing cases of the error study are saved as fixtures, and replayed against the current math. A replay has to
+/// reproduce the approximations, and the errors of the samples must not grow beyond the recorded ones,
+/// while a fix that brings them below the threshold shows up as smaller errors.
+/// The coefficients are stored as the f32 values the shaders receive, which round trip through JSON exactly.
+#[derive(Clone)]
+pub struct ApproximationFixture {
+    pub model_hash: u64,
+    pub view_position: DVec3,
+    pub origin_lod: u32,
+    pub view_coordinates: [Coordinate; 6],
+    pub approximations: [SurfaceApproximation; 6],
+    pub threshold: f64,
+    pub samples: Vec<FixtureSample>,
+}
+
+/// Outcome of replaying a fixture against the current math.
+#[derive(Clone, Debug, Default)]
+pub struct FixtureReplay {
+    /// Largest difference of the recomputed view coordinates to the recorded ones, in uv.
+    /// Only the faces of the samples are compared, the others are never evaluated by the fixture.
+    pub view_deviation: f64,
+    /// Largest difference of the recomputed coefficients to the recorded ones, relative to the length of the recorded ones.
+    /// Only the faces of the samples are compared.
+    pub coefficient_deviation: f32,
+    /// Error of each sample, evaluated with the recomputed approximations.
+    pub errors: Vec<f64>,
+}
+
+impl FixtureReplay {
+    pub fn max_error(&self) -> f64 {
+        self.errors.iter().copied().fold(0.0, f64::max)
+    }
+
+    /// Largest growth of the error of a sample over the recorded one, zero if none of them got worse.
+    pub fn regression(&self, fixture: &ApproximationFixture) -> f64 {
+        fixture
+            .samples
+            .iter()
+            .zip(&self.errors)
+            .map(|(sample, error)| error - sample.error)
+            .fold(0.0, f64::max)
+    }
+}
+
+fn coefficients(approximation: &SurfaceApproximation) -> [Vec3; 6] {
+    [
+        approximation.c,
+        approximation.c_du,
+        approximation.c_dv,
+        approximation.c_duu,
+        approximation.c_duv,
+        approximation.c_dvv,
+    ]
+}
+
+fn parse_f64s<const N: usize>(value: &Value) -> Option<[f64; N]> {
+    let values = value
+        .as_array()?
+        .iter()
+        .map(Value::as_f64)
+        .collect::<Option<Vec<_>>>()?;
+
+    values.try_into().ok()
+}
+
+// Error of the approximated position of each sample, against the exact position on the surface.
+fn sample_errors(
+    view_coordinates: &[Coordinate; 6],
+    approximations: &[SurfaceApproximation; 6],
+    view_position: DVec3,
+    origin_lod: u32,
+    samples: &[FixtureSample],
+    model: &TerrainModel,
+) -> Vec<f64> {
+    let view_coordinates = ViewCoordinates::new(*view_coordinates, origin_lod);
+
+    samples
+        .iter()
+        .map(|sample| {
+            let tile = sample.tile;
+            let uv = (DVec2::new(tile.x as f64, tile.y as f64) + sample.tile_uv.as_dvec2())
+                / tile_count(tile.lod) as f64;
+            let exact = Coordinate::new(tile.face, uv).world_position(model, 0.0);
+
+            view_coordinates
+                .approximate_world_position(
+                    approximations,
+                    true,
+                    view_position,
+                    (tile, sample.tile_uv),
+                )
+                .distance(exact)
+        })
+        .collect()
+}
+
+impl ApproximationFixture {
+    /// Computes the approximations for the view, and keeps the samples whose error exceeds the threshold.
+    pub fn record(
+        view_position: DVec3,
+        origin_lod: u32,
+        samples: &[(TileCoordinate, Vec2)],
+        threshold: f64,
+        model: &TerrainModel,
+    ) -> Self {
+        let view_coordinates = view_coordinates(view_position, model);
+        let approximations = approximations(&view_coordinates, view_position, model);
+
+        let samples = samples
+            .iter()
+            .map(|&(tile, tile_uv)| FixtureSample {
+                tile,
+                tile_uv,
+                error: 0.0,
+            })
+            .collect::<Vec<_>>();
+        let errors = sample_errors(
+            &view_coordinates,
+            &approximations,
+            view_position,
+            origin_lod,
+            &samples,
+            model,
+        );
+
+        Self {
+            model_hash: model_hash(model),
+            view_position,
+            origin_lod,
+            view_coordinates,
+            approximations,
+            threshold,
+            samples: samples
+                .into_iter()
+                .zip(errors)
+                .filter(|&(_, error)| error > threshold)
+                .map(|(sample, error)| FixtureSample { error, ..sample })
+                .collect(),
+        }
+    }
+
+    /// Recomputes the approximations from the view position, and evaluates the samples with them.
+    pub fn replay(&self, model: &TerrainModel) -> Result<FixtureReplay, FixtureError> {
+        if model_hash(model) != self.model_hash {
+            return Err(FixtureError::Model(self.model_hash));
+        }
+
+        let view_coordinates = view_coordinates(self.view_position, model);
+        let approximations = approximations(&view_coordinates, self.view_position, model);
+
+        let sampled = |face: usize| {
+            self.samples
+                .iter()
+                .any(|sample| sample.tile.face as usize == face)
+        };
+
+        let view_deviation = view_coordinates
+            .iter()
+            .zip(&self.view_coordinates)
+            .enumerate()
+            .filter(|&(face, _)| sampled(face))
+            .map(|(_, (recomputed, recorded))| {
+                if recomputed.face == recorded.face {
+                    (recomputed.uv - recorded.uv).abs().max_element()
+                } else {
+                    f64::INFINITY
+                }
+            })
+            .fold(0.0, f64::max);
+        let coefficient_deviation = approximations
+            .iter()
+            .zip(&self.approximations)
+            .enumerate()
+            .filter(|&(face, _)| sampled(face))
+            .flat_map(|(_, (recomputed, recorded))| {
+                coefficients(recomputed)
+                    .into_iter()
+                    .zip(coefficients(recorded))
+                    .map(|(recomputed, recorded)| {
+                        (recomputed - recorded).abs().max_element()
+                            / recorded.length().max(f32::MIN_POSITIVE)
+                    })
+            })
+            .fold(0.0, f32::max);
+
+        Ok(FixtureReplay {
+            view_deviation,
+            coefficient_deviation,
+            errors: sample_errors(
+                &view_coordinates,
+                &approximations,
+                self.view_position,
+                self.origin_lod,
+                &self.samples,
+                model,
+            ),
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        let faces = (0..6)
+            .map(|face| {
+                let coordinate = self.view_coordinates[face];
+                let coefficients = coefficients(&self.approximations[face])
+                    .map(|coefficient| coefficient.as_dvec3().to_array());
+
+                json!({
+                    "face": coordinate.face,
+                    "uv": coordinate.uv.to_array(),
+                    "coefficients": coefficients,
+                })
+            })
+            .collect::<Vec<_>>();
+        let samples = self
+            .samples
+            .iter()
+            .map(|sample| {
+                json!({
+                    "tile": [sample.tile.face, sample.tile.lod, sample.tile.x, sample.tile.y],
+                    "tile_uv": sample.tile_uv.as_dvec2().to_array(),
+                    "error": sample.error,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        json!({
+            "version": FIXTURE_VERSION,
+            // as a string, since JSON numbers do not hold all 64 bits
+            "model_hash": format!("{:016x}", self.model_hash),
+            "view_position": self.view_position.to_array(),
+            "origin_lod": self.origin_lod,
+            "threshold": self.threshold,
+            "faces": faces,
+            "samples": samples,
+        })
+    }
+
+    pub fn parse(source: &str) -> Result<Self, FixtureError> {
+        let document: Value = serde_json::from_str(source).map_err(FixtureError::Json)?;
+
+        let version = document["version"]
+            .as_u64()
+            .ok_or(FixtureError::Invalid("missing version"))?;
+        if version > FIXTURE_VERSION {
+            return Err(FixtureError::Version(version));
+        }
+
+        let model_hash = document["model_hash"]
+            .as_str()
+            .and_then(|hash| u64::from_str_radix(hash, 16).ok())
+            .ok_or(FixtureError::Invalid(
+                "model hash is not a hexadecimal number",
+            ))?;
+        let view_position = parse_f64s(&document["view_position"])
+            .map(DVec3::from_array)
+            .ok_or(FixtureError::Invalid("view position is not three numbers"))?;
+        let origin_lod = document["origin_lod"]
+            .as_u64()
+            .ok_or(FixtureError::Invalid("missing origin lod"))? as u32;
+        let threshold = document["threshold"]
+            .as_f64()
+            .ok_or(FixtureError::Invalid("missing threshold"))?;
+
+        let faces = document["faces"]
+            .as_array()
+            .filter(|faces| faces.len() == 6)
+            .ok_or(FixtureError::Invalid("there are not six faces"))?
+            .iter()
+            .map(|face| {
+                let coordinate = face["face"]
+                    .as_u64()
+                    .zip(parse_f64s(&face["uv"]))
+                    .map(|(face, uv)| Coordinate::new(face as u32, DVec2::from_array(uv)))
+                    .ok_or(FixtureError::Invalid(
+                        "view coordinates are not a face and uv",
+                    ))?;
+                let [c, c_du, c_dv, c_duu, c_duv, c_dvv] = face["coefficients"]
+                    .as_array()
+                    .and_then(|coefficients| {
+                        coefficients
+                            .iter()
+                            .map(|coefficient| {
+                                parse_f64s(coefficient)
+                                    .map(|coefficient| DVec3::from_array(coefficient).as_vec3())
+                            })
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .and_then(|coefficients| <[Vec3; 6]>::try_from(coefficients).ok())
+                    .ok_or(FixtureError::Invalid(
+                        "coefficients are not six vectors of three numbers",
+                    ))?;
+
+                Ok((
+                    coordinate,
+                    SurfaceApproximation {
+                        c,
+                        c_du,
+                        c_dv,
+                        c_duu,
+                        c_duv,
+                        c_dvv,
+                    },
+                ))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let samples = document["samples"]
+            .as_array()
+            .ok_or(FixtureError::Invalid("missing samples"))?
+            .iter()
+            .map(|sample| {
+                let tile = sample["tile"].as_array().and_then(|tile| {
+                    let tile = tile
+                        .iter()
+                        .map(|value| value.as_u64().map(|value| value as u32))
+                        .collect::<Option<Vec<_>>>()?;
+
+                    match tile[..] {
+                        [face, lod, x, y] => Some(TileCoordinate::new(face, lod, x, y)),
+                        _ => None,
+                    }
+                });
+                let tile_uv =
+                    parse_f64s(&sample["tile_uv"]).map(|[u, v]| Vec2::new(u as f32, v as f32));
+                let error = sample["error"].as_f64();
+
+                match (tile, tile_uv, error) {
+                    (Some(tile), Some(tile_uv), Some(error)) => Ok(FixtureSample {
+                        tile,
+                        tile_uv,
+                        error,
+                    }),
+                    _ => Err(FixtureError::Invalid(
+                        "samples are not a tile, a tile uv and an error",
+                    )),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            model_hash,
+            view_position,
+            origin_lod,
+            view_coordinates: std::array::from_fn(|face| faces[face].0),
+            approximations: std::array::from_fn(|face| faces[face].1),
+            threshold,
+            samples,
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FixtureError> {
+        let source = fs::read_to_string(path).map_err(FixtureError::Io)?;
+        Self::parse(&source)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), FixtureError> {
+        let contents = serde_json::to_string_pretty(&self.to_json()).map_err(FixtureError::Json)?;
+        fs::write(path, contents).map_err(FixtureError::Io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn earth() -> TerrainModel {
+        TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0)
+    }
+
+    #[test]
+    fn committed_fixture_replays() {
+        let fixture =
+            ApproximationFixture::parse(include_str!("../../fixtures/earth_face3_1km.json"))
+                .unwrap();
+        let replay = fixture.replay(&earth()).unwrap();
+
+        assert!(!fixture.samples.is_empty());
+        assert_eq!(replay.errors.len(), fixture.samples.len());
+        assert!(replay.view_deviation < 1e-9, "{}", replay.view_deviation);
+        assert!(
+            replay.coefficient_deviation < 1e-5,
+            "{}",
+            replay.coefficient_deviation
+        );
+        assert!(
+            replay.regression(&fixture) <= REPLAY_TOLERANCE,
+            "{}",
+            replay.regression(&fixture)
+        );
+    }
+
+    #[test]
+    fn fixture_round_trip() {
+        let model = earth();
+        let view_position =
+            Coordinate::new(3, DVec2::new(0.37, 0.61)).world_position(&model, 1000.0);
+        let samples = [
+            (TileCoordinate::new(3, 10, 380, 624), Vec2::new(0.93, 0.64)),
+            (TileCoordinate::new(3, 8, 94, 155), Vec2::new(0.72, 0.62)),
+            (
+                TileCoordinate::new(3, 12, 1507, 2494),
+                Vec2::new(0.74, 0.87),
+            ),
+        ];
+
+        let fixture = ApproximationFixture::record(view_position, 10, &samples, 0.0, &model);
+        assert_eq!(fixture.samples.len(), samples.len());
+
+        let source = serde_json::to_string_pretty(&fixture.to_json()).unwrap();
+        let parsed = ApproximationFixture::parse(&source).unwrap();
+
+        assert_eq!(parsed.model_hash, fixture.model_hash);
+        assert_eq!(parsed.view_position, fixture.view_position);
+        assert_eq!(parsed.origin_lod, fixture.origin_lod);
+        assert_eq!(parsed.threshold, fixture.threshold);
+
+        for face in 0..6 {
+            assert_eq!(
+                parsed.view_coordinates[face].face,
+                fixture.view_coordinates[face].face
+            );
+            assert_eq!(
+                parsed.view_coordinates[face].uv,
+                fixture.view_coordinates[face].uv
+            );
+            assert_eq!(
+                coefficients(&parsed.approximations[face]),
+                coefficients(&fixture.approximations[face])
+            );
+        }
+
+        assert_eq!(parsed.samples.len(), fixture.samples.len());
+        for (parsed, recorded) in parsed.samples.iter().zip(&fixture.samples) {
+            assert_eq!(
+                (parsed.tile.face, parsed.tile.lod, parsed.tile.xy()),
+                (recorded.tile.face, recorded.tile.lod, recorded.tile.xy())
+            );
+            assert_eq!(parsed.tile_uv, recorded.tile_uv);
+            assert_eq!(parsed.error, recorded.error);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use bevy::math::{DVec3, Vec2, Vec3};
+use bevy_terrain::math::{Coordinate, SurfaceApproximation, TileCoordinate};
+use std::ops::{Add, Mul};
+
+use crate::math::relative_uv;
+
+// Evaluation of eight approximations at once, with the lanes stored as structures of arrays.
+// The lane-wise loops are simple enough for the compiler to turn into 256 bit vector instructions,
+// without depending on nightly `std::simd` or an external crate.
+// Only the evaluation of the Taylor series is vectorized. The relative uv of each lane is still computed by the scalar
+// `relative_uv`, since it needs the integer tile offsets, and the lanes are gathered from its results.
+// Each lane performs exactly the operations of the scalar path in the same order, so the results are bit-identical,
+// which `validate::simd_consistency` checks across faces and lods.
+
+pub const LANES: usize = 8;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C, align(32))]
+pub struct F32x8(pub [f32; LANES]);
+
+impl F32x8 {
+    pub fn splat(value: f32) -> Self {
+        Self([value; LANES])
+    }
+
+    #[inline(always)]
+    fn zip(self, other: Self, f: impl Fn(f32, f32) -> f32) -> Self {
+        Self(std::array::from_fn(|lane| f(self.0[lane], other.0[lane])))
+    }
+}
+
+impl Add for F32x8 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, other: Self) -> Self {
+        self.zip(other, |a, b| a + b)
+    }
+}
+
+impl Mul for F32x8 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, other: Self) -> Self {
+        self.zip(other, |a, b| a * b)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vec3x8 {
+    pub x: F32x8,
+    pub y: F32x8,
+    pub z: F32x8,
+}
+
+impl Vec3x8 {
+    pub fn gather(values: [Vec3; LANES]) -> Self {
+        Self {
+            x: F32x8(values.map(|value| value.x)),
+            y: F32x8(values.map(|value| value.y)),
+            z: F32x8(values.map(|value| value.z)),
+        }
+    }
+
+    pub fn lane(&self, lane: usize) -> Vec3 {
+        Vec3::new(self.x.0[lane], self.y.0[lane], self.z.0[lane])
+    }
+}
+
+impl Add for Vec3x8 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, other: Self) -> Self {
+        Self {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
+impl Mul<F32x8> for Vec3x8 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, scale: F32x8) -> Self {
+        Self {
+            x: self.x * scale,
+            y: self.y * scale,
+            z: self.z * scale,
+        }
+    }
+}
+
+// The coefficients of the approximation each lane is evaluated with.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SurfaceApproximationX8 {
+    pub c: Vec3x8,
+    pub c_du: Vec3x8,
+    pub c_dv: Vec3x8,
+    pub c_duu: Vec3x8,
+    pub c_duv: Vec3x8,
+    pub c_dvv: Vec3x8,
+}
+
+impl SurfaceApproximationX8 {
+    pub fn gather(approximations: [&SurfaceApproximation; LANES]) -> Self {
+        Self {
+            c: Vec3x8::gather(approximations.map(|approximation| approximation.c)),
+            c_du: Vec3x8::gather(approximations.map(|approximation| approximation.c_du)),
+            c_dv: Vec3x8::gather(approximations.map(|approximation| approximation.c_dv)),
+            c_duu: Vec3x8::gather(approximations.map(|approximation| approximation.c_duu)),
+            c_duv: Vec3x8::gather(approximations.map(|approximation| approximation.c_duv)),
+            c_dvv: Vec3x8::gather(approximations.map(|approximation| approximation.c_dvv)),
+        }
+    }
+}
+
+/// Lane-wise [`evaluate_approximation`](crate::math::evaluate_approximation).
+pub fn evaluate_approximation_x8(
+    approximation: &SurfaceApproximationX8,
+    second_order: bool,
+    u: F32x8,
+    v: F32x8,
+) -> Vec3x8 {
+    let &SurfaceApproximationX8 {
+        c,
+        c_du,
+        c_dv,
+        c_duu,
+        c_duv,
+        c_dvv,
+    } = approximation;
+
+    if second_order {
+        c + c_du * u + c_dv * v + c_duu * u * u + c_duv * u * v + c_dvv * v * v
+    } else {
+        c + c_du * u + c_dv * v
+    }
+}
+
+/// Lane-wise [`approximate_world_position`](crate::math::approximate_world_position).
+/// The integer part of the relative uv is computed per lane, only the Taylor series is evaluated in the vector lanes.
+pub fn approximate_world_positions_x8(
+    view_coordinates: &[Coordinate],
+    approximations: &[SurfaceApproximation],
+    origin_lod: u32,
+    second_order: bool,
+    view_position: DVec3,
+    coordinates: [(TileCoordinate, Vec2); LANES],
+) -> [DVec3; LANES] {
+    let relative_uvs = coordinates.map(|(tile, tile_uv)| {
+        relative_uv(
+            view_coordinates[tile.face as usize],
+            origin_lod,
+            (tile, tile_uv),
+        )
+    });
+    let approximation = SurfaceApproximationX8::gather(
+        coordinates.map(|(tile, _)| &approximations[tile.face as usize]),
+    );
+
+    let relative_positions = evaluate_approximation_x8(
+        &approximation,
+        second_order,
+        F32x8(relative_uvs.map(|uv| uv.x)),
+        F32x8(relative_uvs.map(|uv| uv.y)),
+    );
+
+    std::array::from_fn(|lane| view_position + relative_positions.lane(lane).as_dvec3())
+}
@@ -0,0 +1,212 @@
+use bevy::math::{DVec2, DVec3};
+use bevy_terrain::{math::Coordinate, prelude::*};
+
+use crate::math::{stretch, view_coordinates, FACE_AXES};
+
+/// A point on the unit surface of a face in the local space of the model,
+/// together with its first and second derivatives with respect to the uv of the face.
+#[derive(Clone, Copy, Debug)]
+pub struct SurfacePoint {
+    pub position: DVec3,
+    pub du: DVec3,
+    pub dv: DVec3,
+    pub duu: DVec3,
+    pub duv: DVec3,
+    pub dvv: DVec3,
+}
+
+/// A layout of `FACES` faces, each mapping the unit uv square onto a patch of the unit surface in the local space of the model.
+/// Only the coefficients of the approximations are computed from this mapping, [`compute_topology_approximations`]
+/// works for any number of faces. Evaluating them, i.e. the relative uv, the tile coordinates and the GPU layouts,
+/// still assumes the six faces of the [`Cube`].
+/// Arrays are sized by the const parameter, since array lengths can not depend on associated constants yet.
+///
+/// [`compute_topology_approximations`]: crate::math::compute_topology_approximations
+pub trait FaceTopology<const FACES: usize> {
+    /// Position on the unit surface at the uv of the face.
+    fn position(face: usize, uv: DVec2) -> DVec3;
+
+    /// Position on the unit surface at the uv of the face, with its derivatives.
+    fn surface_point(face: usize, uv: DVec2) -> SurfacePoint;
+
+    /// Projects the view position onto each of the faces, the coordinates the approximations are expanded around.
+    fn view_coordinates(view_position: DVec3, model: &TerrainModel) -> [Coordinate; FACES];
+}
+
+/// The six faces of the spherified cube.
+pub struct Cube;
+
+impl FaceTopology<6> for Cube {
+    // the same operations as the position of `surface_point`, so that culled sides keep the same constant term
+    fn position(face: usize, uv: DVec2) -> DVec3 {
+        let (origin, axis_u, axis_v) = FACE_AXES[face];
+        let (s_u, _, _) = stretch(uv.x);
+        let (s_v, _, _) = stretch(uv.y);
+
+        let p = origin + s_u * axis_u + s_v * axis_v;
+
+        p * p.length_recip()
+    }
+
+    fn surface_point(face: usize, uv: DVec2) -> SurfacePoint {
+        let (origin, axis_u, axis_v) = FACE_AXES[face];
+        let (s_u, ds_u, dds_u) = stretch(uv.x);
+        let (s_v, ds_v, dds_v) = stretch(uv.y);
+
+        let p = origin + s_u * axis_u + s_v * axis_v;
+        let r_inv = p.length_recip();
+        let n = p * r_inv;
+
+        let p_u = ds_u * axis_u;
+        let p_v = ds_v * axis_v;
+        let n_dot_u = n.dot(p_u);
+        let n_dot_v = n.dot(p_v);
+
+        // first and second derivatives of the normalization n = p / |p|
+        let dn = |x: DVec3, n_dot_x: f64| (x - n * n_dot_x) * r_inv;
+        let ddn = |a: DVec3, n_dot_a: f64, b: DVec3, n_dot_b: f64| {
+            (3.0 * n * n_dot_a * n_dot_b - n * a.dot(b) - a * n_dot_b - b * n_dot_a)
+                * (r_inv * r_inv)
+        };
+
+        SurfacePoint {
+            position: n,
+            du: dn(p_u, n_dot_u),
+            dv: dn(p_v, n_dot_v),
+            duu: dn(dds_u * axis_u, n.dot(dds_u * axis_u)) + ddn(p_u, n_dot_u, p_u, n_dot_u),
+            duv: ddn(p_u, n_dot_u, p_v, n_dot_v),
+            dvv: dn(dds_v * axis_v, n.dot(dds_v * axis_v)) + ddn(p_v, n_dot_v, p_v, n_dot_v),
+        }
+    }
+
+    fn view_coordinates(view_position: DVec3, model: &TerrainModel) -> [Coordinate; 6] {
+        view_coordinates(view_position, model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{compute_culled_approximations, cube_to_sphere};
+    use bevy::math::{DMat3, Vec3};
+    use bevy_terrain::math::SurfaceApproximation;
+    use itertools::iproduct;
+
+    // The side approximation as it was computed before the topologies, with the cube mapping inlined.
+    fn reference_side(
+        face: usize,
+        view_uv: DVec2,
+        linear: DMat3,
+        relative_translation: DVec3,
+        validity_radius: f64,
+    ) -> SurfaceApproximation {
+        let (origin, axis_u, axis_v) = FACE_AXES[face];
+        let (s_u, ds_u, dds_u) = stretch(view_uv.x);
+        let (s_v, ds_v, dds_v) = stretch(view_uv.y);
+
+        let p = origin + s_u * axis_u + s_v * axis_v;
+        let r_inv = p.length_recip();
+        let n = p * r_inv;
+
+        let c = linear * n + relative_translation;
+
+        if c.length() > validity_radius {
+            return SurfaceApproximation {
+                c: c.as_vec3(),
+                c_du: Vec3::ZERO,
+                c_dv: Vec3::ZERO,
+                c_duu: Vec3::ZERO,
+                c_duv: Vec3::ZERO,
+                c_dvv: Vec3::ZERO,
+            };
+        }
+
+        let p_u = ds_u * axis_u;
+        let p_v = ds_v * axis_v;
+        let n_dot_u = n.dot(p_u);
+        let n_dot_v = n.dot(p_v);
+
+        let dn = |x: DVec3, n_dot_x: f64| (x - n * n_dot_x) * r_inv;
+        let ddn = |a: DVec3, n_dot_a: f64, b: DVec3, n_dot_b: f64| {
+            (3.0 * n * n_dot_a * n_dot_b - n * a.dot(b) - a * n_dot_b - b * n_dot_a)
+                * (r_inv * r_inv)
+        };
+
+        let n_u = dn(p_u, n_dot_u);
+        let n_v = dn(p_v, n_dot_v);
+        let n_uu = dn(dds_u * axis_u, n.dot(dds_u * axis_u)) + ddn(p_u, n_dot_u, p_u, n_dot_u);
+        let n_uv = ddn(p_u, n_dot_u, p_v, n_dot_v);
+        let n_vv = dn(dds_v * axis_v, n.dot(dds_v * axis_v)) + ddn(p_v, n_dot_v, p_v, n_dot_v);
+
+        SurfaceApproximation {
+            c: c.as_vec3(),
+            c_du: (linear * n_u).as_vec3(),
+            c_dv: (linear * n_v).as_vec3(),
+            c_duu: (linear * (0.5 * n_uu)).as_vec3(),
+            c_duv: (linear * n_uv).as_vec3(),
+            c_dvv: (linear * (0.5 * n_vv)).as_vec3(),
+        }
+    }
+
+    fn bits(approximation: &SurfaceApproximation) -> [[u32; 3]; 6] {
+        [
+            approximation.c,
+            approximation.c_du,
+            approximation.c_dv,
+            approximation.c_duu,
+            approximation.c_duv,
+            approximation.c_dvv,
+        ]
+        .map(|coefficient| coefficient.to_array().map(f32::to_bits))
+    }
+
+    #[test]
+    fn cube_reproduces_the_coefficients_bit_for_bit() {
+        let model = TerrainModel::ellipsoid(
+            DVec3::new(1000.0, -20.0, 3.5),
+            6378137.0,
+            6356752.314245,
+            0.0,
+            0.0,
+        );
+        let linear = DMat3::from_mat4(model.world_from_local);
+
+        for (face, x, y, height, validity_radius) in iproduct!(
+            0..6,
+            [0.0, 0.13, 0.5, 0.999],
+            [0.0, 0.77, 1.0],
+            [10.0, 400000.0],
+            [f64::INFINITY, 2000000.0]
+        ) {
+            let uv = DVec2::new(x, y);
+            let view_position = model
+                .world_from_local
+                .transform_point3(cube_to_sphere(face, uv) * (1.0 + height / 6356752.314245));
+            let view_coordinates = view_coordinates(view_position, &model);
+            let relative_translation = model.world_from_local.w_axis.truncate() - view_position;
+
+            let (approximations, _) = compute_culled_approximations(
+                &view_coordinates,
+                view_position,
+                &model,
+                validity_radius,
+            );
+
+            for side in 0..6 {
+                let reference = reference_side(
+                    side,
+                    view_coordinates[side].uv,
+                    linear,
+                    relative_translation,
+                    validity_radius,
+                );
+
+                assert_eq!(
+                    bits(&approximations[side]),
+                    bits(&reference),
+                    "side {side} differs for the view at uv {uv} of face {face}"
+                );
+            }
+        }
+    }
+}
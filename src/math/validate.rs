@@ -0,0 +1,358 @@
+use bevy::math::{DVec2, DVec3, I64Vec2, IVec2, UVec2};
+use bevy_terrain::{
+    math::{Coordinate, TileCoordinate},
+    prelude::*,
+};
+use itertools::{iproduct, Itertools};
+use std::ops::RangeInclusive;
+
+use crate::math::{
+    approximate_world_position, approximations, cube_to_sphere, face_rotation, face_to_world,
+    relative_uv, relative_uv_with_margin,
+    simd::{approximate_world_positions_x8, LANES},
+    sphere_to_cube, tile_coordinate_from_world_position, tile_count, view_coordinates,
+    world_to_face, TileMargin,
+};
+
+// Round trip checks of the coordinate mappings on a regular grid of samples per face.
+// Each returns the largest deviation it found, so they can be asserted against tolerances or printed as a report.
+
+fn face_samples(resolution: u32) -> impl Iterator<Item = (u32, DVec2)> {
+    iproduct!(0..6, 0..=resolution, 0..=resolution).map(move |(face, x, y)| {
+        (
+            face,
+            DVec2::new(x as f64, y as f64) / resolution.max(1) as f64,
+        )
+    })
+}
+
+/// Largest uv deviation of `sphere_to_cube(cube_to_sphere(uv))`.
+/// Samples on face edges may come back on the adjacent face, those are compared in local space instead.
+pub fn cube_sphere_round_trip(resolution: u32) -> f64 {
+    face_samples(resolution)
+        .map(|(face, uv)| {
+            let local_position = cube_to_sphere(face, uv);
+            let (round_trip_face, round_trip_uv) = sphere_to_cube(local_position);
+
+            if round_trip_face == face {
+                uv.distance(round_trip_uv)
+            } else {
+                local_position.distance(cube_to_sphere(round_trip_face, round_trip_uv))
+            }
+        })
+        .fold(0.0, f64::max)
+}
+
+/// Largest distance in meters between a position and the one reconstructed from its coordinate,
+/// `world_position(from_world_position(world_position(coordinate)))`, at the given height.
+pub fn world_position_round_trip(model: &TerrainModel, resolution: u32, height: f32) -> f64 {
+    face_samples(resolution)
+        .map(|(face, uv)| {
+            let position = Coordinate::new(face, uv).world_position(model, height);
+            let round_trip = Coordinate::from_world_position(position, model);
+
+            position.distance(round_trip.world_position(model, height))
+        })
+        .fold(0.0, f64::max)
+}
+
+/// Largest uv deviation of projecting a coordinate onto another face and back, for each of the 36 face pairs.
+/// Only directions in front of the target face are projected, the others have no projection onto its plane.
+pub fn project_to_face_consistency(model: &TerrainModel, resolution: u32) -> [[f64; 6]; 6] {
+    let mut deviations = [[0.0; 6]; 6];
+
+    for (face, uv) in face_samples(resolution) {
+        let coordinate = Coordinate::new(face, uv);
+        let local_position = cube_to_sphere(face, uv);
+
+        for target_face in 0..6 {
+            if local_position.dot(cube_to_sphere(target_face, DVec2::splat(0.5))) <= 1e-6 {
+                continue;
+            }
+
+            let round_trip = coordinate
+                .project_to_face(target_face, model)
+                .project_to_face(face, model);
+
+            let deviation = &mut deviations[face as usize][target_face as usize];
+            *deviation = deviation.max(uv.distance(round_trip.uv));
+        }
+    }
+
+    deviations
+}
+
+/// Largest distance in local space between the crate's [`cube_to_sphere`] and the mapping of the terrain library.
+/// A mismatch, e.g. a diverging stretch constant, shows up as a large deviation here.
+pub fn library_mapping_consistency(model: &TerrainModel, resolution: u32) -> f64 {
+    let local_from_world = model.world_from_local.inverse();
+
+    face_samples(resolution)
+        .map(|(face, uv)| {
+            let library_position = local_from_world
+                .transform_point3(Coordinate::new(face, uv).world_position(model, 0.0));
+
+            library_position.distance(cube_to_sphere(face, uv))
+        })
+        .fold(0.0, f64::max)
+}
+
+/// Largest distance between the positions of [`approximate_world_positions_x8`] and the scalar path,
+/// for a view one kilometer above the center of face 0.
+/// The samples cycle through lods below, at and above the origin lod, and unless the number of samples per face
+/// is a multiple of [`LANES`], some chunks span two faces.
+/// Both paths perform the same operations, so anything but zero points to a broken lane.
+pub fn simd_consistency(model: &TerrainModel, resolution: u32) -> f64 {
+    let origin_lod = 10;
+    let lods = [origin_lod - 3, origin_lod, origin_lod + 2];
+    let view_position = Coordinate::new(0, DVec2::splat(0.5)).world_position(model, 1000.0);
+    let view_coordinates = view_coordinates(view_position, model);
+    let approximations = approximations(&view_coordinates, view_position, model);
+
+    let coordinates = face_samples(resolution)
+        .zip(lods.into_iter().cycle())
+        .map(|((face, uv), lod)| {
+            let position = Coordinate::new(face, uv).world_position(model, 0.0);
+
+            tile_coordinate_from_world_position(position, lod, model)
+        })
+        .collect_vec();
+
+    iproduct!(coordinates.chunks_exact(LANES), [false, true])
+        .flat_map(|(chunk, second_order)| {
+            let positions = approximate_world_positions_x8(
+                &view_coordinates,
+                &approximations,
+                origin_lod,
+                second_order,
+                view_position,
+                std::array::from_fn(|lane| chunk[lane]),
+            );
+
+            chunk
+                .iter()
+                .zip(positions)
+                .map(move |(&coordinate, position)| {
+                    position.distance(approximate_world_position(
+                        &view_coordinates,
+                        &approximations,
+                        origin_lod,
+                        second_order,
+                        view_position,
+                        coordinate,
+                    ))
+                })
+        })
+        .fold(0.0, f64::max)
+}
+
+/// Largest deviation in texels between the relative uv of a texel in the margin of a tile texture
+/// and the relative uv of the same point on the neighboring tile it was copied from.
+/// The tiles surround a view at the center of face 0, at the origin lod.
+pub fn margin_consistency(margin: TileMargin) -> f64 {
+    let lod = 10;
+    let view_coordinate = Coordinate::new(0, DVec2::splat(0.5));
+    let view_xy = (view_coordinate.uv * tile_count(lod) as f64).as_ivec2();
+    let inner_size = (margin.texture_size - 2 * margin.margin) as f64;
+
+    iproduct!(
+        -2..=2,
+        -2..=2,
+        0..margin.texture_size,
+        0..margin.texture_size
+    )
+    .filter(|&(_, _, x, y)| {
+        let inner = margin.margin..margin.texture_size - margin.margin;
+        !inner.contains(&x) || !inner.contains(&y)
+    })
+    .map(|(dx, dy, x, y)| {
+        let xy = (view_xy + IVec2::new(dx, dy)).as_uvec2();
+        let tile = TileCoordinate::new(0, lod, xy.x, xy.y);
+
+        let texel = UVec2::new(x, y).as_dvec2() + 0.5;
+        let texture_uv = (texel / margin.texture_size as f64).as_vec2();
+
+        // the point on the neighboring tile, computed exactly from the texel
+        let tile_uv = (texel - margin.margin as f64) / inner_size;
+        let offset = tile_uv.floor();
+        let neighbor_xy = (xy.as_dvec2() + offset).as_uvec2();
+        let neighbor = TileCoordinate::new(0, lod, neighbor_xy.x, neighbor_xy.y);
+
+        let with_margin = relative_uv_with_margin(view_coordinate, lod, margin, (tile, texture_uv));
+        let expected = relative_uv(
+            view_coordinate,
+            lod,
+            (neighbor, (tile_uv - offset).as_vec2()),
+        );
+
+        with_margin.as_dvec2().distance(expected.as_dvec2()) * tile_count(lod) as f64 * inner_size
+    })
+    .fold(0.0, f64::max)
+}
+
+/// Largest deviation of the face rotations from the cube mapping they describe.
+/// The rotated axes have to match the directions of u, v and the normal at the center of each face,
+/// taken from `cube_to_sphere` by central differences, and directions have to round trip through world space.
+pub fn face_rotation_consistency(model: &TerrainModel) -> f64 {
+    let delta = 1e-6;
+
+    (0..6)
+        .map(|face| {
+            let rotation = face_rotation(face);
+            let center = DVec2::splat(0.5);
+            let difference = |direction: DVec2| {
+                (cube_to_sphere(face, center + delta * direction)
+                    - cube_to_sphere(face, center - delta * direction))
+                .normalize()
+            };
+
+            let axes = [
+                (rotation * DVec3::X).distance(difference(DVec2::X)),
+                (rotation * DVec3::Y).distance(cube_to_sphere(face, center)),
+                (rotation * DVec3::Z).distance(difference(DVec2::Y)),
+            ];
+
+            let direction = DVec3::new(0.3, 0.8, -0.5).normalize();
+            let world = face_to_world(face, direction, model);
+            let round_trip = world_to_face(face, world, model).distance(direction);
+
+            axes.into_iter().fold(round_trip, f64::max)
+        })
+        .fold(0.0, f64::max)
+}
+
+/// Precision of the relative uv at one lod of [`relative_uv_audit`].
+#[derive(Clone, Copy, Debug)]
+pub struct RelativeUvAudit {
+    pub lod: u32,
+    /// Fraction of the samples whose f32 relative uv equals the exact one.
+    pub exact: f64,
+    /// Whether every f32 relative uv lies within half an ulp of the exact one, i.e. is correctly rounded.
+    pub correctly_rounded: bool,
+    /// Largest deviation from the exact relative uv, in vertex spacings of the lod.
+    pub max_error: f64,
+}
+
+/// Audits the relative uv `(tile_offset + vertex_offset) / (1 << lod)` computed by [`relative_uv`] for each lod,
+/// against the exact offset in f64.
+/// The vertices of the tiles within the neighborhood of the tile below the view are sampled for a grid of view positions,
+/// which are placed off the dyadic grid, so that their fraction inside the origin tile uses all bits of the f32.
+pub fn relative_uv_audit(
+    origin_lod: u32,
+    lods: RangeInclusive<u32>,
+    vertex_resolution: u32,
+    neighborhood: i64,
+) -> Vec<RelativeUvAudit> {
+    let views = iproduct!(0..6, 0..4, 0..4)
+        .map(|(face, x, y)| {
+            let uv = (DVec2::new(x as f64, y as f64) + DVec2::new(0.6180, 0.4142)) / 4.0;
+
+            Coordinate::new(face, uv)
+        })
+        .collect_vec();
+
+    lods.map(|lod| {
+        let count = tile_count(lod) as i64;
+        let spacing = 1.0 / (tile_count(lod) as f64 * vertex_resolution as f64);
+
+        let mut samples = 0;
+        let mut exact = 0;
+        let mut correctly_rounded = true;
+        let mut max_error: f64 = 0.0;
+
+        for &view_coordinate in &views {
+            let view_xy = (view_coordinate.uv * tile_count(lod) as f64)
+                .as_i64vec2()
+                .min(I64Vec2::splat(count - 1));
+
+            for (dx, dy, x, y) in iproduct!(
+                -neighborhood..=neighborhood,
+                -neighborhood..=neighborhood,
+                0..=vertex_resolution,
+                0..=vertex_resolution
+            ) {
+                let xy = view_xy + I64Vec2::new(dx, dy);
+                if xy.min_element() < 0 || xy.max_element() >= count {
+                    continue;
+                }
+
+                let tile = TileCoordinate::new(view_coordinate.face, lod, xy.x as u32, xy.y as u32);
+                let tile_uv = UVec2::new(x, y).as_vec2() / vertex_resolution as f32;
+
+                let result = relative_uv(view_coordinate, origin_lod, (tile, tile_uv));
+                let expected = (xy.as_dvec2() + tile_uv.as_dvec2()) / tile_count(lod) as f64
+                    - view_coordinate.uv;
+
+                let error = (result.as_dvec2() - expected).abs();
+                let half_ulp = result
+                    .abs()
+                    .to_array()
+                    .map(|x| 0.5 * (f32::from_bits(x.to_bits() + 1) - x) as f64);
+
+                samples += 1;
+                exact += (error == DVec2::ZERO) as usize;
+                correctly_rounded &= error.x <= half_ulp[0] && error.y <= half_ulp[1];
+                max_error = max_error.max(error.max_element() / spacing);
+            }
+        }
+
+        RelativeUvAudit {
+            lod,
+            exact: exact as f64 / samples.max(1) as f64,
+            correctly_rounded,
+            max_error,
+        }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESOLUTION: u32 = 16;
+
+    fn earth() -> TerrainModel {
+        TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0)
+    }
+
+    #[test]
+    fn simd_path_is_bit_identical() {
+        // 8 * 8 samples per face fill whole chunks, 17 * 17 samples make chunks span two faces
+        for resolution in [7, RESOLUTION] {
+            assert_eq!(simd_consistency(&earth(), resolution), 0.0);
+        }
+    }
+
+    #[test]
+    fn cube_sphere_round_trip_is_exact() {
+        let deviation = cube_sphere_round_trip(RESOLUTION);
+
+        assert!(deviation < 1e-12, "{deviation}");
+    }
+
+    #[test]
+    fn world_position_round_trip_is_below_a_micrometer() {
+        let model = earth();
+
+        for height in [-500.0, 0.0, 1000.0, 400_000.0] {
+            let deviation = world_position_round_trip(&model, RESOLUTION, height);
+
+            assert!(deviation < 1e-6, "{deviation} m at a height of {height} m");
+        }
+    }
+
+    #[test]
+    fn project_to_face_is_consistent() {
+        let deviations = project_to_face_consistency(&earth(), RESOLUTION);
+
+        for (face, deviations) in deviations.iter().enumerate() {
+            for (target_face, &deviation) in deviations.iter().enumerate() {
+                assert!(
+                    deviation < 1e-8,
+                    "{deviation} from face {face} over face {target_face}"
+                );
+            }
+        }
+    }
+}
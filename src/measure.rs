@@ -0,0 +1,121 @@
+use bevy::{math::DVec3, prelude::*};
+use bevy_terrain::prelude::*;
+
+use crate::{
+    geodesy::{geodesic, Geodetic},
+    math::{
+        evaluate_displaced_approximation, tile_coordinate_from_world_position,
+        within_validity_radius,
+    },
+    view_approximation::ApproximationSnapshot,
+};
+
+// number of segments the geodesic between the points is measured with
+const MEASUREMENT_SEGMENTS: usize = 64;
+
+// Two picked points on the surface, the next pick replaces the start again once both are set.
+#[derive(Resource, Default)]
+pub struct Measurement {
+    pub start: Option<Geodetic>,
+    pub end: Option<Geodetic>,
+}
+
+impl Measurement {
+    pub fn pick(&mut self, point: Geodetic) {
+        match (self.start, self.end) {
+            (Some(_), None) => self.end = Some(point),
+            _ => {
+                self.start = Some(point);
+                self.end = None;
+            }
+        }
+    }
+
+    pub fn points(&self) -> Option<(Geodetic, Geodetic)> {
+        self.start.zip(self.end)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MeasurementReport {
+    // exact positions along the geodesic
+    pub path: Vec<DVec3>,
+    // the same positions, as the approximation around the view places them
+    pub approximated_path: Vec<DVec3>,
+    pub surface_distance: f64,
+    pub chord_distance: f64,
+    pub approximated_surface_distance: f64,
+    pub approximated_chord_distance: f64,
+    // largest distance between an exact and an approximated position along the path
+    pub max_deviation: f64,
+    // positions beyond the validity radius, which were placed exactly instead of approximated
+    pub fallbacks: usize,
+}
+
+fn path_length(path: &[DVec3]) -> f64 {
+    path.windows(2)
+        .map(|segment| segment[0].distance(segment[1]))
+        .sum()
+}
+
+// Measures the geodesic between the points exactly in f64, and as the terrain shaders would see it through the approximation.
+// The approximated positions are only meaningful close to the view, so positions beyond the validity radius
+// fall back to the exact ones and are counted, instead of showing the approximation breaking down by meters.
+pub fn measure(
+    start: Geodetic,
+    end: Geodetic,
+    snapshot: &ApproximationSnapshot,
+    origin_lod: u32,
+    validity_radius: f64,
+    model: &TerrainModel,
+) -> MeasurementReport {
+    let path = geodesic(start, end, MEASUREMENT_SEGMENTS, model).collect::<Vec<_>>();
+    let view_coordinates = snapshot.view_coordinates_at(origin_lod);
+    let mut fallbacks = 0;
+
+    let approximated_path = path
+        .iter()
+        .map(|&position| {
+            let height = Geodetic::from_world_position(position, model).height;
+            let (tile, tile_uv) = tile_coordinate_from_world_position(position, origin_lod, model);
+            let face = tile.face as usize;
+            let relative_uv = view_coordinates.relative_uv((tile, tile_uv));
+
+            if !within_validity_radius(&snapshot.approximations[face], relative_uv, validity_radius)
+            {
+                fallbacks += 1;
+                return position;
+            }
+
+            let relative_position = evaluate_displaced_approximation(
+                &snapshot.approximations[face],
+                snapshot.height_coefficients[face],
+                true,
+                relative_uv,
+                height as f32,
+            );
+
+            snapshot.view_position + relative_position.as_dvec3()
+        })
+        .collect::<Vec<_>>();
+
+    let chord = |path: &[DVec3]| match (path.first(), path.last()) {
+        (Some(first), Some(last)) => first.distance(*last),
+        _ => 0.0,
+    };
+
+    MeasurementReport {
+        surface_distance: path_length(&path),
+        chord_distance: chord(&path),
+        approximated_surface_distance: path_length(&approximated_path),
+        approximated_chord_distance: chord(&approximated_path),
+        max_deviation: path
+            .iter()
+            .zip(&approximated_path)
+            .map(|(exact, approximated)| exact.distance(*approximated))
+            .fold(0.0, f64::max),
+        fallbacks,
+        path,
+        approximated_path,
+    }
+}
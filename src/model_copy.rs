@@ -0,0 +1,238 @@
+use bevy::prelude::*;
+use bevy_terrain::prelude::*;
+
+// Components and resources keeping a copy of the terrain model, to evaluate it without querying the terrain.
+// The copies of every type registered with `add_model_copy` are replaced, whenever the model is edited.
+pub trait ModelCopy {
+    fn set_model(&mut self, model: &TerrainModel);
+}
+
+// Sent after the terrain model was edited, e.g. its axes, with the model that replaces all copies.
+#[derive(Event, Clone)]
+pub struct ModelEdited(pub TerrainModel);
+
+// The copies are replaced before the update, so all systems of a frame see the same model.
+#[derive(SystemSet, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct ModelCopySet;
+
+pub trait ModelCopyAppExt {
+    fn add_model_copy<T: Component + ModelCopy>(&mut self) -> &mut Self;
+
+    fn add_model_copy_resource<T: Resource + ModelCopy>(&mut self) -> &mut Self;
+}
+
+impl ModelCopyAppExt for App {
+    fn add_model_copy<T: Component + ModelCopy>(&mut self) -> &mut Self {
+        self.add_event::<ModelEdited>()
+            .add_systems(PreUpdate, replace_models::<T>.in_set(ModelCopySet))
+    }
+
+    fn add_model_copy_resource<T: Resource + ModelCopy>(&mut self) -> &mut Self {
+        self.add_event::<ModelEdited>()
+            .add_systems(PreUpdate, replace_resource_model::<T>.in_set(ModelCopySet))
+    }
+}
+
+fn replace_models<T: Component + ModelCopy>(
+    mut edited: EventReader<ModelEdited>,
+    mut copy_query: Query<&mut T>,
+) {
+    let Some(ModelEdited(model)) = edited.read().last() else {
+        return;
+    };
+
+    for mut copy in &mut copy_query {
+        copy.set_model(model);
+    }
+}
+
+fn replace_resource_model<T: Resource + ModelCopy>(
+    mut edited: EventReader<ModelEdited>,
+    copy: Option<ResMut<T>>,
+) {
+    let (Some(ModelEdited(model)), Some(mut copy)) = (edited.read().last(), copy) else {
+        return;
+    };
+
+    copy.set_model(model);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        annotation::Annotation,
+        collider::TerrainCollider,
+        culling::TileCulling,
+        depth::AdaptiveNearPlane,
+        error_field::ErrorField,
+        features::{Feature, FeatureStamps},
+        geodesy::{with_axes, Geodetic},
+        height::{ConstantHeight, TerrainHeights},
+        impostor::Impostor,
+        landing_site::LandingSite,
+        ocean::Ocean,
+        orbit::GroundTrack,
+        scene_check::SceneExpectations,
+        second_view::SecondView,
+        shadows::PlanetShadows,
+        track::CameraTrack,
+        vehicle::Vehicle,
+    };
+    use bevy::math::{DMat4, DVec2, DVec3};
+    use bevy_terrain::math::Coordinate;
+
+    fn heights(model: &TerrainModel) -> FeatureStamps {
+        FeatureStamps::new(model.clone(), Box::new(ConstantHeight(0.0))).with_feature(
+            Feature::crater(Coordinate::new(2, DVec2::new(0.3, 0.6)), 50000.0, 500.0),
+        )
+    }
+
+    fn component_model<T: Component>(app: &mut App, model: impl Fn(&T) -> &TerrainModel) -> DMat4 {
+        let mut query = app.world_mut().query::<&T>();
+
+        model(query.single(app.world())).world_from_local
+    }
+
+    #[test]
+    fn edited_axes_reach_every_copy() {
+        let model = TerrainModel::ellipsoid(
+            DVec3::new(0.0, 1.0, 1.0),
+            6378137.0,
+            6356752.314245,
+            0.0,
+            0.0,
+        );
+        let edited = with_axes(&model, 6400000.0, 6300000.0);
+
+        let mut app = App::new();
+        app.add_model_copy::<TerrainCollider>()
+            .add_model_copy::<Ocean>()
+            .add_model_copy::<ErrorField>()
+            .add_model_copy::<GroundTrack>()
+            .add_model_copy::<Vehicle>()
+            .add_model_copy::<PlanetShadows>()
+            .add_model_copy::<Impostor>()
+            .add_model_copy::<AdaptiveNearPlane>()
+            .add_model_copy::<Annotation>()
+            .add_model_copy::<SecondView>()
+            .add_model_copy_resource::<TileCulling>()
+            .add_model_copy_resource::<CameraTrack>()
+            .add_model_copy_resource::<LandingSite>()
+            .add_model_copy_resource::<SceneExpectations>()
+            .add_model_copy_resource::<TerrainHeights>()
+            .insert_resource(TileCulling::new(model.clone(), 1000.0))
+            .insert_resource(CameraTrack::new(model.clone()))
+            .insert_resource(LandingSite::new(model.clone(), 1000.0))
+            .insert_resource(SceneExpectations {
+                model: model.clone(),
+                origin_lod: 10,
+                min_altitude: 1.0,
+                budget: 0.001,
+            })
+            .insert_resource(TerrainHeights(Box::new(heights(&model))));
+
+        app.world_mut().spawn((
+            TerrainCollider {
+                model: model.clone(),
+            },
+            Ocean::new(model.clone()),
+            ErrorField::new(model.clone(), 10),
+            GroundTrack::new(model.clone(), 100.0, Color::WHITE),
+            Vehicle::new(model.clone(), Geodetic::from_degrees(10.0, 20.0, 0.0), 0.0),
+            PlanetShadows::new(model.clone(), DVec3::X),
+            Impostor::new(model.clone(), 1e7),
+            AdaptiveNearPlane::new(model.clone()),
+        ));
+        app.world_mut().spawn((
+            Annotation::new(
+                model.clone(),
+                Coordinate::new(0, DVec2::splat(0.5)),
+                0.0,
+                "annotation",
+                Color::WHITE,
+            ),
+            SecondView {
+                model: model.clone(),
+                window: Entity::PLACEHOLDER,
+                snapshot: None,
+                position_error: 0.0,
+            },
+        ));
+
+        app.world_mut().send_event(ModelEdited(edited.clone()));
+        app.update();
+
+        let expected = edited.world_from_local;
+
+        assert_eq!(
+            component_model(&mut app, |copy: &TerrainCollider| &copy.model),
+            expected
+        );
+        assert_eq!(
+            component_model(&mut app, |copy: &Ocean| &copy.model),
+            expected
+        );
+        assert_eq!(
+            component_model(&mut app, |copy: &ErrorField| &copy.model),
+            expected
+        );
+        assert_eq!(
+            component_model(&mut app, |copy: &GroundTrack| &copy.model),
+            expected
+        );
+        assert_eq!(
+            component_model(&mut app, |copy: &Vehicle| &copy.model),
+            expected
+        );
+        assert_eq!(
+            component_model(&mut app, |copy: &PlanetShadows| &copy.model),
+            expected
+        );
+        assert_eq!(
+            component_model(&mut app, |copy: &Impostor| &copy.model),
+            expected
+        );
+        assert_eq!(
+            component_model(&mut app, |copy: &AdaptiveNearPlane| &copy.model),
+            expected
+        );
+        assert_eq!(
+            component_model(&mut app, |copy: &Annotation| &copy.model),
+            expected
+        );
+        assert_eq!(
+            component_model(&mut app, |copy: &SecondView| &copy.model),
+            expected
+        );
+
+        let world = app.world();
+        assert_eq!(
+            world.resource::<TileCulling>().model.world_from_local,
+            expected
+        );
+        assert_eq!(
+            world.resource::<CameraTrack>().model.world_from_local,
+            expected
+        );
+        assert_eq!(
+            world.resource::<LandingSite>().model.world_from_local,
+            expected
+        );
+        assert_eq!(
+            world.resource::<SceneExpectations>().model.world_from_local,
+            expected
+        );
+
+        // the heights are only observable through the features, which scale with the major axis
+        let coordinate = Coordinate::new(2, DVec2::new(0.301, 0.6));
+        assert_eq!(
+            world.resource::<TerrainHeights>().0.height(coordinate),
+            heights(&edited).height(coordinate)
+        );
+        assert_ne!(
+            heights(&model).height(coordinate),
+            heights(&edited).height(coordinate)
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use bevy::math::DVec2;
+use bevy_terrain::math::{Coordinate, TileCoordinate};
+use std::{fmt, str::FromStr};
+
+use crate::{
+    geodesy::Geodetic,
+    math::{tile_count, MAX_LOD},
+};
+
+// Canonical textual forms, used for log output, command line arguments and bookmark files.
+//   tile:       f2/l8/x123/y456
+//   coordinate: f2/u0.25/v0.75
+//   geodetic:   47.2692,11.4041,1500 (latitude and longitude in degrees, height in meters)
+// The bevy_terrain types are wrapped, since the traits can not be implemented for them directly.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError(pub String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to parse {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Clone, Copy)]
+pub struct TileName(pub TileCoordinate);
+
+#[derive(Clone, Copy)]
+pub struct CoordinateName(pub Coordinate);
+
+impl fmt::Display for TileName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tile = &self.0;
+        write!(f, "f{}/l{}/x{}/y{}", tile.face, tile.lod, tile.x, tile.y)
+    }
+}
+
+impl FromStr for TileName {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseError(format!("tile `{s}`, expected `f<face>/l<lod>/x<x>/y<y>`"));
+
+        let [face, lod, x, y] = fields(s, ["f", "l", "x", "y"]).ok_or_else(error)?;
+        let parse = |field: &str| field.parse::<u32>().map_err(|_| error());
+
+        let (face, lod, x, y) = (parse(face)?, parse(lod)?, parse(x)?, parse(y)?);
+
+        if face >= 6 || lod > MAX_LOD || x as u64 >= tile_count(lod) || y as u64 >= tile_count(lod)
+        {
+            return Err(error());
+        }
+
+        Ok(Self(TileCoordinate::new(face, lod, x, y)))
+    }
+}
+
+impl fmt::Display for CoordinateName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let coordinate = &self.0;
+        write!(
+            f,
+            "f{}/u{}/v{}",
+            coordinate.face, coordinate.uv.x, coordinate.uv.y
+        )
+    }
+}
+
+impl FromStr for CoordinateName {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseError(format!("coordinate `{s}`, expected `f<face>/u<u>/v<v>`"));
+
+        let [face, u, v] = fields(s, ["f", "u", "v"]).ok_or_else(error)?;
+
+        let face = face.parse::<u32>().map_err(|_| error())?;
+        let uv = DVec2::new(
+            u.parse().map_err(|_| error())?,
+            v.parse().map_err(|_| error())?,
+        );
+
+        if face >= 6 || !(0.0..=1.0).contains(&uv.x) || !(0.0..=1.0).contains(&uv.y) {
+            return Err(error());
+        }
+
+        Ok(Self(Coordinate::new(face, uv)))
+    }
+}
+
+impl fmt::Display for Geodetic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{}",
+            self.latitude_degrees(),
+            self.longitude_degrees(),
+            self.height
+        )
+    }
+}
+
+impl FromStr for Geodetic {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || {
+            ParseError(format!(
+                "geodetic position `{s}`, expected `<lat>,<lon>[,<height>]`"
+            ))
+        };
+
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<f64>().map_err(|_| error()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (latitude, longitude, height) = match values[..] {
+            [latitude, longitude] => (latitude, longitude, 0.0),
+            [latitude, longitude, height] => (latitude, longitude, height),
+            _ => return Err(error()),
+        };
+
+        // `f64` parses `NaN` and `inf`, neither is a position
+        if !(-90.0..=90.0).contains(&latitude) || !longitude.is_finite() || !height.is_finite() {
+            return Err(error());
+        }
+
+        Ok(Geodetic::from_degrees(latitude, longitude, height))
+    }
+}
+
+// Splits `s` at slashes and strips the expected prefix of each field.
+fn fields<'a, const N: usize>(s: &'a str, prefixes: [&str; N]) -> Option<[&'a str; N]> {
+    let mut parts = s.split('/');
+    let mut fields = [""; N];
+
+    for (field, prefix) in fields.iter_mut().zip(prefixes) {
+        *field = parts.next()?.strip_prefix(prefix)?;
+    }
+
+    parts.next().is_none().then_some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_round_trip() {
+        for (face, lod) in [(0, 0), (2, 8), (5, MAX_LOD)] {
+            let last = (tile_count(lod) - 1) as u32;
+
+            for (x, y) in [(0, 0), (last, 0), (last / 2, last)] {
+                let name = TileName(TileCoordinate::new(face, lod, x, y)).to_string();
+                let tile = name.parse::<TileName>().unwrap().0;
+
+                assert_eq!(
+                    (tile.face, tile.lod, tile.x, tile.y),
+                    (face, lod, x, y),
+                    "{name}"
+                );
+            }
+        }
+
+        assert_eq!(
+            TileName(TileCoordinate::new(2, 8, 123, 45)).to_string(),
+            "f2/l8/x123/y45"
+        );
+    }
+
+    #[test]
+    fn malformed_tiles_are_rejected() {
+        for name in [
+            "",
+            "f6/l0/x0/y0",
+            "f2/l8/x123",
+            "f2/l8/x123/y45/z0",
+            "f2/l8/y45/x123",
+            "f2/l8/x256/y0",
+            "f2/l8/x0/y256",
+            "f2/l99/x0/y0",
+            "f2/l8/x-1/y0",
+            "f2/l8/x1.5/y0",
+            "2/8/123/45",
+        ] {
+            assert!(name.parse::<TileName>().is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn coordinate_round_trip() {
+        for (face, uv) in [
+            (0, DVec2::ZERO),
+            (3, DVec2::ONE),
+            (5, DVec2::new(0.1, 1.0 / 3.0)),
+            (1, DVec2::new(1e-300, 0.999999999999)),
+        ] {
+            let name = CoordinateName(Coordinate::new(face, uv)).to_string();
+            let coordinate = name.parse::<CoordinateName>().unwrap().0;
+
+            assert_eq!((coordinate.face, coordinate.uv), (face, uv), "{name}");
+        }
+    }
+
+    #[test]
+    fn malformed_coordinates_are_rejected() {
+        for name in [
+            "",
+            "f6/u0.5/v0.5",
+            "f0/u0.5",
+            "f0/u0.5/v0.5/w0.5",
+            "f0/u1.5/v0.5",
+            "f0/u0.5/v-0.1",
+            "f0/uNaN/v0.5",
+            "f0/u0.5/vinf",
+            "f0/v0.5/u0.5",
+        ] {
+            assert!(name.parse::<CoordinateName>().is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn geodetic_round_trip() {
+        for geodetic in [
+            Geodetic::from_degrees(47.2692, 11.4041, 1500.0),
+            Geodetic::from_degrees(-90.0, -180.0, -430.5),
+            Geodetic::from_degrees(90.0, 179.999999, 0.0),
+            Geodetic::from_degrees(1.0 / 3.0, -0.1, 4e5),
+        ] {
+            let name = geodetic.to_string();
+            let parsed = name.parse::<Geodetic>().unwrap();
+
+            // the degrees are converted to radians on the way back
+            assert!(
+                (parsed.latitude - geodetic.latitude).abs() < 1e-15,
+                "{name}"
+            );
+            assert!(
+                (parsed.longitude - geodetic.longitude).abs() < 1e-15,
+                "{name}"
+            );
+            assert_eq!(parsed.height, geodetic.height, "{name}");
+        }
+
+        let geodetic = " 47.5 , 11.25 ".parse::<Geodetic>().unwrap();
+        assert_eq!(geodetic, Geodetic::from_degrees(47.5, 11.25, 0.0));
+    }
+
+    #[test]
+    fn malformed_geodetics_are_rejected() {
+        for name in [
+            "",
+            "47.2",
+            "47.2,11.4,1500,3",
+            "47.2;11.4",
+            "a,11.4",
+            "90.5,0",
+            "-91,0",
+            "NaN,0",
+            "0,NaN",
+            "0,inf",
+            "0,0,-inf",
+            "0,0,NaN",
+        ] {
+            assert!(name.parse::<Geodetic>().is_err(), "{name}");
+        }
+    }
+}
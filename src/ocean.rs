@@ -0,0 +1,190 @@
+use bevy::{
+    color::palettes::css,
+    math::{DVec2, DVec3},
+    prelude::*,
+    render::{
+        mesh::{Indices, MeshVertexAttribute, PrimitiveTopology, VertexAttributeValues},
+        primitives::Aabb,
+        render_asset::RenderAssetUsages,
+    },
+};
+use bevy_terrain::{
+    big_space::{FloatingOrigin, GridTransform, GridTransformReadOnly, ReferenceFrames},
+    math::{Coordinate, SurfaceApproximation},
+    prelude::*,
+};
+use itertools::iproduct;
+
+use crate::{
+    epoch::{shift_model, Rebase},
+    math::{approximations, evaluate_approximation, view_coordinates},
+    model_copy::ModelCopy,
+};
+
+// The sea-level surface around the camera.
+// Its vertices are evaluated relative to the view with a separate surface approximation of the sea-level model,
+// so that the water plane stays as precise as the terrain, independent of the terrain's height range.
+#[derive(Component)]
+pub struct Ocean {
+    pub model: TerrainModel,
+    // size of the patch around the view on each face in uv space
+    pub extent: f64,
+    pub resolution: u32,
+}
+
+impl Ocean {
+    pub fn new(model: TerrainModel) -> Self {
+        Self {
+            model,
+            extent: 1.0 / 64.0,
+            resolution: 64,
+        }
+    }
+}
+
+impl ModelCopy for Ocean {
+    fn set_model(&mut self, model: &TerrainModel) {
+        self.model = model.clone();
+    }
+}
+
+impl Rebase for Ocean {
+    fn rebase(&mut self, offset: DVec3) {
+        self.model = shift_model(&self.model, offset);
+    }
+}
+
+pub fn ocean_material() -> StandardMaterial {
+    StandardMaterial {
+        base_color: css::MIDNIGHT_BLUE.with_alpha(0.8).into(),
+        perceptual_roughness: 0.1,
+        alpha_mode: AlphaMode::Blend,
+        // draw the water in front of coincident terrain at sea level
+        depth_bias: 1.0,
+        double_sided: true,
+        cull_mode: None,
+        ..default()
+    }
+}
+
+// The mesh the patch is written into, it starts out empty.
+pub fn empty_ocean_mesh() -> Mesh {
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+}
+
+fn take_float32x3(mesh: &mut Mesh, attribute: MeshVertexAttribute) -> Vec<[f32; 3]> {
+    match mesh.remove_attribute(attribute) {
+        Some(VertexAttributeValues::Float32x3(values)) => values,
+        _ => Vec::new(),
+    }
+}
+
+// Writes the patch into the mesh, reusing the buffers of the previous patch,
+// so that rebuilding it every frame does not allocate once they are large enough.
+pub fn update_ocean_mesh(
+    mesh: &mut Mesh,
+    view_coordinates: &[Coordinate],
+    approximations: &[SurfaceApproximation],
+    extent: f64,
+    resolution: u32,
+) {
+    let mut positions = take_float32x3(mesh, Mesh::ATTRIBUTE_POSITION);
+    let mut normals = take_float32x3(mesh, Mesh::ATTRIBUTE_NORMAL);
+    let mut indices = match mesh.remove_indices() {
+        Some(Indices::U32(indices)) => indices,
+        _ => Vec::new(),
+    };
+
+    positions.clear();
+    normals.clear();
+    indices.clear();
+
+    for (view_coordinate, approximation) in view_coordinates.iter().zip(approximations) {
+        let start = (view_coordinate.uv - 0.5 * extent).max(DVec2::ZERO);
+        let end = (view_coordinate.uv + 0.5 * extent).min(DVec2::ONE);
+
+        // the patch does not reach onto this face
+        if start.cmpge(end).any() {
+            continue;
+        }
+
+        let &SurfaceApproximation {
+            c_du,
+            c_dv,
+            c_duu,
+            c_duv,
+            c_dvv,
+            ..
+        } = approximation;
+
+        let offset = positions.len() as u32;
+
+        for y in 0..=resolution {
+            for x in 0..=resolution {
+                let uv = start + (end - start) * DVec2::new(x as f64, y as f64) / resolution as f64;
+                let relative_uv = (uv - view_coordinate.uv).as_vec2();
+
+                let position = evaluate_approximation(approximation, true, relative_uv);
+                let tangent_u = c_du + 2.0 * c_duu * relative_uv.x + c_duv * relative_uv.y;
+                let tangent_v = c_dv + c_duv * relative_uv.x + 2.0 * c_dvv * relative_uv.y;
+
+                positions.push(position.to_array());
+                normals.push(tangent_u.cross(tangent_v).normalize_or_zero().to_array());
+            }
+        }
+
+        for (x, y) in iproduct!(0..resolution, 0..resolution) {
+            let index = offset + y * (resolution + 1) + x;
+
+            indices.extend([index, index + 1, index + resolution + 1]);
+            indices.extend([index + 1, index + resolution + 2, index + resolution + 1]);
+        }
+    }
+
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_indices(Indices::U32(indices));
+}
+
+pub fn update_ocean(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut ocean_query: Query<(Entity, &Ocean, &Handle<Mesh>, GridTransform), Without<FloatingOrigin>>,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+    frames: ReferenceFrames,
+) {
+    let Ok((view, view_transform)) = view_query.get_single() else {
+        warn_once!("Skipping update_ocean: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping update_ocean: the camera is not part of a reference frame.");
+        return;
+    };
+    let view_position: DVec3 = view_transform.position_double(&frame);
+
+    for (entity, ocean, mesh, mut ocean_transform) in &mut ocean_query {
+        let view_coordinates = view_coordinates(view_position, &ocean.model);
+        let approximations = approximations(&view_coordinates, view_position, &ocean.model);
+
+        // anchor the patch at the view, the vertices are relative to it
+        *ocean_transform.cell = *view_transform.cell;
+        ocean_transform.transform.translation = view_transform.transform.translation;
+
+        if let Some(mesh) = meshes.get_mut(mesh) {
+            update_ocean_mesh(
+                mesh,
+                &view_coordinates,
+                &approximations,
+                ocean.extent,
+                ocean.resolution,
+            );
+
+            // the bounds are only computed for entities without any, drop the ones of the previous patch
+            commands.entity(entity).remove::<Aabb>();
+        }
+    }
+}
@@ -0,0 +1,430 @@
+use bevy::{
+    math::{DQuat, DVec3},
+    prelude::*,
+};
+use bevy_terrain::{
+    big_space::{FloatingOrigin, GridTransform, GridTransformReadOnly, ReferenceFrames},
+    prelude::*,
+};
+use itertools::Itertools;
+use std::f64::consts::TAU;
+
+use crate::{
+    clock::SimulationTime,
+    draw::PrecisionGizmos,
+    epoch::{shift_model, Rebase},
+    geodesy::{geodesic, Geodetic},
+    model_copy::ModelCopy,
+};
+
+pub const EARTH_GRAVITATIONAL_PARAMETER: f64 = 3.986004418e14;
+
+// segments the path starts with, before those close to the view are subdivided
+const PATH_SEGMENTS: usize = 64;
+const MAX_PATH_SUBDIVISIONS: u32 = 24;
+
+// sub-satellite points sampled across the window of a ground track, and the geodesic segments between each pair
+const GROUND_TRACK_SAMPLES: usize = 256;
+const GROUND_TRACK_SEGMENTS: usize = 4;
+
+// Keplerian elements of an orbit around a fixed center.
+// The reference plane is the xz plane of the world, with the y axis as the pole, matching the terrain models.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+pub struct Orbit {
+    pub center: DVec3,
+    pub gravitational_parameter: f64,
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+    pub inclination: f64,
+    pub longitude_of_ascending_node: f64,
+    pub argument_of_periapsis: f64,
+    pub mean_anomaly_at_epoch: f64,
+}
+
+impl Orbit {
+    pub fn circular(center: DVec3, radius: f64, inclination: f64) -> Self {
+        Self {
+            center,
+            gravitational_parameter: EARTH_GRAVITATIONAL_PARAMETER,
+            semi_major_axis: radius,
+            eccentricity: 0.0,
+            inclination,
+            longitude_of_ascending_node: 0.0,
+            argument_of_periapsis: 0.0,
+            mean_anomaly_at_epoch: 0.0,
+        }
+    }
+
+    pub fn period(&self) -> f64 {
+        TAU * (self.semi_major_axis.powi(3) / self.gravitational_parameter).sqrt()
+    }
+
+    pub fn mean_motion(&self) -> f64 {
+        (self.gravitational_parameter / self.semi_major_axis.powi(3)).sqrt()
+    }
+
+    pub fn eccentric_anomaly(&self, time: f64) -> f64 {
+        let mean_anomaly = (self.mean_anomaly_at_epoch + self.mean_motion() * time).rem_euclid(TAU);
+
+        // solve Kepler's equation M = E - e sin(E) with Newton's method
+        let mut eccentric_anomaly = if self.eccentricity < 0.8 {
+            mean_anomaly
+        } else {
+            std::f64::consts::PI
+        };
+
+        for _ in 0..16 {
+            let delta =
+                (eccentric_anomaly - self.eccentricity * eccentric_anomaly.sin() - mean_anomaly)
+                    / (1.0 - self.eccentricity * eccentric_anomaly.cos());
+            eccentric_anomaly -= delta;
+
+            if delta.abs() < 1e-14 {
+                break;
+            }
+        }
+
+        eccentric_anomaly
+    }
+
+    pub fn orientation(&self) -> DQuat {
+        DQuat::from_rotation_y(self.longitude_of_ascending_node)
+            * DQuat::from_rotation_x(self.inclination)
+            * DQuat::from_rotation_y(self.argument_of_periapsis)
+    }
+
+    pub fn relative_position(&self, time: f64) -> DVec3 {
+        self.relative_position_at_anomaly(self.eccentric_anomaly(time))
+    }
+
+    pub fn relative_position_at_anomaly(&self, eccentric_anomaly: f64) -> DVec3 {
+        let e = self.eccentricity;
+
+        let true_anomaly = 2.0
+            * ((1.0 + e).sqrt() * (0.5 * eccentric_anomaly).sin())
+                .atan2((1.0 - e).sqrt() * (0.5 * eccentric_anomaly).cos());
+        let radius = self.semi_major_axis * (1.0 - e * eccentric_anomaly.cos());
+
+        self.orientation() * (DQuat::from_rotation_y(true_anomaly) * DVec3::X * radius)
+    }
+
+    pub fn position(&self, time: f64) -> DVec3 {
+        self.center + self.relative_position(time)
+    }
+}
+
+impl Rebase for Orbit {
+    fn rebase(&mut self, offset: DVec3) {
+        self.center += offset;
+    }
+}
+
+pub fn propagate_orbits(
+    time: Res<SimulationTime>,
+    frames: ReferenceFrames,
+    mut orbit_query: Query<(Entity, &Orbit, GridTransform)>,
+) {
+    for (entity, orbit, mut grid_transform) in &mut orbit_query {
+        let Some(frame) = frames.parent_frame(entity) else {
+            warn_once!(
+                "Skipping propagate_orbits for {entity:?}: it is not part of a reference frame."
+            );
+            continue;
+        };
+        let (cell, translation) =
+            frame.translation_to_grid(orbit.position(time.elapsed_seconds_wrapped(orbit.period())));
+
+        *grid_transform.cell = cell;
+        grid_transform.transform.translation = translation;
+    }
+}
+
+// Draws the path of the orbit as a closed line, which the body follows.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct OrbitPath {
+    pub color: Color,
+    // angle a segment may span, seen from the view, before it is subdivided
+    pub max_angle: f64,
+}
+
+impl OrbitPath {
+    pub fn new(color: impl Into<Color>) -> Self {
+        Self {
+            color: color.into(),
+            max_angle: 0.002,
+        }
+    }
+}
+
+// Samples the ellipse of the orbit in f64, with segments that appear about equally long from the view.
+// A uniform sampling would either need millions of points, or show the path cutting through the body
+// when viewed from close by, since a chord of a large orbit deviates from the ellipse by kilometers.
+pub fn orbit_path(orbit: &Orbit, view_position: DVec3, max_angle: f64) -> Vec<DVec3> {
+    let position = |anomaly: f64| orbit.center + orbit.relative_position_at_anomaly(anomaly);
+
+    fn subdivide(
+        position: &impl Fn(f64) -> DVec3,
+        view_position: DVec3,
+        max_angle: f64,
+        (start, end): (f64, f64),
+        depth: u32,
+        path: &mut Vec<DVec3>,
+    ) {
+        let (a, b) = (position(start), position(end));
+        let middle = 0.5 * (start + end);
+        let distance = view_position.distance(position(middle)).max(1.0);
+
+        if depth < MAX_PATH_SUBDIVISIONS && a.distance(b) > max_angle * distance {
+            subdivide(
+                position,
+                view_position,
+                max_angle,
+                (start, middle),
+                depth + 1,
+                path,
+            );
+            subdivide(
+                position,
+                view_position,
+                max_angle,
+                (middle, end),
+                depth + 1,
+                path,
+            );
+        } else {
+            path.push(b);
+        }
+    }
+
+    let mut path = vec![position(0.0)];
+
+    for segment in 0..PATH_SEGMENTS {
+        let start = TAU * segment as f64 / PATH_SEGMENTS as f64;
+        let end = TAU * (segment + 1) as f64 / PATH_SEGMENTS as f64;
+
+        subdivide(
+            &position,
+            view_position,
+            max_angle,
+            (start, end),
+            0,
+            &mut path,
+        );
+    }
+
+    path
+}
+
+// The path is drawn relative to the view, so the points close to it keep their full precision
+// and the line stays on the body, instead of wobbling around it as the view moves.
+pub fn draw_orbits(
+    mut gizmos: Gizmos<PrecisionGizmos>,
+    frames: ReferenceFrames,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+    orbit_query: Query<(&Orbit, &OrbitPath)>,
+) {
+    if !gizmos.config_ext.orbits {
+        return;
+    }
+
+    let Ok((view, view_transform)) = view_query.get_single() else {
+        warn_once!("Skipping draw_orbits: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping draw_orbits: the camera is not part of a reference frame.");
+        return;
+    };
+    let view_position = view_transform.position_double(&frame);
+
+    for (orbit, path) in &orbit_query {
+        gizmos.linestrip(
+            orbit_path(orbit, view_position, path.max_angle)
+                .into_iter()
+                .map(|point| {
+                    view_transform.transform.translation + (point - view_position).as_vec3()
+                }),
+            path.color,
+        );
+    }
+}
+
+// Draws the ground track of the orbit, the path of the point on the ellipsoid directly below the body,
+// from `before` seconds in the past to `after` seconds in the future.
+// The planet rotates with the rate around its pole, the track is drawn on the surface as it is oriented now.
+#[derive(Component, Clone, Debug)]
+pub struct GroundTrack {
+    pub model: TerrainModel,
+    pub before: f64,
+    pub after: f64,
+    // angular velocity of the planet in radians per second
+    pub rotation_rate: f64,
+    pub color: Color,
+}
+
+impl GroundTrack {
+    pub fn new(model: TerrainModel, window: f64, color: impl Into<Color>) -> Self {
+        Self {
+            model,
+            before: 0.5 * window,
+            after: 0.5 * window,
+            rotation_rate: 0.0,
+            color: color.into(),
+        }
+    }
+
+    // The geodetic position below the body at the time, on the surface as it is oriented at the current time.
+    pub fn sub_satellite_point(&self, orbit: &Orbit, time: f64, now: f64) -> Geodetic {
+        let pole = self
+            .model
+            .world_from_local
+            .transform_vector3(DVec3::Y)
+            .normalize();
+        let rotation = DQuat::from_axis_angle(pole, -self.rotation_rate * (time - now));
+        let center = self.model.position();
+
+        let position = center + rotation * (orbit.position(time) - center);
+
+        Geodetic {
+            height: 0.0,
+            ..Geodetic::from_world_position(position, &self.model)
+        }
+    }
+
+    // Sub-satellite points across the window, connected by geodesics on the ellipsoid.
+    pub fn path(&self, orbit: &Orbit, now: f64) -> Vec<DVec3> {
+        let points = (0..=GROUND_TRACK_SAMPLES).map(|sample| {
+            let t = sample as f64 / GROUND_TRACK_SAMPLES as f64;
+            let time = now - self.before + t * (self.before + self.after);
+
+            self.sub_satellite_point(orbit, time, now)
+        });
+
+        let mut path = Vec::new();
+
+        for (start, end) in points.tuple_windows() {
+            // the first point of each geodesic is the last one of the previous
+            path.extend(
+                geodesic(start, end, GROUND_TRACK_SEGMENTS, &self.model)
+                    .skip(!path.is_empty() as usize),
+            );
+        }
+
+        path
+    }
+}
+
+impl ModelCopy for GroundTrack {
+    fn set_model(&mut self, model: &TerrainModel) {
+        self.model = model.clone();
+    }
+}
+
+impl Rebase for GroundTrack {
+    fn rebase(&mut self, offset: DVec3) {
+        self.model = shift_model(&self.model, offset);
+    }
+}
+
+// Drawn relative to the view like the orbits, so the track stays on the surface close to the view.
+// Only the time relative to now enters the rotation of the planet, so the wrapped time is as good as the full one.
+pub fn draw_ground_tracks(
+    mut gizmos: Gizmos<PrecisionGizmos>,
+    time: Res<SimulationTime>,
+    frames: ReferenceFrames,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+    track_query: Query<(&Orbit, &GroundTrack)>,
+) {
+    if !gizmos.config_ext.orbits {
+        return;
+    }
+
+    let Ok((view, view_transform)) = view_query.get_single() else {
+        warn_once!("Skipping draw_ground_tracks: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping draw_ground_tracks: the camera is not part of a reference frame.");
+        return;
+    };
+    let view_position = view_transform.position_double(&frame);
+
+    for (orbit, track) in &track_query {
+        gizmos.linestrip(
+            track
+                .path(orbit, time.elapsed_seconds_wrapped(orbit.period()))
+                .into_iter()
+                .map(|point| {
+                    view_transform.transform.translation + (point - view_position).as_vec3()
+                }),
+            track.color,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn orbit(eccentricity: f64) -> Orbit {
+        Orbit {
+            center: DVec3::new(1000.0, -200.0, 30.0),
+            gravitational_parameter: EARTH_GRAVITATIONAL_PARAMETER,
+            semi_major_axis: 7.0e6 / (1.0 - eccentricity),
+            eccentricity,
+            inclination: 0.9,
+            longitude_of_ascending_node: 0.3,
+            argument_of_periapsis: 1.2,
+            mean_anomaly_at_epoch: 0.5,
+        }
+    }
+
+    fn times(orbit: &Orbit) -> impl Iterator<Item = f64> + '_ {
+        (0..100).map(|step| orbit.period() * step as f64 / 97.0)
+    }
+
+    #[test]
+    fn circular_eccentric_anomaly_is_the_mean_anomaly() {
+        let orbit = orbit(0.0);
+
+        for time in times(&orbit) {
+            let mean_anomaly =
+                (orbit.mean_anomaly_at_epoch + orbit.mean_motion() * time).rem_euclid(TAU);
+
+            assert_eq!(orbit.eccentric_anomaly(time), mean_anomaly);
+        }
+    }
+
+    #[test]
+    fn highly_eccentric_anomaly_converges() {
+        let orbit = orbit(0.99);
+
+        for time in times(&orbit) {
+            let mean_anomaly =
+                (orbit.mean_anomaly_at_epoch + orbit.mean_motion() * time).rem_euclid(TAU);
+            let eccentric_anomaly = orbit.eccentric_anomaly(time);
+            let residual =
+                eccentric_anomaly - orbit.eccentricity * eccentric_anomaly.sin() - mean_anomaly;
+
+            assert!(residual.abs() < 1e-12, "{residual} at {time} s");
+        }
+    }
+
+    #[test]
+    fn position_repeats_after_one_period() {
+        for eccentricity in [0.0, 0.3, 0.7] {
+            let orbit = orbit(eccentricity);
+
+            for time in times(&orbit) {
+                let start = orbit.position(time);
+                let deviation = orbit.position(time + orbit.period()).distance(start);
+
+                assert!(
+                    deviation < 1e-6,
+                    "{deviation} m at {time} s with an eccentricity of {eccentricity}"
+                );
+            }
+        }
+    }
+}
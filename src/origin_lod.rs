@@ -0,0 +1,222 @@
+use bevy::{
+    math::{DVec2, DVec3},
+    prelude::*,
+};
+use bevy_terrain::{math::Coordinate, prelude::*};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::ops::Range;
+
+use crate::math::{
+    approximate_world_position, approximations, tile_coordinate_from_world_position,
+    view_coordinates,
+};
+
+// deepest origin lod that is searched, the tile offsets are no longer exact in f32 beyond it
+pub const MAX_ORIGIN_LOD: u32 = 24;
+
+const ALTITUDE_STEPS_PER_DECADE: u32 = 2;
+const VIEW_SAMPLES: u32 = 16;
+const SURFACE_SAMPLES: u32 = 32;
+
+// Smallest origin lod that meets the error budget, for altitudes increasing logarithmically.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct OriginLodTable {
+    pub budget: f64,
+    // (altitude, origin lod), sorted by altitude
+    pub entries: Vec<(f64, u32)>,
+}
+
+impl OriginLodTable {
+    // Picks the larger lod of the two entries around the altitude, so the budget holds in between as well.
+    pub fn origin_lod(&self, altitude: f64) -> Option<u32> {
+        let index = self
+            .entries
+            .partition_point(|&(entry_altitude, _)| entry_altitude <= altitude);
+
+        let below = index.checked_sub(1).map(|index| self.entries[index].1);
+        let above = self.entries.get(index).map(|&(_, lod)| lod);
+
+        below.max(above)
+    }
+}
+
+// Maximum second order error of surface samples within the radius around ground positions below the view.
+// The samples are drawn from the same seed on every call, so all lods and altitudes are compared on the same positions.
+fn max_error(
+    model: &TerrainModel,
+    altitude: f64,
+    sample_radius: f64,
+    origin_lod: u32,
+    seed: u64,
+) -> f64 {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut max_error: f64 = 0.0;
+
+    for _ in 0..VIEW_SAMPLES {
+        let ground = Coordinate::new(
+            rng.gen_range(0..6),
+            DVec2::new(rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0)),
+        );
+        let ground_position = ground.world_position(model, 0.0);
+        let view_position = ground.world_position(model, altitude as f32);
+
+        let view_coordinates = view_coordinates(view_position, model);
+        let approximations = approximations(&view_coordinates, view_position, model);
+
+        for _ in 0..SURFACE_SAMPLES {
+            let direction = DVec3::new(
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+            )
+            .normalize();
+            let offset = rng.gen_range(0.0..1.0) * sample_radius * direction;
+
+            let surface_position = model.position_local_to_world(
+                model.position_world_to_local(ground_position + offset),
+                0.0,
+            );
+
+            let approximate_position = approximate_world_position(
+                &view_coordinates,
+                &approximations,
+                origin_lod,
+                true,
+                view_position,
+                tile_coordinate_from_world_position(surface_position, origin_lod, model),
+            );
+
+            max_error = max_error.max(surface_position.distance(approximate_position));
+        }
+    }
+
+    max_error
+}
+
+// Binary searches the smallest origin lod, whose maximum error stays within the budget, at each altitude of the range.
+// This assumes that the error decreases with the origin lod, which holds up to `MAX_ORIGIN_LOD`.
+// Altitudes where no lod meets the budget fall back to `MAX_ORIGIN_LOD`.
+// The seed of the samples is drawn from the rng, e.g. the `SimulationRng`, so seeded runs find the same table.
+pub fn find_min_origin_lod(
+    model: &TerrainModel,
+    altitude_range: Range<f64>,
+    sample_radius: f64,
+    budget: f64,
+    rng: &mut impl Rng,
+) -> OriginLodTable {
+    let seed = rng.gen();
+
+    debug_assert!(
+        altitude_range.start > 0.0,
+        "the altitudes are spaced logarithmically"
+    );
+
+    let (start, end) = (altitude_range.start.log10(), altitude_range.end.log10());
+    let steps = ((end - start) * ALTITUDE_STEPS_PER_DECADE as f64)
+        .ceil()
+        .max(1.0) as u32;
+
+    let entries = (0..=steps)
+        .map(|step| {
+            let altitude = 10.0_f64.powf(start + (end - start) * step as f64 / steps as f64);
+
+            let (mut low, mut high) = (0, MAX_ORIGIN_LOD);
+
+            while low < high {
+                let lod = (low + high) / 2;
+
+                if max_error(model, altitude, sample_radius, lod, seed) <= budget {
+                    high = lod;
+                } else {
+                    low = lod + 1;
+                }
+            }
+
+            (altitude, low)
+        })
+        .collect();
+
+    OriginLodTable { budget, entries }
+}
+
+// Blends the positions evaluated at the previous origin lod into the ones at the current lod over a few frames.
+// The relative uvs are rounded differently at each lod, so switching at once shifts the geometry for a frame.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct OriginLodTransition {
+    pub origin_lod: u32,
+    // lod the geometry is blended from, until the transition completes
+    pub previous: Option<u32>,
+    pub frames: u32,
+    frame: u32,
+}
+
+impl OriginLodTransition {
+    pub fn new(origin_lod: u32) -> Self {
+        Self {
+            origin_lod,
+            previous: None,
+            frames: 8,
+            frame: 0,
+        }
+    }
+
+    // Starts a transition towards the new lod, a transition in progress restarts from the lod it was heading to.
+    pub fn follow(&mut self, origin_lod: u32) {
+        if origin_lod == self.origin_lod {
+            return;
+        }
+
+        self.previous = Some(self.origin_lod).filter(|_| self.frames > 0);
+        self.origin_lod = origin_lod;
+        self.frame = 0;
+    }
+
+    pub fn advance(&mut self) {
+        self.frame += 1;
+
+        if self.frame >= self.frames {
+            self.previous = None;
+        }
+    }
+
+    pub fn is_blending(&self) -> bool {
+        self.previous.is_some()
+    }
+
+    // weight of the current lod, eased so that the geometry starts and stops moving smoothly
+    pub fn weight(&self) -> f64 {
+        if self.previous.is_none() {
+            return 1.0;
+        }
+
+        let t = self.frame as f64 / self.frames as f64;
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    // Blends the position evaluated at both lods, or evaluates it only once outside of a transition.
+    pub fn blend(&self, position: impl Fn(u32) -> DVec3) -> DVec3 {
+        let current = position(self.origin_lod);
+
+        match self.previous {
+            Some(previous) => position(previous).lerp(current, self.weight()),
+            None => current,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_runs_find_the_same_table() {
+        let model = TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0);
+        let table = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            find_min_origin_lod(&model, 1.0..1e4, 6378.137, 0.01, &mut rng).entries
+        };
+
+        assert_eq!(table(7), table(7));
+    }
+}
@@ -0,0 +1,193 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+use bevy_terrain::big_space::{
+    FloatingOrigin, GridTransform, GridTransformReadOnly, ReferenceFrames,
+};
+use rand::Rng;
+
+use crate::{
+    deterministic::SimulationRng,
+    draw::PrecisionGizmos,
+    grid::{grid_offset, rebase_translation},
+};
+
+pub const PARTICLE_REBASES: DiagnosticPath = DiagnosticPath::const_new("particles/rebases");
+// largest distance a particle moved by being rebased into another cell, which should only be rounding
+pub const PARTICLE_REBASE_ERROR: DiagnosticPath =
+    DiagnosticPath::const_new("particles/rebase_error");
+
+struct Particle {
+    offset: Vec3,
+    velocity: Vec3,
+}
+
+// Dust drifting around the view, as an example of an effect that stays jitter free with a floating origin.
+// The particles are f32 offsets to the grid cell of the cloud, which follows the cell of the view.
+// Whenever the view crosses into another cell, all offsets are rebased by the exact cell difference,
+// so the offsets stay small and the particles do not jump, no matter how far the view has travelled.
+// The translation of the cloud is kept at zero, its cell is all that places it.
+#[derive(Component)]
+pub struct ParticleCloud {
+    pub count: usize,
+    // particles leaving the sphere of this radius around the view are respawned inside it
+    pub radius: f32,
+    pub speed: f32,
+    pub color: Color,
+    particles: Vec<Particle>,
+    pub rebases: u64,
+}
+
+impl ParticleCloud {
+    pub fn new(count: usize, radius: f32, color: impl Into<Color>) -> Self {
+        Self {
+            count,
+            radius,
+            speed: 0.5,
+            color: color.into(),
+            particles: Vec::new(),
+            rebases: 0,
+        }
+    }
+
+    fn spawn_particle(&self, rng: &mut impl Rng, center: Vec3) -> Particle {
+        let random_direction = |rng: &mut dyn rand::RngCore| {
+            Vec3::new(
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+            )
+            .normalize_or_zero()
+        };
+
+        // uniformly distributed inside the sphere
+        let distance = self.radius * rng.gen_range(0.0_f32..1.0).cbrt();
+
+        Particle {
+            offset: center + random_direction(rng) * distance,
+            velocity: random_direction(rng) * self.speed,
+        }
+    }
+}
+
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationRng>()
+            .register_diagnostic(Diagnostic::new(PARTICLE_REBASES))
+            .register_diagnostic(Diagnostic::new(PARTICLE_REBASE_ERROR).with_suffix(" m"))
+            .add_systems(Update, (update_particles, draw_particles).chain());
+    }
+}
+
+fn update_particles(
+    time: Res<Time>,
+    mut rng: ResMut<SimulationRng>,
+    frames: ReferenceFrames,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+    mut cloud_query: Query<(Entity, &mut ParticleCloud, GridTransform), Without<FloatingOrigin>>,
+    mut diagnostics: Diagnostics,
+) {
+    let Ok((view, view_transform)) = view_query.get_single() else {
+        warn_once!("Skipping update_particles: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping update_particles: the camera is not part of a reference frame.");
+        return;
+    };
+
+    let delta_time = time.delta_seconds();
+
+    for (entity, mut cloud, mut cloud_transform) in &mut cloud_query {
+        if frames.parent_frame(entity).is_none() {
+            warn_once!(
+                "Skipping update_particles for {entity:?}: it is not part of a reference frame."
+            );
+            continue;
+        }
+
+        let cloud = &mut *cloud;
+
+        if *cloud_transform.cell != *view_transform.cell {
+            let from_cell = *cloud_transform.cell;
+            let to_cell = *view_transform.cell;
+            let mut max_error: f64 = 0.0;
+
+            for particle in &mut cloud.particles {
+                let before = grid_offset(
+                    &frame,
+                    (&to_cell, &Transform::IDENTITY),
+                    (&from_cell, &Transform::from_translation(particle.offset)),
+                );
+
+                particle.offset = rebase_translation(&frame, &from_cell, &to_cell, particle.offset);
+
+                max_error = max_error.max(before.distance(particle.offset.as_dvec3()));
+            }
+
+            *cloud_transform.cell = to_cell;
+            cloud_transform.transform.translation = Vec3::ZERO;
+            cloud.rebases += 1;
+
+            diagnostics.add_measurement(&PARTICLE_REBASE_ERROR, || max_error);
+        }
+
+        // the view and the cloud share the cell now, so the offsets are relative to the same origin
+        let center = view_transform.transform.translation;
+
+        cloud.particles.truncate(cloud.count);
+        while cloud.particles.len() < cloud.count {
+            let particle = cloud.spawn_particle(&mut rng.0, center);
+            cloud.particles.push(particle);
+        }
+
+        for index in 0..cloud.particles.len() {
+            let particle = &mut cloud.particles[index];
+            particle.offset += particle.velocity * delta_time;
+
+            if particle.offset.distance(center) > cloud.radius {
+                cloud.particles[index] = cloud.spawn_particle(&mut rng.0, center);
+            }
+        }
+
+        diagnostics.add_measurement(&PARTICLE_REBASES, || cloud.rebases as f64);
+    }
+}
+
+// The particles are drawn as short streaks along their velocity, relative to the view like everything else.
+fn draw_particles(
+    mut gizmos: Gizmos<PrecisionGizmos>,
+    frames: ReferenceFrames,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+    cloud_query: Query<(&ParticleCloud, GridTransformReadOnly), Without<FloatingOrigin>>,
+) {
+    if !gizmos.config_ext.particles {
+        return;
+    }
+
+    let Ok((view, view_transform)) = view_query.get_single() else {
+        warn_once!("Skipping draw_particles: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping draw_particles: the camera is not part of a reference frame.");
+        return;
+    };
+
+    for (cloud, cloud_transform) in &cloud_query {
+        let offset = grid_offset(
+            &frame,
+            (view_transform.cell, view_transform.transform),
+            (cloud_transform.cell, cloud_transform.transform),
+        );
+        let origin = view_transform.transform.translation + offset.as_vec3();
+
+        for particle in &cloud.particles {
+            let position = origin + particle.offset;
+            gizmos.line(position, position + 0.05 * particle.velocity, cloud.color);
+        }
+    }
+}
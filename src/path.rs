@@ -0,0 +1,208 @@
+use bevy::math::DVec3;
+use bevy_terrain::{math::Coordinate, prelude::*};
+
+// subdivisions per segment of the table the arc length is looked up in
+const ARC_SAMPLES: usize = 16;
+
+// A smooth path through control points in f64, made of cubic Hermite segments.
+// Each segment is evaluated as an offset to its start point, so paths across a planet stay precise to the
+// f64 rounding of their control points, instead of the rounding of a weighted sum of absolute positions.
+// The path is parameterized by the segment index plus the fraction within the segment,
+// and by the arc length through a table of the cumulative length along each segment.
+#[derive(Clone, Debug, Default)]
+pub struct DPath {
+    points: Vec<DVec3>,
+    tangents: Vec<DVec3>,
+    // cumulative length at each subdivision, ARC_SAMPLES per segment
+    lengths: Vec<f64>,
+}
+
+impl DPath {
+    // Hermite segments with the given tangents, the derivatives with respect to the segment parameter.
+    pub fn hermite(points: Vec<DVec3>, tangents: Vec<DVec3>) -> Self {
+        assert_eq!(
+            points.len(),
+            tangents.len(),
+            "every control point needs a tangent"
+        );
+
+        let mut path = Self {
+            points,
+            tangents,
+            lengths: Vec::new(),
+        };
+
+        let mut length = 0.0;
+        let mut previous = path.position(0.0);
+        path.lengths.push(0.0);
+
+        for sample in 1..=path.segments() * ARC_SAMPLES {
+            let position = path.position(sample as f64 / ARC_SAMPLES as f64);
+            length += position.distance(previous);
+            previous = position;
+            path.lengths.push(length);
+        }
+
+        path
+    }
+
+    // Catmull-Rom spline through the points, the end points repeat themselves as their outer neighbors.
+    pub fn catmull_rom(points: Vec<DVec3>) -> Self {
+        let last = points.len().saturating_sub(1);
+        let tangents = (0..points.len())
+            .map(|index| 0.5 * (points[(index + 1).min(last)] - points[index.saturating_sub(1)]))
+            .collect();
+
+        Self::hermite(points, tangents)
+    }
+
+    // Catmull-Rom spline through the coordinates, placed at the height above the surface.
+    pub fn from_coordinates(coordinates: &[Coordinate], height: f32, model: &TerrainModel) -> Self {
+        Self::catmull_rom(
+            coordinates
+                .iter()
+                .map(|coordinate| coordinate.world_position(model, height))
+                .collect(),
+        )
+    }
+
+    pub fn points(&self) -> &[DVec3] {
+        &self.points
+    }
+
+    pub fn segments(&self) -> usize {
+        self.points.len().saturating_sub(1)
+    }
+
+    // the segment containing the parameter, and the fraction within it
+    fn segment(&self, parameter: f64) -> (usize, f64) {
+        let segments = self.segments();
+        let parameter = parameter.clamp(0.0, segments as f64);
+        let index = (parameter.floor() as usize).min(segments.saturating_sub(1));
+
+        (index, parameter - index as f64)
+    }
+
+    // Position at the parameter, which runs from 0 at the first to the number of segments at the last control point.
+    pub fn position(&self, parameter: f64) -> DVec3 {
+        if self.segments() == 0 {
+            return self.points.first().copied().unwrap_or_default();
+        }
+
+        let (index, t) = self.segment(parameter);
+        let (p0, p1) = (self.points[index], self.points[index + 1]);
+        let (m0, m1) = (self.tangents[index], self.tangents[index + 1]);
+
+        let (t2, t3) = (t * t, t * t * t);
+        let h10 = t3 - 2.0 * t2 + t;
+        let h01 = -2.0 * t3 + 3.0 * t2;
+        let h11 = t3 - t2;
+
+        // h00 + h01 = 1, so the start point only enters once
+        p0 + (p1 - p0) * h01 + m0 * h10 + m1 * h11
+    }
+
+    // Derivative of the position with respect to the parameter.
+    pub fn velocity(&self, parameter: f64) -> DVec3 {
+        if self.segments() == 0 {
+            return DVec3::ZERO;
+        }
+
+        let (index, t) = self.segment(parameter);
+        let (p0, p1) = (self.points[index], self.points[index + 1]);
+        let (m0, m1) = (self.tangents[index], self.tangents[index + 1]);
+
+        let t2 = t * t;
+        let d10 = 3.0 * t2 - 4.0 * t + 1.0;
+        let d01 = -6.0 * t2 + 6.0 * t;
+        let d11 = 3.0 * t2 - 2.0 * t;
+
+        (p1 - p0) * d01 + m0 * d10 + m1 * d11
+    }
+
+    pub fn length(&self) -> f64 {
+        self.lengths.last().copied().unwrap_or(0.0)
+    }
+
+    // The parameter at the arc length along the path, interpolated linearly between the subdivisions of the table.
+    pub fn parameter_at_distance(&self, distance: f64) -> f64 {
+        if self.lengths.len() < 2 {
+            return 0.0;
+        }
+
+        let distance = distance.clamp(0.0, self.length());
+        let sample = self
+            .lengths
+            .partition_point(|&length| length <= distance)
+            .clamp(1, self.lengths.len() - 1);
+        let (start, end) = (self.lengths[sample - 1], self.lengths[sample]);
+        let t = ((distance - start) / (end - start).max(f64::EPSILON)).clamp(0.0, 1.0);
+
+        (sample - 1) as f64 / ARC_SAMPLES as f64 + t / ARC_SAMPLES as f64
+    }
+
+    // Position at the arc length along the path, so that evenly spaced distances move at a constant speed.
+    pub fn at_distance(&self, distance: f64) -> DVec3 {
+        self.position(self.parameter_at_distance(distance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_2;
+
+    const RADIUS: f64 = 6371000.0;
+    const SEGMENTS: usize = 12;
+
+    // Point on a great circle around a center away from the origin, in a tilted plane, and its derivative.
+    fn great_circle(angle: f64) -> (DVec3, DVec3) {
+        let center = DVec3::new(1e6, -2e6, 5e5);
+        let u = DVec3::new(1.0, 1.0, 0.0).normalize();
+        let v = DVec3::new(-1.0, 1.0, 2.0).normalize();
+
+        (
+            center + RADIUS * (u * angle.cos() + v * angle.sin()),
+            RADIUS * (v * angle.cos() - u * angle.sin()),
+        )
+    }
+
+    // Hermite segments along a quarter of the great circle, with the exact derivatives as tangents.
+    fn great_circle_path() -> DPath {
+        let step = FRAC_PI_2 / SEGMENTS as f64;
+        let (points, tangents) = (0..=SEGMENTS)
+            .map(|index| {
+                let (point, derivative) = great_circle(index as f64 * step);
+
+                (point, derivative * step)
+            })
+            .unzip();
+
+        DPath::hermite(points, tangents)
+    }
+
+    #[test]
+    fn length_matches_the_great_circle() {
+        let path = great_circle_path();
+        let length = RADIUS * FRAC_PI_2;
+
+        // the cubic segments and the chords of the table cut the arc short by about 3e-6
+        let relative_error = (path.length() - length).abs() / length;
+        assert!(relative_error < 1e-5, "{relative_error}");
+    }
+
+    #[test]
+    fn evenly_spaced_distances_sample_the_great_circle_evenly() {
+        let path = great_circle_path();
+
+        for sample in 0..=100 {
+            let fraction = sample as f64 / 100.0;
+            let position = path.at_distance(fraction * path.length());
+            let (expected, _) = great_circle(fraction * FRAC_PI_2);
+
+            // the cubic segments bulge away from the circle by up to 5 m
+            let error = position.distance(expected);
+            assert!(error < 10.0, "{error} m at {fraction}");
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use bevy::{
+    math::{DVec2, U64Vec2},
+    prelude::*,
+};
+use bevy_terrain::math::{Coordinate, TileCoordinate};
+use itertools::iproduct;
+
+use crate::math::{cube_to_sphere, sphere_to_cube, tile_count};
+
+// Snaps the probe to the vertices of tile meshes with the resolution at the lod.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VertexSnap {
+    pub lod: u32,
+    pub resolution: u32,
+}
+
+impl VertexSnap {
+    // vertex spacing in uv space of the face
+    pub fn spacing(&self) -> f64 {
+        1.0 / (tile_count(self.lod) * self.resolution as u64) as f64
+    }
+}
+
+// Probes the grids of the lods around the origin lod at once, each spaced a quarter tile of its lod apart.
+// Coarser grids reach further away from the probe, so the first lod whose grid exceeds the threshold
+// bounds the range of lods, whose tiles the current approximation still places precisely enough.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LodHierarchy {
+    // lods probed below and above the origin lod
+    pub spread: u32,
+    pub threshold: f64,
+}
+
+// A point on the surface, around which the approximation is inspected in detail.
+#[derive(Resource, Default)]
+pub struct Probe {
+    pub coordinate: Option<Coordinate>,
+    pub snap: Option<VertexSnap>,
+    pub hierarchy: Option<LodHierarchy>,
+}
+
+impl Probe {
+    pub fn set(&mut self, coordinate: Coordinate) {
+        self.coordinate = Some(match self.snap {
+            Some(snap) => {
+                let (tile, tile_uv) = snap_to_vertex(coordinate, snap);
+                Coordinate::new(tile.face, tile_uv_to_uv(tile, tile_uv))
+            }
+            None => coordinate,
+        });
+    }
+
+    // Moves the probe by the step in uv space, the direction is given in the uv axes of its current face.
+    // Steps beyond the face edges continue on the adjacent face, whose uv axes may be oriented differently.
+    pub fn step(&mut self, direction: IVec2, step: f64) {
+        let Some(coordinate) = self.coordinate else {
+            return;
+        };
+
+        let uv = coordinate.uv + direction.as_dvec2() * step;
+
+        if uv.cmpge(DVec2::ZERO).all() && uv.cmple(DVec2::ONE).all() {
+            self.set(Coordinate::new(coordinate.face, uv));
+        } else {
+            // the mapping extends past the face edge, which is enough for steps of up to a tile
+            let (face, uv) = sphere_to_cube(cube_to_sphere(coordinate.face, uv));
+            self.set(Coordinate::new(face, uv));
+        }
+    }
+
+    // vertex spacing of the snap, or an eighth of an origin tile without one
+    pub fn vertex_step(&self, origin_lod: u32) -> f64 {
+        self.snap
+            .map_or(0.125 / tile_count(origin_lod) as f64, |snap| snap.spacing())
+    }
+
+    // The region around the probe as tile coordinates, spaced a quarter origin tile apart.
+    // With a snap, the region consists of the neighboring vertices instead,
+    // whose tile coordinates are exactly the ones the tile mesh would use.
+    pub fn region(&self, origin_lod: u32, radius: u32) -> Vec<(TileCoordinate, Vec2)> {
+        let Some(coordinate) = self.coordinate else {
+            return Vec::new();
+        };
+
+        match self.snap {
+            Some(snap) => probe_region(coordinate, snap.spacing(), radius)
+                .into_iter()
+                .map(|coordinate| snap_to_vertex(coordinate, snap))
+                .collect(),
+            None => probe_region(coordinate, 0.25 / tile_count(origin_lod) as f64, radius)
+                .into_iter()
+                .map(|coordinate| tile_at_lod(coordinate, origin_lod))
+                .collect(),
+        }
+    }
+
+    // The region around the probe in tiles of the lod, spaced a quarter tile apart regardless of the snap.
+    pub fn region_at_lod(&self, lod: u32, radius: u32) -> Vec<(TileCoordinate, Vec2)> {
+        let Some(coordinate) = self.coordinate else {
+            return Vec::new();
+        };
+
+        probe_region(coordinate, 0.25 / tile_count(lod) as f64, radius)
+            .into_iter()
+            .map(|coordinate| tile_at_lod(coordinate, lod))
+            .collect()
+    }
+}
+
+// Square grid of coordinates centered on the probe, with the spacing in uv space of its face.
+// Coordinates beyond the face edges are clamped onto the face.
+pub fn probe_region(coordinate: Coordinate, spacing: f64, radius: u32) -> Vec<Coordinate> {
+    let radius = radius as i32;
+
+    iproduct!(-radius..=radius, -radius..=radius)
+        .map(|(y, x)| {
+            let uv = coordinate.uv + DVec2::new(x as f64, y as f64) * spacing;
+
+            Coordinate::new(coordinate.face, uv.clamp(DVec2::ZERO, DVec2::ONE))
+        })
+        .collect()
+}
+
+// The tile at the lod containing the coordinate, the upper face edges belong to the last tile.
+pub fn tile_at_lod(coordinate: Coordinate, lod: u32) -> (TileCoordinate, Vec2) {
+    let count = tile_count(lod) as f64;
+    let uv = coordinate.uv * count;
+    let xy = uv.floor().min(DVec2::splat(count - 1.0)).max(DVec2::ZERO);
+
+    (
+        TileCoordinate::new(coordinate.face, lod, xy.x as u32, xy.y as u32),
+        (uv - xy).as_vec2(),
+    )
+}
+
+// The nearest vertex of the tile meshes, the vertices on tile edges are assigned to the lower tile when possible.
+pub fn snap_to_vertex(coordinate: Coordinate, snap: VertexSnap) -> (TileCoordinate, Vec2) {
+    let resolution = snap.resolution as u64;
+    let vertex_count = tile_count(snap.lod) * resolution;
+    let vertex = (coordinate.uv * vertex_count as f64)
+        .round()
+        .as_u64vec2()
+        .min(U64Vec2::splat(vertex_count));
+
+    // a vertex on the upper edge of a tile is the last vertex of that tile, instead of the first of the next one
+    let xy = vertex.saturating_sub(U64Vec2::ONE) / resolution;
+    let local = vertex - xy * resolution;
+
+    (
+        TileCoordinate::new(coordinate.face, snap.lod, xy.x as u32, xy.y as u32),
+        local.as_vec2() / resolution as f32,
+    )
+}
+
+pub fn tile_uv_to_uv(tile: TileCoordinate, tile_uv: Vec2) -> DVec2 {
+    (tile.xy().as_dvec2() + tile_uv.as_dvec2()) / tile_count(tile.lod) as f64
+}
@@ -0,0 +1,278 @@
+use bevy::{
+    math::{DMat4, DVec3},
+    prelude::*,
+};
+use bevy_terrain::{math::Coordinate, prelude::*};
+
+use crate::{
+    geodesy::{axes, Geodetic},
+    height::HeightProvider,
+};
+
+const MARCH_STEPS: u32 = 256;
+const REFINEMENT_STEPS: u32 = 48;
+
+#[derive(Clone, Copy)]
+pub struct Hit {
+    pub position: DVec3,
+    pub coordinate: Coordinate,
+    pub distance: f64,
+    pub height: f64,
+}
+
+// Intersects the ray with the ellipsoid of the model, inflated by the height.
+// Returns the ray parameters of the entry and exit points in units of the direction.
+pub fn ray_ellipsoid_intersection(
+    origin: DVec3,
+    direction: DVec3,
+    model: &TerrainModel,
+    height: f64,
+) -> Option<(f64, f64)> {
+    let (major_axis, minor_axis) = axes(model);
+
+    let world_from_shell = model.world_from_local
+        * DMat4::from_scale(DVec3::new(
+            (major_axis + height) / major_axis,
+            (minor_axis + height) / minor_axis,
+            (major_axis + height) / major_axis,
+        ));
+    let shell_from_world = world_from_shell.inverse();
+
+    // the affine transform preserves the ray parameter
+    let origin = shell_from_world.transform_point3(origin);
+    let direction = shell_from_world.transform_vector3(direction);
+
+    let a = direction.length_squared();
+    let b = 2.0 * origin.dot(direction);
+    let c = origin.length_squared() - 1.0;
+
+    let discriminant = b * b - 4.0 * a * c;
+
+    if a == 0.0 || discriminant < 0.0 {
+        return None;
+    }
+
+    let root = discriminant.sqrt();
+    let (t0, t1) = ((-b - root) / (2.0 * a), (-b + root) / (2.0 * a));
+
+    (t1 >= 0.0).then_some((t0.max(0.0), t1))
+}
+
+fn height_above_terrain(
+    position: DVec3,
+    model: &TerrainModel,
+    heights: &dyn HeightProvider,
+) -> f64 {
+    let altitude = Geodetic::from_world_position(position, model).height;
+    let coordinate = Coordinate::from_world_position(position, model);
+
+    altitude - heights.height(coordinate)
+}
+
+pub fn terrain_raycast(
+    origin: DVec3,
+    direction: DVec3,
+    model: &TerrainModel,
+    heights: &dyn HeightProvider,
+) -> Option<Hit> {
+    let direction = direction.normalize();
+    let (min_height, max_height) = heights.height_range();
+
+    // the terrain is contained between the ellipsoids at the minimum and maximum height
+    let (start, mut end) = ray_ellipsoid_intersection(origin, direction, model, max_height)?;
+
+    // without a height range, the terrain coincides with the shell and there is nothing to march through
+    if max_height <= min_height {
+        return hit(origin, direction, start, model, heights);
+    }
+
+    if let Some((inner_start, _)) = ray_ellipsoid_intersection(origin, direction, model, min_height)
+    {
+        end = end.min(inner_start.max(start));
+    }
+
+    let sample = |t: f64| height_above_terrain(origin + t * direction, model, heights);
+
+    let mut previous_t = start;
+
+    if sample(start) <= 0.0 {
+        return hit(origin, direction, start, model, heights);
+    }
+
+    for step in 1..=MARCH_STEPS {
+        let t = start + (end - start) * step as f64 / MARCH_STEPS as f64;
+
+        if sample(t) <= 0.0 {
+            // refine the crossing between the last two samples by bisection
+            let (mut low, mut high) = (previous_t, t);
+
+            for _ in 0..REFINEMENT_STEPS {
+                let middle = 0.5 * (low + high);
+
+                if sample(middle) > 0.0 {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+
+            return hit(origin, direction, 0.5 * (low + high), model, heights);
+        }
+
+        previous_t = t;
+    }
+
+    None
+}
+
+fn hit(
+    origin: DVec3,
+    direction: DVec3,
+    distance: f64,
+    model: &TerrainModel,
+    heights: &dyn HeightProvider,
+) -> Option<Hit> {
+    let position = origin + distance * direction;
+    let coordinate = Coordinate::from_world_position(position, model);
+
+    Some(Hit {
+        position,
+        coordinate,
+        distance,
+        height: heights.height(coordinate),
+    })
+}
+
+pub fn clamp_to_ground(
+    position: DVec3,
+    model: &TerrainModel,
+    heights: &dyn HeightProvider,
+) -> DVec3 {
+    let geodetic = Geodetic::from_world_position(position, model);
+    let coordinate = Coordinate::from_world_position(position, model);
+
+    Geodetic {
+        height: heights.height(coordinate),
+        ..geodetic
+    }
+    .world_position(model)
+}
+
+pub fn line_of_sight(
+    from: DVec3,
+    to: DVec3,
+    model: &TerrainModel,
+    heights: &dyn HeightProvider,
+) -> bool {
+    let distance = from.distance(to);
+
+    match terrain_raycast(from, to - from, model, heights) {
+        Some(hit) => hit.distance >= distance,
+        None => true,
+    }
+}
+
+// Returns the origin and direction of the ray through the viewport pixel of the camera.
+// Only the rotation of the camera enters the f32 math, the ray starts at its double precision position,
+// so the ray stays exact for cameras in far away grid cells.
+pub fn viewport_ray(
+    camera: &Camera,
+    view_transform: &Transform,
+    view_position: DVec3,
+    pixel: Vec2,
+) -> Option<(DVec3, DVec3)> {
+    let ray = camera.viewport_to_world(
+        &GlobalTransform::from(Transform::from_rotation(view_transform.rotation)),
+        pixel,
+    )?;
+
+    Some((
+        view_position + ray.origin.as_dvec3(),
+        Vec3::from(ray.direction).as_dvec3(),
+    ))
+}
+
+// Finds the terrain under the viewport pixel, for picking and cursor readouts.
+pub fn pick_terrain(
+    camera: &Camera,
+    view_transform: &Transform,
+    view_position: DVec3,
+    pixel: Vec2,
+    model: &TerrainModel,
+    heights: &dyn HeightProvider,
+) -> Option<Hit> {
+    let (origin, direction) = viewport_ray(camera, view_transform, view_position, pixel)?;
+
+    terrain_raycast(origin, direction, model, heights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::height::ConstantHeight;
+
+    const MAJOR_AXIS: f64 = 6378137.0;
+    const MINOR_AXIS: f64 = 6356752.314245;
+
+    fn earth() -> TerrainModel {
+        TerrainModel::ellipsoid(DVec3::ZERO, MAJOR_AXIS, MINOR_AXIS, 0.0, 0.0)
+    }
+
+    #[test]
+    fn raycast_against_constant_height() {
+        let model = earth();
+
+        for height in [0.0, 500.0, -200.0] {
+            let heights = ConstantHeight(height);
+
+            // straight down onto the equator and the pole, where the shell is at the height exactly
+            for (origin, expected) in [
+                (
+                    DVec3::new(MAJOR_AXIS + 1.0e6, 0.0, 0.0),
+                    DVec3::new(MAJOR_AXIS + height, 0.0, 0.0),
+                ),
+                (
+                    DVec3::new(0.0, MINOR_AXIS + 1.0e6, 0.0),
+                    DVec3::new(0.0, MINOR_AXIS + height, 0.0),
+                ),
+            ] {
+                let hit = terrain_raycast(origin, -origin, &model, &heights).unwrap();
+
+                assert!(
+                    hit.position.distance(expected) < 1e-6,
+                    "{} m off at a height of {height} m",
+                    hit.position.distance(expected)
+                );
+                assert!((hit.distance - origin.distance(expected)).abs() < 1e-6);
+                assert_eq!(hit.height, height);
+            }
+
+            // away from the surface
+            let origin = DVec3::new(MAJOR_AXIS + 1.0e6, 0.0, 0.0);
+            assert!(terrain_raycast(origin, origin, &model, &heights).is_none());
+        }
+    }
+
+    #[test]
+    fn oblique_raycast_without_height_range() {
+        let model = earth();
+        // away from the equator and the poles, only the shell at a height of zero is at a constant geodetic height
+        let heights = ConstantHeight(0.0);
+        let origin = DVec3::new(MAJOR_AXIS + 2.0e5, 1.0e5, -3.0e5);
+
+        for direction in [DVec3::new(-1.0, 0.2, 0.5), DVec3::new(-1.0, -0.3, 0.1)] {
+            let hit = terrain_raycast(origin, direction, &model, &heights).unwrap();
+            let geodetic = Geodetic::from_world_position(hit.position, &model);
+
+            assert!(
+                (geodetic.height - 300.0).abs() < 1e-6,
+                "{}",
+                geodetic.height
+            );
+            assert!((origin + hit.distance * direction.normalize()).distance(hit.position) < 1e-6);
+        }
+
+        // past the limb, the ray misses the shell of the constant height
+        assert!(terrain_raycast(origin, DVec3::new(0.0, 1.0, 0.0), &model, &heights).is_none());
+    }
+}
@@ -0,0 +1,236 @@
+use bevy::{math::DVec3, prelude::*};
+use serde_json::{json, Value};
+use std::{fmt, fs, path::Path};
+
+use crate::deterministic::{InputScript, ScriptedKey};
+
+// Replays reproduce a deterministic run from its initial state and the keys pressed and released in each frame.
+// They are JSON documents of the form
+//   { "version": 1, "seed": 7, "initial": { "camera_position": [x, y, z], "camera_rotation": [x, y, z, w], "origin_lod": 8 },
+//     "keys": [[frame, "KeyW", true], [frame, "KeyW", false], ...] }
+// Positions are written in f64, so the camera starts in exactly the same place.
+// Only the keyboard is recorded, since the deterministic runs discard the mouse.
+// Files with a newer version are rejected, instead of being replayed differently than they were recorded.
+pub const REPLAY_VERSION: u64 = 1;
+
+// keys that can be recorded, everything else is dropped from the replay
+const REPLAY_KEYS: [KeyCode; 70] = [
+    KeyCode::KeyA,
+    KeyCode::KeyB,
+    KeyCode::KeyC,
+    KeyCode::KeyD,
+    KeyCode::KeyE,
+    KeyCode::KeyF,
+    KeyCode::KeyG,
+    KeyCode::KeyH,
+    KeyCode::KeyI,
+    KeyCode::KeyJ,
+    KeyCode::KeyK,
+    KeyCode::KeyL,
+    KeyCode::KeyM,
+    KeyCode::KeyN,
+    KeyCode::KeyO,
+    KeyCode::KeyP,
+    KeyCode::KeyQ,
+    KeyCode::KeyR,
+    KeyCode::KeyS,
+    KeyCode::KeyT,
+    KeyCode::KeyU,
+    KeyCode::KeyV,
+    KeyCode::KeyW,
+    KeyCode::KeyX,
+    KeyCode::KeyY,
+    KeyCode::KeyZ,
+    KeyCode::Digit0,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+    KeyCode::Numpad2,
+    KeyCode::Numpad4,
+    KeyCode::Numpad6,
+    KeyCode::Numpad8,
+    KeyCode::ArrowUp,
+    KeyCode::ArrowDown,
+    KeyCode::ArrowLeft,
+    KeyCode::ArrowRight,
+    KeyCode::Space,
+    KeyCode::ShiftLeft,
+    KeyCode::ShiftRight,
+    KeyCode::ControlLeft,
+    KeyCode::ControlRight,
+    KeyCode::AltLeft,
+    KeyCode::AltRight,
+    KeyCode::BracketLeft,
+    KeyCode::BracketRight,
+    KeyCode::Equal,
+    KeyCode::Minus,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Enter,
+    KeyCode::Escape,
+    KeyCode::Tab,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+];
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Version(u64),
+    Invalid(&'static str),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "failed to access replay file: {error}"),
+            ReplayError::Json(error) => write!(f, "failed to parse replay: {error}"),
+            ReplayError::Version(version) => write!(
+                f,
+                "replay version {version} is newer than the supported version {REPLAY_VERSION}"
+            ),
+            ReplayError::Invalid(message) => write!(f, "invalid replay: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ReplayState {
+    pub camera_position: DVec3,
+    pub camera_rotation: Quat,
+    pub origin_lod: u32,
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct Replay {
+    pub seed: u64,
+    pub initial: ReplayState,
+    pub script: InputScript,
+}
+
+fn key_name(key: KeyCode) -> Option<String> {
+    REPLAY_KEYS.contains(&key).then(|| format!("{key:?}"))
+}
+
+fn parse_key(name: &str) -> Option<KeyCode> {
+    REPLAY_KEYS
+        .into_iter()
+        .find(|key| format!("{key:?}") == name)
+}
+
+fn parse_f64s<const N: usize>(value: &Value) -> Option<[f64; N]> {
+    let values = value
+        .as_array()?
+        .iter()
+        .map(Value::as_f64)
+        .collect::<Option<Vec<_>>>()?;
+
+    values.try_into().ok()
+}
+
+impl Replay {
+    pub fn to_json(&self) -> Value {
+        let keys = self
+            .script
+            .keys
+            .iter()
+            .filter_map(|scripted| {
+                key_name(scripted.key).map(|name| json!([scripted.frame, name, scripted.pressed]))
+            })
+            .collect::<Vec<_>>();
+
+        json!({
+            "version": REPLAY_VERSION,
+            "seed": self.seed,
+            "initial": {
+                "camera_position": self.initial.camera_position.to_array(),
+                "camera_rotation": self.initial.camera_rotation.to_array(),
+                "origin_lod": self.initial.origin_lod,
+            },
+            "keys": keys,
+        })
+    }
+
+    pub fn parse(source: &str) -> Result<Self, ReplayError> {
+        let document: Value = serde_json::from_str(source).map_err(ReplayError::Json)?;
+
+        let version = document["version"]
+            .as_u64()
+            .ok_or(ReplayError::Invalid("missing version"))?;
+        if version > REPLAY_VERSION {
+            return Err(ReplayError::Version(version));
+        }
+
+        let seed = document["seed"]
+            .as_u64()
+            .ok_or(ReplayError::Invalid("missing seed"))?;
+
+        let initial = &document["initial"];
+        let [x, y, z] = parse_f64s(&initial["camera_position"])
+            .ok_or(ReplayError::Invalid("camera position is not three numbers"))?;
+        let [qx, qy, qz, qw] = parse_f64s(&initial["camera_rotation"])
+            .ok_or(ReplayError::Invalid("camera rotation is not four numbers"))?;
+        let origin_lod = initial["origin_lod"]
+            .as_u64()
+            .ok_or(ReplayError::Invalid("missing origin lod"))? as u32;
+
+        let keys = document["keys"]
+            .as_array()
+            .ok_or(ReplayError::Invalid("missing keys"))?
+            .iter()
+            .map(|entry| {
+                let frame = entry[0].as_u64();
+                let key = entry[1].as_str().and_then(parse_key);
+                let pressed = entry[2].as_bool();
+
+                match (frame, key, pressed) {
+                    (Some(frame), Some(key), Some(pressed)) => Ok(ScriptedKey {
+                        frame: frame as u32,
+                        key,
+                        pressed,
+                    }),
+                    _ => Err(ReplayError::Invalid(
+                        "keys are not (frame, key, pressed) tuples",
+                    )),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            seed,
+            initial: ReplayState {
+                camera_position: DVec3::new(x, y, z),
+                camera_rotation: Quat::from_xyzw(qx as f32, qy as f32, qz as f32, qw as f32),
+                origin_lod,
+            },
+            script: InputScript { keys },
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let source = fs::read_to_string(path).map_err(ReplayError::Io)?;
+        Self::parse(&source)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        let contents = serde_json::to_string_pretty(&self.to_json()).map_err(ReplayError::Json)?;
+        fs::write(path, contents).map_err(ReplayError::Io)
+    }
+}
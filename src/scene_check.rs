@@ -0,0 +1,169 @@
+use bevy::{math::DVec3, prelude::*};
+use bevy_terrain::{
+    big_space::{FloatingOrigin, ReferenceFrame},
+    prelude::*,
+};
+use std::f64::consts::FRAC_PI_2;
+
+use crate::{
+    depth::AdaptiveNearPlane,
+    epoch::{shift_model, Rebase, RebaseAppExt},
+    format::Length,
+    geodesy::axes,
+    math::tile_count,
+    model_copy::{ModelCopy, ModelCopyAppExt},
+    origin_lod::MAX_ORIGIN_LOD,
+};
+
+// What the scene is expected to handle, the configuration is checked against it once at startup.
+#[derive(Resource, Clone)]
+pub struct SceneExpectations {
+    pub model: TerrainModel,
+    pub origin_lod: u32,
+    // lowest altitude of the camera above the surface
+    pub min_altitude: f64,
+    // largest position error that is acceptable
+    pub budget: f64,
+}
+
+impl ModelCopy for SceneExpectations {
+    fn set_model(&mut self, model: &TerrainModel) {
+        self.model = model.clone();
+    }
+}
+
+impl Rebase for SceneExpectations {
+    fn rebase(&mut self, offset: DVec3) {
+        self.model = shift_model(&self.model, offset);
+    }
+}
+
+// Reports configurations that would otherwise only show up as jitter or clipping, with a hint on how to fix them.
+pub struct SceneCheckPlugin;
+
+impl Plugin for SceneCheckPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_model_copy_resource::<SceneExpectations>()
+            .add_rebase_resource::<SceneExpectations>()
+            .add_systems(PostStartup, check_scene);
+    }
+}
+
+fn check_scene(
+    expectations: Option<Res<SceneExpectations>>,
+    origin_query: Query<(), With<FloatingOrigin>>,
+    frame_query: Query<&ReferenceFrame>,
+    camera_query: Query<(&Projection, Option<&AdaptiveNearPlane>), With<FloatingOrigin>>,
+) {
+    let Some(expectations) = expectations else {
+        warn!("Skipping check_scene: insert the SceneExpectations resource to validate the scene.");
+        return;
+    };
+
+    let mut issues = Vec::new();
+
+    match origin_query.iter().count() {
+        1 => {}
+        0 => issues.push("There is no FloatingOrigin, add it to the camera.".to_string()),
+        count => issues.push(format!(
+            "There are {count} FloatingOrigins, remove all but the one on the camera."
+        )),
+    }
+
+    issues.extend(check_origin_lod(&expectations));
+    issues.extend(
+        frame_query
+            .iter()
+            .filter_map(|frame| check_cell_size(frame, &expectations)),
+    );
+    issues.extend(camera_query.iter().filter_map(|(projection, near_plane)| {
+        check_near_plane(projection, near_plane, &expectations)
+    }));
+
+    for issue in issues {
+        warn!("Scene configuration: {issue}");
+    }
+}
+
+// The relative positions are resolved inside the origin tile in f32, so its size bounds the error.
+fn check_origin_lod(expectations: &SceneExpectations) -> Option<String> {
+    let (radius, _) = axes(&expectations.model);
+    let lod = expectations.origin_lod;
+
+    if lod > MAX_ORIGIN_LOD {
+        return Some(format!(
+            "The origin lod {lod} exceeds {MAX_ORIGIN_LOD}, where the tile offsets are no longer exact in f32."
+        ));
+    }
+
+    let tile_error = |lod: u32| radius * FRAC_PI_2 / tile_count(lod) as f64 * f32::EPSILON as f64;
+    let error = tile_error(lod);
+
+    (error > expectations.budget).then(|| {
+        let min_lod = (lod..=MAX_ORIGIN_LOD)
+            .find(|&lod| tile_error(lod) <= expectations.budget)
+            .unwrap_or(MAX_ORIGIN_LOD);
+
+        format!(
+            "The origin lod {lod} resolves positions to {} on a radius of {}, exceeding the budget of {}. Use an origin lod of at least {min_lod}.",
+            Length(error),
+            Length(radius),
+            Length(expectations.budget)
+        )
+    })
+}
+
+// Translations inside a grid cell are f32, so the cell edge length bounds the error of everything placed in the grid.
+fn check_cell_size(frame: &ReferenceFrame, expectations: &SceneExpectations) -> Option<String> {
+    let (radius, _) = axes(&expectations.model);
+    let edge_length = frame.cell_edge_length() as f64;
+    let error = 0.5 * edge_length * f32::EPSILON as f64;
+
+    if error > expectations.budget {
+        let max_edge_length = 2.0 * expectations.budget / f32::EPSILON as f64;
+
+        Some(format!(
+            "The grid cells of {} resolve positions to {}, exceeding the budget of {}. Use cells of at most {}.",
+            Length(edge_length),
+            Length(error),
+            Length(expectations.budget),
+            Length(max_edge_length)
+        ))
+    } else if edge_length > radius {
+        Some(format!(
+            "The grid cells of {} are larger than the radius of {}, the planet falls into a handful of cells and gains nothing from the grid.",
+            Length(edge_length),
+            Length(radius)
+        ))
+    } else {
+        None
+    }
+}
+
+// A near plane beyond the lowest altitude clips the terrain right below the camera.
+fn check_near_plane(
+    projection: &Projection,
+    near_plane: Option<&AdaptiveNearPlane>,
+    expectations: &SceneExpectations,
+) -> Option<String> {
+    let altitude = expectations.min_altitude;
+
+    let near = match (near_plane, projection) {
+        (Some(near_plane), _) => near_plane.near(altitude) as f64,
+        (None, Projection::Perspective(perspective)) => perspective.near as f64,
+        (None, Projection::Orthographic(_)) => return None,
+    };
+
+    (near >= altitude).then(|| match near_plane {
+        Some(_) => format!(
+            "The adaptive near plane is {} at the minimum altitude of {}, which clips the terrain. Lower its min_near or factor.",
+            Length(near),
+            Length(altitude)
+        ),
+        None => format!(
+            "The near plane of {} is beyond the minimum altitude of {}, which clips the terrain. Lower it or add an AdaptiveNearPlane.",
+            Length(near),
+            Length(altitude)
+        ),
+    })
+}
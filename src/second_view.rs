@@ -0,0 +1,163 @@
+use bevy::{
+    math::DVec3,
+    prelude::*,
+    render::camera::RenderTarget,
+    window::{WindowClosed, WindowRef},
+};
+use bevy_terrain::{
+    big_space::{FloatingOrigin, GridCell, GridTransformReadOnly, ReferenceFrame, ReferenceFrames},
+    prelude::*,
+};
+
+use crate::{
+    depth::AdaptiveNearPlane,
+    epoch::{shift_model, Rebase, RebaseAppExt},
+    model_copy::{ModelCopy, ModelCopyAppExt},
+    view_approximation::ApproximationSnapshot,
+};
+
+// A camera rendering into a window of its own, to compare the precision at two scales side by side,
+// e.g. an orbital overview next to the surface view of the primary window.
+// The camera has a grid cell of its own, but the floating origin stays at the primary camera,
+// so its f32 transform is relative to that camera and loses precision with the distance to it.
+// Its approximation is computed around its own position, like the one of the primary view.
+#[derive(Component)]
+pub struct SecondView {
+    pub model: TerrainModel,
+    pub window: Entity,
+    pub snapshot: Option<ApproximationSnapshot>,
+    // distance between the position the camera is rendered at, relative to the floating origin, and the exact one
+    pub position_error: f64,
+}
+
+impl ModelCopy for SecondView {
+    fn set_model(&mut self, model: &TerrainModel) {
+        self.model = model.clone();
+    }
+}
+
+impl Rebase for SecondView {
+    fn rebase(&mut self, offset: DVec3) {
+        self.model = shift_model(&self.model, offset);
+    }
+}
+
+#[derive(Bundle)]
+pub struct SecondViewBundle {
+    pub camera: Camera3dBundle,
+    pub cell: GridCell,
+    pub near_plane: AdaptiveNearPlane,
+    pub view: SecondView,
+}
+
+impl SecondViewBundle {
+    // A camera rendering into the window, at the position looking at the target.
+    pub fn new(
+        model: TerrainModel,
+        window: Entity,
+        position: DVec3,
+        target: DVec3,
+        up: DVec3,
+        frame: &ReferenceFrame,
+    ) -> Self {
+        let (cell, translation) = frame.translation_to_grid(position);
+
+        Self {
+            camera: Camera3dBundle {
+                camera: Camera {
+                    target: RenderTarget::Window(WindowRef::Entity(window)),
+                    ..default()
+                },
+                transform: Transform::from_translation(translation)
+                    .looking_to((target - position).as_vec3(), up.as_vec3()),
+                ..default()
+            },
+            cell,
+            near_plane: AdaptiveNearPlane::new(model.clone()),
+            view: SecondView {
+                model,
+                window,
+                snapshot: None,
+                position_error: 0.0,
+            },
+        }
+    }
+}
+
+pub struct SecondViewPlugin;
+
+impl Plugin for SecondViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_model_copy::<SecondView>()
+            .add_rebase::<SecondView>()
+            .add_systems(Update, close_second_views)
+            .add_systems(PostUpdate, update_second_views);
+    }
+}
+
+fn update_second_views(
+    frames: ReferenceFrames,
+    origin_query: Query<(Entity, GridTransformReadOnly, &GlobalTransform), With<FloatingOrigin>>,
+    mut view_query: Query<
+        (
+            Entity,
+            &mut SecondView,
+            &GridCell,
+            &Transform,
+            &GlobalTransform,
+        ),
+        Without<FloatingOrigin>,
+    >,
+) {
+    let Ok((origin, origin_transform, origin_global)) = origin_query.get_single() else {
+        warn_once!("Skipping update_second_views: expected exactly one floating origin.");
+        return;
+    };
+    let Some(origin_frame) = frames.parent_frame(origin) else {
+        warn_once!(
+            "Skipping update_second_views: the floating origin is not part of a reference frame."
+        );
+        return;
+    };
+    let origin_position = origin_transform.position_double(&origin_frame);
+
+    for (view, mut second_view, cell, transform, global) in &mut view_query {
+        let Some(frame) = frames.parent_frame(view) else {
+            continue;
+        };
+        let view_position = frame.grid_position_double(cell, transform);
+
+        // both global transforms are the f32 offsets big_space renders at, relative to the cell of the floating origin
+        let rendered = (global.translation() - origin_global.translation()).as_dvec3();
+
+        second_view.position_error = rendered.distance(view_position - origin_position);
+        second_view.snapshot = Some(ApproximationSnapshot::compute(
+            view_position,
+            &second_view.model,
+        ));
+    }
+}
+
+// Despawns the cameras of closed windows together with the ui targeting them, which would render into nothing otherwise.
+fn close_second_views(
+    mut commands: Commands,
+    mut closed: EventReader<WindowClosed>,
+    view_query: Query<(Entity, &SecondView)>,
+    ui_query: Query<(Entity, &TargetCamera)>,
+) {
+    for event in closed.read() {
+        for (view, second_view) in &view_query {
+            if second_view.window != event.window {
+                continue;
+            }
+
+            commands.entity(view).despawn_recursive();
+
+            for (node, target) in &ui_query {
+                if target.entity() == view {
+                    commands.entity(node).despawn_recursive();
+                }
+            }
+        }
+    }
+}
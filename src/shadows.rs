@@ -0,0 +1,247 @@
+use bevy::{
+    math::{DMat3, DVec3},
+    pbr::{CascadeShadowConfigBuilder, DirectionalLightShadowMap},
+    prelude::*,
+};
+use bevy_terrain::{
+    big_space::{FloatingOrigin, GridTransformReadOnly, ReferenceFrames},
+    math::TileCoordinate,
+    prelude::*,
+};
+
+use crate::{
+    epoch::{shift_model, Rebase},
+    geodesy::{axes, Geodetic},
+    math::approximate_relative_position,
+    model_copy::ModelCopy,
+    view_approximation::ApproximationSnapshot,
+};
+
+// Fits the shadow cascades of a directional light to the visible part of the planet.
+// Bevy places the cascades relative to the camera, so a rebase of the floating origin does not move them.
+// The cascade bounds are snapped to powers of two of the altitude, so that they are not refitted every frame,
+// which would make the shadow edges swim while the camera moves.
+#[derive(Component)]
+pub struct PlanetShadows {
+    pub model: TerrainModel,
+    // direction towards the sun in world space
+    pub sun_direction: DVec3,
+    pub num_cascades: usize,
+}
+
+impl PlanetShadows {
+    pub fn new(model: TerrainModel, sun_direction: DVec3) -> Self {
+        Self {
+            model,
+            sun_direction: sun_direction.normalize(),
+            num_cascades: 4,
+        }
+    }
+
+    pub fn cascade_config(&self, altitude: f64) -> CascadeShadowConfigBuilder {
+        let (major_axis, _) = axes(&self.model);
+        let altitude = altitude.max(1.0);
+
+        let horizon_distance = (altitude * (2.0 * major_axis + altitude)).sqrt();
+
+        let maximum_distance = snap(horizon_distance);
+
+        CascadeShadowConfigBuilder {
+            num_cascades: self.num_cascades,
+            minimum_distance: snap(0.01 * altitude),
+            maximum_distance,
+            first_cascade_far_bound: snap(4.0 * altitude).min(0.5 * maximum_distance),
+            ..default()
+        }
+    }
+
+    // Rotation from light space to world space, in f64, matching the f32 transform of the light.
+    // Light space looks along -Z towards the surface, like the transform does.
+    pub fn light_rotation(&self) -> DMat3 {
+        let back = self.sun_direction;
+        let right = DVec3::Y.cross(back).normalize();
+
+        DMat3::from_cols(right, back.cross(right), back)
+    }
+}
+
+impl ModelCopy for PlanetShadows {
+    fn set_model(&mut self, model: &TerrainModel) {
+        self.model = model.clone();
+    }
+}
+
+impl Rebase for PlanetShadows {
+    fn rebase(&mut self, offset: DVec3) {
+        self.model = shift_model(&self.model, offset);
+    }
+}
+
+// Size of a shadow map texel in each cascade.
+// The cascade is bounded by the sphere around its far bound, which the shadow map spans.
+pub fn cascade_texel_sizes(config: &CascadeShadowConfig, shadow_map_size: usize) -> Vec<f64> {
+    config
+        .bounds
+        .iter()
+        .map(|&far_bound| 2.0 * far_bound as f64 / shadow_map_size as f64)
+        .collect()
+}
+
+// Snaps a position to the texel grid of a cascade in light space, in f64.
+// While the camera moves, the snapped origin only moves in whole texels, which moves the rasterized shadows
+// by whole texels as well, instead of resampling them at sub-texel offsets.
+// The snapping happens in f64, before anything is rounded to f32 relative to the floating origin,
+// so a rebase of the origin does not change the result.
+pub fn snap_to_texels(light_rotation: DMat3, position: DVec3, texel_size: f64) -> DVec3 {
+    let light_position = light_rotation.transpose() * position;
+
+    light_rotation * ((light_position / texel_size).round() * texel_size)
+}
+
+// The approximation the shadow pass evaluates its vertices with, expanded around the center of the first cascade
+// instead of the camera. The shadow map covers the surface in front of the camera, where the series around the camera
+// is already less precise, and every error moves the shadow casters.
+// The center is snapped to the texels of the first cascade, so that the approximation, and with it the shadows,
+// only changes in whole texels.
+#[derive(Component, Default)]
+pub struct ShadowApproximation {
+    pub center: DVec3,
+    pub snapshot: Option<ApproximationSnapshot>,
+    // position of the view snapped to the texels of each cascade, in world space
+    pub cascade_origins: Vec<DVec3>,
+}
+
+impl ShadowApproximation {
+    // Position of a point inside a tile relative to the view, as the shadow pass places it.
+    // Only the offset of the center to the view is rounded to f32, like the approximation itself.
+    pub fn relative_position(
+        &self,
+        view_position: DVec3,
+        origin_lod: u32,
+        coordinate: (TileCoordinate, Vec2),
+    ) -> Option<Vec3> {
+        let snapshot = self.snapshot.as_ref()?;
+
+        Some(
+            (self.center - view_position).as_vec3()
+                + approximate_relative_position(
+                    &snapshot.view_coordinates,
+                    &snapshot.approximations,
+                    origin_lod,
+                    true,
+                    coordinate,
+                ),
+        )
+    }
+}
+
+impl Rebase for ShadowApproximation {
+    fn rebase(&mut self, offset: DVec3) {
+        self.center += offset;
+        for origin in &mut self.cascade_origins {
+            *origin += offset;
+        }
+        if let Some(snapshot) = &mut self.snapshot {
+            snapshot.translate(offset);
+        }
+    }
+}
+
+fn snap(distance: f64) -> f32 {
+    2.0_f64.powf(distance.log2().ceil()) as f32
+}
+
+pub fn update_planet_shadows(
+    frames: ReferenceFrames,
+    shadow_map: Option<Res<DirectionalLightShadowMap>>,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+    mut light_query: Query<(
+        &PlanetShadows,
+        &mut Transform,
+        &mut CascadeShadowConfig,
+        Option<&mut ShadowApproximation>,
+    )>,
+) {
+    let Ok((view, view_transform)) = view_query.get_single() else {
+        warn_once!("Skipping update_planet_shadows: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping update_planet_shadows: the camera is not part of a reference frame.");
+        return;
+    };
+    let view_position = view_transform.position_double(&frame);
+    let shadow_map_size =
+        shadow_map.map_or(DirectionalLightShadowMap::default().size, |map| map.size);
+
+    for (shadows, mut transform, mut cascade_config, approximation) in &mut light_query {
+        let altitude = Geodetic::from_world_position(view_position, &shadows.model).height;
+
+        transform.look_to(-shadows.sun_direction.as_vec3(), Vec3::Y);
+
+        let builder = shadows.cascade_config(altitude);
+        let first_cascade = builder.first_cascade_far_bound as f64;
+        let config = builder.build();
+
+        if let Some(mut approximation) = approximation {
+            let light_rotation = shadows.light_rotation();
+            let texel_sizes = cascade_texel_sizes(&config, shadow_map_size);
+            let forward = view_transform.transform.forward().as_dvec3();
+            let center = snap_to_texels(
+                light_rotation,
+                view_position + 0.5 * first_cascade * forward,
+                texel_sizes[0],
+            );
+
+            approximation.cascade_origins = texel_sizes
+                .iter()
+                .map(|&texel_size| snap_to_texels(light_rotation, view_position, texel_size))
+                .collect();
+
+            if approximation.snapshot.is_none() || center != approximation.center {
+                approximation.center = center;
+                approximation.snapshot =
+                    Some(ApproximationSnapshot::compute(center, &shadows.model));
+            }
+        }
+
+        // only replace the config when the snapped bounds change
+        if config.bounds != cascade_config.bounds {
+            *cascade_config = config;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapped_origins_move_in_whole_texels() {
+        let shadows = PlanetShadows::new(
+            TerrainModel::ellipsoid(DVec3::ZERO, 6378137.0, 6356752.314245, 0.0, 0.0),
+            DVec3::new(-1.0, 0.5, 0.3),
+        );
+        let light_rotation = shadows.light_rotation();
+        let texel_size = 0.25;
+        let position = DVec3::new(4_510_123.37, 2_130_456.81, 3_987_654.12);
+
+        assert!(light_rotation.determinant() > 0.0);
+        assert!((light_rotation * DVec3::Z).distance(shadows.sun_direction) < 1e-12);
+
+        let snapped = snap_to_texels(light_rotation, position, texel_size);
+        let texels = light_rotation.transpose() * snapped / texel_size;
+
+        assert!((texels - texels.round()).abs().max_element() < 1e-3);
+        assert!(
+            (light_rotation.transpose() * (snapped - position))
+                .abs()
+                .max_element()
+                <= 0.5 * texel_size + 1e-6
+        );
+
+        // a nudge within the texel keeps the snapped origin
+        let nudged = snapped + light_rotation * DVec3::new(0.1, -0.1, 0.05) * texel_size;
+        assert_eq!(snap_to_texels(light_rotation, nudged, texel_size), snapped);
+    }
+}
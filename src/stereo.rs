@@ -0,0 +1,174 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+    render::camera::{CameraUpdateSystem, ClearColorConfig, Viewport},
+    window::PrimaryWindow,
+};
+
+// distance between the rendered eyes, compared to the offset they should have in the pose of the rig
+pub const STEREO_BASELINE_ERROR: DiagnosticPath =
+    DiagnosticPath::const_new("stereo/baseline_error");
+// angle between the orientations of the eyes, which should be parallel
+pub const STEREO_ROTATION_ERROR: DiagnosticPath =
+    DiagnosticPath::const_new("stereo/rotation_error");
+
+// Renders the view of the camera twice, side by side, from two eyes offset by half the baseline to either side.
+// The eyes are children of the camera without a grid cell of their own, so both derive from its double precision pose
+// and only their small offsets are f32. The camera itself stays the floating origin and the view of the approximation,
+// but no longer renders.
+#[derive(Component)]
+pub struct StereoRig {
+    pub baseline: f32,
+    // baseline error above which the eyes are reported as inconsistent
+    pub tolerance: f32,
+}
+
+impl Default for StereoRig {
+    fn default() -> Self {
+        Self {
+            // average human interpupillary distance
+            baseline: 0.064,
+            tolerance: 0.0001,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct StereoEye {
+    // offset along the right axis of the rig, negative for the left eye
+    pub offset: f32,
+}
+
+pub struct StereoPlugin;
+
+impl Plugin for StereoPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(STEREO_BASELINE_ERROR).with_suffix(" m"))
+            .register_diagnostic(Diagnostic::new(STEREO_ROTATION_ERROR).with_suffix(" rad"))
+            .add_systems(Update, spawn_eyes)
+            .add_systems(PostUpdate, layout_eyes.before(CameraUpdateSystem))
+            .add_systems(Last, check_eyes);
+    }
+}
+
+fn spawn_eyes(
+    mut commands: Commands,
+    mut rig_query: Query<(Entity, &StereoRig, &mut Camera, &Projection), Added<StereoRig>>,
+) {
+    for (entity, rig, mut camera, projection) in &mut rig_query {
+        camera.is_active = false;
+
+        commands.entity(entity).with_children(|parent| {
+            for (order, offset) in [-0.5, 0.5].into_iter().enumerate() {
+                let offset = offset * rig.baseline;
+
+                parent.spawn((
+                    Camera3dBundle {
+                        camera: Camera {
+                            order: camera.order + order as isize + 1,
+                            // the right eye must not clear the left one, since both share the window
+                            clear_color: if order == 0 {
+                                camera.clear_color.clone()
+                            } else {
+                                ClearColorConfig::None
+                            },
+                            ..default()
+                        },
+                        projection: projection.clone(),
+                        transform: Transform::from_xyz(offset, 0.0, 0.0),
+                        ..default()
+                    },
+                    StereoEye { offset },
+                ));
+            }
+        });
+    }
+}
+
+// Splits the window between the eyes and keeps their projections in line with the one of the rig,
+// whose near plane may change with the altitude.
+fn layout_eyes(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    rig_query: Query<&Projection, (With<StereoRig>, Without<StereoEye>)>,
+    mut eye_query: Query<(&StereoEye, &Parent, &mut Camera, &mut Projection)>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        warn_once!("Skipping layout_eyes: expected exactly one primary window.");
+        return;
+    };
+
+    let size = window.physical_size();
+    let eye_size = UVec2::new(size.x / 2, size.y);
+
+    for (eye, parent, mut camera, mut projection) in &mut eye_query {
+        let Ok(rig_projection) = rig_query.get(parent.get()) else {
+            continue;
+        };
+
+        let position = if eye.offset < 0.0 {
+            UVec2::ZERO
+        } else {
+            UVec2::new(eye_size.x, 0)
+        };
+
+        if camera
+            .viewport
+            .as_ref()
+            .map(|viewport| (viewport.physical_position, viewport.physical_size))
+            != Some((position, eye_size))
+        {
+            camera.viewport = Some(Viewport {
+                physical_position: position,
+                physical_size: eye_size,
+                ..default()
+            });
+        }
+
+        if let (Projection::Perspective(rig), Projection::Perspective(eye)) =
+            (rig_projection, projection.as_mut())
+        {
+            if (eye.fov, eye.near, eye.far) != (rig.fov, rig.near, rig.far) {
+                eye.fov = rig.fov;
+                eye.near = rig.near;
+                eye.far = rig.far;
+            }
+        }
+    }
+}
+
+// Compares the rendered eyes with the pose of the rig, after the transforms have been propagated.
+// Any disparity besides the horizontal baseline, or a rotation between the eyes, would be visible as a strain in VR.
+fn check_eyes(
+    rig_query: Query<(&StereoRig, &GlobalTransform, &Children)>,
+    eye_query: Query<(&StereoEye, &GlobalTransform)>,
+    mut diagnostics: Diagnostics,
+) {
+    for (rig, rig_transform, children) in &rig_query {
+        let mut eyes = eye_query.iter_many(children).collect::<Vec<_>>();
+        eyes.sort_by(|(a, _), (b, _)| a.offset.total_cmp(&b.offset));
+
+        let [(left, left_transform), (right, right_transform)] = eyes[..] else {
+            continue;
+        };
+
+        let rotation = rig_transform.compute_transform().rotation;
+        let expected = rotation * Vec3::X * (right.offset - left.offset);
+        let actual = right_transform.translation() - left_transform.translation();
+
+        let baseline_error = (actual - expected).length();
+        let rotation_error = left_transform
+            .compute_transform()
+            .rotation
+            .angle_between(right_transform.compute_transform().rotation);
+
+        diagnostics.add_measurement(&STEREO_BASELINE_ERROR, || baseline_error as f64);
+        diagnostics.add_measurement(&STEREO_ROTATION_ERROR, || rotation_error as f64);
+
+        if baseline_error > rig.tolerance {
+            warn_once!(
+                "The eyes are {baseline_error} m away from their expected offset, above the tolerance of {} m.",
+                rig.tolerance
+            );
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use bevy_terrain::math::TileCoordinate;
+use std::fmt;
+
+use crate::math::tile_count;
+
+/// Deepest lod, whose tiles still fit into a key.
+pub const MAX_KEY_LOD: u32 = 27;
+
+const MORTON_BITS: u32 = 2 * MAX_KEY_LOD;
+const LOD_BITS: u32 = 5;
+
+// A tile packed into a single integer, which is cheap to hash and compare.
+// From the most to the least significant bits it holds the face, the lod and the Morton code of the xy coordinate,
+// so sorting the keys orders the tiles by face, then by lod, and then along the Z-order curve of each face.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TileKey(pub u64);
+
+impl TileKey {
+    pub fn new(face: u32, lod: u32, x: u32, y: u32) -> Self {
+        debug_assert!(face < 6, "the face {face} does not exist");
+        debug_assert!(
+            lod <= MAX_KEY_LOD,
+            "the lod {lod} exceeds the maximum key lod"
+        );
+
+        Self(
+            (face as u64) << (LOD_BITS + MORTON_BITS)
+                | (lod as u64) << MORTON_BITS
+                | interleave(x)
+                | interleave(y) << 1,
+        )
+    }
+
+    pub fn face(self) -> u32 {
+        (self.0 >> (LOD_BITS + MORTON_BITS)) as u32
+    }
+
+    pub fn lod(self) -> u32 {
+        (self.0 >> MORTON_BITS) as u32 & ((1 << LOD_BITS) - 1)
+    }
+
+    pub fn morton(self) -> u64 {
+        self.0 & ((1 << MORTON_BITS) - 1)
+    }
+
+    pub fn x(self) -> u32 {
+        deinterleave(self.morton())
+    }
+
+    pub fn y(self) -> u32 {
+        deinterleave(self.morton() >> 1)
+    }
+
+    pub fn parent(self) -> Option<Self> {
+        (self.lod() > 0)
+            .then(|| Self::new(self.face(), self.lod() - 1, self.x() >> 1, self.y() >> 1))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileKeyError {
+    Face(u32),
+    /// The lod exceeds `MAX_KEY_LOD`, its Morton code would overlap the lod bits.
+    Lod(u32),
+    /// The xy coordinate lies outside of the face at the lod.
+    Position(u32, u32),
+}
+
+impl fmt::Display for TileKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TileKeyError::Face(face) => write!(f, "the face {face} does not exist"),
+            TileKeyError::Lod(lod) => {
+                write!(f, "the lod {lod} exceeds the maximum key lod {MAX_KEY_LOD}")
+            }
+            TileKeyError::Position(x, y) => {
+                write!(f, "the tile ({x}, {y}) lies outside of the face")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TileKeyError {}
+
+impl TryFrom<TileCoordinate> for TileKey {
+    type Error = TileKeyError;
+
+    fn try_from(tile: TileCoordinate) -> Result<Self, Self::Error> {
+        if tile.face >= 6 {
+            return Err(TileKeyError::Face(tile.face));
+        }
+        if tile.lod > MAX_KEY_LOD {
+            return Err(TileKeyError::Lod(tile.lod));
+        }
+        if tile.x as u64 >= tile_count(tile.lod) || tile.y as u64 >= tile_count(tile.lod) {
+            return Err(TileKeyError::Position(tile.x, tile.y));
+        }
+
+        Ok(Self::new(tile.face, tile.lod, tile.x, tile.y))
+    }
+}
+
+impl From<TileKey> for TileCoordinate {
+    fn from(key: TileKey) -> Self {
+        TileCoordinate::new(key.face(), key.lod(), key.x(), key.y())
+    }
+}
+
+// spreads the bits of the value, so that a zero bit lies between each of them
+fn interleave(value: u32) -> u64 {
+    let mut value = value as u64 & 0xffff_ffff;
+    value = (value | value << 16) & 0x0000_ffff_0000_ffff;
+    value = (value | value << 8) & 0x00ff_00ff_00ff_00ff;
+    value = (value | value << 4) & 0x0f0f_0f0f_0f0f_0f0f;
+    value = (value | value << 2) & 0x3333_3333_3333_3333;
+    (value | value << 1) & 0x5555_5555_5555_5555
+}
+
+fn deinterleave(value: u64) -> u32 {
+    let mut value = value & 0x5555_5555_5555_5555;
+    value = (value | value >> 1) & 0x3333_3333_3333_3333;
+    value = (value | value >> 2) & 0x0f0f_0f0f_0f0f_0f0f;
+    value = (value | value >> 4) & 0x00ff_00ff_00ff_00ff;
+    value = (value | value >> 8) & 0x0000_ffff_0000_ffff;
+    ((value | value >> 16) & 0xffff_ffff) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::iproduct;
+
+    #[test]
+    fn interleave_spreads_the_bits() {
+        assert_eq!(interleave(0b1011), 0b100_0101);
+        assert_eq!(interleave(u32::MAX), 0x5555_5555_5555_5555);
+
+        for value in [0, 1, 0b1011, 12_345_678, (1 << MAX_KEY_LOD) - 1, u32::MAX] {
+            assert_eq!(deinterleave(interleave(value)), value);
+        }
+    }
+
+    #[test]
+    fn tile_key_round_trip() {
+        for (face, lod) in iproduct!(0..6, [0, 1, 13, MAX_KEY_LOD]) {
+            let last = (tile_count(lod) - 1) as u32;
+
+            for (x, y) in iproduct!([0, last / 3, last], [0, last / 2, last]) {
+                let tile = TileCoordinate::new(face, lod, x, y);
+                let key = TileKey::try_from(tile).unwrap();
+
+                assert_eq!((key.face(), key.lod(), key.x(), key.y()), (face, lod, x, y));
+
+                let round_trip = TileCoordinate::from(key);
+                assert_eq!(
+                    (round_trip.face, round_trip.lod, round_trip.x, round_trip.y),
+                    (face, lod, x, y)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn tile_key_rejects_tiles_it_cannot_hold() {
+        assert_eq!(
+            TileKey::try_from(TileCoordinate::new(0, MAX_KEY_LOD + 1, 0, 0)),
+            Err(TileKeyError::Lod(MAX_KEY_LOD + 1))
+        );
+        assert_eq!(
+            TileKey::try_from(TileCoordinate::new(6, 0, 0, 0)),
+            Err(TileKeyError::Face(6))
+        );
+        assert_eq!(
+            TileKey::try_from(TileCoordinate::new(0, 2, 4, 0)),
+            Err(TileKeyError::Position(4, 0))
+        );
+    }
+
+    #[test]
+    fn tile_keys_sort_by_face_lod_and_z_order() {
+        // the Z-order of the tiles of lod 1, then the first tile of lod 2, then the next face
+        let sorted = [
+            TileKey::new(0, 1, 0, 0),
+            TileKey::new(0, 1, 1, 0),
+            TileKey::new(0, 1, 0, 1),
+            TileKey::new(0, 1, 1, 1),
+            TileKey::new(0, 2, 0, 0),
+            TileKey::new(
+                0,
+                MAX_KEY_LOD,
+                (1 << MAX_KEY_LOD) - 1,
+                (1 << MAX_KEY_LOD) - 1,
+            ),
+            TileKey::new(1, 0, 0, 0),
+            TileKey::new(5, 0, 0, 0),
+        ];
+
+        assert!(sorted.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // the children of a tile follow each other and share it as their parent
+        let parent = TileKey::new(2, 5, 17, 9);
+        let children = iproduct!([0, 1], [0, 1])
+            .map(|(y, x)| TileKey::new(2, 6, 34 + x, 18 + y))
+            .collect::<Vec<_>>();
+
+        assert!(children.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(children[3].morton() - children[0].morton(), 3);
+        assert!(children.iter().all(|child| child.parent() == Some(parent)));
+    }
+}
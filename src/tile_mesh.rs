@@ -0,0 +1,200 @@
+use bevy::{
+    math::{DVec2, DVec3},
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+};
+use bevy_terrain::{
+    big_space::{FloatingOrigin, GridTransform, GridTransformReadOnly, ReferenceFrames},
+    math::{Coordinate, TileCoordinate},
+    prelude::*,
+};
+use itertools::iproduct;
+
+use crate::{
+    culling::Occluded,
+    height::HeightProvider,
+    math::{tile_count, tile_size},
+};
+
+// A double-precision vector split into two f32 vectors, whose sum reproduces the original value to about 48 bits.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DoubleSplit {
+    pub high: Vec3,
+    pub low: Vec3,
+}
+
+impl DoubleSplit {
+    pub fn new(value: DVec3) -> Self {
+        let high = value.as_vec3();
+        let low = (value - high.as_dvec3()).as_vec3();
+
+        Self { high, low }
+    }
+
+    pub fn value(&self) -> DVec3 {
+        self.high.as_dvec3() + self.low.as_dvec3()
+    }
+
+    // Evaluates the difference in f32 only, the way a vertex shader would.
+    pub fn relative_to(&self, origin: &DoubleSplit) -> Vec3 {
+        (self.high - origin.high) + (self.low - origin.low)
+    }
+}
+
+// The origin of a tile mesh, whose vertex positions are stored relative to it.
+#[derive(Component, Clone, Copy)]
+pub struct TileOrigin {
+    pub tile: TileCoordinate,
+    pub origin: DoubleSplit,
+}
+
+pub fn tile_origin(tile: TileCoordinate, model: &TerrainModel) -> DVec3 {
+    let size = tile_size(tile.lod);
+    let center_uv = (UVec2::new(tile.x, tile.y).as_dvec2() + 0.5) * size;
+
+    Coordinate::new(tile.face, center_uv).world_position(model, 0.0)
+}
+
+fn surface_position(
+    face: u32,
+    uv: DVec2,
+    model: &TerrainModel,
+    heights: &dyn HeightProvider,
+) -> DVec3 {
+    let coordinate = Coordinate::new(face, uv);
+
+    coordinate.world_position(model, heights.height(coordinate) as f32)
+}
+
+pub fn tile_mesh(
+    tile: TileCoordinate,
+    resolution: u32,
+    model: &TerrainModel,
+    heights: &dyn HeightProvider,
+) -> (Mesh, DVec3) {
+    let origin = tile_origin(tile, model);
+    let size = tile_size(tile.lod);
+
+    // step used for the finite differences, a fraction of the vertex spacing
+    let delta = 0.25 * size / resolution as f64;
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut tangents = Vec::new();
+
+    for (y, x) in iproduct!(0..=resolution, 0..=resolution) {
+        let vertex_uv = DVec2::new(x as f64, y as f64) / resolution as f64;
+        let uv = (UVec2::new(tile.x, tile.y).as_dvec2() + vertex_uv) * size;
+
+        let position = surface_position(tile.face, uv, model, heights);
+
+        // central differences, that fall back to one-sided ones at the face edges
+        let difference = |direction: DVec2| {
+            let start = (uv - delta * direction).clamp(DVec2::ZERO, DVec2::ONE);
+            let end = (uv + delta * direction).clamp(DVec2::ZERO, DVec2::ONE);
+
+            surface_position(tile.face, end, model, heights)
+                - surface_position(tile.face, start, model, heights)
+        };
+
+        let tangent_u = difference(DVec2::X).normalize();
+        let tangent_v = difference(DVec2::Y).normalize();
+
+        let mut normal = tangent_u.cross(tangent_v).normalize();
+        if normal.dot(position - model.position()) < 0.0 {
+            normal = -normal;
+        }
+
+        let handedness = normal.cross(tangent_u).dot(tangent_v).signum();
+
+        // the large absolute part is removed in f64, before the conversion to f32
+        positions.push((position - origin).as_vec3().to_array());
+        normals.push(normal.as_vec3().to_array());
+        tangents.push(tangent_u.as_vec3().extend(handedness as f32).to_array());
+    }
+
+    let mut indices = Vec::new();
+
+    for (y, x) in iproduct!(0..resolution, 0..resolution) {
+        let index = y * (resolution + 1) + x;
+
+        indices.extend([index, index + 1, index + resolution + 1]);
+        indices.extend([index + 1, index + resolution + 2, index + resolution + 1]);
+    }
+
+    let mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_TANGENT, tangents)
+    .with_inserted_indices(Indices::U32(indices));
+
+    (mesh, origin)
+}
+
+pub fn tile_material() -> StandardMaterial {
+    StandardMaterial {
+        base_color: Color::srgb(0.4, 0.5, 0.3),
+        double_sided: true,
+        cull_mode: None,
+        ..default()
+    }
+}
+
+pub fn tile_bundles(
+    meshes: &mut Assets<Mesh>,
+    material: &Handle<StandardMaterial>,
+    lod: u32,
+    resolution: u32,
+    model: &TerrainModel,
+    heights: &dyn HeightProvider,
+) -> Vec<(PbrBundle, TileOrigin, Occluded)> {
+    iproduct!(0..6, 0..tile_count(lod), 0..tile_count(lod))
+        .map(|(face, x, y)| {
+            let tile = TileCoordinate::new(face, lod, x as u32, y as u32);
+            let (mesh, origin) = tile_mesh(tile, resolution, model, heights);
+
+            (
+                PbrBundle {
+                    mesh: meshes.add(mesh),
+                    material: material.clone(),
+                    ..default()
+                },
+                TileOrigin {
+                    tile,
+                    origin: DoubleSplit::new(origin),
+                },
+                Occluded::default(),
+            )
+        })
+        .collect()
+}
+
+// Places each tile relative to the view using the double-split origins, instead of the absolute grid position.
+// The tiles share the view's grid cell, so only the small f32 offset of the tile origin to the view remains.
+pub fn update_tile_origins(
+    frames: ReferenceFrames,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+    mut tile_query: Query<(&TileOrigin, GridTransform), Without<FloatingOrigin>>,
+) {
+    let Ok((view, view_transform)) = view_query.get_single() else {
+        warn_once!("Skipping update_tile_origins: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping update_tile_origins: the camera is not part of a reference frame.");
+        return;
+    };
+    let view_origin = DoubleSplit::new(view_transform.position_double(&frame));
+
+    for (tile_origin, mut tile_transform) in &mut tile_query {
+        *tile_transform.cell = *view_transform.cell;
+        tile_transform.transform.translation =
+            view_transform.transform.translation + tile_origin.origin.relative_to(&view_origin);
+    }
+}
@@ -0,0 +1,367 @@
+use bevy::{
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        graph::CameraDriverLabel,
+        render_graph::{self, RenderGraph, RenderLabel},
+        render_resource::{
+            binding_types::{storage_buffer, storage_buffer_read_only},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        Render, RenderApp, RenderSet,
+    },
+};
+use bevy_terrain::math::{Coordinate, TileCoordinate};
+use bytemuck::{Pod, Zeroable};
+
+use crate::math::tile_count;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+#[repr(C)]
+#[derive(ShaderType, Pod, Zeroable, Clone, Copy, Default, Debug, PartialEq)]
+pub struct CompactTile {
+    pub face: u32,
+    pub lod: u32,
+    pub xy: UVec2,
+}
+
+impl From<TileCoordinate> for CompactTile {
+    fn from(tile: TileCoordinate) -> Self {
+        Self {
+            face: tile.face,
+            lod: tile.lod,
+            xy: UVec2::new(tile.x, tile.y),
+        }
+    }
+}
+
+impl From<CompactTile> for TileCoordinate {
+    fn from(tile: CompactTile) -> Self {
+        TileCoordinate::new(tile.face, tile.lod, tile.xy.x, tile.xy.y)
+    }
+}
+
+#[repr(C)]
+#[derive(ShaderType, Pod, Zeroable, Clone, Copy, Default, Debug, PartialEq)]
+pub struct TileData {
+    // offset of the tile to the origin tile, in tiles of the tile's lod
+    pub offset: IVec2,
+    pub lod_difference: i32,
+    // index of the side parameters used to evaluate the tile
+    pub face_remap: u32,
+}
+
+#[derive(ShaderType, Clone, Copy, Default, Debug)]
+pub struct PrepassOrigin {
+    pub origin_lod: u32,
+    pub origin_xy: [UVec2; 6],
+}
+
+impl PrepassOrigin {
+    // The origin tile of a face is the tile containing the view coordinate.
+    // A view on the far edge of the face, with a uv of exactly 1, lies in the last tile.
+    pub fn new(view_coordinates: &[Coordinate; 6], origin_lod: u32) -> Self {
+        let last = UVec2::splat((tile_count(origin_lod) - 1) as u32);
+
+        Self {
+            origin_lod,
+            origin_xy: std::array::from_fn(|face| {
+                (view_coordinates[face].uv * tile_count(origin_lod) as f64)
+                    .as_uvec2()
+                    .min(last)
+            }),
+        }
+    }
+}
+
+// The compact tile list, from which the per-tile data is derived on the GPU.
+// Only this list is uploaded each frame, instead of one uniform per tile.
+#[derive(Resource, ExtractResource, Clone, Default)]
+pub struct TileList {
+    pub origin: PrepassOrigin,
+    pub tiles: Vec<CompactTile>,
+}
+
+// CPU reference of the compute pass, the tests check it against the tile positions in uv.
+pub fn derive_tile_data(tile: CompactTile, origin: &PrepassOrigin) -> TileData {
+    let lod_difference = tile.lod as i32 - origin.origin_lod as i32;
+    let origin_xy = origin.origin_xy[tile.face as usize];
+
+    let origin_xy = if lod_difference >= 0 {
+        origin_xy << lod_difference as u32
+    } else {
+        origin_xy >> (-lod_difference) as u32
+    };
+
+    TileData {
+        offset: tile.xy.as_ivec2() - origin_xy.as_ivec2(),
+        lod_difference,
+        face_remap: tile.face,
+    }
+}
+
+pub struct TilePrepassPlugin;
+
+impl Plugin for TilePrepassPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TileList>()
+            .add_plugins(ExtractResourcePlugin::<TileList>::default());
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+
+        render_app
+            .init_resource::<TilePrepassPipeline>()
+            .init_resource::<TilePrepassBuffers>()
+            .add_systems(
+                Render,
+                prepare_tile_prepass.in_set(RenderSet::PrepareBindGroups),
+            );
+
+        let mut render_graph = render_app.world_mut().resource_mut::<RenderGraph>();
+        render_graph.add_node(TilePrepassLabel, TilePrepassNode);
+        // derive the tile data before any camera renders
+        render_graph.add_node_edge(TilePrepassLabel, CameraDriverLabel);
+    }
+}
+
+#[derive(Resource)]
+pub struct TilePrepassPipeline {
+    layout: BindGroupLayout,
+    pipeline: CachedComputePipelineId,
+    // kept loaded, so that the import of the shared structs resolves
+    _types: Handle<Shader>,
+}
+
+impl FromWorld for TilePrepassPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let layout = render_device.create_bind_group_layout(
+            "tile_prepass_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    storage_buffer_read_only::<PrepassOrigin>(false),
+                    storage_buffer_read_only::<Vec<CompactTile>>(false),
+                    storage_buffer::<Vec<TileData>>(false),
+                ),
+            ),
+        );
+
+        let types = world.load_asset("shaders/precision_types.wgsl");
+        let shader = world.load_asset("shaders/tile_prepass.wgsl");
+        let pipeline =
+            world
+                .resource::<PipelineCache>()
+                .queue_compute_pipeline(ComputePipelineDescriptor {
+                    label: Some("tile_prepass_pipeline".into()),
+                    layout: vec![layout.clone()],
+                    push_constant_ranges: Vec::new(),
+                    shader,
+                    shader_defs: Vec::new(),
+                    entry_point: "main".into(),
+                });
+
+        Self {
+            layout,
+            pipeline,
+            _types: types,
+        }
+    }
+}
+
+// The derived tile data stays on the GPU in `tile_data`, for shaders to bind.
+// None of the terrain shaders of bevy_terrain read it yet, they still receive the offsets per tile.
+#[derive(Resource, Default)]
+pub struct TilePrepassBuffers {
+    origin: StorageBuffer<PrepassOrigin>,
+    tiles: StorageBuffer<Vec<CompactTile>>,
+    pub tile_data: Option<Buffer>,
+    pub tile_count: u32,
+    bind_group: Option<BindGroup>,
+}
+
+fn prepare_tile_prepass(
+    tile_list: Res<TileList>,
+    pipeline: Res<TilePrepassPipeline>,
+    mut buffers: ResMut<TilePrepassBuffers>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    let buffers = buffers.as_mut();
+    let tile_count = tile_list.tiles.len() as u32;
+
+    buffers.tile_count = tile_count;
+
+    if tile_count == 0 {
+        buffers.bind_group = None;
+        return;
+    }
+
+    buffers.origin.set(tile_list.origin);
+    buffers.origin.write_buffer(&render_device, &render_queue);
+    buffers.tiles.set(tile_list.tiles.clone());
+    buffers.tiles.write_buffer(&render_device, &render_queue);
+
+    let size = tile_count as u64 * TileData::min_size().get();
+
+    // only grow the output buffer
+    if buffers
+        .tile_data
+        .as_ref()
+        .map_or(true, |buffer| buffer.size() < size)
+    {
+        buffers.tile_data = Some(render_device.create_buffer(&BufferDescriptor {
+            label: Some("tile_data_buffer"),
+            size,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        }));
+    }
+
+    buffers.bind_group = Some(render_device.create_bind_group(
+        "tile_prepass_bind_group",
+        &pipeline.layout,
+        &BindGroupEntries::sequential((
+            buffers.origin.binding().unwrap(),
+            buffers.tiles.binding().unwrap(),
+            buffers.tile_data.as_ref().unwrap().as_entire_binding(),
+        )),
+    ));
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct TilePrepassLabel;
+
+struct TilePrepassNode;
+
+impl render_graph::Node for TilePrepassNode {
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let pipeline = world.resource::<TilePrepassPipeline>();
+        let buffers = world.resource::<TilePrepassBuffers>();
+
+        let (Some(compute_pipeline), Some(bind_group)) = (
+            pipeline_cache.get_compute_pipeline(pipeline.pipeline),
+            &buffers.bind_group,
+        ) else {
+            return Ok(());
+        };
+
+        let mut pass =
+            render_context
+                .command_encoder()
+                .begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("tile_prepass"),
+                    ..default()
+                });
+
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.set_pipeline(compute_pipeline);
+        pass.dispatch_workgroups(buffers.tile_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::MAX_LOD;
+    use bevy::math::DVec2;
+    use itertools::iproduct;
+
+    // Corner of the tile in uv, exact in f64 up to lod 52.
+    fn corner_uv(lod: u32, xy: UVec2) -> DVec2 {
+        xy.as_dvec2() / tile_count(lod) as f64
+    }
+
+    #[test]
+    fn tile_data_matches_the_tile_positions() {
+        let view_coordinates = std::array::from_fn(|face| {
+            Coordinate::new(face as u32, DVec2::new(0.3141, 0.6180 - 0.05 * face as f64))
+        });
+        let origin_lod = 9;
+        let origin = PrepassOrigin::new(&view_coordinates, origin_lod);
+
+        for (face, lod, dx, dy) in iproduct!(0..6, [0, 4, 8, 9, 10, 16], -2..=2, -2..=2) {
+            let view_xy = (view_coordinates[face as usize].uv * tile_count(lod) as f64).as_ivec2();
+            let xy = view_xy + IVec2::new(dx, dy);
+            if xy.min_element() < 0 || xy.max_element() >= tile_count(lod) as i32 {
+                continue;
+            }
+
+            let tile = CompactTile {
+                face,
+                lod,
+                xy: xy.as_uvec2(),
+            };
+            let data = derive_tile_data(tile, &origin);
+
+            // the origin tile, or the tile containing it at coarser lods
+            let origin_lod = origin_lod.min(lod);
+            let origin_xy =
+                (view_coordinates[face as usize].uv * tile_count(origin_lod) as f64).as_uvec2();
+            let offset_uv = corner_uv(lod, tile.xy) - corner_uv(origin_lod, origin_xy);
+
+            assert_eq!(data.lod_difference, lod as i32 - origin.origin_lod as i32);
+            assert_eq!(data.face_remap, face);
+            assert_eq!(data.offset.as_dvec2() / tile_count(lod) as f64, offset_uv);
+        }
+    }
+
+    #[test]
+    fn origin_tile_contains_the_view_across_tile_edges() {
+        let origin_lod = 9;
+        let count = tile_count(origin_lod);
+
+        for (face, x, offset) in iproduct!(0..6, [0, 1, 255, count - 1, count], [-1e-9, 0.0, 1e-9])
+        {
+            let uv = ((x as f64 + offset) / count as f64).clamp(0.0, 1.0);
+            let view_coordinates = std::array::from_fn(|_| Coordinate::new(face, DVec2::splat(uv)));
+            let origin = PrepassOrigin::new(&view_coordinates, origin_lod);
+
+            // the tile before the edge until the view crosses it, and the last tile on the far edge
+            let crossed = if offset < 0.0 { x.saturating_sub(1) } else { x };
+            let expected = crossed.min(count - 1) as u32;
+
+            assert_eq!(origin.origin_xy, [UVec2::splat(expected); 6]);
+        }
+    }
+
+    #[test]
+    fn compact_tile_round_trip() {
+        for (face, lod) in iproduct!(0..6, [0, 1, MAX_LOD]) {
+            let last = (tile_count(lod) - 1) as u32;
+
+            for (x, y) in iproduct!([0, last / 2, last], [0, last]) {
+                let tile = TileCoordinate::new(face, lod, x, y);
+                let compact = CompactTile::from(tile);
+
+                assert_eq!(
+                    compact,
+                    CompactTile {
+                        face,
+                        lod,
+                        xy: UVec2::new(x, y)
+                    }
+                );
+
+                let round_trip = TileCoordinate::from(compact);
+                assert_eq!(
+                    (round_trip.face, round_trip.lod, round_trip.x, round_trip.y),
+                    (face, lod, x, y)
+                );
+            }
+        }
+    }
+}
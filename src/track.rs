@@ -0,0 +1,279 @@
+use bevy::{math::DVec3, prelude::*};
+use bevy_terrain::{
+    big_space::{FloatingOrigin, GridTransformReadOnly, ReferenceFrames},
+    prelude::*,
+};
+use serde_json::{json, Value};
+use std::{fmt, fmt::Write, fs, path::Path};
+
+use crate::{
+    epoch::{shift_model, Rebase},
+    geodesy::Geodetic,
+    model_copy::ModelCopy,
+};
+
+// Camera tracks and markers are exported with geodetic coordinates in degrees and heights in meters,
+// which Google Earth (KML) and Cesium (CZML) interpret on the WGS84 ellipsoid.
+// This only lines up if the terrain model is the WGS84 ellipsoid, which makes the exports a cross-check of the geodesy.
+
+#[derive(Debug)]
+pub enum TrackError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Invalid(&'static str),
+}
+
+impl fmt::Display for TrackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrackError::Io(error) => write!(f, "failed to read CZML file: {error}"),
+            TrackError::Json(error) => write!(f, "failed to parse CZML: {error}"),
+            TrackError::Invalid(message) => write!(f, "invalid camera track: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for TrackError {}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TrackSample {
+    pub time: f64,
+    pub position: Geodetic,
+}
+
+#[derive(Clone, Debug)]
+pub struct Placemark {
+    pub name: String,
+    pub position: Geodetic,
+}
+
+impl Placemark {
+    pub fn new(name: impl Into<String>, position: Geodetic) -> Self {
+        Self {
+            name: name.into(),
+            position,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct CameraTrack {
+    pub model: TerrainModel,
+    // minimum time between two samples in seconds
+    pub interval: f64,
+    pub recording: bool,
+    pub samples: Vec<TrackSample>,
+}
+
+impl CameraTrack {
+    pub fn new(model: TerrainModel) -> Self {
+        Self {
+            model,
+            interval: 0.5,
+            recording: true,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn to_kml(&self, placemarks: &[Placemark]) -> String {
+        let mut kml = String::new();
+
+        writeln!(kml, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+        writeln!(kml, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#).unwrap();
+        writeln!(kml, "<Document>").unwrap();
+        writeln!(kml, "<name>Camera Track</name>").unwrap();
+
+        writeln!(kml, "<Placemark>").unwrap();
+        writeln!(kml, "<name>Camera</name>").unwrap();
+        writeln!(kml, "<LineString>").unwrap();
+        writeln!(kml, "<altitudeMode>absolute</altitudeMode>").unwrap();
+        writeln!(kml, "<coordinates>").unwrap();
+        for sample in &self.samples {
+            writeln!(kml, "{}", kml_coordinate(sample.position)).unwrap();
+        }
+        writeln!(kml, "</coordinates>").unwrap();
+        writeln!(kml, "</LineString>").unwrap();
+        writeln!(kml, "</Placemark>").unwrap();
+
+        for placemark in placemarks {
+            writeln!(kml, "<Placemark>").unwrap();
+            writeln!(kml, "<name>{}</name>", escape_xml(&placemark.name)).unwrap();
+            writeln!(kml, "<Point>").unwrap();
+            writeln!(kml, "<altitudeMode>absolute</altitudeMode>").unwrap();
+            writeln!(
+                kml,
+                "<coordinates>{}</coordinates>",
+                kml_coordinate(placemark.position)
+            )
+            .unwrap();
+            writeln!(kml, "</Point>").unwrap();
+            writeln!(kml, "</Placemark>").unwrap();
+        }
+
+        writeln!(kml, "</Document>").unwrap();
+        writeln!(kml, "</kml>").unwrap();
+
+        kml
+    }
+
+    // The sample times are exported as seconds relative to the epoch of the document.
+    pub fn to_czml(&self, placemarks: &[Placemark], epoch: &str) -> String {
+        let start = self.samples.first().map_or(0.0, |sample| sample.time);
+        let end = self.samples.last().map_or(0.0, |sample| sample.time);
+
+        let positions = self
+            .samples
+            .iter()
+            .flat_map(|sample| {
+                [
+                    sample.time - start,
+                    sample.position.longitude_degrees(),
+                    sample.position.latitude_degrees(),
+                    sample.position.height,
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let mut packets = vec![
+            json!({
+                "id": "document",
+                "name": "Camera Track",
+                "version": "1.0",
+            }),
+            json!({
+                "id": "camera",
+                "availability": format!("{epoch}/PT{}S", end - start),
+                "position": {
+                    "epoch": epoch,
+                    "cartographicDegrees": positions,
+                },
+                "path": {
+                    "width": 2,
+                    "leadTime": 0,
+                    "trailTime": end - start,
+                },
+                "point": { "pixelSize": 8 },
+            }),
+        ];
+
+        packets.extend(placemarks.iter().enumerate().map(|(index, placemark)| {
+            json!({
+                "id": format!("placemark{index}"),
+                "name": placemark.name,
+                "position": {
+                    "cartographicDegrees": [
+                        placemark.position.longitude_degrees(),
+                        placemark.position.latitude_degrees(),
+                        placemark.position.height,
+                    ],
+                },
+                "point": { "pixelSize": 10 },
+                "label": { "text": placemark.name },
+            })
+        }));
+
+        serde_json::to_string_pretty(&packets).unwrap()
+    }
+}
+
+impl ModelCopy for CameraTrack {
+    fn set_model(&mut self, model: &TerrainModel) {
+        self.model = model.clone();
+    }
+}
+
+impl Rebase for CameraTrack {
+    fn rebase(&mut self, offset: DVec3) {
+        self.model = shift_model(&self.model, offset);
+    }
+}
+
+// Loads the camera track of a CZML document, as written by `CameraTrack::to_czml`.
+// The sample times are relative to the epoch of the document.
+pub fn load_czml_track(path: impl AsRef<Path>) -> Result<Vec<TrackSample>, TrackError> {
+    let source = fs::read_to_string(path).map_err(TrackError::Io)?;
+    parse_czml_track(&source)
+}
+
+pub fn parse_czml_track(source: &str) -> Result<Vec<TrackSample>, TrackError> {
+    let packets: Vec<Value> = serde_json::from_str(source).map_err(TrackError::Json)?;
+
+    let positions = packets
+        .iter()
+        .find(|packet| packet["id"] == "camera")
+        .ok_or(TrackError::Invalid("document without camera packet"))?["position"]
+        ["cartographicDegrees"]
+        .as_array()
+        .ok_or(TrackError::Invalid("camera without cartographic positions"))?;
+
+    if positions.len() % 4 != 0 {
+        return Err(TrackError::Invalid(
+            "positions are not (time, longitude, latitude, height) tuples",
+        ));
+    }
+
+    positions
+        .chunks_exact(4)
+        .map(|sample| {
+            let values = sample
+                .iter()
+                .map(|value| {
+                    value
+                        .as_f64()
+                        .ok_or(TrackError::Invalid("non-numeric position"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(TrackSample {
+                time: values[0],
+                position: Geodetic::from_degrees(values[2], values[1], values[3]),
+            })
+        })
+        .collect()
+}
+
+fn kml_coordinate(position: Geodetic) -> String {
+    format!(
+        "{:.9},{:.9},{:.3}",
+        position.longitude_degrees(),
+        position.latitude_degrees(),
+        position.height
+    )
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub fn record_camera_track(
+    time: Res<Time>,
+    frames: ReferenceFrames,
+    mut track: ResMut<CameraTrack>,
+    view_query: Query<(Entity, GridTransformReadOnly), With<FloatingOrigin>>,
+) {
+    let time = time.elapsed_seconds_f64();
+
+    if !track.recording
+        || track
+            .samples
+            .last()
+            .is_some_and(|sample| time - sample.time < track.interval)
+    {
+        return;
+    }
+
+    let Ok((view, view_transform)) = view_query.get_single() else {
+        warn_once!("Skipping record_camera_track: expected exactly one camera.");
+        return;
+    };
+    let Some(frame) = frames.parent_frame(view) else {
+        warn_once!("Skipping record_camera_track: the camera is not part of a reference frame.");
+        return;
+    };
+    let position =
+        Geodetic::from_world_position(view_transform.position_double(&frame), &track.model);
+
+    track.samples.push(TrackSample { time, position });
+}
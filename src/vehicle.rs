@@ -0,0 +1,117 @@
+use bevy::{math::DVec3, prelude::*};
+use bevy_terrain::{
+    big_space::{GridTransform, ReferenceFrames},
+    prelude::*,
+};
+
+use crate::{
+    epoch::{shift_model, Rebase},
+    geodesy::{enu_frame, Geodetic},
+    height::TerrainHeights,
+    model_copy::ModelCopy,
+    raycast::{clamp_to_ground, terrain_raycast},
+};
+
+const CLEARANCE: f64 = 100.0;
+const ACCELERATION: f64 = 10.0;
+const TURN_RATE: f64 = 1.0;
+
+// A surface vehicle, that drives along its heading and stays clamped to the heightfield.
+// Its position is tracked in f64 and only converted to the grid cell of its frame for rendering.
+#[derive(Component)]
+pub struct Vehicle {
+    pub model: TerrainModel,
+    pub position: DVec3,
+    // clockwise from north in radians
+    pub heading: f64,
+    pub speed: f64,
+}
+
+impl Vehicle {
+    pub fn new(model: TerrainModel, geodetic: Geodetic, heading: f64) -> Self {
+        Self {
+            position: geodetic.world_position(&model),
+            model,
+            heading,
+            speed: 0.0,
+        }
+    }
+
+    pub fn forward(&self) -> DVec3 {
+        let geodetic = Geodetic::from_world_position(self.position, &self.model);
+        let enu = enu_frame(geodetic, &self.model);
+
+        enu.x_axis * self.heading.sin() + enu.y_axis * self.heading.cos()
+    }
+
+    pub fn up(&self) -> DVec3 {
+        let geodetic = Geodetic::from_world_position(self.position, &self.model);
+
+        enu_frame(geodetic, &self.model).z_axis
+    }
+}
+
+impl ModelCopy for Vehicle {
+    fn set_model(&mut self, model: &TerrainModel) {
+        self.model = model.clone();
+    }
+}
+
+impl Rebase for Vehicle {
+    fn rebase(&mut self, offset: DVec3) {
+        self.model = shift_model(&self.model, offset);
+        self.position += offset;
+    }
+}
+
+pub fn drive_vehicles(
+    time: Res<Time>,
+    input: Res<ButtonInput<KeyCode>>,
+    heights: Res<TerrainHeights>,
+    frames: ReferenceFrames,
+    mut vehicle_query: Query<(Entity, &mut Vehicle, GridTransform)>,
+) {
+    let delta_time = time.delta_seconds_f64();
+
+    for (entity, mut vehicle, mut grid_transform) in &mut vehicle_query {
+        if input.pressed(KeyCode::ArrowUp) {
+            vehicle.speed += ACCELERATION * delta_time;
+        }
+        if input.pressed(KeyCode::ArrowDown) {
+            vehicle.speed -= ACCELERATION * delta_time;
+        }
+        if input.pressed(KeyCode::ArrowLeft) {
+            vehicle.heading -= TURN_RATE * delta_time;
+        }
+        if input.pressed(KeyCode::ArrowRight) {
+            vehicle.heading += TURN_RATE * delta_time;
+        }
+
+        let position = vehicle.position + vehicle.forward() * vehicle.speed * delta_time;
+        let up = vehicle.up();
+
+        // drop the vehicle onto the terrain below, fall back to the vertical projection if the ray misses
+        vehicle.position = terrain_raycast(
+            position + up * CLEARANCE,
+            -up,
+            &vehicle.model,
+            heights.0.as_ref(),
+        )
+        .map(|hit| hit.position)
+        .unwrap_or_else(|| clamp_to_ground(position, &vehicle.model, heights.0.as_ref()));
+
+        let Some(frame) = frames.parent_frame(entity) else {
+            warn_once!(
+                "Skipping drive_vehicles for {entity:?}: it is not part of a reference frame."
+            );
+            continue;
+        };
+        let (cell, translation) = frame.translation_to_grid(vehicle.position);
+
+        *grid_transform.cell = cell;
+        grid_transform.transform.translation = translation;
+        grid_transform
+            .transform
+            .look_to(vehicle.forward().as_vec3(), vehicle.up().as_vec3());
+    }
+}
@@ -0,0 +1,391 @@
+use bevy::{math::DVec3, prelude::*, utils::HashMap};
+use bevy_terrain::{
+    math::{Coordinate, SurfaceApproximation},
+    prelude::*,
+};
+
+use crate::{
+    epoch::Rebase,
+    math::{
+        approximations, coordinate_from_world_position, height_coefficients, height_derivative,
+        view_coordinates, ViewCoordinates,
+    },
+};
+
+// The approximation around a single view position.
+#[derive(Clone, Copy)]
+pub struct ApproximationSnapshot {
+    pub view_position: DVec3,
+    pub view_coordinates: [Coordinate; 6],
+    pub approximations: [SurfaceApproximation; 6],
+    pub height_coefficients: [Vec3; 6],
+}
+
+impl ApproximationSnapshot {
+    pub fn compute(view_position: DVec3, model: &TerrainModel) -> Self {
+        let view_coordinates = view_coordinates(view_position, model);
+        let approximations = approximations(&view_coordinates, view_position, model);
+        let height_coefficients = height_coefficients(&view_coordinates, model);
+
+        Self {
+            view_position,
+            view_coordinates,
+            approximations,
+            height_coefficients,
+        }
+    }
+
+    // The view coordinates split at the origin lod, to evaluate many points around the view with.
+    pub fn view_coordinates_at(&self, origin_lod: u32) -> ViewCoordinates {
+        ViewCoordinates::new(self.view_coordinates, origin_lod)
+    }
+
+    // The same approximation relative to another view position nearby.
+    // Only the constant term depends on the view position, the derivative terms stay valid as they are.
+    pub fn shifted(&self, view_position: DVec3) -> Self {
+        let approximations = std::array::from_fn(|face| SurfaceApproximation {
+            c: self.approximations[face].c + (self.view_position - view_position).as_vec3(),
+            ..self.approximations[face]
+        });
+
+        Self {
+            view_position,
+            approximations,
+            ..*self
+        }
+    }
+
+    // Moves the snapshot along with the whole scene, e.g. when the scene epoch is rebased.
+    // The model moves by the same offset, so the coordinates and the relative coefficients stay as they are.
+    pub fn translate(&mut self, offset: DVec3) {
+        self.view_position += offset;
+    }
+
+    // Blends the coefficients and view coordinates towards the other snapshot.
+    // The view coordinate of a face is the projection of the view onto that face, so both lie on the same face.
+    // The blend deviates from the snapshot computed at the blended view position by about t (1 - t) d² / r,
+    // for views a distance d apart on a planet of radius r, e.g. less than a millimeter for views 100 m apart,
+    // since the coefficients and view coordinates vary smoothly with the view position.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let view_coordinates = std::array::from_fn(|face| {
+            let (start, end) = (self.view_coordinates[face], other.view_coordinates[face]);
+
+            Coordinate::new(end.face, start.uv.lerp(end.uv, t as f64))
+        });
+
+        let approximations = std::array::from_fn(|face| {
+            let (start, end) = (&self.approximations[face], &other.approximations[face]);
+
+            SurfaceApproximation {
+                c: start.c.lerp(end.c, t),
+                c_du: start.c_du.lerp(end.c_du, t),
+                c_dv: start.c_dv.lerp(end.c_dv, t),
+                c_duu: start.c_duu.lerp(end.c_duu, t),
+                c_duv: start.c_duv.lerp(end.c_duv, t),
+                c_dvv: start.c_dvv.lerp(end.c_dvv, t),
+            }
+        });
+
+        let height_coefficients = std::array::from_fn(|face| {
+            self.height_coefficients[face]
+                .lerp(other.height_coefficients[face], t)
+                .normalize()
+        });
+
+        Self {
+            view_position: self.view_position.lerp(other.view_position, t as f64),
+            view_coordinates,
+            approximations,
+            height_coefficients,
+        }
+    }
+}
+
+// The approximations around two nearby views, e.g. split screen players, or a camera and a light rendering shadows.
+// Views closer than the share distance expand the surface around the point between them,
+// so the coefficients are computed once, and each view only shifts the constant term by its offset to that point.
+// Views farther apart are computed separately, since one expansion would no longer be valid for both.
+#[derive(Clone, Copy)]
+pub struct DualApproximation {
+    pub snapshots: [ApproximationSnapshot; 2],
+    pub shared: bool,
+}
+
+impl DualApproximation {
+    pub fn compute(view_positions: [DVec3; 2], model: &TerrainModel, share_distance: f64) -> Self {
+        let [first, second] = view_positions;
+
+        if first.distance(second) > share_distance {
+            return Self {
+                snapshots: view_positions
+                    .map(|view_position| ApproximationSnapshot::compute(view_position, model)),
+                shared: false,
+            };
+        }
+
+        let shared = ApproximationSnapshot::compute(first.lerp(second, 0.5), model);
+
+        Self {
+            snapshots: view_positions.map(|view_position| shared.shifted(view_position)),
+            shared: true,
+        }
+    }
+}
+
+// The approximation is recomputed in `FixedUpdate`, which decouples the f64 work from the frame rate.
+// Systems in `Update` blend the last two snapshots with the overstep of the fixed clock.
+// The snapshots are fixed-size, so recomputing and blending them never allocates.
+#[derive(Resource, Default)]
+pub struct ViewApproximation {
+    pub previous: Option<ApproximationSnapshot>,
+    pub current: Option<ApproximationSnapshot>,
+    // number of snapshots pushed so far
+    pub recomputes: u64,
+}
+
+impl ViewApproximation {
+    pub fn push(&mut self, snapshot: ApproximationSnapshot) {
+        self.previous = self.current.replace(snapshot);
+        self.recomputes += 1;
+    }
+
+    pub fn translate(&mut self, offset: DVec3) {
+        for snapshot in self.previous.iter_mut().chain(&mut self.current) {
+            snapshot.translate(offset);
+        }
+    }
+
+    pub fn interpolated(&self, t: f32) -> Option<ApproximationSnapshot> {
+        match (&self.previous, &self.current) {
+            (Some(previous), Some(current)) => Some(previous.lerp(current, t)),
+            (None, current) => *current,
+            (Some(_), None) => None,
+        }
+    }
+}
+
+impl Rebase for ViewApproximation {
+    fn rebase(&mut self, offset: DVec3) {
+        self.translate(offset);
+    }
+}
+
+// The sides of an approximation, each one computed for the view position at the time it was last updated.
+#[derive(Clone)]
+pub struct StaggeredApproximation {
+    pub view_positions: [DVec3; 6],
+    pub view_coordinates: [Coordinate; 6],
+    pub approximations: [SurfaceApproximation; 6],
+    pub height_coefficients: [Vec3; 6],
+    // scheduler updates since the side was last recomputed
+    pub staleness: [u32; 6],
+}
+
+impl StaggeredApproximation {
+    pub fn compute(view_position: DVec3, model: &TerrainModel) -> Self {
+        let snapshot = ApproximationSnapshot::compute(view_position, model);
+
+        Self {
+            view_positions: [view_position; 6],
+            view_coordinates: snapshot.view_coordinates,
+            approximations: snapshot.approximations,
+            height_coefficients: snapshot.height_coefficients,
+            staleness: [0; 6],
+        }
+    }
+
+    pub fn update_side(&mut self, face: usize, view_position: DVec3, model: &TerrainModel) {
+        let view_coordinate = coordinate_from_world_position(view_position, model)
+            .project_to_face(face as u32, model);
+
+        self.view_positions[face] = view_position;
+        self.view_coordinates[face] = view_coordinate;
+        self.approximations[face] =
+            SurfaceApproximation::compute(view_coordinate, view_position, model);
+        self.height_coefficients[face] = height_derivative(view_coordinate, model).as_vec3();
+        self.staleness[face] = 0;
+    }
+
+    pub fn translate(&mut self, offset: DVec3) {
+        for view_position in &mut self.view_positions {
+            *view_position += offset;
+        }
+    }
+
+    // Lower values are recomputed first.
+    // Sides close to the view come first, but every update of staleness moves a far side further up the queue.
+    pub fn priority(&self, face: usize, view_position: DVec3, model: &TerrainModel) -> f64 {
+        let distance = self.view_coordinates[face]
+            .world_position(model, 0.0)
+            .distance(view_position);
+
+        distance / (self.staleness[face] + 1) as f64
+    }
+
+    // The sides relative to the current view position.
+    // The constant terms of stale sides are shifted by the distance the view moved since, the higher terms are kept.
+    pub fn snapshot(&self, view_position: DVec3) -> ApproximationSnapshot {
+        let approximations = std::array::from_fn(|face| SurfaceApproximation {
+            c: self.approximations[face].c + (self.view_positions[face] - view_position).as_vec3(),
+            ..self.approximations[face]
+        });
+
+        ApproximationSnapshot {
+            view_position,
+            view_coordinates: self.view_coordinates,
+            approximations,
+            height_coefficients: self.height_coefficients,
+        }
+    }
+}
+
+// Staggers the recomputation of the approximations of all views and terrains over several updates.
+// At most `max_sides` sides are recomputed per update, the ones with the lowest priority value first,
+// so that the worst case cost stays bounded, no matter how many views and terrains there are.
+// New pairs of a view and terrain are computed in full, since they have no sides to fall back to.
+#[derive(Resource)]
+pub struct ApproximationScheduler {
+    pub max_sides: usize,
+    // keyed by the view and the terrain entity
+    pub approximations: HashMap<(Entity, Entity), StaggeredApproximation>,
+}
+
+impl Default for ApproximationScheduler {
+    fn default() -> Self {
+        Self::new(6)
+    }
+}
+
+impl ApproximationScheduler {
+    pub fn new(max_sides: usize) -> Self {
+        Self {
+            max_sides,
+            approximations: HashMap::default(),
+        }
+    }
+
+    pub fn update<'a>(
+        &mut self,
+        views: impl IntoIterator<Item = ((Entity, Entity), DVec3, &'a TerrainModel)>,
+    ) {
+        let views = views.into_iter().collect::<Vec<_>>();
+
+        self.approximations
+            .retain(|key, _| views.iter().any(|(view_key, ..)| view_key == key));
+
+        let mut candidates = Vec::new();
+
+        for &(key, view_position, model) in &views {
+            match self.approximations.get_mut(&key) {
+                Some(approximation) => {
+                    for staleness in &mut approximation.staleness {
+                        *staleness += 1;
+                    }
+
+                    candidates.extend((0..6).map(|face| {
+                        (
+                            approximation.priority(face, view_position, model),
+                            key,
+                            face,
+                            view_position,
+                            model,
+                        )
+                    }));
+                }
+                None => {
+                    self.approximations
+                        .insert(key, StaggeredApproximation::compute(view_position, model));
+                }
+            }
+        }
+
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        for (_, key, face, view_position, model) in candidates.into_iter().take(self.max_sides) {
+            if let Some(approximation) = self.approximations.get_mut(&key) {
+                approximation.update_side(face, view_position, model);
+            }
+        }
+    }
+
+    pub fn translate(&mut self, offset: DVec3) {
+        for approximation in self.approximations.values_mut() {
+            approximation.translate(offset);
+        }
+    }
+
+    pub fn snapshot(
+        &self,
+        key: (Entity, Entity),
+        view_position: DVec3,
+    ) -> Option<ApproximationSnapshot> {
+        self.approximations
+            .get(&key)
+            .map(|approximation| approximation.snapshot(view_position))
+    }
+}
+
+impl Rebase for ApproximationScheduler {
+    fn rebase(&mut self, offset: DVec3) {
+        self.translate(offset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{approximate_world_position, tile_coordinate_from_world_position};
+    use bevy::math::DVec2;
+    use itertools::iproduct;
+
+    #[test]
+    fn blended_snapshot_matches_the_computed_one() {
+        let (major_axis, minor_axis) = (6378137.0, 6356752.314245);
+        let model = TerrainModel::ellipsoid(DVec3::ZERO, major_axis, minor_axis, 0.0, 0.0);
+        let origin_lod = 12;
+
+        let view_uv = DVec2::new(0.43, 0.57);
+        let start = Coordinate::new(0, view_uv).world_position(&model, 1000.0);
+        // about 100 m further along the surface and 10 m higher
+        let end =
+            Coordinate::new(0, view_uv + DVec2::new(1e-5, -4e-6)).world_position(&model, 1010.0);
+        let distance = start.distance(end);
+
+        let (start_snapshot, end_snapshot) = (
+            ApproximationSnapshot::compute(start, &model),
+            ApproximationSnapshot::compute(end, &model),
+        );
+
+        for t in [0.25, 0.5, 0.9] {
+            let blended = start_snapshot.lerp(&end_snapshot, t);
+            let computed = ApproximationSnapshot::compute(blended.view_position, &model);
+
+            // the bound of `lerp`, with room for the distortion of the cube, and a millimeter for the f32 rounding
+            // of the coefficients and relative uvs, which each snapshot rounds differently
+            let bound = 4.0 * (t * (1.0 - t)) as f64 * distance * distance / minor_axis + 1e-3;
+
+            for (du, dv) in iproduct!(-2..=2, -2..=2) {
+                let uv = view_uv + DVec2::new(du as f64, dv as f64) * 1e-4;
+                let position = Coordinate::new(0, uv).world_position(&model, 0.0);
+                let coordinate = tile_coordinate_from_world_position(position, origin_lod, &model);
+
+                let evaluate = |snapshot: &ApproximationSnapshot| {
+                    approximate_world_position(
+                        &snapshot.view_coordinates,
+                        &snapshot.approximations,
+                        origin_lod,
+                        true,
+                        snapshot.view_position,
+                        coordinate,
+                    )
+                };
+
+                let deviation = evaluate(&blended).distance(evaluate(&computed));
+                assert!(
+                    deviation < bound,
+                    "{deviation} m at t = {t}, bound {bound} m"
+                );
+            }
+        }
+    }
+}